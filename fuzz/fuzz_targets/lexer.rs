@@ -0,0 +1,12 @@
+//! Feeds arbitrary UTF-8 into the DRQL lexer.
+
+#![no_main]
+
+use intersection::drql::lexer::DrqlLexer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    for (start, _, end) in DrqlLexer::new(input).flatten() {
+        assert!(start <= end && end <= input.len());
+    }
+});
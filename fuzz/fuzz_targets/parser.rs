@@ -0,0 +1,16 @@
+//! Feeds arbitrary UTF-8 into the DRQL parser, both directly and through the scanner (as
+//! Intersection does for every message).
+
+#![no_main]
+
+use intersection::drql::{parser::parse_drql, scanner::scan};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    #[allow(let_underscore_drop)]
+    let _ = parse_drql(input);
+    for chunk in scan(input) {
+        #[allow(let_underscore_drop)]
+        let _ = parse_drql(chunk);
+    }
+});
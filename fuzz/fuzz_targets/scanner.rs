@@ -0,0 +1,13 @@
+//! Feeds arbitrary UTF-8 into the DRQL scanner, which runs on every message Intersection sees.
+
+#![no_main]
+
+use intersection::drql::scanner::scan;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    for chunk in scan(input) {
+        // Every chunk must be a slice of the input, enclosed in @{ ... }
+        assert!(input.contains(&format!("@{{{chunk}}}")));
+    }
+});
@@ -0,0 +1,168 @@
+//! Restrictions on who may run queries
+//!
+//! By default, anyone can run queries, as long as they're allowed to mention what their query
+//! resolves to. Guilds can restrict this further to members of some roles, or members with some
+//! permission.
+
+use std::collections::HashSet;
+
+use poise::serenity_prelude::{self as serenity, RoleId};
+use tracing::debug;
+
+use crate::{
+    error::QueryError,
+    i18n::{self, Language},
+};
+
+/// A permission which can be required to run queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum RequiredPermission {
+    /// Manage Messages
+    #[name = "Manage Messages"]
+    ManageMessages,
+    /// Mention @everyone, @here, and All Roles
+    #[name = "Mention @everyone, @here, and All Roles"]
+    MentionEveryone,
+    /// Manage Server
+    #[name = "Manage Server"]
+    ManageGuild,
+    /// Administrator
+    Administrator,
+}
+
+impl RequiredPermission {
+    /// The Discord permission this corresponds to.
+    const fn permissions(self) -> serenity::Permissions {
+        match self {
+            Self::ManageMessages => serenity::Permissions::MANAGE_MESSAGES,
+            Self::MentionEveryone => serenity::Permissions::MENTION_EVERYONE,
+            Self::ManageGuild => serenity::Permissions::MANAGE_GUILD,
+            Self::Administrator => serenity::Permissions::ADMINISTRATOR,
+        }
+    }
+}
+
+/// Who may run queries in a guild
+///
+/// A member may run queries if they have any of `roles`, or `permission`. If neither is set,
+/// anyone may.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryAccess {
+    /// Roles whose members may run queries
+    pub roles: HashSet<RoleId>,
+    /// A permission which allows members to run queries
+    pub permission: Option<RequiredPermission>,
+}
+
+impl QueryAccess {
+    /// Whether anyone may run queries.
+    pub fn is_unrestricted(&self) -> bool {
+        self.roles.is_empty() && self.permission.is_none()
+    }
+
+    /// Check whether a member with the given roles and permissions may run queries, failing with
+    /// [`QueryError::PermissionDenied`] (explained in `language`) if they may not.
+    pub fn check(
+        &self,
+        roles: &[RoleId],
+        permissions: serenity::Permissions,
+        language: Language,
+    ) -> Result<(), QueryError> {
+        if self.is_unrestricted()
+            || roles.iter().any(|role| self.roles.contains(role))
+            || self.permission.is_some_and(|permission| {
+                // Administrators implicitly have every permission
+                permissions.administrator() || permissions.contains(permission.permissions())
+            })
+        {
+            return Ok(());
+        }
+
+        debug!("Member is not allowed to run queries");
+        let mut roles = self.roles.iter().copied().collect::<Vec<_>>();
+        roles.sort_unstable();
+        let allowed = roles
+            .iter()
+            .map(|role| format!("<@&{role}>"))
+            .chain(self.permission.map(|permission| {
+                i18n::message(
+                    language,
+                    "access-permission",
+                    &[("permission", &permission.name())],
+                )
+            }))
+            .reduce(|first, second| {
+                i18n::message(
+                    language,
+                    "list-or",
+                    &[("first", &first), ("second", &second)],
+                )
+            })
+            .unwrap_or_default();
+
+        Err(QueryError::PermissionDenied(i18n::message(
+            language,
+            "access-denied",
+            &[("allowed", &allowed)],
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anyone_may_run_queries_by_default() {
+        assert!(QueryAccess::default()
+            .check(&[], serenity::Permissions::empty(), Language::English)
+            .is_ok());
+    }
+
+    #[test]
+    fn members_of_allowed_roles_may_run_queries() {
+        let access = QueryAccess {
+            roles: HashSet::from([RoleId(1), RoleId(2)]),
+            permission: None,
+        };
+
+        assert!(access
+            .check(
+                &[RoleId(2)],
+                serenity::Permissions::empty(),
+                Language::English
+            )
+            .is_ok());
+        assert_eq!(
+            access
+                .check(&[RoleId(3)], serenity::Permissions::all(), Language::English)
+                .map_err(|err| err.to_string()),
+            Err(
+                "You aren't allowed to run queries in this server. Only members with <@&1> or <@&2> can."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn members_with_the_permission_may_run_queries() {
+        let access = QueryAccess {
+            roles: HashSet::new(),
+            permission: Some(RequiredPermission::ManageMessages),
+        };
+
+        assert!(access
+            .check(
+                &[],
+                serenity::Permissions::MANAGE_MESSAGES,
+                Language::English
+            )
+            .is_ok());
+        assert!(access
+            .check(&[], serenity::Permissions::ADMINISTRATOR, Language::English)
+            .is_ok());
+        assert!(access
+            .check(&[], serenity::Permissions::SEND_MESSAGES, Language::English)
+            .is_err());
+    }
+}
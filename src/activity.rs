@@ -0,0 +1,222 @@
+//! Tracking when members last sent a message, for `active(...)` queries
+//!
+//! `here` only matches members who are online right now, which misses people who chat while
+//! appearing offline. Instead, the time of every member's latest message in each guild is kept,
+//! so `active(7d)` can match everyone who has said something in the last week.
+//!
+//! Only messages Intersection has seen count, so activity from before it joined a guild (or while
+//! it was down) is unknown. Activity is written to a file, rather than a database, at most every
+//! [`SAVE_INTERVAL`], and read again on startup; anything older than [`RETENTION`] is forgotten.
+//! The file is written in the background, from a copy of the activity, so the messages arriving
+//! meanwhile aren't held up. Stopping Intersection (or it crashing) therefore loses up to
+//! [`SAVE_INTERVAL`] of activity, which `active(...)` queries then don't see.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{GuildId, UserId};
+
+use crate::persistence::StoreFile;
+
+/// How long a member's latest message is remembered for
+const RETENTION: Duration = Duration::from_hours(90 * 24);
+
+/// How often activity is written to the file, at most
+///
+/// Messages are sent far too often to write everything out after each of them, so a restart can
+/// lose the activity of up to this long.
+const SAVE_INTERVAL: Duration = Duration::from_mins(5);
+
+/// Keeps track of when each member last sent a message in each guild, in a file if given one
+#[derive(Debug)]
+pub struct ActivityTracker {
+    /// The file activity is written to, if it's kept across restarts
+    file: StoreFile,
+    /// When each member of each guild last sent a message
+    guilds: Mutex<HashMap<GuildId, HashMap<UserId, DateTime<Utc>>>>,
+    /// When activity was last written to `path`
+    last_saved: Mutex<Instant>,
+}
+
+/// Write a member's latest message in a guild as a single line, with tab separated fields.
+fn to_line(guild: GuildId, user: UserId, time: DateTime<Utc>) -> String {
+    format!("{guild}\t{user}\t{}", time.timestamp())
+}
+
+/// Read a member's latest message written by [`to_line`].
+fn from_line(line: &str) -> Option<(GuildId, UserId, DateTime<Utc>)> {
+    let mut fields = line.split('\t');
+    let guild = GuildId(fields.next()?.parse().ok()?);
+    let user = UserId(fields.next()?.parse().ok()?);
+    let time = DateTime::from_timestamp(fields.next()?.parse().ok()?, 0)?;
+    fields.next().is_none().then_some((guild, user, time))
+}
+
+impl ActivityTracker {
+    /// Create a new [`ActivityTracker`] writing to `path`, reading the activity remembered the last
+    /// time from it.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let file = StoreFile::new(path, "member activity");
+        let mut guilds = HashMap::<GuildId, HashMap<UserId, DateTime<Utc>>>::new();
+        for (guild, user, time) in file.load(from_line) {
+            guilds.entry(guild).or_default().insert(user, time);
+        }
+
+        Self {
+            file,
+            guilds: Mutex::new(guilds),
+            last_saved: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Remember that `user` sent a message in `guild` at `time`.
+    pub fn record(&self, guild: GuildId, user: UserId, time: DateTime<Utc>) {
+        let mut guilds = self.guilds.lock().expect("activity lock was poisoned");
+        let latest = guilds.entry(guild).or_default().entry(user).or_insert(time);
+        *latest = (*latest).max(time);
+
+        let mut last_saved = self.last_saved.lock().expect("activity lock was poisoned");
+        if last_saved.elapsed() >= SAVE_INTERVAL {
+            *last_saved = Instant::now();
+            drop(last_saved);
+            Self::forget_old(&mut guilds, Utc::now());
+            self.save(&guilds);
+        }
+        drop(guilds);
+    }
+
+    /// The members of `guild` who have sent a message since `since`.
+    pub fn active_since(&self, guild: GuildId, since: DateTime<Utc>) -> HashSet<UserId> {
+        self.guilds
+            .lock()
+            .expect("activity lock was poisoned")
+            .get(&guild)
+            .map(|members| {
+                members
+                    .iter()
+                    .filter(|(_, time)| **time >= since)
+                    .map(|(user, _)| *user)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Forget every message `user` sent, in every guild, writing that out without waiting for
+    /// [`SAVE_INTERVAL`].
+    pub fn forget_user(&self, user: UserId) {
+        let mut guilds = self.guilds.lock().expect("activity lock was poisoned");
+        for members in guilds.values_mut() {
+            members.remove(&user);
+        }
+        guilds.retain(|_, members| !members.is_empty());
+        self.save(&guilds);
+        drop(guilds);
+    }
+
+    /// Forget messages sent longer than [`RETENTION`] before `now`.
+    fn forget_old(
+        guilds: &mut HashMap<GuildId, HashMap<UserId, DateTime<Utc>>>,
+        now: DateTime<Utc>,
+    ) {
+        let Some(cutoff) = chrono::Duration::from_std(RETENTION)
+            .ok()
+            .and_then(|retention| now.checked_sub_signed(retention))
+        else {
+            return;
+        };
+        for members in guilds.values_mut() {
+            members.retain(|_, time| *time >= cutoff);
+        }
+        guilds.retain(|_, members| !members.is_empty());
+    }
+
+    /// Write all activity to the file in the background, from a copy of `guilds`.
+    ///
+    /// Saving with the lock held keeps an older save from overwriting a newer one.
+    fn save(&self, guilds: &HashMap<GuildId, HashMap<UserId, DateTime<Utc>>>) {
+        let guilds = guilds.clone();
+        self.file.save_in_background(move || {
+            guilds
+                .iter()
+                .flat_map(|(guild, members)| {
+                    members
+                        .iter()
+                        .map(|(user, time)| to_line(*guild, *user, *time))
+                })
+                .collect::<Vec<_>>()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn activity_survives_being_written_out() {
+        let time = DateTime::from_timestamp(1_700_000_000, 0).expect("the time should be valid");
+
+        assert_eq!(
+            from_line(&to_line(GuildId(1), UserId(2), time)),
+            Some((GuildId(1), UserId(2), time))
+        );
+        assert_eq!(from_line("1\tnot a user\t1700000000"), None);
+    }
+
+    #[test]
+    fn latest_messages_are_remembered() {
+        let path = env::temp_dir().join(format!("intersection-activity-{}", std::process::id()));
+        let tracker = ActivityTracker::open(Some(path.clone()));
+        let now = Utc::now();
+        let week_ago = now - chrono::Duration::weeks(1);
+        let year_ago = now - chrono::Duration::weeks(52);
+
+        tracker.record(GuildId(1), UserId(2), now);
+        // An older message doesn't replace a newer one
+        tracker.record(GuildId(1), UserId(2), year_ago);
+        tracker.record(GuildId(1), UserId(3), year_ago);
+        tracker.record(GuildId(4), UserId(5), now);
+
+        assert_eq!(
+            tracker.active_since(GuildId(1), week_ago),
+            HashSet::from([UserId(2)])
+        );
+        assert!(tracker.active_since(GuildId(6), week_ago).is_empty());
+
+        // As if Intersection restarted, after the messages were written out
+        let mut guilds = tracker.guilds.lock().expect("activity lock was poisoned");
+        ActivityTracker::forget_old(&mut guilds, now);
+        tracker.save(&guilds);
+        drop(guilds);
+        let tracker = ActivityTracker::open(Some(path.clone()));
+        assert_eq!(
+            tracker.active_since(GuildId(1), year_ago),
+            HashSet::from([UserId(2)])
+        );
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+
+    #[test]
+    fn members_can_be_forgotten() {
+        let tracker = ActivityTracker::open(None);
+        let now = Utc::now();
+        tracker.record(GuildId(1), UserId(2), now);
+        tracker.record(GuildId(1), UserId(3), now);
+        tracker.record(GuildId(4), UserId(2), now);
+
+        tracker.forget_user(UserId(2));
+        assert_eq!(
+            tracker.active_since(GuildId(1), now),
+            HashSet::from([UserId(3)])
+        );
+        assert!(tracker.active_since(GuildId(4), now).is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+//! Restrictions on which channels queries are processed in
+//!
+//! Guilds can limit Intersection to some channels (an allowlist), or keep it out of some channels
+//! (a denylist). Messages elsewhere are quietly ignored. Threads follow the channel they're in.
+
+use std::collections::HashSet;
+
+use poise::serenity_prelude::ChannelId;
+
+/// How a [`ChannelFilter`]'s channels are treated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ChannelFilterMode {
+    /// Queries are processed everywhere except the listed channels
+    #[default]
+    #[name = "Everywhere except the listed channels"]
+    Denylist,
+    /// Queries are only processed in the listed channels
+    #[name = "Only in the listed channels"]
+    Allowlist,
+}
+
+/// The channels queries are processed in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelFilter {
+    /// How `channels` are treated
+    pub mode: ChannelFilterMode,
+    /// The channels allowed or denied, depending on `mode`
+    pub channels: HashSet<ChannelId>,
+}
+
+impl ChannelFilter {
+    /// Whether queries are processed in `channel`, which is a thread in `parent` if given.
+    pub fn allows(&self, channel: ChannelId, parent: Option<ChannelId>) -> bool {
+        let listed = self.channels.contains(&channel)
+            || parent.is_some_and(|parent| self.channels.contains(&parent));
+        match self.mode {
+            ChannelFilterMode::Denylist => !listed,
+            ChannelFilterMode::Allowlist => listed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_channel_is_allowed_by_default() {
+        assert!(ChannelFilter::default().allows(ChannelId(1), None));
+    }
+
+    #[test]
+    fn allowlists_only_allow_listed_channels() {
+        let filter = ChannelFilter {
+            mode: ChannelFilterMode::Allowlist,
+            channels: HashSet::from([ChannelId(1)]),
+        };
+
+        assert!(filter.allows(ChannelId(1), None));
+        assert!(!filter.allows(ChannelId(2), None));
+        // A thread in an allowed channel
+        assert!(filter.allows(ChannelId(3), Some(ChannelId(1))));
+    }
+
+    #[test]
+    fn denylists_deny_listed_channels() {
+        let filter = ChannelFilter {
+            mode: ChannelFilterMode::Denylist,
+            channels: HashSet::from([ChannelId(1)]),
+        };
+
+        assert!(!filter.allows(ChannelId(1), None));
+        assert!(filter.allows(ChannelId(2), None));
+        assert!(!filter.allows(ChannelId(3), Some(ChannelId(1))));
+    }
+}
@@ -6,13 +6,32 @@
 )]
 
 mod about;
+mod alias;
+mod config;
 mod debug;
 mod dry_run;
+mod history;
+mod members;
+mod pager;
+mod pause;
 mod ping;
+mod preferences;
+mod privacy;
+mod schedule;
+mod stats;
 mod version;
 
 pub use about::about;
+pub use alias::alias;
+pub use config::config;
 pub use debug::debug;
-pub use dry_run::dry_run;
+pub use dry_run::{dry_run, text_dry_run};
+pub use history::history;
+pub use members::members;
+pub use pause::{pause, resume};
 pub use ping::ping;
+pub use preferences::{dnd, optin, optout, preferences};
+pub use privacy::privacy;
+pub use schedule::schedule;
+pub use stats::stats;
 pub use version::version;
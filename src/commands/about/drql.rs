@@ -1,32 +1,45 @@
 use super::super::super::Context;
-use crate::util;
+use crate::{i18n, util};
 
 /// Learn some of the DRQL syntax and how to use it!
 #[poise::command(slash_command)]
 pub async fn drql(ctx: Context<'_>) -> Result<(), anyhow::Error> {
     let serenity_ctx = ctx.serenity_context();
+    let language = ctx.data().language(ctx.guild_id());
 
-    // Way too big. We'll send two messages.
+    // Way too big. We'll send three messages.
 
     let reply_handle = ctx
-        .say(format!(
-            include_str!("./drql_1.md"),
-            cmd_about_set_theory =
-                util::mention_application_command(serenity_ctx, "about set_theory").await?,
-            bot_user_id = serenity_ctx.cache.current_user_id(),
+        .say(i18n::message(
+            language,
+            "about-drql-1",
+            &[
+                (
+                    "about-set-theory",
+                    &util::mention_application_command(serenity_ctx, "about set_theory").await?,
+                ),
+                ("bot", &serenity_ctx.cache.current_user_id()),
+            ],
         ))
         .await?;
 
     // I think this is the closest thing to Discord.js Interaction#followUp...
-    reply_handle
+    let second = reply_handle
         .into_message()
         .await?
+        .reply(ctx, i18n::message(language, "about-drql-2", &[]))
+        .await?;
+
+    second
         .reply(
             ctx,
-            format!(
-                include_str!("./drql_2.md"),
-                cmd_about_how_it_works =
-                    util::mention_application_command(serenity_ctx, "about how_it_works").await?
+            i18n::message(
+                language,
+                "about-drql-3",
+                &[(
+                    "about-how-it-works",
+                    &util::mention_application_command(serenity_ctx, "about how_it_works").await?,
+                )],
             ),
         )
         .await?;
@@ -1,17 +1,19 @@
 use super::super::super::Context;
-use crate::util;
+use crate::{i18n, util};
 
 /// Learn about what the Intersection Project is
 #[poise::command(slash_command)]
 pub async fn intersection(ctx: Context<'_>) -> Result<(), anyhow::Error> {
     let serenity_ctx = ctx.serenity_context();
+    let language = ctx.data().language(ctx.guild_id());
 
     // Way too big. Two messages!
 
     let reply_handle = ctx
-        .say(format!(
-            include_str!("./intersection_1.md"),
-            bot_user_id = serenity_ctx.cache.current_user_id(),
+        .say(i18n::message(
+            language,
+            "about-intersection-1",
+            &[("bot", &serenity_ctx.cache.current_user_id())],
         ))
         .await?;
 
@@ -21,14 +23,25 @@ pub async fn intersection(ctx: Context<'_>) -> Result<(), anyhow::Error> {
         .await?
         .reply(
             ctx,
-            format!(
-                include_str!("./intersection_2.md"),
-                cmd_about_set_theory =
-                    util::mention_application_command(serenity_ctx, "about set_theory").await?,
-                cmd_about_drql =
-                    util::mention_application_command(serenity_ctx, "about drql").await?,
-                cmd_about_how_it_works =
-                    util::mention_application_command(serenity_ctx, "about how_it_works").await?,
+            i18n::message(
+                language,
+                "about-intersection-2",
+                &[
+                    (
+                        "about-set-theory",
+                        &util::mention_application_command(serenity_ctx, "about set_theory")
+                            .await?,
+                    ),
+                    (
+                        "about-drql",
+                        &util::mention_application_command(serenity_ctx, "about drql").await?,
+                    ),
+                    (
+                        "about-how-it-works",
+                        &util::mention_application_command(serenity_ctx, "about how_it_works")
+                            .await?,
+                    ),
+                ],
             ),
         )
         .await?;
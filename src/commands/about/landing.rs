@@ -1,13 +1,17 @@
 use super::super::super::Context;
-use crate::util;
+use crate::{i18n, util};
 
 /// I just got pinged by Intersection, what does this mean?
 #[poise::command(slash_command)]
 pub async fn landing(ctx: Context<'_>) -> Result<(), anyhow::Error> {
-    ctx.say(format!(
-        include_str!("./landing.md"),
-        cmd_about_intersection =
-            util::mention_application_command(ctx.serenity_context(), "about intersection").await?
+    ctx.say(i18n::message(
+        ctx.data().language(ctx.guild_id()),
+        "about-landing",
+        &[(
+            "about-intersection",
+            &util::mention_application_command(ctx.serenity_context(), "about intersection")
+                .await?,
+        )],
     ))
     .await?;
     Ok(())
@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+
+use anyhow::{bail, Context as _};
+use intersection::drql::aliases::alias_name;
+use poise::serenity_prelude as serenity;
+
+use super::{super::Context, pager};
+use crate::{
+    export::{read_aliases, write_aliases, AliasImportError, ALIASES_FILE_NAME},
+    i18n, pipeline,
+};
+
+/// The most aliases a server may have
+const MAX_ALIASES: usize = 100;
+
+/// The longest query an alias may stand for, the same as `/alias add` allows
+const MAX_ALIAS_LENGTH: usize = 500;
+
+/// The largest file of aliases `/alias import` reads, in bytes
+const MAX_IMPORT_SIZE: u64 = 64 * 1024;
+
+/// Give names to parts of queries, like $oncall, to use in this server's queries
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list", "export", "import")
+)]
+pub async fn alias(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// Name a part of a query, so queries can use it like $oncall, or change what a name stands for
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "The alias's name, like oncall for $oncall"]
+    #[max_length = 32]
+    name: String,
+    #[description = "The query the alias stands for, like \"on call\" & here"]
+    #[max_length = 500]
+    query: String,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.get(guild_id);
+    let Some(name) = alias_name(&name) else {
+        ctx.say(i18n::message(config.language, "alias-invalid-name", &[]))
+            .await?;
+        return Ok(());
+    };
+
+    let mut aliases = config.aliases;
+    if aliases.len() >= MAX_ALIASES && !aliases.contains_key(&name) {
+        ctx.say(i18n::message(
+            config.language,
+            "alias-limit",
+            &[("limit", &MAX_ALIASES)],
+        ))
+        .await?;
+        return Ok(());
+    }
+    // Refuse it now if it doesn't parse, or uses an alias which doesn't exist (or itself)
+    aliases.insert(name.clone(), query.clone());
+    pipeline::parse_chunks(&[&query], &aliases, config.language)?;
+
+    ctx.data().config.update(guild_id, |config| {
+        config.aliases.insert(name.clone(), query);
+    });
+
+    ctx.say(i18n::message(
+        config.language,
+        "alias-added",
+        &[("name", &name)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Stop queries in this server from using an alias
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "The alias's name, like oncall for $oncall"] name: String,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let name = alias_name(&name).unwrap_or(name);
+    let mut removed = None;
+    ctx.data().config.update(guild_id, |config| {
+        removed = config.aliases.remove(&name);
+    });
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        if removed.is_some() {
+            "alias-removed"
+        } else {
+            "alias-unknown"
+        },
+        &[("name", &name)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// List the aliases queries in this server can use
+#[poise::command(slash_command, guild_only, ephemeral)]
+async fn list(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.get(guild_id);
+    let aliases = config.aliases;
+    if aliases.is_empty() {
+        ctx.say(i18n::message(config.language, "alias-list-empty", &[]))
+            .await?;
+        return Ok(());
+    }
+
+    let lines = aliases
+        .iter()
+        .map(|(name, query)| format!("`${name}`: `{}`", query.replace('`', "\u{2cb}")))
+        .collect::<Vec<_>>();
+    pager::page_through(
+        ctx,
+        i18n::message(config.language, "alias-list", &[("count", &lines.len())]),
+        &i18n::message(config.language, "alias-list-title", &[]),
+        &lines,
+        false,
+        config.language,
+    )
+    .await
+}
+
+/// Save this server's aliases to a file, which /alias import can add to another server
+#[poise::command(slash_command, guild_only, ephemeral)]
+async fn export(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.get(guild_id);
+    let aliases = config.aliases;
+    if aliases.is_empty() {
+        ctx.say(i18n::message(config.language, "alias-export-empty", &[]))
+            .await?;
+        return Ok(());
+    }
+
+    let file_contents = write_aliases(&aliases);
+    ctx.send(|builder| {
+        builder
+            .content(i18n::message(
+                config.language,
+                "alias-exported",
+                &[("count", &aliases.len())],
+            ))
+            .attachment(serenity::AttachmentType::Bytes {
+                data: Cow::Borrowed(file_contents.as_bytes()),
+                filename: ALIASES_FILE_NAME.to_string(),
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Add the aliases in a file from /alias export, replacing any with the same names
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn import(
+    ctx: Context<'_>,
+    #[description = "A file made by /alias export"] file: serenity::Attachment,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.get(guild_id);
+    if file.size > MAX_IMPORT_SIZE {
+        ctx.say(i18n::message(
+            config.language,
+            "import-too-large",
+            &[("limit", &(MAX_IMPORT_SIZE / 1024))],
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let contents = file.download().await.context("Error downloading aliases")?;
+    let imported = String::from_utf8(contents)
+        .map_err(|_| AliasImportError::NotJson)
+        .and_then(|contents| read_aliases(&contents));
+    let imported = match imported {
+        Ok(imported) => imported,
+        Err(error) => {
+            ctx.say(error.explain(config.language)).await?;
+            return Ok(());
+        }
+    };
+    if let Some(name) = imported
+        .iter()
+        .find_map(|(name, query)| (query.chars().count() > MAX_ALIAS_LENGTH).then_some(name))
+    {
+        ctx.say(i18n::message(
+            config.language,
+            "import-query-too-long",
+            &[("name", name), ("limit", &MAX_ALIAS_LENGTH)],
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let mut aliases = config.aliases;
+    aliases.extend(imported.clone());
+    if aliases.len() > MAX_ALIASES {
+        ctx.say(i18n::message(
+            config.language,
+            "import-limit",
+            &[("limit", &MAX_ALIASES)],
+        ))
+        .await?;
+        return Ok(());
+    }
+    // Refuse them all if any uses an alias which doesn't exist (or itself)
+    for query in imported.values() {
+        pipeline::parse_chunks(&[query], &aliases, config.language)?;
+    }
+
+    let count = imported.len();
+    ctx.data().config.update(guild_id, |config| {
+        config.aliases.extend(imported);
+    });
+
+    ctx.say(i18n::message(
+        config.language,
+        "alias-imported",
+        &[("count", &count)],
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,1256 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Write as _},
+    time::Duration,
+};
+
+use anyhow::{bail, Context as _};
+use poise::serenity_prelude as serenity;
+
+use super::super::Context;
+use crate::{
+    access::RequiredPermission,
+    channel_filter::ChannelFilterMode,
+    config::{EveryoneHere, GuildConfig, QueryEntryPoints, RoleNameMatching},
+    cooldowns::{CooldownScope, RateLimit},
+    duplicates::{DuplicateQueryMode, DuplicateQuerySettings},
+    i18n::{self, Language},
+    pipeline::{
+        ConfirmationTimeout, Delivery, ModeratorApproval, CONFIRMATION_TIMEOUT,
+        DEFAULT_CONFIRMATION_THRESHOLD,
+    },
+};
+
+/// Change how Intersection behaves in this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands(
+        "get",
+        "reset",
+        "silent",
+        "per_chunk",
+        "embed",
+        "slash_only",
+        "delivery",
+        "cooldown",
+        "cooldown_bypass",
+        "max_mentions",
+        "query_role",
+        "query_permission",
+        "channels",
+        "audit_channel",
+        "language",
+        "error_expiry",
+        "mention_expiry",
+        "duplicate_queries",
+        "approval",
+        "typed_confirmation",
+        "confirm_threshold",
+        "confirm_timeout",
+        "do_not_ping",
+        "emergency_role",
+        "case_sensitive_roles",
+        "everyone_here",
+        "prefix"
+    )
+)]
+pub async fn config(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// Choose whether mentions are sent silently (without push notifications) by default
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn silent(
+    ctx: Context<'_>,
+    #[description = "Whether mentions should be sent silently by default"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.silent = enabled);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        if enabled {
+            "config-silent-on"
+        } else {
+            "config-silent-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether queries can only be run with slash commands, ignoring `@{}` in messages
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn slash_only(
+    ctx: Context<'_>,
+    #[description = "Whether to ignore queries in messages"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data().config.update(guild_id, |config| {
+        config.entry_points = if enabled {
+            QueryEntryPoints::SlashCommandsOnly
+        } else {
+            QueryEntryPoints::Everywhere
+        };
+    });
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        if enabled {
+            "config-slash-only-on"
+        } else {
+            "config-slash-only-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether each `@{}` in a message is mentioned separately, instead of all together
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn per_chunk(
+    ctx: Context<'_>,
+    #[description = "Whether each query in a message should be mentioned separately"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.per_chunk = enabled);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        if enabled {
+            "config-per-chunk-on"
+        } else {
+            "config-per-chunk-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether notifications are explained in an embed instead of in the message text
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn embed(
+    ctx: Context<'_>,
+    #[description = "Whether notifications should use an embed"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.embed = enabled);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        if enabled {
+            "config-embed-on"
+        } else {
+            "config-embed-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose how notifications are delivered
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn delivery(
+    ctx: Context<'_>,
+    #[description = "How notifications should be delivered"] mode: Delivery,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.delivery = mode);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        match mode {
+            Delivery::Reply => "config-delivery-reply",
+            Delivery::Webhook => "config-delivery-webhook",
+            Delivery::Thread => "config-delivery-thread",
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Limit how many notifications can be sent within a window of time
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn cooldown(
+    ctx: Context<'_>,
+    #[description = "Who the limit applies to"] scope: CooldownScope,
+    #[description = "How many notifications can be sent within the window (0 for no limit)"]
+    count: usize,
+    #[description = "How long the window is, in minutes"]
+    #[min = 1]
+    minutes: u64,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let limit = (count > 0).then(|| RateLimit {
+        count,
+        window: Duration::from_mins(minutes),
+    });
+    ctx.data().config.update(guild_id, |config| {
+        *config.cooldowns.limit_mut(scope) = limit;
+    });
+
+    let language = ctx.data().language(Some(guild_id));
+    let who = i18n::message(
+        language,
+        match scope {
+            CooldownScope::Author => "cooldown-scope-author",
+            CooldownScope::Guild => "cooldown-scope-guild",
+        },
+        &[],
+    );
+    ctx.say(if limit.is_some() {
+        i18n::message(
+            language,
+            "config-cooldown-set",
+            &[("count", &count), ("who", &who), ("minutes", &minutes)],
+        )
+    } else {
+        i18n::message(language, "config-cooldown-unset", &[("who", &who)])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether members of a role are exempt from cooldowns
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn cooldown_bypass(
+    ctx: Context<'_>,
+    #[description = "The role to exempt (or stop exempting)"] role: serenity::Role,
+    #[description = "Whether members of the role should be exempt"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data().config.update(guild_id, |config| {
+        if enabled {
+            config.cooldowns.bypass_roles.insert(role.id);
+        } else {
+            config.cooldowns.bypass_roles.remove(&role.id);
+        }
+    });
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        if enabled {
+            "config-cooldown-bypass-on"
+        } else {
+            "config-cooldown-bypass-off"
+        },
+        &[("role", &role.name)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether members of a role notify members during their quiet hours too
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn emergency_role(
+    ctx: Context<'_>,
+    #[description = "The role to allow (or stop allowing) to override quiet hours"]
+    role: serenity::Role,
+    #[description = "Whether members of the role should override quiet hours"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data().config.update(guild_id, |config| {
+        if enabled {
+            config.emergency_roles.insert(role.id);
+        } else {
+            config.emergency_roles.remove(&role.id);
+        }
+    });
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        if enabled {
+            "config-emergency-role-on"
+        } else {
+            "config-emergency-role-off"
+        },
+        &[("role", &role.name)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Set the most members a query may match, above which it is refused outright
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn max_mentions(
+    ctx: Context<'_>,
+    #[description = "The most members a query may match (0 for no limit)"] limit: usize,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let max_mentions = (limit > 0).then_some(limit);
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.max_mentions = max_mentions);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(if max_mentions.is_some() {
+        i18n::message(language, "config-max-mentions-set", &[("limit", &limit)])
+    } else {
+        i18n::message(language, "config-max-mentions-unset", &[])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether members of a role may run queries, once only some members may
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn query_role(
+    ctx: Context<'_>,
+    #[description = "The role to allow (or stop allowing)"] role: serenity::Role,
+    #[description = "Whether members of the role may run queries"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.update(guild_id, |config| {
+        if enabled {
+            config.access.roles.insert(role.id);
+        } else {
+            config.access.roles.remove(&role.id);
+        }
+    });
+
+    let language = config.language;
+    ctx.say(if config.access.is_unrestricted() {
+        i18n::message(language, "config-access-unrestricted", &[])
+    } else if enabled {
+        i18n::message(language, "config-query-role-on", &[("role", &role.name)])
+    } else {
+        i18n::message(language, "config-query-role-off", &[("role", &role.name)])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose a permission which allows members to run queries, once only some members may
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn query_permission(
+    ctx: Context<'_>,
+    #[description = "The permission to require (leave empty to no longer allow by permission)"]
+    permission: Option<RequiredPermission>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx
+        .data()
+        .config
+        .update(guild_id, |config| config.access.permission = permission);
+
+    let language = config.language;
+    ctx.say(if config.access.is_unrestricted() {
+        i18n::message(language, "config-access-unrestricted", &[])
+    } else if let Some(permission) = permission {
+        i18n::message(
+            language,
+            "config-query-permission-set",
+            &[("permission", &permission.name())],
+        )
+    } else {
+        i18n::message(language, "config-query-permission-unset", &[])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose which channels queries are processed in
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("channels_mode", "channels_add", "channels_remove", "channels_list")
+)]
+async fn channels(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// Choose whether queries are only processed in the listed channels, or everywhere else
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "mode"
+)]
+async fn channels_mode(
+    ctx: Context<'_>,
+    #[description = "How the listed channels are treated"] mode: ChannelFilterMode,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.channels.mode = mode);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        match mode {
+            ChannelFilterMode::Denylist => "config-channels-denylist",
+            ChannelFilterMode::Allowlist => "config-channels-allowlist",
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Add a channel to the list
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "add"
+)]
+async fn channels_add(
+    ctx: Context<'_>,
+    #[description = "The channel to add"] channel: serenity::GuildChannel,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.update(guild_id, |config| {
+        config.channels.channels.insert(channel.id);
+    });
+
+    ctx.say(i18n::message(
+        config.language,
+        match config.channels.mode {
+            ChannelFilterMode::Denylist => "config-channel-ignored",
+            ChannelFilterMode::Allowlist => "config-channel-processed",
+        },
+        &[("channel", &format!("<#{}>", channel.id))],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a channel from the list
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "remove"
+)]
+async fn channels_remove(
+    ctx: Context<'_>,
+    #[description = "The channel to remove"] channel: serenity::GuildChannel,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.update(guild_id, |config| {
+        config.channels.channels.remove(&channel.id);
+    });
+
+    ctx.say(i18n::message(
+        config.language,
+        match config.channels.mode {
+            ChannelFilterMode::Denylist => "config-channel-processed",
+            ChannelFilterMode::Allowlist => "config-channel-ignored",
+        },
+        &[("channel", &format!("<#{}>", channel.id))],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Show which channels queries are processed in
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "list"
+)]
+async fn channels_list(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.get(guild_id);
+    let filter = config.channels;
+
+    let mut channels = filter.channels.into_iter().collect::<Vec<_>>();
+    channels.sort_unstable();
+    let channels = channels
+        .iter()
+        .map(|channel| format!("<#{channel}>"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    ctx.say(i18n::message(
+        config.language,
+        match filter.mode {
+            ChannelFilterMode::Denylist if channels.is_empty() => "channels-all",
+            ChannelFilterMode::Denylist => "channels-except",
+            ChannelFilterMode::Allowlist if channels.is_empty() => "channels-none",
+            ChannelFilterMode::Allowlist => "channels-only",
+        },
+        &[("channels", &channels)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose a channel to post a summary of every notification to, for moderators
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn audit_channel(
+    ctx: Context<'_>,
+    #[description = "The channel to post to (leave empty to stop posting)"] channel: Option<
+        serenity::GuildChannel,
+    >,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let audit_channel = channel.map(|channel| channel.id);
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.audit_channel = audit_channel);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(audit_channel.map_or_else(
+        || i18n::message(language, "config-audit-channel-unset", &[]),
+        |audit_channel| {
+            i18n::message(
+                language,
+                "config-audit-channel-set",
+                &[("channel", &format!("<#{audit_channel}>"))],
+            )
+        },
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose the language Intersection sends messages in
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn language(
+    ctx: Context<'_>,
+    #[description = "The language to send messages in"] language: Language,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.language = language);
+
+    ctx.say(i18n::message(language, "language-set", &[]))
+        .await?;
+
+    Ok(())
+}
+
+/// Choose how long explanations of mistakes in queries are kept before being deleted
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn error_expiry(
+    ctx: Context<'_>,
+    #[description = "How many seconds to keep them for (0 to keep them forever)"] seconds: u64,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let error_expiry = (seconds > 0).then(|| Duration::from_secs(seconds));
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.error_expiry = error_expiry);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(if error_expiry.is_some() {
+        i18n::message(
+            language,
+            "config-error-expiry-set",
+            &[("seconds", &seconds)],
+        )
+    } else {
+        i18n::message(language, "config-error-expiry-unset", &[])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose how long messages mentioning members are kept before being deleted
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn mention_expiry(
+    ctx: Context<'_>,
+    #[description = "How many minutes to keep them for (0 to keep them forever)"]
+    #[max = 10080]
+    minutes: u64,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let mention_expiry = (minutes > 0).then(|| Duration::from_mins(minutes));
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.mention_expiry = mention_expiry);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(if mention_expiry.is_some() {
+        i18n::message(
+            language,
+            "config-mention-expiry-set",
+            &[("minutes", &minutes)],
+        )
+    } else {
+        i18n::message(language, "config-mention-expiry-unset", &[])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose what happens when a query is sent again in a channel shortly after it was last sent
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn duplicate_queries(
+    ctx: Context<'_>,
+    #[description = "What happens to queries sent again shortly after they were last sent"]
+    mode: DuplicateQueryMode,
+    #[description = "How long after being sent a query counts as a duplicate, in seconds"]
+    #[min = 1]
+    seconds: u64,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let settings = DuplicateQuerySettings {
+        mode,
+        window: Duration::from_secs(seconds),
+    };
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.duplicate_queries = settings);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(i18n::message(
+        language,
+        match mode {
+            DuplicateQueryMode::Allow => "config-duplicates-allow",
+            DuplicateQueryMode::Confirm => "config-duplicates-confirm",
+            DuplicateQueryMode::Refuse => "config-duplicates-refuse",
+        },
+        &[("seconds", &seconds)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Require a moderator to approve notifications to very many members, instead of their author
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn approval(
+    ctx: Context<'_>,
+    #[description = "How many members a query must match to need approval (0 to never need it)"]
+    threshold: usize,
+    #[description = "The role whose members can approve notifications"] role: Option<
+        serenity::Role,
+    >,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let approval = match (threshold, role) {
+        (0, _) => None,
+        (threshold, Some(role)) => Some(ModeratorApproval {
+            threshold,
+            role: role.id,
+        }),
+        (_, None) => {
+            ctx.say(i18n::message(language, "config-approval-role-missing", &[]))
+                .await?;
+            return Ok(());
+        }
+    };
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.approval = approval);
+
+    ctx.say(approval.map_or_else(
+        || i18n::message(language, "config-approval-unset", &[]),
+        |approval| {
+            i18n::message(
+                language,
+                "config-approval-set",
+                &[
+                    ("threshold", &approval.threshold),
+                    ("role", &format!("<@&{}>", approval.role)),
+                ],
+            )
+        },
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Make authors type the number of members a very large query matches to confirm it
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn typed_confirmation(
+    ctx: Context<'_>,
+    #[description = "How many members a query must match to need typing (0 to never need it)"]
+    threshold: usize,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let typed_confirmation = (threshold > 0).then_some(threshold);
+    ctx.data().config.update(guild_id, |config| {
+        config.typed_confirmation = typed_confirmation;
+    });
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(if typed_confirmation.is_some() {
+        i18n::message(
+            language,
+            "config-typed-confirmation-set",
+            &[("threshold", &threshold)],
+        )
+    } else {
+        i18n::message(language, "config-typed-confirmation-unset", &[])
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Choose how many members a query can match before its author has to confirm it
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn confirm_threshold(
+    ctx: Context<'_>,
+    #[description = "The most members a query can match without confirming it (50 by default)"]
+    #[min = 1]
+    #[max = 1000]
+    members: usize,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data().config.update(guild_id, |config| {
+        config.confirmation_threshold = Some(members);
+    });
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        "config-confirm-threshold",
+        &[("members", &members)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose how long authors have to confirm large queries, for moderators checking who they mention
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn confirm_timeout(
+    ctx: Context<'_>,
+    #[description = "How many seconds authors have to confirm (30 by default)"]
+    #[min = 10]
+    #[max = 600]
+    seconds: u64,
+    #[description = "Whether to remind authors halfway through that it's still waiting for them"]
+    reminder: Option<bool>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let reminder = reminder.unwrap_or(false);
+    ctx.data().config.update(guild_id, |config| {
+        config.confirmation_timeout = Some(ConfirmationTimeout {
+            timeout: Duration::from_secs(seconds),
+            reminder,
+        });
+    });
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        if reminder {
+            "config-confirm-timeout-reminder"
+        } else {
+            "config-confirm-timeout"
+        },
+        &[("seconds", &seconds)],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose a role whose members are never mentioned, whatever the query
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn do_not_ping(
+    ctx: Context<'_>,
+    #[description = "The role whose members are never mentioned (leave out to mention anyone)"]
+    role: Option<serenity::Role>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let do_not_ping = role.map(|role| role.id);
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.do_not_ping = do_not_ping);
+
+    let language = ctx.data().language(Some(guild_id));
+    ctx.say(do_not_ping.map_or_else(
+        || i18n::message(language, "config-do-not-ping-unset", &[]),
+        |role| {
+            i18n::message(
+                language,
+                "config-do-not-ping-set",
+                &[("role", &format!("<@&{role}>"))],
+            )
+        },
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether role names in queries have to match the role's name exactly, case included
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn case_sensitive_roles(
+    ctx: Context<'_>,
+    #[description = "Whether role names should only match roles with exactly the same case"]
+    enabled: bool,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data().config.update(guild_id, |config| {
+        config.role_names = if enabled {
+            RoleNameMatching::ExactCase
+        } else {
+            RoleNameMatching::IgnoreCase
+        };
+    });
+    // Cached results were resolved with the old setting
+    ctx.data().query_cache.invalidate_guild(guild_id);
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        if enabled {
+            "config-case-sensitive-on"
+        } else {
+            "config-case-sensitive-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether `everyone` and `here` can be used in queries, even by those allowed to mention them
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn everyone_here(
+    ctx: Context<'_>,
+    #[description = "Whether members allowed to mention everyone can use them in queries"]
+    enabled: bool,
+    #[description = "Why they can't, shown to whoever tries to use them"]
+    #[max_length = 200]
+    explanation: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let everyone_here = if enabled {
+        EveryoneHere::Allowed
+    } else {
+        EveryoneHere::Refused(explanation)
+    };
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.everyone_here = everyone_here);
+    // Cached results were resolved with the old setting
+    ctx.data().query_cache.invalidate_guild(guild_id);
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        if enabled {
+            "config-everyone-here-on"
+        } else {
+            "config-everyone-here-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose the prefix for text commands, like `!dry_run staff & here`, for servers that prefer them
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn prefix(
+    ctx: Context<'_>,
+    #[description = "What text commands start with (leave empty to turn them off)"]
+    #[max_length = 10]
+    prefix: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let prefix = prefix.map(|prefix| prefix.trim().to_string());
+    if prefix
+        .as_ref()
+        .is_some_and(|prefix| prefix.is_empty() || prefix.contains(char::is_whitespace))
+    {
+        ctx.say(i18n::message(language, "config-prefix-invalid", &[]))
+            .await?;
+        return Ok(());
+    }
+    ctx.data()
+        .config
+        .update(guild_id, |config| config.prefix.clone_from(&prefix));
+
+    ctx.say(prefix.map_or_else(
+        || i18n::message(language, "config-prefix-unset", &[]),
+        |prefix| i18n::message(language, "config-prefix-set", &[("prefix", &prefix)]),
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Write `roles` as mentions, or `none` in `language` if there aren't any.
+fn role_list(roles: &HashSet<serenity::RoleId>, language: Language) -> String {
+    let mut roles = roles.iter().collect::<Vec<_>>();
+    roles.sort_unstable();
+    if roles.is_empty() {
+        return i18n::message(language, "value-none", &[]);
+    }
+    roles
+        .iter()
+        .map(|role| format!("<@&{role}>"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Write `limit` like `3 per 60 seconds`, or `none` if there isn't one, in `language`.
+fn rate_limit(limit: Option<RateLimit>, language: Language) -> String {
+    limit.map_or_else(
+        || i18n::message(language, "value-none", &[]),
+        |limit| {
+            i18n::message(
+                language,
+                "value-rate-limit",
+                &[
+                    ("count", &limit.count),
+                    ("seconds", &limit.window.as_secs()),
+                ],
+            )
+        },
+    )
+}
+
+/// Write `enabled` as `yes` or `no` in `language`.
+fn yes_no(enabled: bool, language: Language) -> String {
+    i18n::message(
+        language,
+        if enabled { "value-yes" } else { "value-no" },
+        &[],
+    )
+}
+
+/// Describe which channels queries are processed in under `config`.
+fn channel_description(config: &GuildConfig) -> String {
+    let mut channels = config.channels.channels.iter().collect::<Vec<_>>();
+    channels.sort_unstable();
+    let channels = channels
+        .iter()
+        .map(|channel| format!("<#{channel}>"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    i18n::message(
+        config.language,
+        match config.channels.mode {
+            ChannelFilterMode::Denylist if channels.is_empty() => "value-channels-all",
+            ChannelFilterMode::Denylist => "value-channels-except",
+            ChannelFilterMode::Allowlist if channels.is_empty() => "value-channels-none",
+            ChannelFilterMode::Allowlist => "value-channels-only",
+        },
+        &[("channels", &channels)],
+    )
+}
+
+/// Describe every setting in `config`, one per line, with the subcommand which changes it, in the
+/// guild's language.
+#[allow(clippy::too_many_lines)] // It's one setting after another
+fn describe(config: &GuildConfig) -> Result<String, fmt::Error> {
+    let language = config.language;
+    let value =
+        |id: &str, args: &[(&str, &(dyn fmt::Display + Sync))]| i18n::message(language, id, args);
+    let none = || value("value-none", &[]);
+    let forever = || value("value-forever", &[]);
+    let never_needed = || value("value-never-needed", &[]);
+    let settings = [
+        ("setting-silent", "silent", yes_no(config.silent, language)),
+        (
+            "setting-per-chunk",
+            "per_chunk",
+            yes_no(config.per_chunk, language),
+        ),
+        ("setting-embed", "embed", yes_no(config.embed, language)),
+        (
+            "setting-slash-only",
+            "slash_only",
+            yes_no(
+                config.entry_points == QueryEntryPoints::SlashCommandsOnly,
+                language,
+            ),
+        ),
+        (
+            "setting-delivery",
+            "delivery",
+            value(
+                match config.delivery {
+                    Delivery::Reply => "value-delivery-reply",
+                    Delivery::Webhook => "value-delivery-webhook",
+                    Delivery::Thread => "value-delivery-thread",
+                },
+                &[],
+            ),
+        ),
+        (
+            "setting-cooldown-author",
+            "cooldown",
+            rate_limit(config.cooldowns.per_author, language),
+        ),
+        (
+            "setting-cooldown-guild",
+            "cooldown",
+            rate_limit(config.cooldowns.per_guild, language),
+        ),
+        (
+            "setting-cooldown-bypass",
+            "cooldown_bypass",
+            role_list(&config.cooldowns.bypass_roles, language),
+        ),
+        (
+            "setting-max-mentions",
+            "max_mentions",
+            config
+                .max_mentions
+                .map_or_else(|| value("value-no-limit", &[]), |max| max.to_string()),
+        ),
+        (
+            "setting-query-role",
+            "query_role",
+            if config.access.roles.is_empty() {
+                value("value-any", &[])
+            } else {
+                role_list(&config.access.roles, language)
+            },
+        ),
+        (
+            "setting-query-permission",
+            "query_permission",
+            config
+                .access
+                .permission
+                .map_or_else(none, |permission| permission.to_string()),
+        ),
+        ("setting-channels", "channels", channel_description(config)),
+        (
+            "setting-audit-channel",
+            "audit_channel",
+            config
+                .audit_channel
+                .map_or_else(none, |channel| format!("<#{channel}>")),
+        ),
+        ("setting-language", "language", config.language.to_string()),
+        (
+            "setting-error-expiry",
+            "error_expiry",
+            config.error_expiry.map_or_else(forever, |expiry| {
+                value("value-seconds", &[("seconds", &expiry.as_secs())])
+            }),
+        ),
+        (
+            "setting-mention-expiry",
+            "mention_expiry",
+            config.mention_expiry.map_or_else(forever, |expiry| {
+                value("value-minutes", &[("minutes", &(expiry.as_secs() / 60))])
+            }),
+        ),
+        (
+            "setting-duplicate-queries",
+            "duplicate_queries",
+            value(
+                "value-duplicate-queries",
+                &[
+                    (
+                        "mode",
+                        &value(
+                            match config.duplicate_queries.mode {
+                                DuplicateQueryMode::Allow => "value-duplicates-allow",
+                                DuplicateQueryMode::Confirm => "value-duplicates-confirm",
+                                DuplicateQueryMode::Refuse => "value-duplicates-refuse",
+                            },
+                            &[],
+                        ),
+                    ),
+                    ("seconds", &config.duplicate_queries.window.as_secs()),
+                ],
+            ),
+        ),
+        (
+            "setting-approval",
+            "approval",
+            config.approval.map_or_else(never_needed, |approval| {
+                value(
+                    "value-approval",
+                    &[
+                        ("threshold", &approval.threshold),
+                        ("role", &format!("<@&{}>", approval.role)),
+                    ],
+                )
+            }),
+        ),
+        (
+            "setting-typed-confirmation",
+            "typed_confirmation",
+            config
+                .typed_confirmation
+                .map_or_else(never_needed, |threshold| {
+                    value("value-threshold", &[("threshold", &threshold)])
+                }),
+        ),
+        (
+            "setting-confirmation",
+            "confirm_threshold",
+            value(
+                "value-confirmation",
+                &[(
+                    "members",
+                    &config
+                        .confirmation_threshold
+                        .unwrap_or(DEFAULT_CONFIRMATION_THRESHOLD),
+                )],
+            ),
+        ),
+        (
+            "setting-confirm-timeout",
+            "confirm_timeout",
+            config.confirmation_timeout.map_or_else(
+                || {
+                    value(
+                        "value-seconds",
+                        &[("seconds", &CONFIRMATION_TIMEOUT.as_secs())],
+                    )
+                },
+                |timeout| {
+                    value(
+                        if timeout.reminder {
+                            "value-seconds-with-reminder"
+                        } else {
+                            "value-seconds"
+                        },
+                        &[("seconds", &timeout.timeout.as_secs())],
+                    )
+                },
+            ),
+        ),
+        (
+            "setting-do-not-ping",
+            "do_not_ping",
+            config
+                .do_not_ping
+                .map_or_else(none, |role| format!("<@&{role}>")),
+        ),
+        (
+            "setting-emergency-role",
+            "emergency_role",
+            role_list(&config.emergency_roles, language),
+        ),
+        (
+            "setting-case-sensitive-roles",
+            "case_sensitive_roles",
+            yes_no(config.role_names == RoleNameMatching::ExactCase, language),
+        ),
+        (
+            "setting-everyone-here",
+            "everyone_here",
+            match &config.everyone_here {
+                EveryoneHere::Allowed => yes_no(true, language),
+                EveryoneHere::Refused(None) => yes_no(false, language),
+                EveryoneHere::Refused(Some(explanation)) => {
+                    value("value-refused", &[("explanation", explanation)])
+                }
+            },
+        ),
+        (
+            "setting-aliases",
+            "/alias",
+            match config.aliases.len() {
+                0 => none(),
+                count => value("value-aliases", &[("count", &count)]),
+            },
+        ),
+        (
+            "setting-prefix",
+            "prefix",
+            config
+                .prefix
+                .as_ref()
+                .map_or_else(none, |prefix| format!("`{prefix}`")),
+        ),
+    ];
+
+    let mut description = String::new();
+    for (name, subcommand, value) in settings {
+        writeln!(
+            &mut description,
+            "**{}** (`{subcommand}`): {value}",
+            i18n::message(language, name, &[])
+        )?;
+    }
+    Ok(description)
+}
+
+/// Show how Intersection is configured in this server
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn get(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let config = ctx.data().config.get(guild_id);
+    let description = describe(&config)?;
+
+    ctx.send(|builder| {
+        builder
+            .allowed_mentions(|allowed_mentions| {
+                allowed_mentions.empty_parse().empty_users().empty_roles()
+            })
+            .embed(|embed| {
+                embed
+                    .title(i18n::message(config.language, "config-title", &[]))
+                    .description(description)
+                    .footer(|footer| {
+                        footer.text(i18n::message(config.language, "config-footer", &[]))
+                    })
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Undo every change to how Intersection behaves in this server
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn reset(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data().config.reset(guild_id);
+    // Cached results may have been resolved with a different role name setting
+    ctx.data().query_cache.invalidate_guild(guild_id);
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        "config-reset",
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
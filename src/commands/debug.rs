@@ -1,10 +1,29 @@
 use anyhow::{bail, Context as _};
+use poise::serenity_prelude::{self as serenity, Mentionable};
 
 use super::super::{drql, Context};
-use crate::drql::ast::Expr;
+use crate::{
+    drql::{ast::Expr, diagnostics::Diagnostic},
+    error,
+    extensions::{CustomGuildChannelImpl, CustomMemberImpl},
+    i18n,
+    pipeline::{bench_query, explain_query, QueryContext},
+};
 
 /// Debug DRQL queries or the DRQL facilities itself
-#[poise::command(slash_command, subcommands("scan", "parse_one", "reduce"))]
+#[poise::command(
+    slash_command,
+    subcommands(
+        "scan",
+        "tokens",
+        "parse_one",
+        "reduce",
+        "format",
+        "explain",
+        "permissions",
+        "bench"
+    )
+)]
 pub async fn debug(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
     bail!("unreachable");
 }
@@ -15,14 +34,16 @@ async fn scan(
     ctx: Context<'_>,
     #[description = "The message to scan for queries in"] msg: String,
 ) -> Result<(), anyhow::Error> {
+    let language = ctx.data().language(ctx.guild_id());
     let chunks = drql::scanner::scan(msg.as_str()).collect::<Vec<_>>();
 
     if chunks.is_empty() {
-        ctx.say("No chunks were scanned.").await?;
+        ctx.say(i18n::message(language, "debug-no-chunks", &[]))
+            .await?;
     } else {
         ctx.say(format!(
-            "Found {} chunks:\n\n{}",
-            chunks.len(),
+            "{}\n\n{}",
+            i18n::message(language, "debug-chunks", &[("count", &chunks.len())]),
             chunks
                 .iter()
                 .map(|x| format!("`{x}`"))
@@ -35,42 +56,97 @@ async fn scan(
     Ok(())
 }
 
+/// Lex a single DRQL query, showing each token the parser would be given
+#[poise::command(slash_command)]
+async fn tokens(
+    ctx: Context<'_>,
+    #[description = "The DRQL query to lex (DO NOT include @{})"] query: String,
+) -> Result<(), anyhow::Error> {
+    let language = ctx.data().language(ctx.guild_id());
+    let mut lexer = drql::lexer::DrqlLexer::new(query.as_str());
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next() {
+        let span = lexer.span();
+        tokens.push(match token {
+            Ok((_, token, _)) => format!("{span:?} {token:?}"),
+            Err(err) => format!(
+                "{span:?} {}",
+                i18n::message(language, "debug-token-error", &[("error", &err)])
+            ),
+        });
+    }
+
+    ctx.say(if tokens.is_empty() {
+        i18n::message(language, "debug-no-tokens", &[])
+    } else {
+        format!(
+            "{}\n\n```{}```",
+            i18n::message(language, "debug-tokens", &[("count", &tokens.len())]),
+            tokens.join("\n").replace('`', "\u{2cb}")
+        )
+    })
+    .await?;
+
+    Ok(())
+}
+
 /// Parse a single DRQL query
 #[poise::command(slash_command)]
 async fn parse_one(
     ctx: Context<'_>,
     #[description = "The DRQL query to parse (DO NOT include @{})"] query: String,
 ) -> Result<(), anyhow::Error> {
+    let language = ctx.data().language(ctx.guild_id());
     ctx.say(match drql::parser::parse_drql(query.as_str()) {
-        Err(err) => format!("Encountered an error while parsing:\n\n```{err:?}```"),
-        Ok(ast) => format!("Successfully parsed:\n\n```{ast:?}```"),
+        Err(err) => format!(
+            "{}\n\n```{err:?}```",
+            i18n::message(language, "debug-parse-error", &[])
+        ),
+        // Drawn as a tree rather than with Debug, so how the query was grouped can be seen at a
+        // glance
+        Ok(ast) => format!(
+            "{}\n\n```\n{}\n```",
+            i18n::message(language, "debug-parsed", &[]),
+            drql::formatter::tree(&ast, |_| None).replace('`', "\u{2cb}")
+        ),
     })
     .await?;
 
     Ok(())
 }
 
-/// Scan the input, parse each query, and finally reduce into one tree
+/// Scan the input, parse each query, and finally reduce into one optimized tree
 #[poise::command(slash_command)]
 async fn reduce(
     ctx: Context<'_>,
     #[description = "The message to scan"] msg: String,
 ) -> Result<(), anyhow::Error> {
+    let language = ctx.data().language(ctx.guild_id());
     ctx.say(
         match drql::scanner::scan(msg.as_str())
             .enumerate()
             .map(|(n, chunk)| {
-                drql::parser::parse_drql(chunk).context(format!("Error parsing chunk {n}"))
+                drql::parser::parse_drql(chunk)
+                    .with_context(|| i18n::message(language, "debug-chunk-error", &[("chunk", &n)]))
             })
             .collect::<Result<Vec<_>, _>>()
         {
-            Err(err) => format!("Encountered an error while parsing:\n\n```{err:#}```"),
+            Err(err) => format!(
+                "{}\n\n```{err:#}```",
+                i18n::message(language, "debug-parse-error", &[])
+            ),
             Ok(ast) => ast
                 .into_iter()
                 .reduce(|acc, chunk| Expr::Union(Box::new(acc), Box::new(chunk)))
+                .map(drql::optimizer::optimize)
                 .map_or_else(
-                    || "No chunks found.".to_string(),
-                    |ast| format!("Success! Resulting AST:\n\n```{ast:?}```"),
+                    || i18n::message(language, "debug-no-chunks", &[]),
+                    |ast| {
+                        format!(
+                            "{}\n\n```{ast:?}```",
+                            i18n::message(language, "debug-reduced", &[])
+                        )
+                    },
                 ),
         },
     )
@@ -78,3 +154,168 @@ async fn reduce(
 
     Ok(())
 }
+
+/// Parse a single DRQL query, and write it back out in a canonical form
+#[poise::command(slash_command)]
+async fn format(
+    ctx: Context<'_>,
+    #[description = "The DRQL query to format (DO NOT include @{})"] query: String,
+) -> Result<(), anyhow::Error> {
+    let language = ctx.data().language(ctx.guild_id());
+    ctx.say(match drql::parser::parse_drql(query.as_str()) {
+        Err(err) => {
+            let diagnostic = Diagnostic::new(&query, &err);
+            format!(
+                "{} {}",
+                i18n::message(language, "debug-format-error", &[]),
+                diagnostic.render_as(&query, &error::explain(&diagnostic.problem, language))
+            )
+        }
+        Ok(ast) => format!(
+            "{}\n\n```{}```",
+            i18n::message(language, "debug-formatted", &[]),
+            drql::formatter::format(&ast).replace('`', "\u{2cb}")
+        ),
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Evaluate a DRQL query, showing how many members each part of it matched
+#[poise::command(slash_command, ephemeral)]
+async fn explain(
+    ctx: Context<'_>,
+    #[description = "The DRQL query to explain (DO NOT include @{})"] query: String,
+) -> Result<(), anyhow::Error> {
+    // Evaluating the query can take longer than the few seconds Discord waits for a response
+    ctx.defer_ephemeral().await?;
+
+    let guild = ctx
+        .guild()
+        .context("DRQL queries are not available in DMs")?;
+    let member = ctx.author_member().await.context("Error fetching member")?;
+    let channel = ctx
+        .guild_channel()
+        .await
+        .context("Error fetching channel")?
+        .permission_channel(ctx.serenity_context())
+        .await?;
+
+    let config = ctx.data().config.get(guild.id);
+    config.access.check(
+        &member.roles,
+        channel.permissions_for_user(ctx.serenity_context(), member.user.id)?,
+        config.language,
+    )?;
+
+    let context = QueryContext::new(
+        ctx.serenity_context(),
+        &ctx.data().activity,
+        &guild,
+        &member,
+        &channel,
+        &config,
+    );
+    let tree = explain_query(&context, &[&query]).await?;
+    ctx.say(format!("```\n{}\n```", tree.replace('`', "\u{2cb}")))
+        .await?;
+
+    Ok(())
+}
+
+/// Show each check that decides whether a member can mention a role in this channel
+#[poise::command(slash_command, ephemeral)]
+async fn permissions(
+    ctx: Context<'_>,
+    #[description = "The role to check"] role: serenity::Role,
+    #[description = "The member to check for (defaults to you)"] member: Option<serenity::Member>,
+) -> Result<(), anyhow::Error> {
+    let member = match member {
+        Some(member) => member,
+        None => ctx
+            .author_member()
+            .await
+            .context("Error fetching member")?
+            .into_owned(),
+    };
+    let channel = ctx
+        .guild_channel()
+        .await
+        .context("Error fetching channel")?
+        .permission_channel(ctx.serenity_context())
+        .await?;
+
+    let language = ctx.data().language(ctx.guild_id());
+    let checks = member.role_mention_checks(ctx.serenity_context(), &role, &channel)?;
+    let verdict = if checks.iter().any(|(_, passed)| *passed) {
+        "debug-can-mention"
+    } else {
+        "debug-cannot-mention"
+    };
+    ctx.say(format!(
+        "{}\n\n{}",
+        i18n::message(
+            language,
+            verdict,
+            &[
+                ("member", &member.user.id.mention()),
+                ("role", &role.id.mention()),
+                ("channel", &channel.id.mention()),
+            ],
+        ),
+        checks
+            .iter()
+            .map(|(check, passed)| format!(
+                "{} {}",
+                if *passed { "\u{2705}" } else { "\u{274c}" },
+                check.explain(language)
+            ))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Time each stage of evaluating the queries in a message
+#[poise::command(slash_command, ephemeral)]
+async fn bench(
+    ctx: Context<'_>,
+    #[description = "The message to scan for queries in"] msg: String,
+) -> Result<(), anyhow::Error> {
+    // Evaluating the query can take longer than the few seconds Discord waits for a response
+    ctx.defer_ephemeral().await?;
+
+    let guild = ctx
+        .guild()
+        .context("DRQL queries are not available in DMs")?;
+    let member = ctx.author_member().await.context("Error fetching member")?;
+    let channel = ctx
+        .guild_channel()
+        .await
+        .context("Error fetching channel")?
+        .permission_channel(ctx.serenity_context())
+        .await?;
+
+    let config = ctx.data().config.get(guild.id);
+    config.access.check(
+        &member.roles,
+        channel.permissions_for_user(ctx.serenity_context(), member.user.id)?,
+        config.language,
+    )?;
+
+    let context = QueryContext::new(
+        ctx.serenity_context(),
+        &ctx.data().activity,
+        &guild,
+        &member,
+        &channel,
+        &config,
+    );
+    let bench = bench_query(&context, &msg).await?;
+    ctx.say(bench.report(config.language)).await?;
+
+    Ok(())
+}
@@ -1,22 +1,168 @@
-use std::{borrow::Cow, fmt::Write as _};
+use std::{borrow::Cow, future::Future};
 
-use anyhow::{bail, Context as _};
+use anyhow::Context as _;
 use poise::serenity_prelude::{self as serenity};
-use tracing::{debug, trace};
+use tokio::sync::watch;
+use tracing::{debug, trace, warn};
 
-use super::super::Context;
-use crate::{extensions::CustomGuildImpl, models, parse_and_evaluate_query, util};
+use super::{
+    super::{drql, Context},
+    pager,
+};
+use crate::{
+    error::QueryError,
+    export::{ExportFormat, ExportedMember},
+    extensions::{CustomGuildChannelImpl, CustomGuildImpl},
+    i18n::{self, Language},
+    models,
+    pipeline::{
+        self, parse_and_evaluate_query, Evaluation, QueryContext, EVALUATION_PROGRESS_DELAY,
+    },
+    util,
+};
+
+/// Set up a reply to a dry run, which is only shown to its author unless `public`.
+///
+/// The reply never pings anyone, as the mentions in it are only there to show who the query would
+/// mention.
+fn dry_run_reply<'a, 'att>(
+    reply: &'a mut poise::CreateReply<'att>,
+    public: bool,
+) -> &'a mut poise::CreateReply<'att> {
+    reply
+        .ephemeral(!public)
+        .allowed_mentions(|allowed_mentions| {
+            allowed_mentions.empty_parse().empty_users().empty_roles()
+        })
+}
+
+/// Wait for an evaluation to finish, showing its progress in `language` as it resolves the `total`
+/// operands of the query if it takes a while.
+async fn evaluate_with_progress(
+    ctx: Context<'_>,
+    evaluation: impl Future<Output = Result<Evaluation, QueryError>> + Send,
+    resolved: &mut watch::Receiver<usize>,
+    total: usize,
+    public: bool,
+    language: Language,
+) -> Result<Evaluation, QueryError> {
+    tokio::pin!(evaluation);
+    tokio::select! {
+        biased;
+        result = &mut evaluation => return result,
+        () = tokio::time::sleep(EVALUATION_PROGRESS_DELAY) => {}
+    }
+
+    let progress_message = |resolved: usize| {
+        i18n::message(
+            language,
+            "evaluating",
+            &[("resolved", &resolved), ("total", &total)],
+        )
+    };
+    let count = *resolved.borrow_and_update();
+    let interim = match ctx
+        .send(|reply| dry_run_reply(reply, public).content(progress_message(count)))
+        .await
+    {
+        Ok(interim) => interim,
+        Err(err) => {
+            // Progress is only informational, so failing to show it shouldn't stop the query
+            warn!("Unable to show the progress of an evaluation: {err}");
+            return evaluation.await;
+        }
+    };
+
+    let result = loop {
+        tokio::select! {
+            biased;
+            result = &mut evaluation => break result,
+            Ok(()) = resolved.changed() => {
+                let count = *resolved.borrow_and_update();
+                if let Err(err) = interim.edit(ctx, |reply| reply.content(progress_message(count))).await {
+                    warn!("Unable to update the progress of an evaluation: {err}");
+                }
+            }
+        }
+    };
+    if let Err(err) = interim.delete(ctx).await {
+        warn!("Unable to delete the progress of an evaluation: {err}");
+    }
+
+    result
+}
+
+/// Suggest the guild's role names, and names like `everyone`, to finish the query being typed.
+async fn autocomplete_query(ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let Some(guild) = ctx.guild() else {
+        return Vec::new().into_iter();
+    };
+    // The `@everyone` role shares the guild's ID, and is suggested as `everyone` already
+    let mut names = guild
+        .roles
+        .values()
+        .filter(|role| role.id.0 != guild.id.0)
+        .map(|role| role.name.as_str())
+        .collect::<Vec<_>>();
+    names.sort_unstable_by_key(|name| name.to_lowercase());
+    drql::completion::complete(partial, names).into_iter()
+}
 
 /// Run a DRQL query and test what it would do
-#[poise::command(slash_command, ephemeral)]
-#[allow(clippy::too_many_lines)]
+#[poise::command(slash_command)]
 pub async fn dry_run(
     ctx: Context<'_>,
-    #[description = "The query you would like to test"] query: String,
+    #[description = "The query you would like to test"]
+    #[autocomplete = "autocomplete_query"]
+    query: String,
+    #[description = "The format to attach the matched members in, instead of listing them in the \
+                     reply"]
+    format: Option<ExportFormat>,
+    #[description = "Show the result to everyone in the channel, rather than just you (without \
+                     pinging anyone)"]
+    public: Option<bool>,
+) -> Result<(), anyhow::Error> {
+    run_dry_run(ctx, query, format, public.unwrap_or(false)).await
+}
+
+/// Run a DRQL query and test what it would do, from a message starting with the server's prefix
+///
+/// Like the message, the reply can be seen by everyone in the channel, but it doesn't ping anyone.
+#[poise::command(prefix_command, aliases("dry_run"), hide_in_help)]
+pub async fn text_dry_run(
+    ctx: Context<'_>,
+    #[description = "The query you would like to test"]
+    #[rest]
+    query: String,
+) -> Result<(), anyhow::Error> {
+    run_dry_run(ctx, query, None, true).await
+}
+
+/// Test what `query` would do, attaching the members it matches in `format` if one is chosen, and
+/// showing the result to everyone in the channel if `public`.
+#[allow(clippy::too_many_lines)]
+async fn run_dry_run(
+    ctx: Context<'_>,
+    query: String,
+    format: Option<ExportFormat>,
+    public: bool,
 ) -> Result<(), anyhow::Error> {
     if ctx.guild().is_none() {
         debug!("Ignoring DRQL query sent in DMs.");
-        bail!("DRQL queries are not available in DMs.");
+        return Err(QueryError::ResolutionError(i18n::message(
+            Language::English,
+            "dm-queries-unavailable",
+            &[],
+        ))
+        .into());
+    }
+
+    // On large guilds, fetching everything and evaluating the query can take longer than the few
+    // seconds Discord waits for a response
+    if public {
+        ctx.defer().await?;
+    } else {
+        ctx.defer_ephemeral().await?;
     }
 
     trace!("Fetching guild, channel, and member information");
@@ -25,15 +171,56 @@ pub async fn dry_run(
     let channel = ctx
         .guild_channel()
         .await
-        .context("Error fetching channel")?;
+        .context("Error fetching channel")?
+        .permission_channel(ctx.serenity_context())
+        .await?;
+
+    let config = ctx.data().config.get(guild.id);
+    config.access.check(
+        &member.roles,
+        channel.permissions_for_user(ctx.serenity_context(), member.user.id)?,
+        config.language,
+    )?;
 
     trace!("Running DRQL parser/interpreter on message");
-    let members_to_ping =
-        parse_and_evaluate_query(ctx.serenity_context(), &[&query], &guild, &member, &channel)
-            .await?;
+    let total =
+        pipeline::parse_chunks(&[&query], &config.aliases, config.language)?.operand_count();
+    let (progress, mut resolved) = watch::channel(0);
+    let query_context = QueryContext::new(
+        ctx.serenity_context(),
+        &ctx.data().activity,
+        &guild,
+        &member,
+        &channel,
+        &config,
+    );
+    let Evaluation {
+        members: members_to_ping,
+        unmentionable_roles,
+        timed_out,
+        excluded,
+        breakdown,
+    } = evaluate_with_progress(
+        ctx,
+        parse_and_evaluate_query(
+            &query_context,
+            &ctx.data().query_cache,
+            &[&query],
+            Some(&progress),
+        ),
+        &mut resolved,
+        total,
+        public,
+        config.language,
+    )
+    .await?;
 
     // A hashmap of every role in the guild and its members.
-    let roles_and_their_members = guild.all_roles_and_members(ctx.serenity_context())?;
+    let mut roles_and_their_members = guild.all_roles_and_members(ctx.serenity_context())?;
+    // These roles will never be mentioned on the author's behalf
+    for role in &unmentionable_roles {
+        roles_and_their_members.remove(&models::mention::RoleType::Role(*role));
+    }
 
     // next, we represent the list of users as a bunch of roles containing them and one outliers set.
     let util::unionize_set::UnionizeSetResult { sets, outliers } =
@@ -53,28 +240,71 @@ pub async fn dry_run(
 
     debug!("dry run result: {stringified_mentions:?}");
 
+    let timed_out_note = if timed_out.is_empty() {
+        String::new()
+    } else {
+        let operands = timed_out
+            .iter()
+            .map(|operand| format!("`{operand}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            " {}",
+            i18n::message(
+                config.language,
+                "dry-run-timed-out",
+                &[("operands", &operands)],
+            )
+        )
+    };
+
+    // How many members each part of the query matched, to explain where the result came from
+    let breakdown_note = match breakdown.split_last() {
+        None => String::new(),
+        Some(((operator, total), operands)) => {
+            let parts = operands
+                .iter()
+                .map(|(operand, count)| format!("`{}`: {count}", operand.replace('`', "\u{2cb}")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                " {}",
+                i18n::message(
+                    config.language,
+                    "dry-run-breakdown",
+                    &[("parts", &parts), ("operator", operator), ("total", total)],
+                )
+            )
+        }
+    };
+
+    let excluded_note = if excluded.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " {}",
+            i18n::message(
+                config.language,
+                "query-excluded",
+                &[("count", &excluded.len())],
+            )
+        )
+    };
+
     if stringified_mentions.is_empty() {
         debug!("Nobody to mention!");
-        ctx.say("Your query matches 0 users.").await?;
+        ctx.send(|reply| {
+            dry_run_reply(reply, public).content(format!(
+                "{}{breakdown_note}{timed_out_note}{excluded_note}",
+                i18n::message(config.language, "query-matches-none", &[])
+            ))
+        })
+        .await?;
         return Ok(());
     }
 
-    let message_count_if_optimized = util::wrap_string_vec(
-        &sets
-            .iter()
-            .copied()
-            .map(|id| models::mention::Mention::Role(*id))
-            .chain(
-                outliers
-                    .iter()
-                    .map(|&id| models::mention::Mention::User(*id)),
-            )
-            .map(|x| x.to_string())
-            .collect::<Vec<_>>(),
-        " ",
-        2000,
-    )?
-    .len();
+    let message_count_if_optimized =
+        pipeline::count_messages(&members_to_ping, &roles_and_their_members);
 
     debug!(
         "stringified_mentions: {stringified_mentions:?}",
@@ -82,61 +312,120 @@ pub async fn dry_run(
     );
 
     let message_header = format!(
-        "Your query matches the following {} users:\n",
-        stringified_mentions.len()
+        "{}\n",
+        i18n::message(
+            config.language,
+            "dry-run-header",
+            &[("count", &stringified_mentions.len())],
+        )
     );
+    let unmentionable_note = if unmentionable_roles.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " {}",
+            i18n::message(
+                config.language,
+                "dry-run-unmentionable",
+                &[("count", &unmentionable_roles.len())],
+            )
+        )
+    };
+    let saved = stringified_mentions.len() - (sets.len() + outliers.len());
     let message_footer = format!(
-        concat!(
-            "\n\nThis will require sending {} messages.",
-            " (optimized by pinging {} roles, saving you {} mentions)."
-        ),
-        message_count_if_optimized,
-        sets.len(),
-        stringified_mentions.len() - (sets.len() + outliers.len())
+        "\n\n{}{breakdown_note}{unmentionable_note}{timed_out_note}{excluded_note}",
+        i18n::message(
+            config.language,
+            "dry-run-footer",
+            &[
+                ("messages", &message_count_if_optimized),
+                ("roles", &sets.len()),
+                ("saved", &saved),
+            ],
+        )
     );
 
-    if stringified_mentions.join(" ").len() <= (2000 - message_header.len() - message_footer.len())
+    if format.is_none()
+        && stringified_mentions.join(" ").len()
+            <= (2000 - message_header.len() - message_footer.len())
     {
         debug!("All mentions fit in one message!");
-        ctx.say(format!(
-            "{}{}{}",
-            message_header,
-            stringified_mentions.join(" "),
-            message_footer
-        ))
+        ctx.send(|reply| {
+            dry_run_reply(reply, public).content(format!(
+                "{}{}{}",
+                message_header,
+                stringified_mentions.join(" "),
+                message_footer
+            ))
+        })
         .await?;
         return Ok(());
     }
 
-    debug!("Mentions do not fit in one message, using text file");
+    let content = format!(
+        "{}{breakdown_note}{unmentionable_note}{timed_out_note}{excluded_note}",
+        i18n::message(
+            config.language,
+            if format.is_some() {
+                "dry-run-summary-attached"
+            } else {
+                "dry-run-summary-below"
+            },
+            &[
+                ("count", &stringified_mentions.len()),
+                ("messages", &message_count_if_optimized),
+                ("roles", &sets.len()),
+                ("saved", &saved),
+            ],
+        )
+    );
+
+    let Some(format) = format else {
+        debug!("Mentions do not fit in one message, paging through them");
+        let mut members = members_to_ping.into_iter().collect::<Vec<_>>();
+        members.sort_unstable();
+        let mentions = members
+            .into_iter()
+            .map(|id| models::mention::Mention::User(id).to_string())
+            .collect::<Vec<_>>();
+        // Mentions in embeds never ping anyone, so the members can be listed as mentions
+        return pager::page_through(
+            ctx,
+            content,
+            &i18n::message(config.language, "matched-members", &[]),
+            &mentions,
+            public,
+            config.language,
+        )
+        .await;
+    };
+
+    debug!("A format was chosen, attaching a file");
 
-    let mut file_contents = String::new();
+    let mut exported = Vec::with_capacity(members_to_ping.len());
     for id in &members_to_ping {
         let member = guild.member(ctx.serenity_context(), *id).await?;
-        writeln!(
-            &mut file_contents,
-            "{}#{} ({})",
-            member.user.name, member.user.discriminator, member.user.id
-        )?;
+        exported.push(ExportedMember {
+            id: member.user.id,
+            username: member.user.name.clone(),
+            discriminator: member.user.discriminator,
+            nickname: member.nick.clone(),
+            roles: member
+                .roles
+                .iter()
+                .filter_map(|role| Some(guild.roles.get(role)?.name.clone()))
+                .collect(),
+        });
     }
+    let file_contents = format.write(&exported)?;
 
     ctx.send(|builder| {
-        builder
-            .content(format!(
-                concat!(
-                    "Your query matches the attached {} users.",
-                    " This will require sending {} messages",
-                    " (optimized by pinging {} roles, saving you {} mentions)."
-                ),
-                stringified_mentions.len(),
-                message_count_if_optimized,
-                sets.len(),
-                stringified_mentions.len() - (sets.len() + outliers.len())
-            ))
-            .attachment(serenity::AttachmentType::Bytes {
+        dry_run_reply(builder, public).content(content).attachment(
+            serenity::AttachmentType::Bytes {
                 data: Cow::Borrowed(file_contents.as_bytes()),
-                filename: "dry_run.txt".to_string(),
-            })
+                filename: format.file_name().to_string(),
+            },
+        )
     })
     .await?;
 
@@ -0,0 +1,76 @@
+use anyhow::{bail, Context as _};
+use poise::serenity_prelude::UserId;
+
+use super::{super::Context, pager};
+use crate::{
+    history::{HistoryEntry, HISTORY_LENGTH},
+    i18n::{self, Language},
+};
+
+/// Describe a notification in a guild's history in `language`, like
+/// `<t:1:R> <@1> `@{staff}`: 2 members`.
+fn history_line(entry: &HistoryEntry, language: Language) -> String {
+    i18n::message_count(
+        language,
+        "history-line",
+        entry.members,
+        &[
+            ("sent", &entry.sent_at.unix_timestamp()),
+            ("author", &entry.author),
+            ("query", &entry.query),
+        ],
+    )
+}
+
+/// List the recent notifications in this server, only including those sent by `author` if given.
+async fn list_history(ctx: Context<'_>, author: Option<UserId>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let entries = ctx.data().history.recent(guild_id, author);
+    if entries.is_empty() {
+        ctx.say(i18n::message(language, "history-empty", &[]))
+            .await?;
+        return Ok(());
+    }
+
+    let lines = entries
+        .iter()
+        .map(|entry| history_line(entry, language))
+        .collect::<Vec<_>>();
+    pager::page_through(
+        ctx,
+        i18n::message(
+            language,
+            "history-list",
+            &[("count", &lines.len()), ("limit", &HISTORY_LENGTH)],
+        ),
+        &i18n::message(language, "history-title", &[]),
+        &lines,
+        false,
+        language,
+    )
+    .await
+}
+
+/// See the notifications recently sent with Intersection
+#[poise::command(slash_command, guild_only, ephemeral, subcommands("server", "me"))]
+pub async fn history(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// See the notifications recently sent by anyone in this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    ephemeral,
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn server(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    list_history(ctx, None).await
+}
+
+/// See the notifications you recently sent in this server
+#[poise::command(slash_command, guild_only, ephemeral)]
+async fn me(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    list_history(ctx, Some(ctx.author().id)).await
+}
@@ -0,0 +1,122 @@
+use anyhow::Context as _;
+use tracing::{debug, trace};
+
+use super::{super::Context, pager};
+use crate::{
+    error::QueryError,
+    extensions::CustomGuildChannelImpl,
+    i18n::{self, Language},
+    pipeline::{parse_and_evaluate_query, Evaluation, QueryContext},
+};
+
+/// Escape the characters in `name` which Discord would read as markdown, so it's shown as written.
+fn escape_markdown(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for char in name.chars() {
+        if matches!(
+            char,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '#' | '-' | '[' | ']'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+    escaped
+}
+
+/// List the names of the members a DRQL query matches, without mentioning anyone
+#[poise::command(slash_command, prefix_command, ephemeral)]
+pub async fn members(
+    ctx: Context<'_>,
+    #[description = "The query to list the members of"]
+    #[rest]
+    query: String,
+) -> Result<(), anyhow::Error> {
+    if ctx.guild().is_none() {
+        debug!("Ignoring DRQL query sent in DMs.");
+        return Err(QueryError::ResolutionError(i18n::message(
+            Language::English,
+            "dm-queries-unavailable",
+            &[],
+        ))
+        .into());
+    }
+
+    // On large guilds, fetching everything and evaluating the query can take longer than the few
+    // seconds Discord waits for a response
+    ctx.defer_ephemeral().await?;
+
+    trace!("Fetching guild, channel, and member information");
+    let guild = ctx.guild().context("Unable to resolve guild")?;
+    let member = ctx.author_member().await.context("Error fetching member")?;
+    let channel = ctx
+        .guild_channel()
+        .await
+        .context("Error fetching channel")?
+        .permission_channel(ctx.serenity_context())
+        .await?;
+
+    let config = ctx.data().config.get(guild.id);
+    config.access.check(
+        &member.roles,
+        channel.permissions_for_user(ctx.serenity_context(), member.user.id)?,
+        config.language,
+    )?;
+
+    let context = QueryContext::new(
+        ctx.serenity_context(),
+        &ctx.data().activity,
+        &guild,
+        &member,
+        &channel,
+        &config,
+    );
+    let Evaluation {
+        members, excluded, ..
+    } = parse_and_evaluate_query(&context, &ctx.data().query_cache, &[&query], None).await?;
+
+    let mut names = Vec::with_capacity(members.len());
+    for id in &members {
+        let member = guild.member(ctx.serenity_context(), *id).await?;
+        names.push(member.display_name().into_owned());
+    }
+    names.sort_unstable_by_key(|name| name.to_lowercase());
+
+    let excluded_note = if excluded.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " {}",
+            i18n::message(
+                config.language,
+                "query-excluded",
+                &[("count", &excluded.len())],
+            )
+        )
+    };
+    if names.is_empty() {
+        ctx.say(format!(
+            "{}{excluded_note}",
+            i18n::message(config.language, "query-matches-none", &[])
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let names = names
+        .iter()
+        .map(|name| escape_markdown(name))
+        .collect::<Vec<_>>();
+    pager::page_through(
+        ctx,
+        format!(
+            "{}{excluded_note}",
+            i18n::message(config.language, "members-below", &[("count", &names.len())],)
+        ),
+        &i18n::message(config.language, "matched-members", &[]),
+        &names,
+        false,
+        config.language,
+    )
+    .await
+}
@@ -0,0 +1,148 @@
+//! Replies listing more than fits in a message, a page at a time
+
+use std::time::Duration;
+
+use anyhow::Context as _;
+use poise::serenity_prelude as serenity;
+use tracing::trace;
+
+use super::super::Context;
+use crate::i18n::{self, Language};
+
+/// How many lines are listed on each page
+const PAGE_SIZE: usize = 25;
+
+/// How long the buttons paging through a list keep working after they were last pressed
+const PAGER_TIMEOUT: Duration = Duration::from_mins(2);
+
+/// The custom ID of the button showing the previous page of a list
+const PREVIOUS_BUTTON: &str = "pager_previous";
+
+/// The custom ID of the button showing the next page of a list
+const NEXT_BUTTON: &str = "pager_next";
+
+/// Show one page of `lines` under `title`, the `page`th of `pages`, in `embed`, with its footer in
+/// `language`.
+fn page_embed<'a>(
+    embed: &'a mut serenity::CreateEmbed,
+    title: &str,
+    lines: &[String],
+    page: usize,
+    pages: usize,
+    language: Language,
+) -> &'a mut serenity::CreateEmbed {
+    embed
+        .title(title)
+        .description(
+            lines
+                .iter()
+                .skip(page * PAGE_SIZE)
+                .take(PAGE_SIZE)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+        .footer(|footer| {
+            footer.text(i18n::message(
+                language,
+                "page-footer",
+                &[("page", &(page + 1)), ("pages", &pages)],
+            ))
+        })
+}
+
+/// Add the buttons moving to the page before and after the `page`th of `pages` to `components`,
+/// labelled in `language`, which are all disabled once they stop working.
+fn pager_buttons(
+    components: &mut serenity::CreateComponents,
+    page: usize,
+    pages: usize,
+    disabled: bool,
+    language: Language,
+) -> &mut serenity::CreateComponents {
+    components.create_action_row(|action_row| {
+        action_row
+            .create_button(|button| {
+                button
+                    .custom_id(PREVIOUS_BUTTON)
+                    .label(i18n::message(language, "page-previous", &[]))
+                    .style(serenity::ButtonStyle::Secondary)
+                    .disabled(disabled || page == 0)
+            })
+            .create_button(|button| {
+                button
+                    .custom_id(NEXT_BUTTON)
+                    .label(i18n::message(language, "page-next", &[]))
+                    .style(serenity::ButtonStyle::Secondary)
+                    .disabled(disabled || page + 1 >= pages)
+            })
+    })
+}
+
+/// Reply with `content` and `lines` in an embed titled `title`, [`PAGE_SIZE`] at a time, which the
+/// author can page through with buttons until they haven't pressed one for [`PAGER_TIMEOUT`].
+///
+/// The reply is only shown to the author unless `public`, and never pings anyone. Its footer and
+/// buttons are in `language`.
+pub async fn page_through(
+    ctx: Context<'_>,
+    content: String,
+    title: &str,
+    lines: &[String],
+    public: bool,
+    language: Language,
+) -> Result<(), anyhow::Error> {
+    let pages = lines.len().div_ceil(PAGE_SIZE).max(1);
+    let mut page = 0;
+    let reply = ctx
+        .send(|builder| {
+            builder
+                .ephemeral(!public)
+                .allowed_mentions(|allowed_mentions| {
+                    allowed_mentions.empty_parse().empty_users().empty_roles()
+                })
+                .content(content)
+                .embed(|embed| page_embed(embed, title, lines, page, pages, language))
+                .components(|components| pager_buttons(components, page, pages, false, language))
+        })
+        .await?;
+    let message = reply.message().await?;
+
+    while let Some(interaction) = serenity::CollectComponentInteraction::new(ctx.serenity_context())
+        .message_id(message.id)
+        .author_id(ctx.author().id)
+        .collect_limit(1)
+        .timeout(PAGER_TIMEOUT)
+        .await
+    {
+        page = match interaction.data.custom_id.as_str() {
+            PREVIOUS_BUTTON => page.saturating_sub(1),
+            NEXT_BUTTON => (page + 1).min(pages - 1),
+            _ => page,
+        };
+        interaction
+            .create_interaction_response(ctx.serenity_context(), |response| {
+                response
+                    .kind(serenity::InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|data| {
+                        data.embed(|embed| page_embed(embed, title, lines, page, pages, language))
+                            .components(|components| {
+                                pager_buttons(components, page, pages, false, language)
+                            })
+                    })
+            })
+            .await
+            .context("Error changing page")?;
+    }
+
+    trace!("Pager timed out, disabling its buttons");
+    reply
+        .edit(ctx, |builder| {
+            builder
+                .embed(|embed| page_embed(embed, title, lines, page, pages, language))
+                .components(|components| pager_buttons(components, page, pages, true, language))
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Context as _;
+
+use super::super::Context;
+use crate::i18n;
+
+/// Stop handling queries in this server until someone runs /resume, like while pings are abused
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn pause(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let author = ctx.author().id;
+    let mut already_paused_by = None;
+    ctx.data().config.update(guild_id, |config| {
+        already_paused_by = config.paused_by;
+        config.paused_by.get_or_insert(author);
+    });
+    let language = ctx.data().language(Some(guild_id));
+
+    ctx.send(|builder| {
+        builder
+            .allowed_mentions(|allowed_mentions| {
+                allowed_mentions.empty_parse().empty_users().empty_roles()
+            })
+            .content(already_paused_by.map_or_else(
+                || i18n::message(language, "paused", &[]),
+                |paused_by| i18n::message(language, "already-paused", &[("author", &paused_by)]),
+            ))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Handle queries in this server again, after /pause
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn resume(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let mut paused_by = None;
+    ctx.data().config.update(guild_id, |config| {
+        paused_by = config.paused_by.take();
+    });
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        if paused_by.is_some() {
+            "resumed"
+        } else {
+            "not-paused"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
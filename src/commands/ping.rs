@@ -2,12 +2,14 @@ use anyhow::Context as _;
 use poise::serenity_prelude::ShardId;
 
 use super::super::Context;
+use crate::i18n;
 
 /// Check if Intersection is online
 #[poise::command(slash_command)]
 #[allow(clippy::significant_drop_tightening)] // faulty rule in this case i think -- needs investigation
 pub async fn ping(ctx: Context<'_>) -> Result<(), anyhow::Error> {
-    let response = ctx.say("Ping?").await?;
+    let language = ctx.data().language(ctx.guild_id());
+    let response = ctx.say(i18n::message(language, "ping", &[])).await?;
 
     let poise::Context::Application(new_ctx) = ctx else {
         panic!();
@@ -28,13 +30,19 @@ pub async fn ping(ctx: Context<'_>) -> Result<(), anyhow::Error> {
 
     response
         .edit(ctx, |edit_handle| {
-            edit_handle.content(format!(
-                "Pong :ping_pong:! (Round trip: {}ms. Heartbeat: {}.)",
-                diff_ms,
-                shard_latency.map_or_else(
-                    || "unknown".to_string(),
-                    |latency| format!("{}ms", latency.as_millis())
-                )
+            edit_handle.content(i18n::message(
+                language,
+                "pong",
+                &[
+                    ("round-trip", &diff_ms),
+                    (
+                        "heartbeat",
+                        &shard_latency.map_or_else(
+                            || i18n::message(language, "heartbeat-unknown", &[]),
+                            |latency| format!("{}ms", latency.as_millis()),
+                        ),
+                    ),
+                ],
             ))
         })
         .await?;
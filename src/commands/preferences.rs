@@ -0,0 +1,110 @@
+use anyhow::{bail, Context as _};
+
+use super::super::Context;
+use crate::{i18n, quiet_hours::QuietHours};
+
+/// Change how Intersection notifies you
+#[poise::command(slash_command, subcommands("dm"))]
+pub async fn preferences(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// Choose whether you're notified by DM instead of being mentioned in the channel
+#[poise::command(slash_command, ephemeral)]
+async fn dm(
+    ctx: Context<'_>,
+    #[description = "Whether you should be notified by DM"] enabled: bool,
+) -> Result<(), anyhow::Error> {
+    ctx.data()
+        .preferences
+        .set_dm_notifications(ctx.author().id, enabled);
+
+    ctx.say(i18n::message(
+        ctx.data().language(ctx.guild_id()),
+        if enabled {
+            "preferences-dm-on"
+        } else {
+            "preferences-dm-off"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Stop being notified by queries in this server
+#[poise::command(slash_command, guild_only, ephemeral)]
+pub async fn optout(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    ctx.data()
+        .preferences
+        .set_opted_out(guild_id, ctx.author().id, true);
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        "opted-out",
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Be notified by queries in this server again, after /optout
+#[poise::command(slash_command, guild_only, ephemeral)]
+pub async fn optin(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let preferences = &ctx.data().preferences;
+    let had_opted_out = preferences.has_opted_out(guild_id, ctx.author().id);
+    preferences.set_opted_out(guild_id, ctx.author().id, false);
+
+    ctx.say(i18n::message(
+        ctx.data().language(Some(guild_id)),
+        if had_opted_out {
+            "opted-in"
+        } else {
+            "not-opted-out"
+        },
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Choose daily quiet hours in this server, during which queries don't notify you
+#[poise::command(slash_command, guild_only, ephemeral)]
+pub async fn dnd(
+    ctx: Context<'_>,
+    #[description = "Your quiet hours, like 22:00-08:00, or \"off\" to stop having any"]
+    window: String,
+    #[description = "Your time zone as an offset from UTC, like UTC+2 (UTC if not given)"]
+    tz: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let preferences = &ctx.data().preferences;
+    if window.trim().eq_ignore_ascii_case("off") {
+        preferences.set_quiet_hours(guild_id, ctx.author().id, None);
+        ctx.say(i18n::message(language, "quiet-hours-off", &[]))
+            .await?;
+        return Ok(());
+    }
+
+    let Some(hours) = QuietHours::parse(&window, tz.as_deref()) else {
+        ctx.say(i18n::message(language, "quiet-hours-invalid", &[]))
+            .await?;
+        return Ok(());
+    };
+    preferences.set_quiet_hours(guild_id, ctx.author().id, Some(hours));
+
+    ctx.say(i18n::message(
+        language,
+        "quiet-hours-set",
+        &[("hours", &hours)],
+    ))
+    .await?;
+
+    Ok(())
+}
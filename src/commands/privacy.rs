@@ -0,0 +1,29 @@
+use anyhow::bail;
+
+use super::super::Context;
+use crate::i18n;
+
+/// Manage the data Intersection keeps about you
+#[poise::command(slash_command, subcommands("delete"))]
+pub async fn privacy(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// Delete everything Intersection remembers about you, in every server
+#[poise::command(slash_command, ephemeral)]
+async fn delete(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let user = ctx.author().id;
+    let data = ctx.data();
+    data.history.forget_user(user);
+    data.preferences.forget_user(user);
+    data.activity.forget_user(user);
+
+    ctx.say(i18n::message(
+        data.language(ctx.guild_id()),
+        "privacy-deleted",
+        &[],
+    ))
+    .await?;
+
+    Ok(())
+}
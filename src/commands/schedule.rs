@@ -0,0 +1,283 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context as _};
+use poise::serenity_prelude as serenity;
+
+use super::{super::Context, pager};
+use crate::{
+    extensions::CustomGuildChannelImpl,
+    i18n, pipeline,
+    scheduler::{self, Recurrence, ScheduledQuery},
+};
+
+/// The most queries a server may have scheduled at once
+const MAX_SCHEDULED: usize = 25;
+
+/// How far ahead a query may be scheduled
+const MAX_SCHEDULE_AHEAD: Duration = Duration::from_hours(365 * 24);
+
+/// Check the author may run `query` in this channel, and that this server may schedule another
+/// query, replying and returning `false` if it can't.
+async fn check_schedulable(
+    ctx: Context<'_>,
+    guild_id: serenity::GuildId,
+    query: &str,
+) -> Result<bool, anyhow::Error> {
+    let member = ctx.author_member().await.context("Error fetching member")?;
+    let channel = ctx
+        .guild_channel()
+        .await
+        .context("Error fetching channel")?
+        .permission_channel(ctx.serenity_context())
+        .await?;
+
+    // Everything is checked again when the query is run, but it's better to find out now
+    let config = ctx.data().config.get(guild_id);
+    config.access.check(
+        &member.roles,
+        channel.permissions_for_user(ctx.serenity_context(), member.user.id)?,
+        config.language,
+    )?;
+    pipeline::parse_chunks(&[query], &config.aliases, config.language)?;
+
+    if ctx.data().schedule.count(guild_id) >= MAX_SCHEDULED {
+        ctx.say(i18n::message(
+            config.language,
+            "schedule-limit",
+            &[("limit", &MAX_SCHEDULED)],
+        ))
+        .await?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Schedule `query` to run in this channel at `at`, and again as often as `repeat` says, then
+/// reply with when it'll run.
+async fn add(
+    ctx: Context<'_>,
+    guild_id: serenity::GuildId,
+    query: String,
+    at: SystemTime,
+    repeat: Option<Recurrence>,
+) -> Result<(), anyhow::Error> {
+    let language = ctx.data().language(Some(guild_id));
+    let description = pipeline::describe_query(&[&query]);
+    let id = ctx.data().schedule.add(ScheduledQuery {
+        guild: guild_id,
+        channel: ctx.channel_id(),
+        author: ctx.author().id,
+        at,
+        repeat,
+        query,
+    });
+
+    let at = scheduler::timestamp(at);
+    let content = repeat.map_or_else(
+        || {
+            i18n::message(
+                language,
+                "schedule-added",
+                &[("query", &description), ("at", &at), ("id", &id)],
+            )
+        },
+        |repeat| {
+            i18n::message(
+                language,
+                "schedule-added-recurring",
+                &[
+                    ("query", &description),
+                    ("repeat", &repeat.describe(language)),
+                    ("at", &at),
+                    ("id", &id),
+                ],
+            )
+        },
+    );
+    ctx.send(|builder| {
+        builder
+            .allowed_mentions(|allowed_mentions| {
+                allowed_mentions.empty_parse().empty_users().empty_roles()
+            })
+            .content(content)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Run queries later, like announcement pings before an event
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("run", "repeat", "list", "cancel")
+)]
+pub async fn schedule(_ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    bail!("unreachable");
+}
+
+/// Notify the members a query matches at a later time, as if you sent it in this channel then
+#[poise::command(slash_command, guild_only)]
+async fn run(
+    ctx: Context<'_>,
+    #[description = "The query to run, like raiders & available"]
+    #[max_length = 500]
+    query: String,
+    #[description = "When to run it, like 2024-06-01 18:00"] at: String,
+    #[description = "The time zone the time is in, like UTC+2 or -05:00 (UTC if not given)"]
+    tz: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    if !check_schedulable(ctx, guild_id, &query).await? {
+        return Ok(());
+    }
+
+    let language = ctx.data().language(Some(guild_id));
+    let Some(at) = scheduler::parse_time(&at, tz.as_deref()) else {
+        ctx.say(i18n::message(language, "schedule-invalid-time", &[]))
+            .await?;
+        return Ok(());
+    };
+    let now = SystemTime::now();
+    if at <= now {
+        ctx.say(i18n::message(
+            language,
+            "schedule-passed",
+            &[("at", &scheduler::timestamp(at))],
+        ))
+        .await?;
+        return Ok(());
+    }
+    if at.duration_since(now).unwrap_or_default() > MAX_SCHEDULE_AHEAD {
+        ctx.say(i18n::message(language, "schedule-too-far", &[]))
+            .await?;
+        return Ok(());
+    }
+
+    add(ctx, guild_id, query, at, None).await
+}
+
+/// Notify the members a query matches every day or week, as if you sent it in this channel then
+#[poise::command(slash_command, guild_only)]
+async fn repeat(
+    ctx: Context<'_>,
+    #[description = "The query to run, like raiders & available"]
+    #[max_length = 500]
+    query: String,
+    #[description = "When to run it, like every monday 17:00 or every day 09:30"] when: String,
+    #[description = "The time zone the time is in, like UTC+2 or -05:00 (UTC if not given)"]
+    tz: Option<String>,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    if !check_schedulable(ctx, guild_id, &query).await? {
+        return Ok(());
+    }
+
+    let Some(repeat) = Recurrence::parse(&when, tz.as_deref()) else {
+        ctx.say(i18n::message(
+            ctx.data().language(Some(guild_id)),
+            "schedule-invalid-recurrence",
+            &[],
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    add(
+        ctx,
+        guild_id,
+        query,
+        repeat.next_after(SystemTime::now()),
+        Some(repeat),
+    )
+    .await
+}
+
+/// List the queries scheduled in this server
+#[poise::command(slash_command, guild_only, ephemeral)]
+async fn list(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let scheduled = ctx.data().schedule.in_guild(guild_id);
+    if scheduled.is_empty() {
+        ctx.say(i18n::message(language, "schedule-list-empty", &[]))
+            .await?;
+        return Ok(());
+    }
+
+    let lines = scheduled
+        .iter()
+        .map(|(id, scheduled)| {
+            let repeat = scheduled
+                .repeat
+                .map(|repeat| repeat.describe(language))
+                .unwrap_or_default();
+            i18n::message(
+                language,
+                if scheduled.repeat.is_some() {
+                    "schedule-line-recurring"
+                } else {
+                    "schedule-line"
+                },
+                &[
+                    ("id", id),
+                    ("query", &pipeline::describe_query(&[&scheduled.query])),
+                    ("at", &scheduler::timestamp(scheduled.at)),
+                    ("author", &scheduled.author),
+                    ("channel", &scheduled.channel),
+                    ("repeat", &repeat),
+                ],
+            )
+        })
+        .collect::<Vec<_>>();
+    pager::page_through(
+        ctx,
+        i18n::message(language, "schedule-list", &[("count", &lines.len())]),
+        &i18n::message(language, "schedule-list-title", &[]),
+        &lines,
+        false,
+        language,
+    )
+    .await
+}
+
+/// Stop a scheduled query from running. Only its author or server managers can cancel it
+#[poise::command(slash_command, guild_only, ephemeral)]
+async fn cancel(
+    ctx: Context<'_>,
+    #[description = "The number of the scheduled query, as listed by /schedule list"] id: u64,
+) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let Some(scheduled) = ctx.data().schedule.get(guild_id, id) else {
+        ctx.say(i18n::message(language, "schedule-unknown", &[("id", &id)]))
+            .await?;
+        return Ok(());
+    };
+
+    if scheduled.author != ctx.author().id {
+        let member = ctx.author_member().await.context("Error fetching member")?;
+        if !member.permissions(ctx.serenity_context())?.manage_guild() {
+            ctx.say(i18n::message(
+                language,
+                "schedule-cancel-forbidden",
+                &[("author", &scheduled.author), ("id", &id)],
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    ctx.data().schedule.cancel(guild_id, id);
+    ctx.say(i18n::message(
+        language,
+        "schedule-cancelled",
+        &[
+            ("id", &id),
+            ("query", &pipeline::describe_query(&[&scheduled.query])),
+        ],
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::Context as _;
+
+use super::super::Context;
+use crate::i18n;
+
+/// How many of the most used roles are listed
+const TOP_ROLES: usize = 5;
+
+/// See how much Intersection has been used in this server
+#[poise::command(slash_command, guild_only)]
+pub async fn stats(ctx: Context<'_>) -> Result<(), anyhow::Error> {
+    let guild_id = ctx.guild_id().context("Unable to resolve guild")?;
+    let language = ctx.data().language(Some(guild_id));
+    let stats = ctx.data().stats.get(guild_id);
+
+    let top_roles = stats.most_used_roles(TOP_ROLES);
+    let roles = if top_roles.is_empty() {
+        i18n::message(language, "stats-no-roles", &[])
+    } else {
+        top_roles
+            .iter()
+            .map(|(role, uses)| format!("{role} ({uses})"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    ctx.send(|builder| {
+        builder
+            .allowed_mentions(|allowed_mentions| {
+                allowed_mentions.empty_parse().empty_users().empty_roles()
+            })
+            .embed(|embed| {
+                embed
+                    .title(i18n::message(language, "stats-title", &[]))
+                    .field(
+                        i18n::message(language, "stats-queries", &[]),
+                        stats.queries,
+                        true,
+                    )
+                    .field(
+                        i18n::message(language, "stats-members-pinged", &[]),
+                        stats.members_pinged,
+                        true,
+                    )
+                    .field(
+                        i18n::message(language, "stats-largest-query", &[]),
+                        i18n::message(
+                            language,
+                            "stats-members",
+                            &[("count", &stats.largest_query)],
+                        ),
+                        true,
+                    )
+                    .field(
+                        i18n::message(language, "stats-most-used-roles", &[]),
+                        roles,
+                        false,
+                    )
+                    .footer(|footer| footer.text(i18n::message(language, "stats-footer", &[])));
+                if let Some(since) = stats.since {
+                    embed.field(
+                        i18n::message(language, "stats-since", &[]),
+                        format!("<t:{}:D>", since.unix_timestamp()),
+                        false,
+                    );
+                }
+                embed
+            })
+    })
+    .await?;
+
+    Ok(())
+}
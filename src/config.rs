@@ -0,0 +1,663 @@
+//! Per-guild configuration
+//!
+//! Each guild can override some of Intersection's default behavior. Configuration is written to a
+//! file, if given one, as soon as it changes, with one line for each setting a guild changed from
+//! the default, so it survives restarts.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    hash::Hash,
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use poise::serenity_prelude::{ChannelId, GuildId, RoleId, UserId};
+
+use crate::{
+    access::{QueryAccess, RequiredPermission},
+    channel_filter::{ChannelFilter, ChannelFilterMode},
+    cooldowns::{CooldownSettings, RateLimit},
+    duplicates::{DuplicateQueryMode, DuplicateQuerySettings},
+    error::QueryError,
+    i18n::{self, Language},
+    persistence::{escape, unescape, StoreFile},
+    pipeline::{ConfirmationTimeout, Delivery, ModeratorApproval},
+};
+
+/// Where a guild's queries can be run from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryEntryPoints {
+    /// `@{}` in messages, as well as slash commands
+    #[default]
+    Everywhere,
+    /// Only slash commands, leaving `@{}` in messages alone
+    SlashCommandsOnly,
+}
+
+/// How a role name in a query, like `mods`, is matched against the names of a guild's roles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoleNameMatching {
+    /// Ignoring case, unless a role's name matches exactly
+    #[default]
+    IgnoreCase,
+    /// Only roles with exactly the same name, case included
+    ExactCase,
+}
+
+/// Whether `everyone` and `here` can be used in a guild's queries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EveryoneHere {
+    /// They can, by members allowed to mention everyone
+    #[default]
+    Allowed,
+    /// They can't, even by members allowed to mention everyone, with the guild's explanation why
+    /// if it gave one
+    Refused(Option<String>),
+}
+
+impl EveryoneHere {
+    /// Check whether `literal` (`everyone` or `here`) can be used in a query, failing with
+    /// [`QueryError::PermissionDenied`], explained in `language`, if it can't.
+    pub fn check(&self, literal: &str, language: Language) -> Result<(), QueryError> {
+        match self {
+            Self::Allowed => Ok(()),
+            Self::Refused(explanation) => Err(QueryError::PermissionDenied(i18n::message(
+                language,
+                "everyone-here-refused",
+                &[
+                    ("literal", &literal),
+                    (
+                        "explanation",
+                        &explanation
+                            .as_ref()
+                            .map_or_else(String::new, |explanation| format!(" {explanation}")),
+                    ),
+                ],
+            ))),
+        }
+    }
+}
+
+/// The configuration of a single guild
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuildConfig {
+    /// Whether mention messages are sent silently (without push notifications) by default
+    pub silent: bool,
+    /// Whether each chunk of a query is mentioned separately, instead of their union
+    pub per_chunk: bool,
+    /// Whether notifications are explained in an embed, rather than in the message content
+    pub embed: bool,
+    /// Where queries can be run from
+    pub entry_points: QueryEntryPoints,
+    /// How notifications are delivered
+    pub delivery: Delivery,
+    /// How often notifications may be sent
+    pub cooldowns: CooldownSettings,
+    /// The most members a query may match, above which it is refused outright
+    pub max_mentions: Option<usize>,
+    /// Who may run queries
+    pub access: QueryAccess,
+    /// The channels queries are processed in
+    pub channels: ChannelFilter,
+    /// The channel a summary of every notification is posted to
+    pub audit_channel: Option<ChannelId>,
+    /// The language messages are sent in
+    pub language: Language,
+    /// How long explanations of mistakes in queries are kept before being deleted
+    pub error_expiry: Option<Duration>,
+    /// How long the messages mentioning members are kept before being deleted, once the
+    /// notification has been sent
+    pub mention_expiry: Option<Duration>,
+    /// How queries sent again shortly after they were last sent in a channel are treated
+    pub duplicate_queries: DuplicateQuerySettings,
+    /// Which notifications need approving by a moderator, if any
+    pub approval: Option<ModeratorApproval>,
+    /// The number of members a query must match (at least) for its author to have to type that
+    /// number to confirm it
+    pub typed_confirmation: Option<usize>,
+    /// The number of members a query must match (more than) to need confirming, if not the
+    /// default
+    pub confirmation_threshold: Option<usize>,
+    /// How long authors have to confirm large queries, if not the default
+    pub confirmation_timeout: Option<ConfirmationTimeout>,
+    /// The role whose members are never mentioned, whatever the query
+    pub do_not_ping: Option<RoleId>,
+    /// The roles whose members' queries notify members during their quiet hours too
+    pub emergency_roles: HashSet<RoleId>,
+    /// How role names in queries are matched against the names of roles
+    pub role_names: RoleNameMatching,
+    /// Whether `everyone` and `here` can be used in queries
+    pub everyone_here: EveryoneHere,
+    /// Who paused query handling, if it's paused
+    pub paused_by: Option<UserId>,
+    /// The prefix text commands like `!dry_run` start with, if they can be used at all
+    pub prefix: Option<String>,
+    /// The query fragments the guild gave names to, keyed by their lowercase name, which queries
+    /// use like `$oncall`. They're kept across restarts with the rest of the configuration.
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// How each setting with a fixed set of choices is written to the file
+const ENTRY_POINTS: [(QueryEntryPoints, &str); 2] = [
+    (QueryEntryPoints::Everywhere, "everywhere"),
+    (QueryEntryPoints::SlashCommandsOnly, "slash_commands"),
+];
+/// See [`ENTRY_POINTS`]
+const DELIVERIES: [(Delivery, &str); 3] = [
+    (Delivery::Reply, "reply"),
+    (Delivery::Webhook, "webhook"),
+    (Delivery::Thread, "thread"),
+];
+/// See [`ENTRY_POINTS`]
+const PERMISSIONS: [(RequiredPermission, &str); 4] = [
+    (RequiredPermission::ManageMessages, "manage_messages"),
+    (RequiredPermission::MentionEveryone, "mention_everyone"),
+    (RequiredPermission::ManageGuild, "manage_guild"),
+    (RequiredPermission::Administrator, "administrator"),
+];
+/// See [`ENTRY_POINTS`]
+const CHANNEL_MODES: [(ChannelFilterMode, &str); 2] = [
+    (ChannelFilterMode::Denylist, "denylist"),
+    (ChannelFilterMode::Allowlist, "allowlist"),
+];
+/// See [`ENTRY_POINTS`]
+const LANGUAGES: [(Language, &str); 2] = [(Language::English, "en"), (Language::German, "de")];
+/// See [`ENTRY_POINTS`]
+const DUPLICATE_MODES: [(DuplicateQueryMode, &str); 3] = [
+    (DuplicateQueryMode::Allow, "allow"),
+    (DuplicateQueryMode::Confirm, "confirm"),
+    (DuplicateQueryMode::Refuse, "refuse"),
+];
+/// See [`ENTRY_POINTS`]
+const ROLE_NAMES: [(RoleNameMatching, &str); 2] = [
+    (RoleNameMatching::IgnoreCase, "ignore_case"),
+    (RoleNameMatching::ExactCase, "exact_case"),
+];
+
+/// Write one of a setting's choices as a field, with the keywords it's written as.
+fn keyword<T: PartialEq>(value: &T, keywords: &[(T, &str)]) -> String {
+    keywords
+        .iter()
+        .find(|(choice, _)| choice == value)
+        .map_or_else(String::new, |(_, keyword)| (*keyword).to_string())
+}
+
+/// Read one of a setting's choices written by [`keyword`].
+fn parse_keyword<T: Copy>(field: &str, keywords: &[(T, &str)]) -> Option<T> {
+    keywords
+        .iter()
+        .find(|(_, keyword)| *keyword == field)
+        .map(|(choice, _)| *choice)
+}
+
+/// Write a flag as a field, `1` if it's set and `0` otherwise.
+fn flag(value: bool) -> String {
+    u8::from(value).to_string()
+}
+
+/// Read a flag written by [`flag`].
+fn parse_flag(field: &str) -> Option<bool> {
+    match field {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+/// Write an optional value as a field, which is empty if there's no value.
+fn optional(value: Option<impl Display>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+/// Read an optional value written by [`optional`], with `parse` reading the value itself.
+fn parse_optional<T>(field: &str, parse: impl FnOnce(&str) -> Option<T>) -> Result<Option<T>, ()> {
+    if field.is_empty() {
+        Ok(None)
+    } else {
+        parse(field).map(Some).ok_or(())
+    }
+}
+
+/// Read a whole number, or ID.
+fn parse_number<T: FromStr>(field: &str) -> Option<T> {
+    field.parse().ok()
+}
+
+/// Read a whole number of seconds.
+fn parse_seconds(field: &str) -> Option<Duration> {
+    field.parse().ok().map(Duration::from_secs)
+}
+
+/// Write a set of IDs as a field, separated by commas.
+fn ids<T: Copy + Into<u64>>(ids: &HashSet<T>) -> String {
+    let mut ids = ids.iter().map(|id| (*id).into()).collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Read a set of IDs written by [`ids`].
+fn parse_ids<T: From<u64> + Eq + Hash>(field: &str) -> Option<HashSet<T>> {
+    field
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| parse_number::<u64>(id).map(T::from))
+        .collect()
+}
+
+/// Read two values separated by a slash, like `3/600`.
+fn parse_pair<First, Second>(
+    field: &str,
+    parse_first: impl FnOnce(&str) -> Option<First>,
+    parse_second: impl FnOnce(&str) -> Option<Second>,
+) -> Option<(First, Second)> {
+    let (first, second) = field.split_once('/')?;
+    Some((parse_first(first)?, parse_second(second)?))
+}
+
+/// Write a rate limit as a field, like `3/600` for 3 notifications every 10 minutes.
+fn rate_limit(limit: Option<RateLimit>) -> String {
+    optional(limit.map(|limit| format!("{}/{}", limit.count, limit.window.as_secs())))
+}
+
+/// Read a rate limit written by [`rate_limit`].
+fn parse_rate_limit(field: &str) -> Option<RateLimit> {
+    parse_pair(field, parse_number, parse_seconds)
+        .map(|(count, window)| RateLimit { count, window })
+}
+
+impl GuildConfig {
+    /// Every setting (apart from aliases) by name, along with its value written as a field.
+    fn settings(&self) -> [(&'static str, String); 29] {
+        let seconds =
+            |duration: Option<Duration>| optional(duration.map(|duration| duration.as_secs()));
+        [
+            ("silent", flag(self.silent)),
+            ("per_chunk", flag(self.per_chunk)),
+            ("embed", flag(self.embed)),
+            ("entry_points", keyword(&self.entry_points, &ENTRY_POINTS)),
+            ("delivery", keyword(&self.delivery, &DELIVERIES)),
+            ("cooldown_author", rate_limit(self.cooldowns.per_author)),
+            ("cooldown_guild", rate_limit(self.cooldowns.per_guild)),
+            ("cooldown_bypass_roles", ids(&self.cooldowns.bypass_roles)),
+            ("max_mentions", optional(self.max_mentions)),
+            ("access_roles", ids(&self.access.roles)),
+            (
+                "access_permission",
+                self.access
+                    .permission
+                    .map_or_else(String::new, |permission| keyword(&permission, &PERMISSIONS)),
+            ),
+            ("channel_mode", keyword(&self.channels.mode, &CHANNEL_MODES)),
+            ("channels", ids(&self.channels.channels)),
+            ("audit_channel", optional(self.audit_channel)),
+            ("language", keyword(&self.language, &LANGUAGES)),
+            ("error_expiry", seconds(self.error_expiry)),
+            ("mention_expiry", seconds(self.mention_expiry)),
+            (
+                "duplicate_queries",
+                keyword(&self.duplicate_queries.mode, &DUPLICATE_MODES),
+            ),
+            (
+                "duplicate_window",
+                self.duplicate_queries.window.as_secs().to_string(),
+            ),
+            (
+                "approval",
+                optional(
+                    self.approval
+                        .map(|approval| format!("{}/{}", approval.threshold, approval.role)),
+                ),
+            ),
+            ("typed_confirmation", optional(self.typed_confirmation)),
+            (
+                "confirmation_threshold",
+                optional(self.confirmation_threshold),
+            ),
+            (
+                "confirmation_timeout",
+                optional(self.confirmation_timeout.map(|timeout| {
+                    format!("{}/{}", timeout.timeout.as_secs(), flag(timeout.reminder))
+                })),
+            ),
+            ("do_not_ping", optional(self.do_not_ping)),
+            ("emergency_roles", ids(&self.emergency_roles)),
+            ("role_names", keyword(&self.role_names, &ROLE_NAMES)),
+            (
+                "everyone_here",
+                match &self.everyone_here {
+                    EveryoneHere::Allowed => "allowed".to_string(),
+                    EveryoneHere::Refused(None) => "refused".to_string(),
+                    EveryoneHere::Refused(Some(explanation)) => {
+                        format!("refused\t{}", escape(explanation))
+                    }
+                },
+            ),
+            ("paused_by", optional(self.paused_by)),
+            ("prefix", optional(self.prefix.as_deref().map(escape))),
+        ]
+    }
+
+    /// Write the settings this guild changed from the default as lines, with tab separated fields.
+    fn to_lines(&self, guild: GuildId) -> Vec<String> {
+        self.settings()
+            .into_iter()
+            .zip(Self::default().settings())
+            .filter(|(setting, default)| setting != default)
+            .map(|((name, value), _)| format!("{guild}\t{name}\t{value}"))
+            .chain(self.aliases.iter().map(|(name, query)| {
+                format!("{guild}\talias\t{}\t{}", escape(name), escape(query))
+            }))
+            .collect()
+    }
+
+    /// Change the setting called `name` to `value`, written by [`GuildConfig::to_lines`].
+    ///
+    /// Returns `None`, leaving the configuration as it was, if the setting doesn't exist or the
+    /// value is invalid.
+    fn apply(&mut self, name: &str, value: &str) -> Option<()> {
+        match name {
+            "silent" => self.silent = parse_flag(value)?,
+            "per_chunk" => self.per_chunk = parse_flag(value)?,
+            "embed" => self.embed = parse_flag(value)?,
+            "entry_points" => self.entry_points = parse_keyword(value, &ENTRY_POINTS)?,
+            "delivery" => self.delivery = parse_keyword(value, &DELIVERIES)?,
+            "cooldown_author" => {
+                self.cooldowns.per_author = parse_optional(value, parse_rate_limit).ok()?;
+            }
+            "cooldown_guild" => {
+                self.cooldowns.per_guild = parse_optional(value, parse_rate_limit).ok()?;
+            }
+            "cooldown_bypass_roles" => self.cooldowns.bypass_roles = parse_ids(value)?,
+            "max_mentions" => self.max_mentions = parse_optional(value, parse_number).ok()?,
+            "access_roles" => self.access.roles = parse_ids(value)?,
+            "access_permission" => {
+                self.access.permission =
+                    parse_optional(value, |value| parse_keyword(value, &PERMISSIONS)).ok()?;
+            }
+            "channel_mode" => self.channels.mode = parse_keyword(value, &CHANNEL_MODES)?,
+            "channels" => self.channels.channels = parse_ids(value)?,
+            "audit_channel" => {
+                self.audit_channel = parse_optional(value, parse_number).ok()?.map(ChannelId);
+            }
+            "language" => self.language = parse_keyword(value, &LANGUAGES)?,
+            "error_expiry" => self.error_expiry = parse_optional(value, parse_seconds).ok()?,
+            "mention_expiry" => self.mention_expiry = parse_optional(value, parse_seconds).ok()?,
+            "duplicate_queries" => {
+                self.duplicate_queries.mode = parse_keyword(value, &DUPLICATE_MODES)?;
+            }
+            "duplicate_window" => self.duplicate_queries.window = parse_seconds(value)?,
+            "approval" => {
+                self.approval = parse_optional(value, |value| {
+                    parse_pair(value, parse_number, parse_number).map(|(threshold, role)| {
+                        ModeratorApproval {
+                            threshold,
+                            role: RoleId(role),
+                        }
+                    })
+                })
+                .ok()?;
+            }
+            "typed_confirmation" => {
+                self.typed_confirmation = parse_optional(value, parse_number).ok()?;
+            }
+            "confirmation_threshold" => {
+                self.confirmation_threshold = parse_optional(value, parse_number).ok()?;
+            }
+            "confirmation_timeout" => {
+                self.confirmation_timeout = parse_optional(value, |value| {
+                    parse_pair(value, parse_seconds, parse_flag)
+                        .map(|(timeout, reminder)| ConfirmationTimeout { timeout, reminder })
+                })
+                .ok()?;
+            }
+            "do_not_ping" => {
+                self.do_not_ping = parse_optional(value, parse_number).ok()?.map(RoleId);
+            }
+            "emergency_roles" => self.emergency_roles = parse_ids(value)?,
+            "role_names" => self.role_names = parse_keyword(value, &ROLE_NAMES)?,
+            "everyone_here" => {
+                self.everyone_here = match value.split_once('\t') {
+                    None if value == "allowed" => EveryoneHere::Allowed,
+                    None if value == "refused" => EveryoneHere::Refused(None),
+                    Some(("refused", explanation)) => {
+                        EveryoneHere::Refused(Some(unescape(explanation)?))
+                    }
+                    _ => return None,
+                };
+            }
+            "paused_by" => self.paused_by = parse_optional(value, parse_number).ok()?.map(UserId),
+            "prefix" => self.prefix = parse_optional(value, unescape).ok()?,
+            "alias" => {
+                let (name, query) = value.split_once('\t')?;
+                self.aliases.insert(unescape(name)?, unescape(query)?);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// The configuration of every guild Intersection is in, falling back to the default
+/// [`GuildConfig`] for guilds that haven't changed anything.
+#[derive(Debug)]
+pub struct ConfigStore {
+    /// The file configuration is written to, if it's kept across restarts
+    file: StoreFile,
+    /// The configuration of every guild that has changed something
+    guilds: Mutex<HashMap<GuildId, GuildConfig>>,
+}
+
+impl ConfigStore {
+    /// Create a new [`ConfigStore`] writing to `path`, reading the configuration guilds chose the
+    /// last time from it. Every other guild has the default configuration.
+    ///
+    /// Settings which can't be read are left at their default, rather than losing the rest of the
+    /// guild's configuration.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let file = StoreFile::new(path, "guild configuration");
+        let mut guilds = HashMap::<GuildId, GuildConfig>::new();
+        file.load(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let guild = GuildId(fields.next()?.parse().ok()?);
+            let (name, value) = (fields.next()?, fields.next()?);
+            guilds.entry(guild).or_default().apply(name, value)
+        });
+
+        Self {
+            file,
+            guilds: Mutex::new(guilds),
+        }
+    }
+
+    /// Obtain the configuration of a guild.
+    pub fn get(&self, guild: GuildId) -> GuildConfig {
+        self.guilds
+            .lock()
+            .expect("config store lock was poisoned")
+            .get(&guild)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Modify the configuration of a guild, returning the updated configuration.
+    pub fn update(&self, guild: GuildId, modify: impl FnOnce(&mut GuildConfig)) -> GuildConfig {
+        let mut guilds = self.guilds.lock().expect("config store lock was poisoned");
+        let config = guilds.entry(guild).or_default();
+        modify(config);
+        let updated = config.clone();
+        // Saving with the lock held keeps an older save from overwriting a newer one
+        self.save(&guilds);
+        drop(guilds);
+
+        updated
+    }
+
+    /// Put a guild back to the default configuration, undoing every change it made.
+    pub fn reset(&self, guild: GuildId) {
+        let mut guilds = self.guilds.lock().expect("config store lock was poisoned");
+        if guilds.remove(&guild).is_some() {
+            self.save(&guilds);
+        }
+        drop(guilds);
+    }
+
+    /// Write the configuration of every guild to the file.
+    fn save(&self, guilds: &HashMap<GuildId, GuildConfig>) {
+        self.file.save(
+            guilds
+                .iter()
+                .flat_map(|(guild, config)| config.to_lines(*guild)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn guilds_start_with_the_default_config() {
+        assert_eq!(
+            ConfigStore::open(None).get(GuildId(1)),
+            GuildConfig::default()
+        );
+    }
+
+    #[test]
+    fn updates_only_affect_one_guild() {
+        let store = ConfigStore::open(None);
+        let updated = store.update(GuildId(1), |config| config.silent = true);
+
+        assert!(updated.silent);
+        assert_eq!(store.get(GuildId(1)), updated);
+        assert_eq!(store.get(GuildId(2)), GuildConfig::default());
+    }
+
+    #[test]
+    fn resets_restore_the_default_config() {
+        let store = ConfigStore::open(None);
+        store.update(GuildId(1), |config| config.silent = true);
+        store.update(GuildId(2), |config| config.embed = true);
+        store.reset(GuildId(1));
+
+        assert_eq!(store.get(GuildId(1)), GuildConfig::default());
+        assert!(store.get(GuildId(2)).embed);
+    }
+
+    #[test]
+    fn config_survives_a_restart() {
+        let path = env::temp_dir().join(format!("intersection-config-{}", std::process::id()));
+        let store = ConfigStore::open(Some(path.clone()));
+        let configured = store.update(GuildId(1), |config| {
+            config.silent = true;
+            config.delivery = Delivery::Thread;
+            config.cooldowns.per_author = Some(RateLimit {
+                count: 1,
+                window: Duration::from_mins(10),
+            });
+            config.cooldowns.bypass_roles = HashSet::from([RoleId(2), RoleId(3)]);
+            config.max_mentions = Some(50);
+            config.access.permission = Some(RequiredPermission::ManageGuild);
+            config.channels.mode = ChannelFilterMode::Allowlist;
+            config.channels.channels = HashSet::from([ChannelId(4)]);
+            config.language = Language::German;
+            config.duplicate_queries.mode = DuplicateQueryMode::Refuse;
+            config.approval = Some(ModeratorApproval {
+                threshold: 100,
+                role: RoleId(5),
+            });
+            config.confirmation_timeout = Some(ConfirmationTimeout {
+                timeout: Duration::from_mins(2),
+                reminder: true,
+            });
+            config.do_not_ping = Some(RoleId(6));
+            config.everyone_here =
+                EveryoneHere::Refused(Some("Ping\t@Staff\ninstead.".to_string()));
+            config.paused_by = Some(UserId(7));
+            config.prefix = Some("!".to_string());
+            config
+                .aliases
+                .insert("oncall".to_string(), "sre & online".to_string());
+        });
+        store.update(GuildId(8), |config| config.embed = true);
+        store.update(GuildId(9), |config| config.embed = true);
+        store.reset(GuildId(9));
+
+        // As if Intersection restarted
+        let store = ConfigStore::open(Some(path.clone()));
+        assert_eq!(store.get(GuildId(1)), configured);
+        assert!(store.get(GuildId(8)).embed);
+        assert_eq!(store.get(GuildId(9)), GuildConfig::default());
+
+        // A setting which can't be read doesn't lose the rest of the guild's configuration
+        fs::write(
+            &path,
+            "1\tsilent\t1\n1\tmax_mentions\tmany\n1\tno_such_setting\t1\n",
+        )
+        .expect("the file should be writable");
+        let store = ConfigStore::open(Some(path.clone()));
+        assert!(store.get(GuildId(1)).silent);
+        assert_eq!(store.get(GuildId(1)).max_mentions, None);
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+
+    #[test]
+    fn aliases_survive_a_restart() {
+        let path = env::temp_dir().join(format!("intersection-aliases-{}", std::process::id()));
+        let store = ConfigStore::open(Some(path.clone()));
+        store.update(GuildId(1), |config| {
+            config
+                .aliases
+                .insert("oncall".to_string(), "sre & online".to_string());
+            config
+                .aliases
+                .insert("quoted".to_string(), "\"on\tcall\\\" | here".to_string());
+            config
+                .aliases
+                .insert("old".to_string(), "everyone".to_string());
+        });
+        let aliases = store
+            .update(GuildId(1), |config| {
+                config.aliases.remove("old");
+            })
+            .aliases;
+
+        // As if Intersection restarted
+        let store = ConfigStore::open(Some(path.clone()));
+        assert_eq!(store.get(GuildId(1)).aliases, aliases);
+        assert!(!store.get(GuildId(1)).aliases.contains_key("old"));
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+
+    #[test]
+    fn everyone_and_here_can_be_refused() {
+        assert!(EveryoneHere::Allowed
+            .check("everyone", Language::English)
+            .is_ok());
+        assert_eq!(
+            EveryoneHere::Refused(None)
+                .check("here", Language::English)
+                .map_err(|err| err.to_string()),
+            Err("`here` can't be used in queries in this server.".to_string())
+        );
+        assert_eq!(
+            EveryoneHere::Refused(Some("Ping @Staff instead.".to_string()))
+                .check("everyone", Language::English)
+                .map_err(|err| err.to_string()),
+            Err(
+                "`everyone` can't be used in queries in this server. Ping @Staff instead."
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Cooldowns limiting how often notifications can be sent
+//!
+//! Guilds can limit how many notifications each author, and the guild as a whole, may send within
+//! a window of time, with members of bypass roles being exempt. Before a query is evaluated, its
+//! notification reserves a place within the cooldowns, so any number of queries waiting to be
+//! confirmed or approved at once can't get past them together. The place is given back if the
+//! query is declined or fails, so only notifications which were actually sent count towards them.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{GuildId, RoleId, UserId};
+use tracing::debug;
+
+use crate::{
+    error::QueryError,
+    i18n::{self, Language},
+};
+
+/// At most `count` notifications within `window`
+///
+/// A `count` of zero means there is no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// How many notifications may be sent within the window
+    pub count: usize,
+    /// How long each notification counts towards the limit
+    pub window: Duration,
+}
+
+/// Who a [`RateLimit`] applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum CooldownScope {
+    /// Each author separately
+    #[name = "Each author"]
+    Author,
+    /// The whole guild together
+    #[name = "The whole server"]
+    Guild,
+}
+
+/// The cooldowns configured in a guild
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CooldownSettings {
+    /// The limit on notifications sent by each author
+    pub per_author: Option<RateLimit>,
+    /// The limit on notifications sent in the whole guild
+    pub per_guild: Option<RateLimit>,
+    /// Roles whose members are exempt from cooldowns
+    pub bypass_roles: HashSet<RoleId>,
+}
+
+impl CooldownSettings {
+    /// The limit for a scope, to change it.
+    pub const fn limit_mut(&mut self, scope: CooldownScope) -> &mut Option<RateLimit> {
+        match scope {
+            CooldownScope::Author => &mut self.per_author,
+            CooldownScope::Guild => &mut self.per_guild,
+        }
+    }
+
+    /// How long notifications need to be remembered for to enforce these limits.
+    fn retention(&self) -> Option<Duration> {
+        self.per_author
+            .iter()
+            .chain(&self.per_guild)
+            .map(|limit| limit.window)
+            .max()
+    }
+}
+
+/// A notification counting towards cooldowns
+#[derive(Debug, Clone, Copy)]
+struct Notification {
+    /// Identifies the notification's [`CooldownReservation`]
+    id: u64,
+    /// Who sent the notification
+    author: UserId,
+    /// When the notification was sent, or [`None`] if it's still waiting to be sent
+    sent_at: Option<Instant>,
+}
+
+impl Notification {
+    /// When the notification counts as having been sent: now for those still waiting to be sent,
+    /// so they count until they're sent or given back.
+    fn counted_at(&self, now: Instant) -> Instant {
+        self.sent_at.unwrap_or(now)
+    }
+}
+
+/// Remembers recent notifications in every guild, to enforce their [`CooldownSettings`].
+#[derive(Debug, Default)]
+pub struct CooldownTracker {
+    /// The notifications sent (or waiting to be sent) in each guild
+    notifications: Mutex<HashMap<GuildId, Vec<Notification>>>,
+    /// The ID of the next reservation
+    next_id: AtomicU64,
+}
+
+/// A notification's place within the cooldowns of its guild, reserved by
+/// [`CooldownTracker::reserve`] while the notification waits to be sent
+///
+/// Dropping the reservation gives the place back, unless the notification was
+/// [sent](CooldownReservation::sent).
+#[derive(Debug)]
+#[must_use = "dropping a reservation gives its place back straight away"]
+pub struct CooldownReservation<'a> {
+    /// The tracker the place was reserved in
+    tracker: &'a CooldownTracker,
+    /// The guild the notification is sent in
+    guild: GuildId,
+    /// Identifies the notification in `tracker`
+    id: u64,
+    /// Whether the notification was sent, keeping its place
+    sent: bool,
+}
+
+impl CooldownReservation<'_> {
+    /// Count the notification as sent now, keeping its place within the cooldowns.
+    pub fn sent(mut self) {
+        self.sent = true;
+        let mut notifications = self
+            .tracker
+            .notifications
+            .lock()
+            .expect("cooldown tracker lock was poisoned");
+        let sent = notifications.entry(self.guild).or_default();
+        let now = Instant::now();
+        if let Some(notification) = sent
+            .iter_mut()
+            .find(|notification| notification.id == self.id)
+        {
+            notification.sent_at = Some(now);
+        }
+        drop(notifications);
+    }
+}
+
+impl Drop for CooldownReservation<'_> {
+    fn drop(&mut self) {
+        if self.sent {
+            return;
+        }
+        let mut notifications = self
+            .tracker
+            .notifications
+            .lock()
+            .expect("cooldown tracker lock was poisoned");
+        if let Some(sent) = notifications.get_mut(&self.guild) {
+            sent.retain(|notification| notification.id != self.id);
+        }
+        drop(notifications);
+    }
+}
+
+/// How long until another notification can be sent under `limit`, given the times the relevant
+/// notifications count as sent at, or [`None`] if one can be sent now.
+fn remaining(limit: RateLimit, counted_at: &[Instant]) -> Option<Duration> {
+    if limit.count == 0 {
+        return None;
+    }
+
+    let mut recent = counted_at
+        .iter()
+        .filter(|counted_at| counted_at.elapsed() < limit.window)
+        .collect::<Vec<_>>();
+    if recent.len() < limit.count {
+        return None;
+    }
+
+    // Once this many of the recent notifications expire, there's room for another
+    recent.sort_unstable();
+    let blocking = recent[recent.len() - limit.count];
+    Some(limit.window.saturating_sub(blocking.elapsed()))
+}
+
+/// Describe a wait like "3 minutes" or "45 seconds", rounding up.
+pub fn describe_wait(wait: Duration, language: Language) -> String {
+    let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    let (amount, unit) = if seconds > 60 {
+        (seconds.div_ceil(60), "wait-minutes")
+    } else {
+        (seconds.max(1), "wait-seconds")
+    };
+    i18n::message_count(
+        language,
+        unit,
+        usize::try_from(amount).unwrap_or(usize::MAX),
+        &[],
+    )
+}
+
+impl CooldownTracker {
+    /// Create a new [`CooldownTracker`] which hasn't seen any notifications.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserve a place within the cooldowns of `guild` for a notification by `author` (who has the
+    /// given roles), failing with [`QueryError::LimitExceeded`], explained in `language`, if
+    /// they're on cooldown.
+    ///
+    /// Checking and reserving happen together, so two queries can't both take the last place.
+    /// Returns [`None`] if the notification doesn't count towards any cooldown.
+    pub fn reserve(
+        &self,
+        guild: GuildId,
+        author: UserId,
+        roles: &[RoleId],
+        settings: &CooldownSettings,
+        language: Language,
+    ) -> Result<Option<CooldownReservation<'_>>, QueryError> {
+        if roles
+            .iter()
+            .any(|role| settings.bypass_roles.contains(role))
+        {
+            return Ok(None);
+        }
+        let Some(retention) = settings.retention() else {
+            // There are no cooldowns, so there's no need to remember anything
+            return Ok(None);
+        };
+
+        let mut notifications = self
+            .notifications
+            .lock()
+            .expect("cooldown tracker lock was poisoned");
+        let sent = notifications.entry(guild).or_default();
+        let now = Instant::now();
+        // Opportunistically drop notifications which no longer count towards any limit
+        sent.retain(|notification| {
+            notification
+                .sent_at
+                .is_none_or(|sent_at| sent_at.elapsed() < retention)
+        });
+        let by_author = sent
+            .iter()
+            .filter(|notification| notification.author == author)
+            .map(|notification| notification.counted_at(now))
+            .collect::<Vec<_>>();
+        let author_wait = settings
+            .per_author
+            .and_then(|limit| remaining(limit, &by_author));
+        let guild_wait = settings.per_guild.and_then(|limit| {
+            remaining(
+                limit,
+                &sent
+                    .iter()
+                    .map(|notification| notification.counted_at(now))
+                    .collect::<Vec<_>>(),
+            )
+        });
+        if author_wait.is_none() && guild_wait.is_none() {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            sent.push(Notification {
+                id,
+                author,
+                sent_at: None,
+            });
+            drop(notifications);
+            return Ok(Some(CooldownReservation {
+                tracker: self,
+                guild,
+                id,
+                sent: false,
+            }));
+        }
+        drop(notifications);
+
+        // Report whichever cooldown lasts longer, as waiting out the other wouldn't be enough
+        if let Some(guild_wait) =
+            guild_wait.filter(|&guild_wait| author_wait.is_none_or(|wait| guild_wait > wait))
+        {
+            debug!("Guild is on cooldown for {guild_wait:?}");
+            return Err(QueryError::LimitExceeded(i18n::message(
+                language,
+                "cooldown-guild",
+                &[("wait", &describe_wait(guild_wait, language))],
+            )));
+        }
+        let author_wait = author_wait.unwrap_or_default();
+        debug!("Author is on cooldown for {author_wait:?}");
+        Err(QueryError::LimitExceeded(i18n::message(
+            language,
+            "cooldown-author",
+            &[("wait", &describe_wait(author_wait, language))],
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(per_author: Option<usize>, per_guild: Option<usize>) -> CooldownSettings {
+        let limit = |count| RateLimit {
+            count,
+            window: Duration::from_mins(10),
+        };
+        CooldownSettings {
+            per_author: per_author.map(limit),
+            per_guild: per_guild.map(limit),
+            bypass_roles: HashSet::from([RoleId(1)]),
+        }
+    }
+
+    /// Send a notification by `author` in `guild`, which must not be on cooldown.
+    fn send(tracker: &CooldownTracker, guild: u64, author: u64, settings: &CooldownSettings) {
+        if let Some(reservation) = tracker
+            .reserve(
+                GuildId(guild),
+                UserId(author),
+                &[],
+                settings,
+                Language::English,
+            )
+            .expect("the author shouldn't be on cooldown")
+        {
+            reservation.sent();
+        }
+    }
+
+    /// Check whether `author` may send a notification in `guild`, without sending one.
+    fn check(
+        tracker: &CooldownTracker,
+        guild: u64,
+        author: u64,
+        roles: &[RoleId],
+        settings: &CooldownSettings,
+    ) -> Result<(), QueryError> {
+        tracker
+            .reserve(
+                GuildId(guild),
+                UserId(author),
+                roles,
+                settings,
+                Language::English,
+            )
+            .map(drop)
+    }
+
+    #[test]
+    fn authors_are_limited_separately() {
+        let tracker = CooldownTracker::new();
+        let settings = settings(Some(1), None);
+        send(&tracker, 1, 1, &settings);
+
+        let err =
+            check(&tracker, 1, 1, &[], &settings).expect_err("the author should be on cooldown");
+        assert!(err
+            .to_string()
+            .starts_with("You've sent too many notifications recently. Try again in"));
+        assert!(check(&tracker, 1, 2, &[], &settings).is_ok());
+        assert!(check(&tracker, 2, 1, &[], &settings).is_ok());
+    }
+
+    #[test]
+    fn guilds_are_limited_together() {
+        let tracker = CooldownTracker::new();
+        let settings = settings(None, Some(2));
+        send(&tracker, 1, 1, &settings);
+        assert!(check(&tracker, 1, 2, &[], &settings).is_ok());
+
+        send(&tracker, 1, 2, &settings);
+        let err =
+            check(&tracker, 1, 3, &[], &settings).expect_err("the guild should be on cooldown");
+        assert_eq!(
+            err.to_string(),
+            "Too many notifications have been sent in this server recently. Try again in 10 minutes."
+        );
+    }
+
+    #[test]
+    fn reservations_hold_their_place_until_given_back() {
+        let tracker = CooldownTracker::new();
+        let settings = settings(Some(1), None);
+        let reservation = tracker
+            .reserve(GuildId(1), UserId(1), &[], &settings, Language::English)
+            .expect("the author shouldn't be on cooldown");
+        assert!(reservation.is_some());
+
+        // The author's second query can't get past the cooldown while the first waits
+        assert!(check(&tracker, 1, 1, &[], &settings).is_err());
+        drop(reservation);
+        assert!(check(&tracker, 1, 1, &[], &settings).is_ok());
+    }
+
+    #[test]
+    fn bypass_roles_are_exempt() {
+        let tracker = CooldownTracker::new();
+        let settings = settings(Some(1), Some(1));
+        send(&tracker, 1, 1, &settings);
+
+        assert!(check(&tracker, 1, 1, &[RoleId(1)], &settings).is_ok());
+    }
+
+    #[test]
+    fn nothing_is_recorded_without_cooldowns() {
+        let tracker = CooldownTracker::new();
+        send(&tracker, 1, 1, &CooldownSettings::default());
+
+        assert!(tracker
+            .notifications
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+    }
+
+    #[test]
+    fn waits_are_rounded_up() {
+        assert_eq!(
+            describe_wait(Duration::from_millis(200), Language::English),
+            "1 second"
+        );
+        assert_eq!(
+            describe_wait(Duration::from_secs(45), Language::English),
+            "45 seconds"
+        );
+        assert_eq!(
+            describe_wait(Duration::from_secs(61), Language::English),
+            "2 minutes"
+        );
+        assert_eq!(
+            describe_wait(Duration::from_mins(10), Language::English),
+            "10 minutes"
+        );
+    }
+}
@@ -0,0 +1,1039 @@
+//! An abstraction over the Discord-facing operations of the query pipeline
+//!
+//! The [query pipeline] doesn't talk to Serenity directly. Instead, it performs every operation
+//! (evaluating the query, sending replies, waiting for button presses) through the [`Discord`]
+//! trait. [`SerenityDiscord`] is the real implementation, used when handling a [`Message`], while
+//! tests use an in-memory fake.
+//!
+//! [query pipeline]: crate::pipeline
+//! [`Message`]: serenity::Message
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context as _;
+use poise::{async_trait, serenity_prelude as serenity};
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+use crate::{
+    activity::ActivityTracker,
+    config::GuildConfig,
+    cooldowns::{CooldownReservation, CooldownTracker},
+    duplicates::{DuplicateQueryMode, DuplicateQueryTracker},
+    error::QueryError,
+    extensions::{CustomGuildChannelImpl, CustomGuildImpl},
+    history::{HistoryEntry, QueryHistory},
+    i18n,
+    models::mention::RoleType,
+    pending_sends::{PendingSend, PendingSendStore},
+    pipeline::{parse_and_evaluate_query, Evaluation, QueryContext},
+    preferences::PreferenceStore,
+    query_cache::QueryCache,
+    recipients::RecipientStore,
+    reply_tracker::{self, ReplyTracker},
+    send_queue::{SendQueueGuard, SendQueues},
+    stats::UsageStats,
+    util,
+    webhooks::{self, WebhookStore},
+};
+
+/// A button attached to an [`OutgoingMessage`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Button {
+    /// The custom ID reported back by [`Discord::await_button_press`] when this button is pressed
+    pub custom_id: String,
+    /// The text on the button
+    pub label: String,
+    /// A unicode emoji displayed next to the label
+    pub emoji: Option<String>,
+    /// The style (color) of the button
+    pub style: serenity::ButtonStyle,
+}
+
+/// A modal asking the author to type a single line of text, opened by a button
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPrompt {
+    /// The title of the modal
+    pub title: String,
+    /// The label above the text input
+    pub label: String,
+}
+
+/// How the author responded to the buttons on a message, in
+/// [`Discord::await_button_press_or_text`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ButtonResponse {
+    /// The author pressed the button with this custom ID
+    Pressed(String),
+    /// The author typed this text into the button's [`TextPrompt`]
+    Typed(String),
+}
+
+/// An embed attached to an [`OutgoingMessage`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Embed {
+    /// The title of the embed
+    pub title: String,
+    /// The text below the title
+    pub description: String,
+    /// Fields displayed inline below the description, as `(name, value)` pairs
+    pub fields: Vec<(String, String)>,
+}
+
+/// A message sent (or edited) through [`Discord`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutgoingMessage {
+    /// The text content of the message
+    pub content: String,
+    /// Buttons attached to the message, in a single action row
+    ///
+    /// When editing a message, an empty list removes any existing buttons.
+    pub buttons: Vec<Button>,
+    /// An embed attached to the message
+    pub embed: Option<Embed>,
+    /// Whether the message is sent without triggering push notifications (like `@silent`)
+    ///
+    /// This has no effect when editing a message.
+    pub silent: bool,
+    /// Whether mentions in the message are displayed without notifying anyone
+    ///
+    /// This has no effect when editing a message.
+    pub suppress_mentions: bool,
+    /// Whether the message is sent through a channel webhook with the author's name and avatar,
+    /// instead of as a reply from the bot
+    ///
+    /// If a webhook can't be used, the message is sent as a normal reply. This has no effect when
+    /// editing a message.
+    pub send_as_author: bool,
+}
+
+impl OutgoingMessage {
+    /// Create an [`OutgoingMessage`] with just text content.
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Attach a button to this message.
+    #[must_use]
+    pub fn button(mut self, button: Button) -> Self {
+        self.buttons.push(button);
+        self
+    }
+
+    /// Attach an embed to this message.
+    #[must_use]
+    pub fn embed(mut self, embed: Embed) -> Self {
+        self.embed = Some(embed);
+        self
+    }
+
+    /// Set whether this message is sent silently.
+    #[must_use]
+    pub const fn silent(mut self, silent: bool) -> Self {
+        self.silent = silent;
+        self
+    }
+
+    /// Set whether mentions in this message are displayed without notifying anyone.
+    #[must_use]
+    pub const fn suppress_mentions(mut self, suppress_mentions: bool) -> Self {
+        self.suppress_mentions = suppress_mentions;
+        self
+    }
+
+    /// Set whether this message is sent with the author's name and avatar.
+    #[must_use]
+    pub const fn send_as_author(mut self, send_as_author: bool) -> Self {
+        self.send_as_author = send_as_author;
+        self
+    }
+}
+
+/// The Discord-facing operations performed while handling a single query.
+///
+/// Every operation is performed in the context of the message (or command) the query was sent in:
+/// replies are sent to its channel, and the query is evaluated as its author.
+#[async_trait]
+pub trait Discord: Send + Sync {
+    /// Parse and evaluate the given DRQL chunks as their union, counting the operands resolved so
+    /// far in `progress`.
+    async fn evaluate(
+        &self,
+        chunks: &[&str],
+        progress: &watch::Sender<usize>,
+    ) -> Result<Evaluation, QueryError>;
+
+    /// The query fragments the guild gave names to, keyed by name, which queries use like
+    /// `$oncall`.
+    fn aliases(&self) -> &BTreeMap<String, String>;
+
+    /// Obtain every role in the guild (including `@everyone` and `@here`) and its members.
+    async fn roles_and_members(
+        &self,
+    ) -> Result<HashMap<RoleType, HashSet<serenity::UserId>>, QueryError>;
+
+    /// Reply to the query, returning the ID of the sent message.
+    async fn reply(&self, message: OutgoingMessage) -> Result<serenity::MessageId, QueryError>;
+
+    /// Send a message to another channel, returning the ID of the sent message.
+    ///
+    /// Unlike [`Discord::reply`], the message isn't deleted along with the query.
+    async fn send_to_channel(
+        &self,
+        channel: serenity::ChannelId,
+        message: OutgoingMessage,
+    ) -> Result<serenity::MessageId, QueryError>;
+
+    /// Edit a message previously sent through [`Discord::reply`].
+    async fn edit(
+        &self,
+        id: serenity::MessageId,
+        message: OutgoingMessage,
+    ) -> Result<(), QueryError>;
+
+    /// Delete a message previously sent through [`Discord::reply`].
+    async fn delete(&self, id: serenity::MessageId) -> Result<(), QueryError>;
+
+    /// Delete messages previously sent through [`Discord::reply`] once `after` has passed, without
+    /// waiting for it.
+    fn delete_later(&self, messages: Vec<serenity::MessageId>, after: Duration);
+
+    /// Show that we're typing in the query's channel, until the next message is sent or a few
+    /// seconds pass.
+    async fn broadcast_typing(&self) -> Result<(), QueryError>;
+
+    /// React to the message containing the query with a unicode emoji.
+    async fn react_to_query(&self, emoji: &str) -> Result<(), QueryError>;
+
+    /// Wait for our turn to send a notification in the query's channel.
+    ///
+    /// See [`SendQueues`].
+    async fn lock_send_queue(&self) -> SendQueueGuard;
+
+    /// Wait for some time to pass, e.g. before retrying a failed operation.
+    async fn wait(&self, duration: Duration);
+
+    /// Wait for the query's author to press one of the buttons on a message, returning its custom
+    /// ID, or [`None`] if the timeout elapsed first.
+    async fn await_button_press(
+        &self,
+        id: serenity::MessageId,
+        timeout: Duration,
+    ) -> Result<Option<String>, QueryError>;
+
+    /// Wait for the query's author to respond to the buttons on a message, like
+    /// [`Discord::await_button_press`], except that pressing `text_button` asks them to type some
+    /// text in a modal first.
+    ///
+    /// Returns [`None`] if the timeout elapsed first, either before a button was pressed or before
+    /// the modal was submitted.
+    async fn await_button_press_or_text(
+        &self,
+        id: serenity::MessageId,
+        text_button: &str,
+        prompt: &TextPrompt,
+        timeout: Duration,
+    ) -> Result<Option<ButtonResponse>, QueryError>;
+
+    /// Wait for a member of `role` other than the query's author to press one of the buttons on a
+    /// message, returning its custom ID and who pressed it, or [`None`] if the timeout elapsed
+    /// first.
+    ///
+    /// The author may still press the buttons in `author_buttons`, like one cancelling the query.
+    async fn await_approval(
+        &self,
+        id: serenity::MessageId,
+        role: serenity::RoleId,
+        author_buttons: &[&str],
+        timeout: Duration,
+    ) -> Result<Option<(String, serenity::UserId)>, QueryError>;
+
+    /// Obtain the text mentioning an application command, like `about landing`.
+    ///
+    /// See [`util::mention_application_command`].
+    async fn mention_command(&self, command: &str) -> Result<String, QueryError>;
+
+    /// Start a thread from the query message, returning its ID.
+    ///
+    /// If the query was sent in a thread, that thread is used instead.
+    async fn create_thread(&self, name: &str) -> Result<serenity::ChannelId, QueryError>;
+
+    /// Add members to a thread obtained from [`Discord::create_thread`].
+    async fn add_thread_members(
+        &self,
+        thread: serenity::ChannelId,
+        members: &[serenity::UserId],
+    ) -> Result<(), QueryError>;
+
+    /// Send a direct message to a member, failing if they don't accept DMs from us.
+    ///
+    /// Only the content of the message is sent.
+    async fn send_dm(
+        &self,
+        user: serenity::UserId,
+        message: OutgoingMessage,
+    ) -> Result<(), QueryError>;
+
+    /// The author of the query.
+    fn author(&self) -> serenity::UserId;
+
+    /// Check that the author is allowed to run queries at all, failing with
+    /// [`QueryError::PermissionDenied`] if they aren't.
+    ///
+    /// See [`QueryAccess`](crate::access::QueryAccess).
+    async fn check_access(&self) -> Result<(), QueryError>;
+
+    /// Reserve the author's notification a place within their cooldowns, failing with
+    /// [`QueryError::LimitExceeded`] if they're on cooldown.
+    ///
+    /// The place is given back when the reservation is dropped, unless it's passed to
+    /// [`Discord::record_notification`]. See [`CooldownTracker`].
+    fn reserve_cooldown(&self) -> Result<Option<CooldownReservation<'_>>, QueryError>;
+
+    /// Record that the author sent the notification `reservation` was for, counting towards their
+    /// cooldowns.
+    fn record_notification(&self, reservation: Option<CooldownReservation<'_>>);
+
+    /// How long ago a normalized query was last sent in the channel, if it was recently enough to
+    /// count as a duplicate.
+    ///
+    /// See [`DuplicateQueryTracker`].
+    fn last_sent(&self, query: &str) -> Option<Duration>;
+
+    /// Record that a normalized query was sent in the channel, to spot it being sent again.
+    fn record_query(&self, query: &str);
+
+    /// Record a notification in the guild's history, with its query (as written in replies) and
+    /// how many members it matched.
+    ///
+    /// See [`QueryHistory`].
+    fn record_history(&self, query: &str, members: usize);
+
+    /// Count a notification in the guild's usage stats, with how many members it matched and the
+    /// roles it mentioned.
+    ///
+    /// See [`UsageStats`].
+    fn record_stats(&self, members: usize, roles: &[RoleType]);
+
+    /// The channel the query was sent in.
+    fn channel(&self) -> serenity::ChannelId;
+
+    /// A link to the message containing the query.
+    fn query_link(&self) -> String;
+
+    /// A link to a message sent through [`Discord::reply`].
+    fn link(&self, message: serenity::MessageId) -> String;
+
+    /// Whether a member would rather be notified by DM than mentioned in the channel.
+    ///
+    /// See [`PreferenceStore`].
+    fn wants_dm_notifications(&self, user: serenity::UserId) -> bool;
+
+    /// Whether a member opted out of being notified by queries in the guild.
+    ///
+    /// See [`PreferenceStore`].
+    fn has_opted_out(&self, user: serenity::UserId) -> bool;
+
+    /// Whether a member is in the quiet hours they chose in the guild right now.
+    ///
+    /// See [`PreferenceStore`].
+    fn is_quiet(&self, user: serenity::UserId) -> bool;
+
+    /// Whether the author has one of the guild's emergency roles, so their queries notify members
+    /// in their quiet hours too.
+    fn overrides_quiet_hours(&self) -> bool;
+
+    /// Remember the members mentioned by a notification, for its "Who was pinged?" button.
+    ///
+    /// See [`RecipientStore`].
+    fn record_recipients(&self, message: serenity::MessageId, members: &HashSet<serenity::UserId>);
+
+    /// Remember the messages a split notification starting with `notice` has left to send, so it
+    /// can be finished if Intersection restarts part-way through.
+    ///
+    /// See [`PendingSendStore`].
+    fn save_pending_send(&self, notice: serenity::MessageId, remaining: &[String], silent: bool);
+
+    /// Forget the messages a split notification had left to send, once they've all been sent.
+    fn finish_pending_send(&self, notice: serenity::MessageId);
+}
+
+/// The real implementation of [`Discord`], handling a query sent in a [`serenity::Message`].
+pub struct SerenityDiscord<'a> {
+    /// The Context made available to the event handler
+    pub ctx: &'a serenity::Context,
+    /// The message containing the query
+    pub msg: &'a serenity::Message,
+    /// The cache used when evaluating the query
+    pub query_cache: &'a QueryCache,
+    /// Where every reply is recorded, so it can be deleted along with the message
+    pub reply_tracker: &'a ReplyTracker,
+    /// Where the members mentioned by each notification are recorded
+    pub recipients: &'a RecipientStore,
+    /// The preferences of the members matched by the query
+    pub preferences: &'a PreferenceStore,
+    /// The webhooks used to send messages with the author's name and avatar
+    pub webhooks: &'a WebhookStore,
+    /// The queue each notification waits in before being sent
+    pub send_queues: &'a SendQueues,
+    /// The split notifications being sent, kept in case of a restart
+    pub pending_sends: &'a PendingSendStore,
+    /// When each member last sent a message, for `active(...)` queries
+    pub activity: &'a ActivityTracker,
+    /// Recent notifications, used to enforce cooldowns
+    pub cooldowns: &'a CooldownTracker,
+    /// Recently sent queries, used to spot duplicates
+    pub duplicate_queries: &'a DuplicateQueryTracker,
+    /// The recent notifications in every guild, which this one is added to
+    pub history: &'a QueryHistory,
+    /// How much Intersection is used in every guild, which this notification counts towards
+    pub stats: &'a UsageStats,
+    /// The configuration of the guild
+    pub config: &'a GuildConfig,
+}
+
+impl SerenityDiscord<'_> {
+    /// The roles of the query's author, from the message or, failing that, the cache.
+    fn author_roles(&self) -> Vec<serenity::RoleId> {
+        self.msg
+            .member
+            .as_ref()
+            .map(|member| member.roles.clone())
+            .or_else(|| {
+                let guild_id = self.msg.guild_id?;
+                Some(self.ctx.cache.member(guild_id, self.msg.author.id)?.roles)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Obtain the guild the message was sent in from the cache.
+    fn guild(&self) -> Result<serenity::Guild, QueryError> {
+        self.msg.guild(self.ctx).ok_or_else(|| {
+            debug!("Ignoring DRQL query sent in DMs.");
+            QueryError::ResolutionError(i18n::message(
+                self.config.language,
+                "dm-queries-unavailable",
+                &[],
+            ))
+        })
+    }
+
+    /// Send a message as a reply to the query message.
+    async fn send_reply(&self, message: OutgoingMessage) -> serenity::Result<serenity::MessageId> {
+        Ok(self
+            .msg
+            .channel_id
+            .send_message(self.ctx, |builder| {
+                // Like Message::reply, this doesn't ping the author of the message being replied to
+                builder
+                    .reference_message(self.msg)
+                    .allowed_mentions(|allowed_mentions| {
+                        set_allowed_mentions(allowed_mentions.replied_user(false), &message)
+                    })
+                    .content(&message.content);
+                if let Some(embed) = message.embed {
+                    builder.embed(|create_embed| build_embed(create_embed, embed));
+                }
+                if message.silent {
+                    builder.flags(serenity::MessageFlags::SUPPRESS_NOTIFICATIONS);
+                }
+                if !message.buttons.is_empty() {
+                    builder.components(|components| add_buttons(components, message.buttons));
+                }
+                builder
+            })
+            .await?
+            .id)
+    }
+
+    /// Send a message through the channel's webhook with the author's name and avatar, returning
+    /// [`None`] if a webhook can't be used in this channel.
+    async fn send_as_author(
+        &self,
+        message: OutgoingMessage,
+    ) -> serenity::Result<Option<serenity::MessageId>> {
+        let serenity::Channel::Guild(channel) = self.msg.channel(self.ctx).await? else {
+            return Ok(None);
+        };
+        let Some(webhook) = webhooks::channel_webhook(self.ctx, &channel, self.webhooks).await?
+        else {
+            return Ok(None);
+        };
+        let name = self
+            .msg
+            .author_nick(self.ctx)
+            .await
+            .unwrap_or_else(|| self.msg.author.name.clone());
+
+        let sent = webhook
+            .execute(self.ctx, true, |builder| {
+                builder
+                    .username(name)
+                    .avatar_url(self.msg.author.face())
+                    .allowed_mentions(|allowed_mentions| {
+                        set_allowed_mentions(allowed_mentions, &message)
+                    })
+                    .content(&message.content);
+                if let Some(embed) = message.embed {
+                    builder.embeds(vec![serenity::Embed::fake(|create_embed| {
+                        build_embed(create_embed, embed)
+                    })]);
+                }
+                if message.silent {
+                    builder.flags(serenity::MessageFlags::SUPPRESS_NOTIFICATIONS);
+                }
+                if !message.buttons.is_empty() {
+                    builder.components(|components| add_buttons(components, message.buttons));
+                }
+                builder
+            })
+            .await?;
+
+        Ok(sent.map(|sent| sent.id))
+    }
+}
+
+#[async_trait]
+impl Discord for SerenityDiscord<'_> {
+    async fn evaluate(
+        &self,
+        chunks: &[&str],
+        progress: &watch::Sender<usize>,
+    ) -> Result<Evaluation, QueryError> {
+        let guild = self.guild()?;
+        let member = self.msg.member(self.ctx).await?;
+        let Some(channel) = self.msg.channel(self.ctx).await?.guild() else {
+            // DMs would have been prevented already, and messages can't be sent in categories
+            return Err(QueryError::ResolutionError(i18n::message(
+                self.config.language,
+                "server-channels-only",
+                &[],
+            )));
+        };
+        // Threads and forum posts are evaluated with the permissions of the channel they're in
+        let channel = channel.permission_channel(self.ctx).await?;
+
+        let context = QueryContext::new(
+            self.ctx,
+            self.activity,
+            &guild,
+            &member,
+            &channel,
+            self.config,
+        );
+        parse_and_evaluate_query(&context, self.query_cache, chunks, Some(progress)).await
+    }
+
+    fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.config.aliases
+    }
+
+    async fn roles_and_members(
+        &self,
+    ) -> Result<HashMap<RoleType, HashSet<serenity::UserId>>, QueryError> {
+        Ok(self.guild()?.all_roles_and_members(self.ctx)?)
+    }
+
+    async fn reply(&self, message: OutgoingMessage) -> Result<serenity::MessageId, QueryError> {
+        let id = if message.send_as_author {
+            match self.send_as_author(message.clone()).await {
+                Ok(Some(id)) => id,
+                Ok(None) => self.send_reply(message).await?,
+                Err(err) => {
+                    warn!("Unable to send message through a webhook, replying instead: {err}");
+                    self.send_reply(message).await?
+                }
+            }
+        } else {
+            self.send_reply(message).await?
+        };
+        self.reply_tracker.record(self.msg.id, id);
+
+        Ok(id)
+    }
+
+    async fn send_to_channel(
+        &self,
+        channel: serenity::ChannelId,
+        message: OutgoingMessage,
+    ) -> Result<serenity::MessageId, QueryError> {
+        Ok(channel
+            .send_message(self.ctx, |builder| {
+                builder
+                    .allowed_mentions(|allowed_mentions| {
+                        set_allowed_mentions(allowed_mentions, &message)
+                    })
+                    .content(&message.content);
+                if let Some(embed) = message.embed {
+                    builder.embed(|create_embed| build_embed(create_embed, embed));
+                }
+                builder
+            })
+            .await?
+            .id)
+    }
+
+    async fn edit(
+        &self,
+        id: serenity::MessageId,
+        message: OutgoingMessage,
+    ) -> Result<(), QueryError> {
+        self.msg
+            .channel_id
+            .edit_message(self.ctx, id, |edit_handle| {
+                edit_handle
+                    .content(message.content)
+                    .components(|components| add_buttons(components, message.buttons))
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: serenity::MessageId) -> Result<(), QueryError> {
+        self.msg.channel_id.delete_message(self.ctx, id).await?;
+        Ok(())
+    }
+
+    fn delete_later(&self, messages: Vec<serenity::MessageId>, after: Duration) {
+        let ctx = self.ctx.clone();
+        let channel = self.msg.channel_id;
+        tokio::spawn(async move {
+            tokio::time::sleep(after).await;
+            // Members may have deleted some of them in the meantime, so it's only worth a mention
+            if let Err(err) = reply_tracker::delete_messages(&ctx, channel, &messages).await {
+                debug!("Unable to delete expired mention messages: {err}");
+            }
+        });
+    }
+
+    async fn broadcast_typing(&self) -> Result<(), QueryError> {
+        self.msg.channel_id.broadcast_typing(self.ctx).await?;
+        Ok(())
+    }
+
+    async fn react_to_query(&self, emoji: &str) -> Result<(), QueryError> {
+        self.msg
+            .react(self.ctx, serenity::ReactionType::Unicode(emoji.to_string()))
+            .await?;
+        Ok(())
+    }
+
+    async fn lock_send_queue(&self) -> SendQueueGuard {
+        self.send_queues.acquire(self.msg.channel_id).await
+    }
+
+    async fn wait(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    async fn await_button_press(
+        &self,
+        id: serenity::MessageId,
+        timeout: Duration,
+    ) -> Result<Option<String>, QueryError> {
+        let Some(interaction) = serenity::CollectComponentInteraction::new(self.ctx)
+            .message_id(id)
+            .author_id(self.msg.author.id)
+            .collect_limit(1)
+            .timeout(timeout)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        // Acknowledge the interaction so Discord doesn't show it as failed. Any changes to the
+        // message are made separately.
+        interaction
+            .create_interaction_response(self.ctx, |response| {
+                response.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await
+            .context("Error acknowledging button press")?;
+
+        Ok(Some(interaction.data.custom_id.clone()))
+    }
+
+    async fn await_button_press_or_text(
+        &self,
+        id: serenity::MessageId,
+        text_button: &str,
+        prompt: &TextPrompt,
+        timeout: Duration,
+    ) -> Result<Option<ButtonResponse>, QueryError> {
+        let Some(interaction) = serenity::CollectComponentInteraction::new(self.ctx)
+            .message_id(id)
+            .author_id(self.msg.author.id)
+            .collect_limit(1)
+            .timeout(timeout)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        if interaction.data.custom_id != text_button {
+            interaction
+                .create_interaction_response(self.ctx, |response| {
+                    response.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await
+                .context("Error acknowledging button press")?;
+            return Ok(Some(ButtonResponse::Pressed(
+                interaction.data.custom_id.clone(),
+            )));
+        }
+
+        // Each prompt's modal gets its own ID, so submitting an old one can't answer a newer one
+        let modal_id = format!("text_prompt_{id}");
+        interaction
+            .create_interaction_response(self.ctx, |response| {
+                response
+                    .kind(serenity::InteractionResponseType::Modal)
+                    .interaction_response_data(|data| {
+                        data.custom_id(&modal_id)
+                            .title(&prompt.title)
+                            .components(|components| {
+                                components.create_action_row(|row| {
+                                    row.create_input_text(|input| {
+                                        input
+                                            .custom_id("text")
+                                            .label(&prompt.label)
+                                            .style(serenity::InputTextStyle::Short)
+                                            .required(true)
+                                    })
+                                })
+                            })
+                    })
+            })
+            .await
+            .context("Error opening text prompt")?;
+
+        let Some(submission) = serenity::CollectModalInteraction::new(self.ctx)
+            .author_id(self.msg.author.id)
+            .filter(move |submission| submission.data.custom_id == modal_id)
+            .collect_limit(1)
+            .timeout(timeout)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        submission
+            .create_interaction_response(self.ctx, |response| {
+                response.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await
+            .context("Error acknowledging text prompt")?;
+
+        let text = submission
+            .data
+            .components
+            .iter()
+            .flat_map(|row| &row.components)
+            .find_map(|component| {
+                if let serenity::ActionRowComponent::InputText(input) = component {
+                    Some(input.value.clone())
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Ok(Some(ButtonResponse::Typed(text)))
+    }
+
+    async fn await_approval(
+        &self,
+        id: serenity::MessageId,
+        role: serenity::RoleId,
+        author_buttons: &[&str],
+        timeout: Duration,
+    ) -> Result<Option<(String, serenity::UserId)>, QueryError> {
+        let author = self.msg.author.id;
+        let author_buttons = author_buttons
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let Some(interaction) = serenity::CollectComponentInteraction::new(self.ctx)
+            .message_id(id)
+            .filter(move |interaction| {
+                if interaction.user.id == author {
+                    author_buttons.contains(&interaction.data.custom_id)
+                } else {
+                    interaction
+                        .member
+                        .as_ref()
+                        .is_some_and(|member| member.roles.contains(&role))
+                }
+            })
+            .collect_limit(1)
+            .timeout(timeout)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        // Acknowledge the interaction so Discord doesn't show it as failed. Any changes to the
+        // message are made separately.
+        interaction
+            .create_interaction_response(self.ctx, |response| {
+                response.kind(serenity::InteractionResponseType::DeferredUpdateMessage)
+            })
+            .await
+            .context("Error acknowledging button press")?;
+
+        Ok(Some((
+            interaction.data.custom_id.clone(),
+            interaction.user.id,
+        )))
+    }
+
+    async fn mention_command(&self, command: &str) -> Result<String, QueryError> {
+        Ok(util::mention_application_command(self.ctx, command).await?)
+    }
+
+    async fn create_thread(&self, name: &str) -> Result<serenity::ChannelId, QueryError> {
+        if let serenity::Channel::Guild(channel) = self.msg.channel(self.ctx).await? {
+            if channel.thread_metadata.is_some() {
+                debug!("Query was sent in a thread, using it");
+                return Ok(channel.id);
+            }
+        }
+
+        Ok(self
+            .msg
+            .channel_id
+            .create_public_thread(self.ctx, self.msg.id, |thread| thread.name(name))
+            .await?
+            .id)
+    }
+
+    async fn add_thread_members(
+        &self,
+        thread: serenity::ChannelId,
+        members: &[serenity::UserId],
+    ) -> Result<(), QueryError> {
+        // Discord can only add one member at a time; Serenity waits out any rate limits for us
+        for &member in members {
+            thread.add_thread_member(self.ctx, member).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_dm(
+        &self,
+        user: serenity::UserId,
+        message: OutgoingMessage,
+    ) -> Result<(), QueryError> {
+        user.create_dm_channel(self.ctx)
+            .await?
+            .send_message(self.ctx, |builder| {
+                builder
+                    .allowed_mentions(|allowed_mentions| {
+                        set_allowed_mentions(allowed_mentions, &message)
+                    })
+                    .content(&message.content)
+            })
+            .await?;
+        Ok(())
+    }
+
+    fn author(&self) -> serenity::UserId {
+        self.msg.author.id
+    }
+
+    async fn check_access(&self) -> Result<(), QueryError> {
+        if self.config.access.is_unrestricted() {
+            return Ok(());
+        }
+        let serenity::Channel::Guild(channel) = self.msg.channel(self.ctx).await? else {
+            // DMs are refused later on
+            return Ok(());
+        };
+        let channel = channel.permission_channel(self.ctx).await?;
+
+        let member = self.msg.member(self.ctx).await?;
+        let permissions = channel.permissions_for_user(self.ctx, member.user.id)?;
+        self.config
+            .access
+            .check(&member.roles, permissions, self.config.language)
+    }
+
+    fn reserve_cooldown(&self) -> Result<Option<CooldownReservation<'_>>, QueryError> {
+        let Some(guild_id) = self.msg.guild_id else {
+            return Ok(None);
+        };
+
+        self.cooldowns.reserve(
+            guild_id,
+            self.msg.author.id,
+            &self.author_roles(),
+            &self.config.cooldowns,
+            self.config.language,
+        )
+    }
+
+    fn record_notification(&self, reservation: Option<CooldownReservation<'_>>) {
+        if let Some(reservation) = reservation {
+            reservation.sent();
+        }
+    }
+
+    fn last_sent(&self, query: &str) -> Option<Duration> {
+        self.duplicate_queries.last_sent(
+            self.msg.channel_id,
+            query,
+            self.config.duplicate_queries.window,
+        )
+    }
+
+    fn record_query(&self, query: &str) {
+        if self.config.duplicate_queries.mode != DuplicateQueryMode::Allow {
+            self.duplicate_queries.record(
+                self.msg.channel_id,
+                query,
+                self.config.duplicate_queries.window,
+            );
+        }
+    }
+
+    fn record_history(&self, query: &str, members: usize) {
+        if let Some(guild_id) = self.msg.guild_id {
+            self.history.record(
+                guild_id,
+                HistoryEntry {
+                    author: self.msg.author.id,
+                    query: query.to_string(),
+                    members,
+                    sent_at: self.msg.timestamp,
+                },
+            );
+        }
+    }
+
+    fn record_stats(&self, members: usize, roles: &[RoleType]) {
+        if let Some(guild_id) = self.msg.guild_id {
+            self.stats.record(guild_id, members, roles);
+        }
+    }
+
+    fn channel(&self) -> serenity::ChannelId {
+        self.msg.channel_id
+    }
+
+    fn query_link(&self) -> String {
+        self.msg.link()
+    }
+
+    fn link(&self, message: serenity::MessageId) -> String {
+        message.link(self.msg.channel_id, self.msg.guild_id)
+    }
+
+    fn wants_dm_notifications(&self, user: serenity::UserId) -> bool {
+        self.preferences.wants_dm_notifications(user)
+    }
+
+    fn has_opted_out(&self, user: serenity::UserId) -> bool {
+        self.msg
+            .guild_id
+            .is_some_and(|guild_id| self.preferences.has_opted_out(guild_id, user))
+    }
+
+    fn is_quiet(&self, user: serenity::UserId) -> bool {
+        self.msg
+            .guild_id
+            .and_then(|guild_id| self.preferences.quiet_hours(guild_id, user))
+            .is_some_and(|hours| hours.contains(chrono::Utc::now()))
+    }
+
+    fn overrides_quiet_hours(&self) -> bool {
+        self.author_roles()
+            .iter()
+            .any(|role| self.config.emergency_roles.contains(role))
+    }
+
+    fn record_recipients(&self, message: serenity::MessageId, members: &HashSet<serenity::UserId>) {
+        self.recipients.record(message, members);
+    }
+
+    fn save_pending_send(&self, notice: serenity::MessageId, remaining: &[String], silent: bool) {
+        let Some(guild) = self.msg.guild_id else {
+            return;
+        };
+        self.pending_sends.update(
+            notice,
+            PendingSend {
+                guild,
+                channel: self.msg.channel_id,
+                author: self.msg.author.id,
+                started: SystemTime::now(),
+                silent,
+                remaining: remaining.to_vec(),
+            },
+        );
+    }
+
+    fn finish_pending_send(&self, notice: serenity::MessageId) {
+        self.pending_sends.finish(notice);
+    }
+}
+
+/// Allow the mentions in a message to notify people, unless they're suppressed.
+fn set_allowed_mentions<'a>(
+    allowed_mentions: &'a mut serenity::CreateAllowedMentions,
+    message: &OutgoingMessage,
+) -> &'a mut serenity::CreateAllowedMentions {
+    if !message.suppress_mentions {
+        allowed_mentions
+            .parse(serenity::ParseValue::Everyone)
+            .parse(serenity::ParseValue::Users)
+            .parse(serenity::ParseValue::Roles);
+    }
+    allowed_mentions
+}
+
+/// Fill in an embed builder from an [`Embed`].
+fn build_embed(
+    create_embed: &mut serenity::CreateEmbed,
+    embed: Embed,
+) -> &mut serenity::CreateEmbed {
+    create_embed
+        .title(embed.title)
+        .description(embed.description)
+        .fields(
+            embed
+                .fields
+                .into_iter()
+                .map(|(name, value)| (name, value, true)),
+        )
+}
+
+/// Add a row of [`Button`]s to a component builder, if there are any.
+fn add_buttons(
+    components: &mut serenity::CreateComponents,
+    buttons: Vec<Button>,
+) -> &mut serenity::CreateComponents {
+    if buttons.is_empty() {
+        return components;
+    }
+
+    components.create_action_row(|action_row| {
+        for button in buttons {
+            action_row.create_button(|create_button| {
+                create_button
+                    .custom_id(button.custom_id)
+                    .label(button.label)
+                    .style(button.style);
+                if let Some(emoji) = button.emoji {
+                    create_button.emoji(serenity::ReactionType::Unicode(emoji));
+                }
+                create_button
+            });
+        }
+        action_row
+    })
+}
@@ -2,8 +2,16 @@
 //!
 //! This module provides all of the tools you could ever need to work with DRQL.
 
+pub mod aliases;
 pub mod ast;
+pub mod completion;
+pub mod diagnostics;
+pub mod formatter;
 pub mod interpreter;
 pub mod lexer;
+pub mod optimizer;
 pub mod parser;
+pub mod pattern;
 pub mod scanner;
+pub mod testing;
+pub mod time;
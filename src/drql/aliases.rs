@@ -0,0 +1,268 @@
+//! Expanding aliases, the query fragments a guild gave names to, like `$oncall`
+//!
+//! An alias stands for a query of its own, like `"on call" & here`, which may use other aliases.
+//! [`expand`] replaces every alias in a query with the query it stands for before the query is
+//! interpreted, so the interpreter never sees them. An alias which (eventually) uses itself could
+//! never be expanded, so it's refused, and so are aliases expanding to more nodes than a query may
+//! have, which a few aliases each using the previous one twice quickly would.
+
+use std::fmt::{Display, Formatter};
+
+use lalrpop_util::ParseError;
+
+use super::{
+    ast::Expr,
+    lexer::{DrqlLexer, LexicalError, Tok},
+    parser::parse_drql,
+};
+
+/// Why the aliases in a query couldn't be expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasError {
+    /// The query used an alias which isn't defined
+    Unknown(String),
+    /// An alias uses itself, through the aliases listed in order, starting and ending with it
+    Cycle(Vec<String>),
+    /// An alias stands for something which isn't a valid query
+    Invalid(String, ParseError<usize, Tok, LexicalError>),
+    /// Expanding the query's aliases gave more than this many nodes
+    TooLarge(usize),
+}
+
+impl Display for AliasError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "`${name}` isn't an alias in this server."),
+            Self::Cycle(names) => write!(
+                f,
+                "`${}` can't be expanded, as it uses itself: `{}`.",
+                names.first().map_or("", String::as_str),
+                names
+                    .iter()
+                    .map(|name| format!("${name}"))
+                    .collect::<Vec<_>>()
+                    .join(" \u{2192} ")
+            ),
+            Self::Invalid(name, error) => {
+                write!(f, "`${name}` doesn't stand for a valid query: {error}")
+            }
+            Self::TooLarge(limit) => write!(
+                f,
+                "The query's aliases expand to more than {limit} operators and operands."
+            ),
+        }
+    }
+}
+
+/// Read the name of an alias, written with or without its `$`, as it's stored (in lowercase), or
+/// [`None`] if it isn't a valid name.
+#[must_use]
+pub fn alias_name(name: &str) -> Option<String> {
+    let written = format!("${}", name.trim().trim_start_matches('$'));
+    let mut lexer = DrqlLexer::new(&written);
+    match (lexer.next(), lexer.next()) {
+        (Some(Ok((_, Tok::Alias(name), _))), None) => Some(name.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Replace every alias in `expr` with the query it stands for, as given by `definition` (which is
+/// passed the alias's name without the `$`), recursively.
+///
+/// # Errors
+///
+/// Fails if an alias isn't defined, stands for an invalid query, or uses itself, or if the
+/// expanded query would have more than `max_nodes` nodes.
+pub fn expand(
+    expr: Expr,
+    definition: impl Fn(&str) -> Option<String>,
+    max_nodes: usize,
+) -> Result<Expr, AliasError> {
+    let mut expander = Expander {
+        definition,
+        expanding: Vec::new(),
+        nodes: 0,
+        max_nodes,
+    };
+    expander.expand(expr)
+}
+
+/// The state of expanding the aliases in a query
+struct Expander<F> {
+    /// What each alias stands for
+    definition: F,
+    /// The aliases being expanded, outermost first, which would be a cycle if used again
+    expanding: Vec<String>,
+    /// How many nodes the expanded query has so far
+    nodes: usize,
+    /// The most nodes the expanded query may have
+    max_nodes: usize,
+}
+
+impl<F: Fn(&str) -> Option<String>> Expander<F> {
+    /// Expand the aliases in `expr`, counting its nodes.
+    fn expand(&mut self, expr: Expr) -> Result<Expr, AliasError> {
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            return Err(AliasError::TooLarge(self.max_nodes));
+        }
+
+        let binary = |expander: &mut Self, lhs: Box<Expr>, rhs: Box<Expr>| {
+            Ok::<_, AliasError>((
+                Box::new(expander.expand(*lhs)?),
+                Box::new(expander.expand(*rhs)?),
+            ))
+        };
+        Ok(match expr {
+            Expr::Union(lhs, rhs) => {
+                let (lhs, rhs) = binary(self, lhs, rhs)?;
+                Expr::Union(lhs, rhs)
+            }
+            Expr::Intersection(lhs, rhs) => {
+                let (lhs, rhs) = binary(self, lhs, rhs)?;
+                Expr::Intersection(lhs, rhs)
+            }
+            Expr::Difference(lhs, rhs) => {
+                let (lhs, rhs) = binary(self, lhs, rhs)?;
+                Expr::Difference(lhs, rhs)
+            }
+            Expr::Complement(inner) => Expr::Complement(Box::new(self.expand(*inner)?)),
+            Expr::Sample(inner, count) => Expr::Sample(Box::new(self.expand(*inner)?), count),
+            Expr::Limit(inner, count) => Expr::Limit(Box::new(self.expand(*inner)?), count),
+            Expr::AtLeast(count, operands) => Expr::AtLeast(
+                count,
+                operands
+                    .into_iter()
+                    .map(|operand| self.expand(operand))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::Alias(name) => {
+                // The alias itself is replaced, so it isn't a node of the expanded query
+                self.nodes -= 1;
+                self.expand_alias(&name)?
+            }
+            Expr::StringLiteral(_)
+            | Expr::UnknownID(_)
+            | Expr::UserID(_)
+            | Expr::RoleID(_)
+            | Expr::ChannelID(_)
+            | Expr::Me
+            | Expr::Event(_)
+            | Expr::Permission(_)
+            | Expr::Joined(_, _)
+            | Expr::AccountAge(_)
+            | Expr::Name(_)
+            | Expr::Active(_)
+            | Expr::Reacted(_, _) => expr,
+        })
+    }
+
+    /// Replace the alias `name` with the query it stands for, with its own aliases expanded.
+    fn expand_alias(&mut self, name: &str) -> Result<Expr, AliasError> {
+        let name = name.to_lowercase();
+        if let Some(start) = self.expanding.iter().position(|outer| *outer == name) {
+            let mut cycle = self.expanding[start..].to_vec();
+            cycle.push(name);
+            return Err(AliasError::Cycle(cycle));
+        }
+
+        let definition =
+            (self.definition)(&name).ok_or_else(|| AliasError::Unknown(name.clone()))?;
+        let expr =
+            parse_drql(&definition).map_err(|error| AliasError::Invalid(name.clone(), error))?;
+        self.expanding.push(name);
+        let expanded = self.expand(expr)?;
+        self.expanding.pop();
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Expand the aliases in `query`, defined by `aliases`, within 100 nodes and write the result
+    /// out.
+    fn expanded(query: &str, aliases: &[(&str, &str)]) -> Result<String, AliasError> {
+        let aliases = aliases.iter().copied().collect::<HashMap<_, _>>();
+        let expr = parse_drql(query).expect("test queries should parse");
+        expand(expr, |name| aliases.get(name).map(ToString::to_string), 100)
+            .map(|expr| expr.to_string())
+    }
+
+    #[test]
+    fn aliases_are_replaced_by_what_they_stand_for() {
+        let aliases = [("oncall", "\"on call\" | $leads"), ("leads", "<@&1>")];
+        assert_eq!(
+            expanded("$oncall & here", &aliases),
+            Ok("((\"on call\" | <@&1>) & here)".to_string())
+        );
+        assert_eq!(expanded("$OnCall", &aliases), expanded("$oncall", &aliases));
+        assert_eq!(expanded("staff - me", &[]), Ok("(staff - me)".to_string()));
+    }
+
+    #[test]
+    fn alias_names_are_read_with_or_without_a_dollar_sign() {
+        assert_eq!(alias_name("OnCall"), Some("oncall".to_string()));
+        assert_eq!(alias_name("$team_2"), Some("team_2".to_string()));
+        assert_eq!(alias_name("2team"), None);
+        assert_eq!(alias_name("on call"), None);
+        assert_eq!(alias_name(""), None);
+    }
+
+    #[test]
+    fn unknown_aliases_are_refused() {
+        assert_eq!(
+            expanded("staff + $nobody", &[]),
+            Err(AliasError::Unknown("nobody".to_string()))
+        );
+        assert!(matches!(
+            expanded("$broken", &[("broken", "staff +")]),
+            Err(AliasError::Invalid(name, _)) if name == "broken"
+        ));
+    }
+
+    #[test]
+    fn cycles_are_refused() {
+        let aliases = [("a", "staff | $b"), ("b", "sample($c, 1)"), ("c", "$a")];
+        assert_eq!(
+            expanded("here & $b", &aliases),
+            Err(AliasError::Cycle(vec![
+                "b".to_string(),
+                "c".to_string(),
+                "a".to_string(),
+                "b".to_string()
+            ]))
+        );
+        assert_eq!(
+            expanded("$a", &[("a", "$a")]).map_err(|error| error.to_string()),
+            Err("`$a` can't be expanded, as it uses itself: `$a \u{2192} $a`.".to_string())
+        );
+        // Using an alias twice, side by side, isn't a cycle
+        assert!(expanded("$b + $b", &[("b", "staff")]).is_ok());
+    }
+
+    #[test]
+    fn expansions_are_limited_in_size() {
+        // Each alias is twice the size of the one before it
+        let names = (0..10).map(|n| format!("a{n}")).collect::<Vec<_>>();
+        let definitions = (0..10)
+            .map(|n| {
+                if n == 0 {
+                    "staff".to_string()
+                } else {
+                    format!("$a{0} + $a{0}", n - 1)
+                }
+            })
+            .collect::<Vec<_>>();
+        let aliases = names
+            .iter()
+            .map(String::as_str)
+            .zip(definitions.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        assert!(expanded("$a5", &aliases).is_ok());
+        assert_eq!(expanded("$a9", &aliases), Err(AliasError::TooLarge(100)));
+    }
+}
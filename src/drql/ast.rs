@@ -1,18 +1,40 @@
 //! DRQL's Abstract Syntax Tree
 
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    time::Duration,
+};
 
-use poise::serenity_prelude::model::prelude::{RoleId, UserId};
+use poise::serenity_prelude::model::prelude::{
+    ChannelId, GuildId, MessageId, Permissions, ReactionType, RoleId, ScheduledEventId, UserId,
+};
+
+use super::{
+    lexer::{quote, LexicalError, KEYWORDS},
+    pattern::NamePattern,
+    time::{self, Age, Moment, Side},
+};
 
 /// Represents a single DRQL query, or a view into that query
-#[derive(Debug, PartialEq)]
+///
+/// Expressions are compared and hashed structurally, so two copies of the same subtree are equal.
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Expr {
     /// Represents the union of two expressions, `a + b` or `a | b`
-    Union(Box<Expr>, Box<Expr>),
+    Union(Box<Self>, Box<Self>),
     /// Represents the intersection of two expressions, `a & b`
-    Intersection(Box<Expr>, Box<Expr>),
+    Intersection(Box<Self>, Box<Self>),
     /// Represents the difference between two expressions, `a - b`
-    Difference(Box<Expr>, Box<Expr>),
+    Difference(Box<Self>, Box<Self>),
+    /// Represents everyone except the members of an expression, `!a` or `~a`
+    Complement(Box<Self>),
+    /// Represents some randomly chosen members of an expression, `sample(a, 5)`
+    Sample(Box<Self>, usize),
+    /// Represents the members of at least `count` of several expressions, `atleast(2, a, b, c)`
+    AtLeast(usize, Vec<Self>),
+    /// Represents the members of an expression with the lowest IDs, at most `count` of them,
+    /// `a limit 25`
+    Limit(Box<Self>, usize),
 
     /// The name of a role itself, like `everyone`
     StringLiteral(String),
@@ -26,6 +48,276 @@ pub enum Expr {
     ///
     /// This is generated when a user is mentioned directly in a query.
     RoleID(RoleId),
+    /// A channel, generated when a channel is mentioned in a query.
+    ///
+    /// A voice channel refers to the members connected to it, a thread to its members, and a text
+    /// channel to the members who can view it.
+    ChannelID(ChannelId),
+    /// The author of the query, `me`
+    Me,
+    /// The users interested in a scheduled event, `event(123)`
+    Event(ScheduledEventId),
+    /// The members with a guild permission, `perm(ban_members)`
+    Permission(Permissions),
+    /// The members who joined before or after a moment, `joined_before(30d)` or
+    /// `joined_after("2024-01-01")`
+    Joined(Side, Moment),
+    /// The members whose accounts are younger or older than a duration, `account_age(<7d)`
+    AccountAge(Age),
+    /// The members whose username or nickname matches a pattern, `name("*dev*")` or
+    /// `name(/^team-red/)`
+    Name(NamePattern),
+    /// The members who sent a message within a duration, `active(7d)`
+    Active(Duration),
+    /// The users who reacted to a message with an emoji, `reacted(https://discord.com/channels/1/2/3, ✅)`
+    Reacted(MessageLink, ReactionType),
+    /// A query fragment the guild gave a name to, `$oncall`, which is replaced by what it stands
+    /// for before the query is interpreted (see [`expand`](super::aliases::expand))
+    Alias(String),
+}
+
+/// A link to a message, like `https://discord.com/channels/1/2/3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageLink {
+    /// The guild the message was sent in, or `None` if it was sent in a DM (`@me`)
+    pub guild: Option<GuildId>,
+    /// The channel the message was sent in
+    pub channel: ChannelId,
+    /// The message itself
+    pub message: MessageId,
+}
+
+impl MessageLink {
+    /// Parse a link to a message, without looking at the host it links to.
+    #[must_use]
+    pub fn parse(link: &str) -> Option<Self> {
+        let mut parts = link.rsplit('/');
+        let message = MessageId(parts.next()?.parse().ok()?);
+        let channel = ChannelId(parts.next()?.parse().ok()?);
+        let guild = match parts.next()? {
+            "@me" => None,
+            guild => Some(GuildId(guild.parse().ok()?)),
+        };
+        Some(Self {
+            guild,
+            channel,
+            message,
+        })
+    }
+}
+
+impl Display for MessageLink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "https://discord.com/channels/")?;
+        match self.guild {
+            Some(guild) => write!(f, "{guild}")?,
+            None => write!(f, "@me")?,
+        }
+        write!(f, "/{}/{}", self.channel, self.message)
+    }
+}
+
+/// The count written as a function's argument, like the `5` in `sample(volunteers, 5)`
+fn count(argument: Option<Expr>) -> Option<usize> {
+    if let Some(Expr::UnknownID(id)) = argument {
+        id.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// The name of a single permission in `perm(...)`, like `ban_members`
+fn permission_name(permission: Permissions) -> String {
+    // Single flags are debug formatted as their name, like `BAN_MEMBERS`
+    format!("{permission:?}").to_lowercase()
+}
+
+impl Expr {
+    /// Create the expression for a call to the function `name`, like `event(123)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no such function, or it doesn't take `argument`.
+    pub fn call(name: &str, argument: &str) -> Result<Self, LexicalError> {
+        let invalid = || LexicalError::InvalidFunctionCall(format!("{name}({argument})"));
+        match name {
+            "event" => Ok(Self::Event(ScheduledEventId(
+                argument.parse().map_err(|_| invalid())?,
+            ))),
+            "perm" => (0..u64::BITS)
+                .map(|bit| Permissions::from_bits_truncate(1 << bit))
+                .find(|permission| {
+                    !permission.is_empty()
+                        && permission_name(*permission) == argument.to_lowercase()
+                })
+                .map(Self::Permission)
+                .ok_or_else(invalid),
+            "joined_before" => Moment::parse(argument)
+                .map(|moment| Self::Joined(Side::Before, moment))
+                .ok_or_else(invalid),
+            "joined_after" => Moment::parse(argument)
+                .map(|moment| Self::Joined(Side::After, moment))
+                .ok_or_else(invalid),
+            "account_age" => Age::parse(argument)
+                .map(Self::AccountAge)
+                .ok_or_else(invalid),
+            "active" => time::parse_duration(argument)
+                .map(Self::Active)
+                .ok_or_else(invalid),
+            "name" => Self::name(NamePattern::Glob(argument.to_string())).ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Create the expression for a call to the function `name` taking a regex, like
+    /// `name(/^team-red/)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no such function, or `regex` is invalid.
+    pub fn call_with_regex(name: &str, regex: &str) -> Result<Self, LexicalError> {
+        let invalid = || LexicalError::InvalidFunctionCall(format!("{name}(/{regex}/)"));
+        match name {
+            "name" => Self::name(NamePattern::Regex(regex.to_string())).ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Create the expression for a call to the function `name` taking several expressions, like
+    /// `sample(volunteers, 5)` or `atleast(2, red, green, blue)`, where counts are written as IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no such function, or it doesn't take `arguments`.
+    pub fn call_with_exprs(name: &str, arguments: Vec<Self>) -> Result<Self, LexicalError> {
+        let error = LexicalError::InvalidFunctionCall(format!(
+            "{name}({})",
+            arguments
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        let mut arguments = arguments.into_iter();
+        match name {
+            "sample" => match (arguments.next(), count(arguments.next()), arguments.next()) {
+                (Some(inner), Some(count), None) => Ok(Self::Sample(Box::new(inner), count)),
+                _ => Err(error),
+            },
+            "atleast" => match (count(arguments.next()), arguments.collect::<Vec<_>>()) {
+                (Some(count), operands) if !operands.is_empty() => {
+                    Ok(Self::AtLeast(count, operands))
+                }
+                _ => Err(error),
+            },
+            _ => Err(error),
+        }
+    }
+
+    /// Create a `name(...)` expression, if its pattern compiles.
+    fn name(pattern: NamePattern) -> Option<Self> {
+        pattern.compile().is_ok().then_some(Self::Name(pattern))
+    }
+
+    /// Create the expression for a call to the function `name` taking a message and an emoji, like
+    /// `reacted(https://discord.com/channels/1/2/3, ✅)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no such function, or `link` or `emoji` is invalid.
+    pub fn call_with_reaction(name: &str, link: &str, emoji: &str) -> Result<Self, LexicalError> {
+        let invalid = || LexicalError::InvalidFunctionCall(format!("{name}({link}, {emoji})"));
+        match name {
+            "reacted" => Ok(Self::Reacted(
+                MessageLink::parse(link).ok_or_else(invalid)?,
+                ReactionType::try_from(emoji).map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The expressions directly inside this one, in order, like `a` and `b` in `a | b`.
+    #[must_use]
+    pub fn children(&self) -> Vec<&Self> {
+        match self {
+            Self::Union(lhs, rhs) | Self::Intersection(lhs, rhs) | Self::Difference(lhs, rhs) => {
+                vec![lhs, rhs]
+            }
+            Self::Complement(inner) | Self::Sample(inner, _) | Self::Limit(inner, _) => vec![inner],
+            Self::AtLeast(_, operands) => operands.iter().collect(),
+            Self::StringLiteral(_)
+            | Self::UnknownID(_)
+            | Self::UserID(_)
+            | Self::RoleID(_)
+            | Self::ChannelID(_)
+            | Self::Me
+            | Self::Event(_)
+            | Self::Permission(_)
+            | Self::Joined(_, _)
+            | Self::AccountAge(_)
+            | Self::Name(_)
+            | Self::Active(_)
+            | Self::Reacted(_, _)
+            | Self::Alias(_) => Vec::new(),
+        }
+    }
+
+    /// Whether this expression picks members at random, like `sample(a, 1)`, so two copies of it
+    /// may not be the same set.
+    #[must_use]
+    pub fn is_random(&self) -> bool {
+        match self {
+            Self::Sample(_, _) => true,
+            Self::Union(lhs, rhs) | Self::Intersection(lhs, rhs) | Self::Difference(lhs, rhs) => {
+                lhs.is_random() || rhs.is_random()
+            }
+            Self::Complement(inner) | Self::Limit(inner, _) => inner.is_random(),
+            Self::AtLeast(_, operands) => operands.iter().any(Self::is_random),
+            Self::StringLiteral(_)
+            | Self::UnknownID(_)
+            | Self::UserID(_)
+            | Self::RoleID(_)
+            | Self::ChannelID(_)
+            | Self::Me
+            | Self::Event(_)
+            | Self::Permission(_)
+            | Self::Joined(_, _)
+            | Self::AccountAge(_)
+            | Self::Name(_)
+            | Self::Active(_)
+            | Self::Reacted(_, _)
+            | Self::Alias(_) => false,
+        }
+    }
+
+    /// Count the operands (string literals and IDs) in this expression, each of which has to be
+    /// resolved to evaluate it.
+    #[must_use]
+    pub fn operand_count(&self) -> usize {
+        match self {
+            Self::Union(lhs, rhs) | Self::Intersection(lhs, rhs) | Self::Difference(lhs, rhs) => {
+                lhs.operand_count() + rhs.operand_count()
+            }
+            Self::Complement(inner) | Self::Sample(inner, _) | Self::Limit(inner, _) => {
+                inner.operand_count()
+            }
+            Self::AtLeast(_, operands) => operands.iter().map(Self::operand_count).sum(),
+            Self::StringLiteral(_)
+            | Self::UnknownID(_)
+            | Self::UserID(_)
+            | Self::RoleID(_)
+            | Self::ChannelID(_)
+            | Self::Me
+            | Self::Event(_)
+            | Self::Permission(_)
+            | Self::Joined(_, _)
+            | Self::AccountAge(_)
+            | Self::Name(_)
+            | Self::Active(_)
+            | Self::Reacted(_, _)
+            | Self::Alias(_) => 1,
+        }
+    }
 }
 
 impl Display for Expr {
@@ -34,20 +326,204 @@ impl Display for Expr {
             Self::Union(lhs, rhs) => write!(f, "({lhs} | {rhs})"),
             Self::Intersection(lhs, rhs) => write!(f, "({lhs} & {rhs})"),
             Self::Difference(lhs, rhs) => write!(f, "({lhs} - {rhs})"),
+            Self::Complement(inner) => write!(f, "!{inner}"),
+            Self::Sample(inner, count) => write!(f, "sample({inner}, {count})"),
+            Self::Limit(inner, count) => write!(f, "({inner} limit {count})"),
+            Self::AtLeast(count, operands) => {
+                write!(f, "atleast({count}")?;
+                for operand in operands {
+                    write!(f, ", {operand}")?;
+                }
+                write!(f, ")")
+            }
 
             Self::StringLiteral(contents) => {
-                if contents
-                    .chars()
-                    .all(|char| char.is_ascii_alphanumeric() || char == '_')
+                // Only print the literal bare if it would be lexed back as the same literal,
+                // e.g. `123` or `1abc` must be quoted as they would otherwise lex as IDs, and
+                // `and` as it would lex as a keyword
+                if !KEYWORDS
+                    .iter()
+                    .any(|keyword| keyword.eq_ignore_ascii_case(contents))
+                    && contents
+                        .chars()
+                        .next()
+                        .is_some_and(|char| char.is_ascii_alphabetic() || char == '_')
+                    && contents
+                        .chars()
+                        .all(|char| char.is_ascii_alphanumeric() || char == '_')
                 {
                     write!(f, "{contents}")
                 } else {
-                    write!(f, "\"{contents}\"")
+                    write!(f, "{}", quote(contents))
                 }
             }
             Self::UnknownID(id) => write!(f, "{id}"),
             Self::UserID(id) => write!(f, "<@{id}>"),
             Self::RoleID(id) => write!(f, "<@&{id}>"),
+            Self::ChannelID(id) => write!(f, "<#{id}>"),
+            Self::Me => write!(f, "me"),
+            Self::Event(id) => write!(f, "event({id})"),
+            Self::Permission(permission) => write!(f, "perm({})", permission_name(*permission)),
+            Self::Joined(Side::Before, moment) => write!(f, "joined_before({moment})"),
+            Self::Joined(Side::After, moment) => write!(f, "joined_after({moment})"),
+            Self::AccountAge(age) => write!(f, "account_age({age})"),
+            Self::Name(pattern) => write!(f, "name({pattern})"),
+            Self::Active(within) => write!(f, "active({})", time::format_duration(*within)),
+            Self::Reacted(link, emoji) => write!(f, "reacted({link}, {emoji})"),
+            Self::Alias(name) => write!(f, "${name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use poise::serenity_prelude::model::prelude::EmojiId;
+    use proptest::{prelude::*, strategy::LazyJust};
+
+    use super::*;
+    use crate::drql::parser::parse_drql;
+
+    /// Generate arbitrary [`Expr`]s which should survive a round trip through [`Display`] and the
+    /// parser.
+    fn arb_expr() -> impl Strategy<Value = Expr> {
+        let leaf = prop_oneof![
+            "(\\PC|\n)*".prop_map(Expr::StringLiteral),
+            "[0-9]{1,20}".prop_map(Expr::UnknownID),
+            any::<u64>().prop_map(|id| Expr::UserID(UserId(id))),
+            any::<u64>().prop_map(|id| Expr::RoleID(RoleId(id))),
+            any::<u64>().prop_map(|id| Expr::ChannelID(ChannelId(id))),
+            any::<u64>().prop_map(|id| Expr::Event(ScheduledEventId(id))),
+            (0..=40_u32)
+                .prop_map(|bit| Expr::Permission(Permissions::from_bits_truncate(1 << bit))),
+            LazyJust::new(|| Expr::Me),
+            (
+                prop_oneof![Just(Side::Before), Just(Side::After)],
+                prop_oneof![
+                    (0..4_000_000_000_i64).prop_map(|seconds| Moment::At(
+                        DateTime::from_timestamp(seconds, 0).expect("the time should be in range")
+                    )),
+                    (0..1_000_000_000_u64)
+                        .prop_map(|seconds| Moment::Ago(Duration::from_secs(seconds))),
+                ]
+            )
+                .prop_map(|(side, moment)| Expr::Joined(side, moment)),
+            (any::<bool>(), 0..1_000_000_000_u64).prop_map(|(younger, seconds)| {
+                let duration = Duration::from_secs(seconds);
+                Expr::AccountAge(if younger {
+                    Age::YoungerThan(duration)
+                } else {
+                    Age::OlderThan(duration)
+                })
+            }),
+            "[^\"\\\\]*".prop_map(|glob| Expr::Name(NamePattern::Glob(glob))),
+            "[a-z0-9 ^$.*+?|()-]{1,20}"
+                .prop_map(NamePattern::Regex)
+                .prop_filter("the regex should compile", |regex| regex.compile().is_ok())
+                .prop_map(Expr::Name),
+            (0..1_000_000_000_u64).prop_map(|seconds| Expr::Active(Duration::from_secs(seconds))),
+            (
+                any::<Option<u64>>(),
+                any::<u64>(),
+                any::<u64>(),
+                prop_oneof![
+                    prop_oneof![
+                        Just("\u{2705}"),
+                        Just("\u{1f44d}"),
+                        Just("\u{1f1fa}\u{1f1f8}"),
+                        Just("\u{1f44b}\u{1f3fd}")
+                    ]
+                    .prop_map(|emoji| ReactionType::Unicode(emoji.to_string())),
+                    (any::<bool>(), any::<u64>(), "[a-zA-Z0-9_]{1,32}").prop_map(
+                        |(animated, id, name)| ReactionType::Custom {
+                            animated,
+                            id: EmojiId(id),
+                            name: Some(name),
+                        }
+                    ),
+                ]
+            )
+                .prop_map(|(guild, channel, message, emoji)| Expr::Reacted(
+                    MessageLink {
+                        guild: guild.map(GuildId),
+                        channel: ChannelId(channel),
+                        message: MessageId(message),
+                    },
+                    emoji
+                )),
+        ];
+        leaf.prop_recursive(8, 64, 2, |inner| {
+            prop_oneof![
+                (inner.clone(), inner.clone())
+                    .prop_map(|(lhs, rhs)| Expr::Union(Box::new(lhs), Box::new(rhs))),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(lhs, rhs)| Expr::Intersection(Box::new(lhs), Box::new(rhs))),
+                (inner.clone(), inner.clone())
+                    .prop_map(|(lhs, rhs)| Expr::Difference(Box::new(lhs), Box::new(rhs))),
+                inner
+                    .clone()
+                    .prop_map(|inner| Expr::Complement(Box::new(inner))),
+                (inner.clone(), any::<usize>())
+                    .prop_map(|(inner, count)| Expr::Sample(Box::new(inner), count)),
+                (inner.clone(), any::<usize>())
+                    .prop_map(|(inner, count)| Expr::Limit(Box::new(inner), count)),
+                (any::<usize>(), prop::collection::vec(inner, 1..4))
+                    .prop_map(|(count, operands)| Expr::AtLeast(count, operands)),
+            ]
+        })
+    }
+
+    #[test]
+    fn numeric_string_literals_are_quoted() {
+        assert_eq!(
+            Expr::StringLiteral("123".to_string()).to_string(),
+            "\"123\""
+        );
+        assert_eq!(
+            Expr::StringLiteral("1abc".to_string()).to_string(),
+            "\"1abc\""
+        );
+        assert_eq!(Expr::StringLiteral(String::new()).to_string(), "\"\"");
+        assert_eq!(Expr::StringLiteral("Or".to_string()).to_string(), "\"Or\"");
+        assert_eq!(Expr::StringLiteral("me".to_string()).to_string(), "\"me\"");
+        assert_eq!(
+            Expr::StringLiteral("abc_1".to_string()).to_string(),
+            "abc_1"
+        );
+    }
+
+    #[test]
+    fn string_literals_are_escaped() {
+        assert_eq!(
+            Expr::StringLiteral("Role \"A\"".to_string()).to_string(),
+            r#""Role \"A\"""#
+        );
+        assert_eq!(
+            Expr::StringLiteral("a\\b\nc".to_string()).to_string(),
+            r#""a\\b\nc""#
+        );
+    }
+
+    #[test]
+    fn operands_are_counted() {
+        assert_eq!(
+            parse_drql("(a + b) & <@1> - 123")
+                .expect("query should parse")
+                .operand_count(),
+            4
+        );
+        assert_eq!(
+            parse_drql("!a - ~(b | c)")
+                .expect("query should parse")
+                .operand_count(),
+            3
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn display_round_trips_through_parser(ast in arb_expr()) {
+            prop_assert_eq!(parse_drql(&ast.to_string()), Ok(ast));
         }
     }
 }
@@ -0,0 +1,161 @@
+//! Suggesting how to finish a DRQL query while it's being typed
+//!
+//! Discord replaces the whole value of an option with the suggestion picked for it, so each
+//! suggestion is the query typed so far with its last operand finished, like `staff & here` for
+//! `staff & he`.
+
+use super::lexer::{quote, unescape, DrqlLexer, LexicalError, Tok};
+
+/// Names which can be used in any guild's queries, whatever its roles are called
+pub const BUILT_INS: [&str; 2] = ["everyone", "here"];
+
+/// The most suggestions Discord shows for an option
+pub const MAX_SUGGESTIONS: usize = 25;
+
+/// The longest suggestion, in characters, Discord accepts for an option
+pub const MAX_SUGGESTION_LENGTH: usize = 100;
+
+/// Where the operand being typed at the end of `partial` starts, and what has been typed of it, as
+/// it was meant (so without quotes or escapes).
+///
+/// When `partial` doesn't end in the middle of an operand, like after an operator or a space, the
+/// next operand starts at the end and nothing has been typed of it yet. When it ends in something
+/// which can't be finished as a name, like an ID, there's nothing to suggest.
+fn last_operand(partial: &str) -> Option<(usize, String)> {
+    let Some(last) = DrqlLexer::new(partial).last() else {
+        return Some((partial.len(), String::new()));
+    };
+
+    match last {
+        Ok((start, token, end)) if end == partial.len() => match token {
+            Tok::StringLiteral(contents) => Some((start, contents)),
+            // Names can start with a keyword, like `members`, so they're finished like any name
+            Tok::And | Tok::Or | Tok::Not | Tok::MinusKeyword | Tok::Me | Tok::Limit => {
+                Some((start, partial[start..].to_string()))
+            }
+            Tok::Plus
+            | Tok::Minus
+            | Tok::Pipe
+            | Tok::Ampersand
+            | Tok::LeftParen
+            | Tok::RightParen
+            | Tok::Bang
+            | Tok::Tilde
+            | Tok::LessThan
+            | Tok::GreaterThan
+            | Tok::Comma => Some((partial.len(), String::new())),
+            Tok::IDLiteral(_)
+            | Tok::Duration(_)
+            | Tok::UserMention(_)
+            | Tok::RoleMention(_)
+            | Tok::ChannelMention(_)
+            | Tok::EventLink(_)
+            | Tok::MessageLink(_)
+            | Tok::Emoji(_)
+            | Tok::Regex(_)
+            | Tok::Alias(_) => None,
+        },
+        Ok(_) => Some((partial.len(), String::new())),
+        // A quoted name which hasn't been closed yet runs to the end of the query
+        Err(LexicalError::UnterminatedStringLiteral(start)) => Some((
+            start,
+            unescape(&partial[start..].chars().skip(1).collect::<String>()),
+        )),
+        Err(_) => None,
+    }
+}
+
+/// Write `name` as an operand, quoting it unless it would be read back as the same name without.
+fn operand(name: &str) -> String {
+    let mut lexer = DrqlLexer::new(name);
+    match (lexer.next(), lexer.next()) {
+        (Some(Ok((_, Tok::StringLiteral(contents), _))), None) if contents == name => {
+            name.to_string()
+        }
+        _ => quote(name),
+    }
+}
+
+/// Suggest ways to finish `partial` by completing its last operand with one of [`BUILT_INS`] or
+/// `names`, in that order, ignoring case.
+///
+/// Names are quoted where they need to be, and names written the same are only suggested once.
+/// At most [`MAX_SUGGESTIONS`] suggestions are made, each at most [`MAX_SUGGESTION_LENGTH`]
+/// characters long.
+#[must_use]
+pub fn complete<'a>(partial: &str, names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let Some((start, typed)) = last_operand(partial) else {
+        return Vec::new();
+    };
+    let typed = typed.to_lowercase();
+    let before = &partial[..start];
+
+    let mut suggestions = Vec::new();
+    for name in BUILT_INS.into_iter().chain(names) {
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+        if !name.to_lowercase().starts_with(&typed) {
+            continue;
+        }
+        let suggestion = format!("{before}{}", operand(name));
+        if suggestion.chars().count() <= MAX_SUGGESTION_LENGTH && !suggestions.contains(&suggestion)
+        {
+            suggestions.push(suggestion);
+        }
+    }
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Roles in a guild, as named in it
+    const ROLES: [&str; 4] = ["Helpers", "Staff", "Red Team", "me"];
+
+    #[test]
+    fn last_operands_are_completed() {
+        assert_eq!(
+            complete("staff & he", ROLES),
+            ["staff & here", "staff & Helpers"]
+        );
+        assert_eq!(complete("EV", ROLES), ["everyone"]);
+        assert_eq!(complete("(staff)|s", ROLES), ["(staff)|Staff"]);
+        assert!(complete("staff & x", ROLES).is_empty());
+        assert_eq!(complete("staff - ME", ROLES), ["staff - \"me\""]);
+        assert!(complete("staff & 12", ROLES).is_empty());
+    }
+
+    #[test]
+    fn every_name_is_suggested_for_a_new_operand() {
+        assert_eq!(
+            complete("staff - ", ROLES),
+            [
+                "staff - everyone",
+                "staff - here",
+                "staff - Helpers",
+                "staff - Staff",
+                "staff - \"Red Team\"",
+                "staff - \"me\"",
+            ]
+        );
+        assert_eq!(complete("", ["here"]), ["everyone", "here"]);
+    }
+
+    #[test]
+    fn quoted_names_are_completed() {
+        assert_eq!(complete("staff | \"red", ROLES), ["staff | \"Red Team\""]);
+        assert_eq!(complete("\"Red Team\"", ROLES), ["\"Red Team\""]);
+    }
+
+    #[test]
+    fn suggestions_are_limited() {
+        let names = (0..30).map(|n| format!("role{n}")).collect::<Vec<_>>();
+        assert_eq!(
+            complete("r", names.iter().map(String::as_str)).len(),
+            MAX_SUGGESTIONS
+        );
+        assert!(complete(&"a | ".repeat(25), ROLES).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+//! Explaining why a chunk of a DRQL query couldn't be parsed
+//!
+//! The parser reports where it went wrong as byte offsets into the chunk it was given, like
+//! ``Unrecognized token `+` found at 6:7``, which means little to whoever wrote the query. A
+//! [`Diagnostic`] instead points at the offending token in the query as it was written in the
+//! message, with a caret under it:
+//!
+//! ```text
+//! @{staff +}
+//!         ^
+//! ```
+
+use std::ops::Range;
+
+use lalrpop_util::ParseError;
+
+use super::{
+    lexer::{LexicalError, Tok},
+    scanner,
+};
+
+/// What went wrong parsing a chunk of a query, for describing it in other words (or languages)
+/// than [`Diagnostic::message`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The lexer didn't recognize a token
+    UnknownToken,
+    /// The query ended while the parser expected one of these terminals
+    EndedEarly(Vec<String>),
+    /// A token, as written, came where the parser expected one of these terminals
+    Unexpected(String, Vec<String>),
+    /// A token, as written, came after the end of the query
+    ExtraToken(String),
+    /// A character which can't start any token
+    UnknownCharacter(char),
+    /// A string literal which is never closed
+    UnterminatedString,
+    /// Any other error from the lexer
+    Lexical(LexicalError),
+}
+
+/// Why a chunk of a query couldn't be parsed, and where in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// What went wrong, like "Unexpected `+`"
+    pub message: String,
+    /// What went wrong, in a form which can be described differently
+    pub problem: Problem,
+    /// The bytes of the chunk which are wrong, if the parser knows
+    ///
+    /// An empty span points at the position it starts at, like the end of a query which ended too
+    /// early.
+    pub span: Option<Range<usize>>,
+}
+
+/// Describe a terminal the parser expected, like `"("` or `STRING_LITERAL`.
+fn describe_terminal(terminal: &str) -> String {
+    if let Some(token) = terminal
+        .strip_prefix('"')
+        .and_then(|terminal| terminal.strip_suffix('"'))
+    {
+        return format!("`{token}`");
+    }
+    match terminal {
+        "STRING_LITERAL" => "a name",
+        "ID_LITERAL" => "a number or ID",
+        "DURATION" => "a duration",
+        "USER_MENTION" => "a member",
+        "ROLE_MENTION" => "a role",
+        "CHANNEL_MENTION" => "a channel",
+        "EVENT_LINK" => "an event link",
+        "MESSAGE_LINK" => "a message link",
+        "EMOJI" => "an emoji",
+        "REGEX" => "a regex",
+        "ALIAS" => "an alias",
+        terminal => terminal,
+    }
+    .to_string()
+}
+
+/// Write out what the parser expected instead, like ", expected `)` or a name".
+fn describe_expected(terminals: &[String]) -> String {
+    let described = terminals
+        .iter()
+        .map(|terminal| describe_terminal(terminal))
+        .collect::<Vec<_>>();
+    match described.split_last() {
+        None => String::new(),
+        Some((last, [])) => format!(", expected {last}"),
+        Some((last, rest)) => format!(", expected {} or {last}", rest.join(", ")),
+    }
+}
+
+impl Diagnostic {
+    /// Explain `error`, from parsing `chunk` of a query.
+    #[must_use]
+    pub fn new(chunk: &str, error: &ParseError<usize, Tok, LexicalError>) -> Self {
+        // Tokens are quoted as they were written, rather than as the parser saw them
+        let written = |start: usize, token: &Tok, end: usize| {
+            chunk
+                .get(start..end)
+                .map_or_else(|| token.to_string(), ToString::to_string)
+        };
+        let (problem, span) = match error {
+            ParseError::InvalidToken { location } => {
+                (Problem::UnknownToken, Some(*location..*location))
+            }
+            ParseError::UnrecognizedEof { location, expected } => (
+                Problem::EndedEarly(expected.clone()),
+                Some(*location..*location),
+            ),
+            ParseError::UnrecognizedToken {
+                token: (start, token, end),
+                expected,
+            } => (
+                Problem::Unexpected(written(*start, token, *end), expected.clone()),
+                Some(*start..*end),
+            ),
+            ParseError::ExtraToken {
+                token: (start, token, end),
+            } => (
+                Problem::ExtraToken(written(*start, token, *end)),
+                Some(*start..*end),
+            ),
+            ParseError::User {
+                error: LexicalError::UnknownToken((index, char)),
+            } => (
+                Problem::UnknownCharacter(*char),
+                Some(*index..index + char.len_utf8()),
+            ),
+            ParseError::User {
+                error: LexicalError::UnterminatedStringLiteral(index),
+            } => (Problem::UnterminatedString, Some(*index..*index)),
+            ParseError::User { error } => (Problem::Lexical(error.clone()), None),
+        };
+        let message = match &problem {
+            Problem::UnknownToken => "Unknown token".to_string(),
+            Problem::EndedEarly(expected) => {
+                format!("Your query ended too early{}", describe_expected(expected))
+            }
+            Problem::Unexpected(token, expected) => {
+                format!("Unexpected `{token}`{}", describe_expected(expected))
+            }
+            Problem::ExtraToken(token) => {
+                format!("Unexpected `{token}` after the end of the query")
+            }
+            Problem::UnknownCharacter(char) => format!("Unknown character `{char}`"),
+            Problem::UnterminatedString => "This string literal is never closed".to_string(),
+            Problem::Lexical(error) => error.to_string(),
+        };
+        Self {
+            message,
+            problem,
+            span,
+        }
+    }
+
+    /// Write this out for a reply to the query, pointing at the span in `chunk` (as returned by
+    /// [`scanner::scan`]) as it was written in the message.
+    #[must_use]
+    pub fn render(&self, chunk: &str) -> String {
+        self.render_as(chunk, &self.message)
+    }
+
+    /// Write this out like [`Diagnostic::render`], but explained by `message` instead of
+    /// [`Diagnostic::message`], like a translation of it.
+    #[must_use]
+    pub fn render_as(&self, chunk: &str, message: &str) -> String {
+        let Some(span) = &self.span else {
+            return message.to_string();
+        };
+
+        let written = scanner::unscan(chunk);
+        let start = span.start + scanner::OPENING.len();
+        let end = span.end + scanner::OPENING.len();
+        let indent = written
+            .get(..start)
+            .map_or(0, |before| before.chars().count());
+        let width = written
+            .get(start..end)
+            .map_or(0, |token| token.chars().count())
+            .max(1);
+        // Backticks would end the code block early, so they're replaced with a similar-looking
+        // character
+        format!(
+            "{}\n```\n{}\n{}{}\n```",
+            message,
+            written.replace('`', "\u{2cb}"),
+            " ".repeat(indent),
+            "^".repeat(width)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drql::parser::parse_drql;
+
+    /// Render the diagnostic for parsing `chunk`, which shouldn't parse.
+    fn rendered(chunk: &str) -> String {
+        Diagnostic::new(
+            chunk,
+            &parse_drql(chunk).expect_err("the chunk should not parse"),
+        )
+        .render(chunk)
+    }
+
+    #[test]
+    fn carets_point_at_the_offending_token() {
+        assert_eq!(
+            rendered("staff + )"),
+            "Unexpected `)`, expected `!`, `(`, `me`, `not`, `~`, an alias, a channel, a number or ID, a \
+            role, a name or a member\n```\n@{staff + )}\n          ^\n```"
+        );
+        assert_eq!(
+            rendered("staff artists"),
+            "Unexpected `artists`, expected `&`, `(`, `+`, `-`, `and`, `limit`, `minus`, `or` or \
+            `|`\n```\n@{staff artists}\n        ^^^^^^^\n```"
+        );
+    }
+
+    #[test]
+    fn carets_point_past_queries_which_end_too_early() {
+        assert!(rendered("staff +").ends_with("\n```\n@{staff +}\n         ^\n```"));
+        assert!(rendered("staff +").starts_with("Your query ended too early, expected `!`"));
+    }
+
+    #[test]
+    fn lexer_errors_are_located() {
+        assert_eq!(
+            rendered("staff $ \"a`b"),
+            "Unknown character `$`\n```\n@{staff $ \"a\u{2cb}b}\n        ^\n```"
+        );
+        assert_eq!(
+            rendered("staff | \"a`b"),
+            "This string literal is never closed\n```\n@{staff | \"a\u{2cb}b}\n          ^\n```"
+        );
+        // Function calls aren't located, so the error is given on its own
+        assert_eq!(rendered("perm(fly)"), "Invalid function call: `perm(fly)`");
+    }
+}
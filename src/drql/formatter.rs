@@ -0,0 +1,253 @@
+//! Writing DRQL queries out in a canonical form
+//!
+//! [`Expr`]'s [`Display`](std::fmt::Display) parenthesizes every operator, which is unambiguous
+//! but hard to read. [`format`] only adds the parentheses the parser needs to read the query back
+//! the same way, and always uses the same spelling of each operator (`|`, `&`, `-` and `!`), so two
+//! queries which parse the same are written the same.
+//!
+//! Every binary operator has the same precedence and groups to the left, so `a | b & c` means
+//! `(a | b) & c`, and only a right operand made of operators needs parentheses.
+
+use super::ast::Expr;
+
+/// Where an expression is written, which decides whether it needs parentheses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// The whole query, or the inside of parentheses, where anything goes
+    Query,
+    /// The left operand of an operator or an argument of a function, where `limit` can't be
+    Operand,
+    /// The right operand of an operator or what `!` applies to, where only a single operand can
+    /// be
+    Primary,
+}
+
+/// Write `expr` out with as few parentheses as possible.
+#[must_use]
+pub fn format(expr: &Expr) -> String {
+    let mut formatted = String::new();
+    write(expr, Position::Query, &mut formatted);
+    formatted
+}
+
+/// Write `expr`, as it appears at `position`, to the end of `out`.
+fn write(expr: &Expr, position: Position, out: &mut String) {
+    let parenthesized = match position {
+        Position::Query => false,
+        Position::Operand => matches!(expr, Expr::Limit(_, _)),
+        Position::Primary => matches!(
+            expr,
+            Expr::Limit(_, _)
+                | Expr::Union(_, _)
+                | Expr::Intersection(_, _)
+                | Expr::Difference(_, _)
+        ),
+    };
+    if parenthesized {
+        out.push('(');
+        write(expr, Position::Query, out);
+        out.push(')');
+        return;
+    }
+
+    match expr {
+        Expr::Union(lhs, rhs) => write_binary(lhs, "|", rhs, out),
+        Expr::Intersection(lhs, rhs) => write_binary(lhs, "&", rhs, out),
+        Expr::Difference(lhs, rhs) => write_binary(lhs, "-", rhs, out),
+        Expr::Complement(inner) => {
+            out.push('!');
+            write(inner, Position::Primary, out);
+        }
+        Expr::Limit(inner, count) => {
+            write(inner, Position::Operand, out);
+            out.push_str(" limit ");
+            out.push_str(&count.to_string());
+        }
+        Expr::Sample(inner, count) => {
+            out.push_str("sample(");
+            write(inner, Position::Operand, out);
+            out.push_str(", ");
+            out.push_str(&count.to_string());
+            out.push(')');
+        }
+        Expr::AtLeast(count, operands) => {
+            out.push_str("atleast(");
+            out.push_str(&count.to_string());
+            for operand in operands {
+                out.push_str(", ");
+                write(operand, Position::Operand, out);
+            }
+            out.push(')');
+        }
+        // Operands never need parentheses, and are written the same as ever
+        Expr::StringLiteral(_)
+        | Expr::UnknownID(_)
+        | Expr::UserID(_)
+        | Expr::RoleID(_)
+        | Expr::ChannelID(_)
+        | Expr::Me
+        | Expr::Event(_)
+        | Expr::Permission(_)
+        | Expr::Joined(_, _)
+        | Expr::AccountAge(_)
+        | Expr::Name(_)
+        | Expr::Active(_)
+        | Expr::Reacted(_, _)
+        | Expr::Alias(_) => out.push_str(&expr.to_string()),
+    }
+}
+
+/// Write a binary operator and its operands to the end of `out`.
+fn write_binary(lhs: &Expr, operator: &str, rhs: &Expr, out: &mut String) {
+    write(lhs, Position::Operand, out);
+    out.push(' ');
+    out.push_str(operator);
+    out.push(' ');
+    write(rhs, Position::Primary, out);
+}
+
+/// What a node of a query is written as in a [`tree`], without the nodes inside it.
+fn label(expr: &Expr) -> String {
+    match expr {
+        Expr::Union(_, _) => "|".to_string(),
+        Expr::Intersection(_, _) => "&".to_string(),
+        Expr::Difference(_, _) => "-".to_string(),
+        Expr::Complement(_) => "!".to_string(),
+        Expr::Limit(_, count) => format!("limit {count}"),
+        Expr::Sample(_, count) => format!("sample {count}"),
+        Expr::AtLeast(count, _) => format!("atleast {count}"),
+        Expr::StringLiteral(_)
+        | Expr::UnknownID(_)
+        | Expr::UserID(_)
+        | Expr::RoleID(_)
+        | Expr::ChannelID(_)
+        | Expr::Me
+        | Expr::Event(_)
+        | Expr::Permission(_)
+        | Expr::Joined(_, _)
+        | Expr::AccountAge(_)
+        | Expr::Name(_)
+        | Expr::Active(_)
+        | Expr::Reacted(_, _)
+        | Expr::Alias(_) => expr.to_string(),
+    }
+}
+
+/// Draw `expr` as a tree, one node per line with the nodes inside it below, which shows how its
+/// operators are grouped.
+///
+/// `count` gives how many members a node matched, if known, which is written next to it: like
+/// `staff (41)` for an operand, and `& => 17` for an operator.
+#[must_use]
+pub fn tree(expr: &Expr, count: impl Fn(&Expr) -> Option<usize>) -> String {
+    let mut lines = Vec::new();
+    // Each node, with what comes before it on its line and before the lines of the nodes inside it
+    let mut unvisited = vec![(expr, String::new(), String::new())];
+    while let Some((node, prefix, indent)) = unvisited.pop() {
+        let children = node.children();
+        let count = match count(node) {
+            Some(count) if children.is_empty() => format!(" ({count})"),
+            Some(count) => format!(" => {count}"),
+            None => String::new(),
+        };
+        lines.push(format!("{prefix}{}{count}", label(node)));
+
+        // Pushed last first, so they're drawn in order
+        for (n, child) in children.iter().enumerate().rev() {
+            let (branch, continuation) = if n + 1 == children.len() {
+                ("\u{2514}\u{2500}\u{2500} ", "    ")
+            } else {
+                ("\u{251c}\u{2500}\u{2500} ", "\u{2502}   ")
+            };
+            unvisited.push((
+                *child,
+                format!("{indent}{branch}"),
+                format!("{indent}{continuation}"),
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drql::parser::parse_drql;
+
+    /// Parse and format `query`, checking that the result parses back the same.
+    fn formatted(query: &str) -> String {
+        let ast = parse_drql(query).expect("the query should parse");
+        let formatted = format(&ast);
+        assert_eq!(
+            parse_drql(&formatted),
+            Ok(ast),
+            "{formatted} should parse back the same"
+        );
+        formatted
+    }
+
+    #[test]
+    fn only_needed_parentheses_are_kept() {
+        assert_eq!(formatted("((a | b) & (c))"), "a | b & c");
+        assert_eq!(formatted("a | (b & c)"), "a | (b & c)");
+        assert_eq!(formatted("!(a) - !(b | c)"), "!a - !(b | c)");
+        assert_eq!(formatted("(a | b) limit 5"), "a | b limit 5");
+        assert_eq!(formatted("a | ((b) limit 5)"), "a | (b limit 5)");
+        assert_eq!(formatted("((a limit 5) limit 2)"), "(a limit 5) limit 2");
+    }
+
+    #[test]
+    fn operators_are_spelled_canonically() {
+        assert_eq!(formatted("a or b and c minus d + e"), "a | b & c - d | e");
+        assert_eq!(formatted("not a & ~b"), "!a & !b");
+    }
+
+    #[test]
+    fn functions_are_formatted_inside() {
+        assert_eq!(
+            formatted("atleast(2, (a | b), (c limit 1), \"Red Team\")"),
+            "atleast(2, a | b, (c limit 1), \"Red Team\")"
+        );
+        assert_eq!(formatted("sample((a - b), 3)"), "sample(a - b, 3)");
+        assert_eq!(
+            formatted("<@1> | perm(ban_members)"),
+            "<@1> | perm(ban_members)"
+        );
+    }
+
+    #[test]
+    fn trees_show_grouping() {
+        let ast = parse_drql("staff | !(a & b) limit 2").expect("the query should parse");
+        assert_eq!(
+            tree(&ast, |_| None),
+            [
+                "limit 2",
+                "\u{2514}\u{2500}\u{2500} |",
+                "    \u{251c}\u{2500}\u{2500} staff",
+                "    \u{2514}\u{2500}\u{2500} !",
+                "        \u{2514}\u{2500}\u{2500} &",
+                "            \u{251c}\u{2500}\u{2500} a",
+                "            \u{2514}\u{2500}\u{2500} b",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn trees_show_counts() {
+        let ast = parse_drql("staff & here").expect("the query should parse");
+        let count = |node: &Expr| {
+            Some(if *node == Expr::StringLiteral("staff".to_string()) {
+                41
+            } else if *node == Expr::StringLiteral("here".to_string()) {
+                102
+            } else {
+                17
+            })
+        };
+        assert_eq!(
+            tree(&ast, count),
+            "& => 17\n\u{251c}\u{2500}\u{2500} staff (41)\n\u{2514}\u{2500}\u{2500} here (102)"
+        );
+    }
+}
@@ -1,60 +1,616 @@
 //! Utilities and functions for interpreting DRQL queries
 
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    ptr,
+    time::Duration,
+};
 
-use async_recursion::async_recursion;
+use futures::{stream, try_join, StreamExt, TryStreamExt};
 use poise::{
     async_trait,
-    serenity_prelude::{RoleId, UserId},
+    serenity_prelude::{ChannelId, Permissions, ReactionType, RoleId, ScheduledEventId, UserId},
 };
-use tracing::instrument;
+use rand::seq::IteratorRandom;
+use tracing::{instrument, Span};
 
-use super::ast::Expr;
+use super::{
+    ast::{Expr, MessageLink},
+    pattern::NamePattern,
+    time::{Age, Moment, Side},
+};
 
 /// Describes a set of functions used to resolve values in [interpret].
 #[allow(clippy::module_name_repetitions)]
 #[async_trait]
 pub trait InterpreterResolver<E> {
-    /// Resolve a role name to the HashSet of its members
-    async fn resolve_string_literal(&mut self, literal: String) -> Result<HashSet<UserId>, E>;
-    /// Resolve an ID to the HashSet of its members
-    async fn resolve_unknown_id(&mut self, id: String) -> Result<HashSet<UserId>, E>;
-    /// Resolve a user ID to the HashSet of just its ID
-    async fn resolve_user_id(&mut self, id: UserId) -> Result<HashSet<UserId>, E>;
-    /// Resolve a role ID to the HashSet of its members
-    async fn resolve_role_id(&mut self, id: RoleId) -> Result<HashSet<UserId>, E>;
-}
-
-/// Interpret a DRQL AST, deferring to the Resolver to resolve string literals, user IDs, and role IDs.
-#[async_recursion]
-#[instrument(skip_all, fields(node = %node))]
-#[allow(clippy::multiple_bound_locations)]
-pub async fn interpret<E: Send>(
+    /// Resolve a role name to the [`HashSet`] of its members
+    async fn resolve_string_literal(&self, literal: String) -> Result<HashSet<UserId>, E>;
+    /// Resolve an ID to the [`HashSet`] of its members
+    async fn resolve_unknown_id(&self, id: String) -> Result<HashSet<UserId>, E>;
+    /// Resolve a user ID to the [`HashSet`] of just its ID
+    async fn resolve_user_id(&self, id: UserId) -> Result<HashSet<UserId>, E>;
+    /// Resolve a role ID to the [`HashSet`] of its members
+    async fn resolve_role_id(&self, id: RoleId) -> Result<HashSet<UserId>, E>;
+    /// Resolve a channel ID to the [`HashSet`] of the members it refers to
+    async fn resolve_channel_id(&self, id: ChannelId) -> Result<HashSet<UserId>, E>;
+    /// Resolve `me` to the [`HashSet`] of just the query's author
+    async fn resolve_me(&self) -> Result<HashSet<UserId>, E>;
+    /// Resolve a scheduled event's ID to the [`HashSet`] of the users interested in it
+    async fn resolve_event(&self, id: ScheduledEventId) -> Result<HashSet<UserId>, E>;
+    /// Resolve to the [`HashSet`] of the members who joined on `side` of `moment`
+    async fn resolve_joined(&self, side: Side, moment: Moment) -> Result<HashSet<UserId>, E>;
+    /// Resolve to the [`HashSet`] of the members whose accounts are `age` old
+    async fn resolve_account_age(&self, age: Age) -> Result<HashSet<UserId>, E>;
+    /// Resolve a pattern to the [`HashSet`] of the members whose username or nickname matches it
+    async fn resolve_name(&self, pattern: NamePattern) -> Result<HashSet<UserId>, E>;
+    /// Resolve to the [`HashSet`] of the members who sent a message within the last `within`
+    async fn resolve_active(&self, within: Duration) -> Result<HashSet<UserId>, E>;
+    /// Resolve a message and an emoji to the [`HashSet`] of the users who reacted to the message
+    /// with it
+    async fn resolve_reacted(
+        &self,
+        link: MessageLink,
+        emoji: ReactionType,
+    ) -> Result<HashSet<UserId>, E>;
+    /// Resolve a guild permission to the [`HashSet`] of the members who have it
+    async fn resolve_permission(&self, permission: Permissions) -> Result<HashSet<UserId>, E>;
+    /// Resolve the [`HashSet`] of everyone a complement (`!a`) is taken relative to
+    async fn resolve_everyone(&self) -> Result<HashSet<UserId>, E>;
+    /// Resolve an alias which wasn't expanded before the query was interpreted (see
+    /// [`expand`](super::aliases::expand)), usually by refusing it
+    async fn resolve_alias(&self, name: String) -> Result<HashSet<UserId>, E>;
+
+    /// Called by [interpret] after each operand (string literal or ID) is resolved, e.g. to report
+    /// progress. Does nothing by default.
+    fn operand_resolved(&self) {}
+}
+
+/// How many operands [`interpret_with_limits`] resolves at once
+pub const MAX_CONCURRENT_OPERANDS: usize = 8;
+
+/// How big a query [`interpret_with_limits`] agrees to evaluate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The most nodes (operators, functions and operands) the query may have
+    pub max_nodes: usize,
+    /// The most nodes deep the query may nest, counting the outermost one
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 1000,
+            max_depth: 500,
+        }
+    }
+}
+
+impl Limits {
+    /// Check that `node` is within these limits, without recursing into it, so even absurdly deep
+    /// queries can be checked before anything else walks them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `node` has too many nodes, or nests them too deeply.
+    pub fn check(self, node: &Expr) -> Result<(), TooComplex> {
+        let mut nodes = 0_usize;
+        let mut unchecked = vec![(node, 1_usize)];
+        while let Some((node, depth)) = unchecked.pop() {
+            nodes += 1;
+            if nodes > self.max_nodes {
+                return Err(TooComplex::TooManyNodes(self.max_nodes));
+            }
+            if depth > self.max_depth {
+                return Err(TooComplex::TooDeep(self.max_depth));
+            }
+            unchecked.extend(node.children().into_iter().map(|child| (child, depth + 1)));
+        }
+        Ok(())
+    }
+}
+
+/// A query too big to evaluate within some [`Limits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TooComplex {
+    /// The query has more nodes than the limit
+    TooManyNodes(usize),
+    /// The query nests its nodes deeper than the limit
+    TooDeep(usize),
+}
+
+impl Display for TooComplex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooManyNodes(limit) => write!(
+                f,
+                "Your query is too long to evaluate: it can use at most {limit} roles, members, \
+                operators and functions. Try splitting it up into smaller queries?"
+            ),
+            Self::TooDeep(limit) => write!(
+                f,
+                "Your query is nested too deeply to evaluate: it can only go {limit} levels deep. \
+                Try removing some parentheses?"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TooComplex {}
+
+/// A step left to take while interpreting a query
+enum Task<'a> {
+    /// Evaluate a node, pushing its members (after any nodes inside it) onto the results
+    Evaluate(&'a Expr),
+    /// Replace the results of the operands of a node with the result of the node itself
+    Combine(&'a Expr, Combination),
+}
+
+/// How a node combines the results of its operands, which are the last ones on the results
+#[derive(Debug, Clone, Copy)]
+enum Combination {
+    /// `a - b`
+    Difference,
+    /// `a & b`
+    Intersection,
+    /// `a | b`
+    Union,
+    /// `!a`
+    Complement,
+    /// `atleast(count, ...)`, with how many operands it has
+    AtLeast(usize, usize),
+    /// `a limit count`
+    Limit(usize),
+    /// `sample(a, count)`
+    Sample(usize),
+}
+
+/// Interpret a DRQL AST within the default [`Limits`], deferring to the Resolver to resolve string
+/// literals, user IDs, and role IDs.
+///
+/// # Errors
+///
+/// Returns an error if the query is too big, or any of its operands fail to resolve.
+pub async fn interpret<E: Send + From<TooComplex>>(
     node: Expr,
-    resolver: &mut (impl InterpreterResolver<E> + Send),
+    resolver: &(impl InterpreterResolver<E> + Sync),
 ) -> Result<HashSet<UserId>, E> {
-    Ok(match node {
-        Expr::Difference(lhs, rhs) => interpret(*lhs, resolver)
-            .await?
-            .difference(&interpret(*rhs, resolver).await?)
-            .copied()
-            .collect::<HashSet<_>>(),
-        Expr::Intersection(lhs, rhs) => interpret(*lhs, resolver)
-            .await?
-            .intersection(&interpret(*rhs, resolver).await?)
-            .copied()
-            .collect::<HashSet<_>>(),
-        Expr::Union(lhs, rhs) => interpret(*lhs, resolver)
-            .await?
-            .union(&interpret(*rhs, resolver).await?)
+    interpret_with_limits(node, resolver, Limits::default()).await
+}
+
+/// Interpret a DRQL AST, deferring to the Resolver to resolve string literals, user IDs, and role
+/// IDs, as long as it's within `limits`.
+///
+/// Every distinct operand is resolved first, up to [`MAX_CONCURRENT_OPERANDS`] at a time, since
+/// each of them might have to ask Discord. Their results are then combined from a worklist rather
+/// than by recursing into the query, so deep queries can't overflow the stack, and a subtree
+/// appearing more than once in the query, like the `a & b` in `(a & b) | ((a & b) - c)`, is only
+/// combined the first time.
+///
+/// # Errors
+///
+/// Returns an error if the query is too big, or any of its operands fail to resolve.
+#[instrument(skip_all, fields(node = tracing::field::Empty))]
+pub async fn interpret_with_limits<E: Send + From<TooComplex>>(
+    node: Expr,
+    resolver: &(impl InterpreterResolver<E> + Sync),
+    limits: Limits,
+) -> Result<HashSet<UserId>, E> {
+    limits.check(&node)?;
+    // Writing the query out recurses into it, so it can only be done once it's been checked
+    Span::current().record("node", tracing::field::display(&node));
+
+    let subtrees = subtrees(&node);
+    run(&node, &subtrees, resolver, |_, _| {}).await
+}
+
+/// How many members each node of a query matched, as counted by [`interpret_counting`]
+#[derive(Debug)]
+pub struct Counts {
+    /// The subtree under each node of the query, keyed by the node's [`address`]
+    subtrees: HashMap<usize, Subtree>,
+    /// How many members each node matched, keyed by its [`address`]
+    by_node: HashMap<usize, usize>,
+    /// How many members each subtree matched, keyed by its ID
+    ///
+    /// A repeated subtree is only evaluated once, so the nodes inside its other copies are only
+    /// found here.
+    by_subtree: HashMap<usize, usize>,
+}
+
+impl Counts {
+    /// How many members `node` matched, if it's a node of the query these were counted for.
+    #[must_use]
+    pub fn get(&self, node: &Expr) -> Option<usize> {
+        let address = address(node);
+        self.by_node
+            .get(&address)
+            .or_else(|| self.by_subtree.get(&self.subtrees.get(&address)?.id))
             .copied()
-            .collect::<HashSet<_>>(),
+    }
+}
 
-        Expr::StringLiteral(contents) => resolver.resolve_string_literal(contents).await?,
-        Expr::UnknownID(id) => resolver.resolve_unknown_id(id).await?,
-        Expr::UserID(id) => resolver.resolve_user_id(id).await?,
-        Expr::RoleID(id) => resolver.resolve_role_id(id).await?,
+/// Interpret a DRQL AST like [`interpret_with_limits`], also counting how many members each node
+/// of it matched, to explain how the query came to its result.
+///
+/// # Errors
+///
+/// Returns an error if the query is too big, or any of its operands fail to resolve.
+#[instrument(skip_all, fields(node = tracing::field::Empty))]
+pub async fn interpret_counting<E: Send + From<TooComplex>>(
+    node: &Expr,
+    resolver: &(impl InterpreterResolver<E> + Sync),
+    limits: Limits,
+) -> Result<(HashSet<UserId>, Counts), E> {
+    limits.check(node)?;
+    Span::current().record("node", tracing::field::display(node));
+
+    let subtrees = subtrees(node);
+    let mut by_node = HashMap::new();
+    let members = run(node, &subtrees, resolver, |node, members| {
+        by_node.insert(address(node), members.len());
     })
+    .await?;
+    let by_subtree = by_node
+        .iter()
+        .map(|(address, count)| (subtrees[address].id, *count))
+        .collect();
+    Ok((
+        members,
+        Counts {
+            subtrees,
+            by_node,
+            by_subtree,
+        },
+    ))
+}
+
+/// Evaluate `root`, whose [`Subtree`]s are `subtrees`, calling `evaluated` with each node and its
+/// members as it goes.
+async fn run<E: Send>(
+    root: &Expr,
+    subtrees: &HashMap<usize, Subtree>,
+    resolver: &(impl InterpreterResolver<E> + Sync),
+    mut evaluated: impl FnMut(&Expr, &HashSet<UserId>) + Send,
+) -> Result<HashSet<UserId>, E> {
+    let repeated = repeated_subtrees(root, subtrees);
+    let (mut known, everyone) = resolve_operands(root, subtrees, resolver).await?;
+    let mut tasks = vec![Task::Evaluate(root)];
+    let mut results = Vec::<HashSet<UserId>>::new();
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Evaluate(node) => {
+                let id = subtrees[&address(node)].id;
+                // Anything only needed once can be taken, rather than copied
+                let members = if repeated.contains(&id) {
+                    known.get(&id).cloned()
+                } else {
+                    known.remove(&id)
+                };
+                if let Some(members) = members {
+                    evaluated(node, &members);
+                    results.push(members);
+                } else {
+                    schedule(node, &mut tasks);
+                }
+            }
+            Task::Combine(node, combination) => {
+                let members = combine(combination, &mut results, everyone.as_ref());
+                evaluated(node, &members);
+                let id = subtrees[&address(node)].id;
+                if repeated.contains(&id) {
+                    known.insert(id, members.clone());
+                }
+                results.push(members);
+            }
+        }
+    }
+
+    Ok(results
+        .pop()
+        .expect("interpreting a query should leave exactly its result"))
+}
+
+/// A single node, without the nodes inside it
+#[derive(PartialEq, Eq, Hash)]
+enum Shape<'a> {
+    /// `a - b`
+    Difference,
+    /// `a & b`
+    Intersection,
+    /// `a | b`
+    Union,
+    /// `!a`
+    Complement,
+    /// `atleast(count, ...)`
+    AtLeast(usize),
+    /// `a limit count`
+    Limit(usize),
+    /// `sample(a, count)`
+    Sample(usize),
+    /// An operand, which has nothing inside it
+    Operand(&'a Expr),
+}
+
+impl<'a> From<&'a Expr> for Shape<'a> {
+    fn from(node: &'a Expr) -> Self {
+        match node {
+            Expr::Difference(_, _) => Self::Difference,
+            Expr::Intersection(_, _) => Self::Intersection,
+            Expr::Union(_, _) => Self::Union,
+            Expr::Complement(_) => Self::Complement,
+            Expr::AtLeast(count, _) => Self::AtLeast(*count),
+            Expr::Limit(_, count) => Self::Limit(*count),
+            Expr::Sample(_, count) => Self::Sample(*count),
+            Expr::StringLiteral(_)
+            | Expr::UnknownID(_)
+            | Expr::UserID(_)
+            | Expr::RoleID(_)
+            | Expr::ChannelID(_)
+            | Expr::Me
+            | Expr::Event(_)
+            | Expr::Permission(_)
+            | Expr::Joined(_, _)
+            | Expr::AccountAge(_)
+            | Expr::Name(_)
+            | Expr::Active(_)
+            | Expr::Reacted(_, _)
+            | Expr::Alias(_) => Self::Operand(node),
+        }
+    }
+}
+
+/// What every copy of a subtree of a query has in common
+#[derive(Debug, Clone, Copy)]
+struct Subtree {
+    /// Two nodes have the same ID exactly when the subtrees under them are the same
+    id: usize,
+    /// Whether the subtree picks members at random, so two copies of it may not be the same set
+    random: bool,
+}
+
+/// Where `node` is in memory, which tells apart two identical copies of a subtree.
+fn address(node: &Expr) -> usize {
+    ptr::from_ref(node).addr()
+}
+
+/// Find the [`Subtree`] under each node of `root`, keyed by the node's [`address`].
+///
+/// Hashing or comparing whole subtrees would recurse into them, so instead, each node is given an
+/// ID from its [`Shape`] and the IDs of its children, starting from the operands.
+fn subtrees(root: &Expr) -> HashMap<usize, Subtree> {
+    let mut subtrees = HashMap::<usize, Subtree>::new();
+    let mut ids = HashMap::<(Shape<'_>, Vec<usize>), usize>::new();
+    let mut unvisited = vec![(root, false)];
+    while let Some((node, children_visited)) = unvisited.pop() {
+        if !children_visited {
+            unvisited.push((node, true));
+            unvisited.extend(node.children().into_iter().map(|child| (child, false)));
+            continue;
+        }
+
+        let children = node
+            .children()
+            .into_iter()
+            .map(|child| subtrees[&address(child)])
+            .collect::<Vec<_>>();
+        let shape = Shape::from(node);
+        let random = matches!(shape, Shape::Sample(_)) || children.iter().any(|child| child.random);
+        let next_id = ids.len();
+        let id = *ids
+            .entry((shape, children.iter().map(|child| child.id).collect()))
+            .or_insert(next_id);
+        subtrees.insert(address(node), Subtree { id, random });
+    }
+    subtrees
+}
+
+/// Find the IDs of the subtrees of `root` which appear in it more than once, and always evaluate
+/// to the same members, so they're worth remembering the members of.
+fn repeated_subtrees(root: &Expr, subtrees: &HashMap<usize, Subtree>) -> HashSet<usize> {
+    let mut occurrences = HashMap::<usize, usize>::new();
+    let mut unvisited = vec![root];
+    while let Some(node) = unvisited.pop() {
+        let subtree = subtrees[&address(node)];
+        let seen = occurrences.entry(subtree.id).or_default();
+        *seen += 1;
+        // Everything inside a remembered subtree is only evaluated once, along with it
+        if *seen == 1 || subtree.random {
+            unvisited.extend(node.children());
+        }
+        if subtree.random {
+            occurrences.remove(&subtree.id);
+        }
+    }
+
+    occurrences
+        .into_iter()
+        .filter(|(_, occurrences)| *occurrences > 1)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Resolve every distinct operand of `root`, keyed by its subtree ID, along with everyone if
+/// `root` takes a complement.
+///
+/// Up to [`MAX_CONCURRENT_OPERANDS`] operands are resolved at once, and each one is reported as
+/// resolved once for every time it appears in `root`.
+async fn resolve_operands<E: Send>(
+    root: &Expr,
+    subtrees: &HashMap<usize, Subtree>,
+    resolver: &(impl InterpreterResolver<E> + Sync),
+) -> Result<(HashMap<usize, HashSet<UserId>>, Option<HashSet<UserId>>), E> {
+    // Each distinct operand, from left to right, with how many times it appears
+    let mut operands = Vec::<(usize, &Expr, usize)>::new();
+    let mut positions = HashMap::<usize, usize>::new();
+    let mut needs_everyone = false;
+    let mut unvisited = vec![root];
+    while let Some(node) = unvisited.pop() {
+        needs_everyone |= matches!(node, Expr::Complement(_) | Expr::AtLeast(0, _));
+        if matches!(Shape::from(node), Shape::Operand(_)) {
+            let id = subtrees[&address(node)].id;
+            let position = *positions.entry(id).or_insert_with(|| {
+                operands.push((id, node, 0));
+                operands.len() - 1
+            });
+            operands[position].2 += 1;
+        }
+        unvisited.extend(node.children().into_iter().rev());
+    }
+
+    // Collected first, since a stream mapping to futures borrowing the resolver confuses the
+    // compiler about whether the whole future is `Send`
+    let resolutions = operands
+        .into_iter()
+        .map(|(id, node, occurrences)| async move {
+            let members = resolve(node, resolver).await?;
+            for _ in 0..occurrences {
+                resolver.operand_resolved();
+            }
+            Ok((id, members))
+        })
+        .collect::<Vec<_>>();
+    let operands = stream::iter(resolutions)
+        .buffered(MAX_CONCURRENT_OPERANDS)
+        .try_collect::<HashMap<_, _>>();
+    let everyone = async {
+        if needs_everyone {
+            resolver.resolve_everyone().await.map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    try_join!(operands, everyone)
+}
+
+/// Resolve an operand to its members.
+async fn resolve<E: Send>(
+    node: &Expr,
+    resolver: &(impl InterpreterResolver<E> + Sync),
+) -> Result<HashSet<UserId>, E> {
+    match node {
+        Expr::StringLiteral(contents) => resolver.resolve_string_literal(contents.clone()).await,
+        Expr::UnknownID(id) => resolver.resolve_unknown_id(id.clone()).await,
+        Expr::UserID(id) => resolver.resolve_user_id(*id).await,
+        Expr::RoleID(id) => resolver.resolve_role_id(*id).await,
+        Expr::ChannelID(id) => resolver.resolve_channel_id(*id).await,
+        Expr::Me => resolver.resolve_me().await,
+        Expr::Event(id) => resolver.resolve_event(*id).await,
+        Expr::Joined(side, moment) => resolver.resolve_joined(*side, *moment).await,
+        Expr::AccountAge(age) => resolver.resolve_account_age(*age).await,
+        Expr::Name(pattern) => resolver.resolve_name(pattern.clone()).await,
+        Expr::Active(within) => resolver.resolve_active(*within).await,
+        Expr::Reacted(link, emoji) => resolver.resolve_reacted(*link, emoji.clone()).await,
+        Expr::Permission(permission) => resolver.resolve_permission(*permission).await,
+        Expr::Alias(name) => resolver.resolve_alias(name.clone()).await,
+
+        Expr::Difference(_, _)
+        | Expr::Intersection(_, _)
+        | Expr::Union(_, _)
+        | Expr::Complement(_)
+        | Expr::AtLeast(_, _)
+        | Expr::Limit(_, _)
+        | Expr::Sample(_, _) => unreachable!("only operands are resolved"),
+    }
+}
+
+/// Schedule evaluating the nodes inside an operator, and then combining their results.
+fn schedule<'a>(node: &'a Expr, tasks: &mut Vec<Task<'a>>) {
+    let combination = match node {
+        Expr::Difference(_, _) => Combination::Difference,
+        Expr::Intersection(_, _) => Combination::Intersection,
+        Expr::Union(_, _) => Combination::Union,
+        Expr::Complement(_) => Combination::Complement,
+        Expr::AtLeast(count, operands) => Combination::AtLeast(*count, operands.len()),
+        Expr::Limit(_, count) => Combination::Limit(*count),
+        Expr::Sample(_, count) => Combination::Sample(*count),
+
+        Expr::StringLiteral(_)
+        | Expr::UnknownID(_)
+        | Expr::UserID(_)
+        | Expr::RoleID(_)
+        | Expr::ChannelID(_)
+        | Expr::Me
+        | Expr::Event(_)
+        | Expr::Permission(_)
+        | Expr::Joined(_, _)
+        | Expr::AccountAge(_)
+        | Expr::Name(_)
+        | Expr::Active(_)
+        | Expr::Reacted(_, _)
+        | Expr::Alias(_) => unreachable!("every operand is resolved before combining"),
+    };
+
+    // Tasks are taken from the end, so the left-most operands are pushed last to be evaluated first
+    tasks.push(Task::Combine(node, combination));
+    tasks.extend(node.children().into_iter().rev().map(Task::Evaluate));
+}
+
+/// Take the results of a node's operands off the end of `results`, and combine them into the
+/// result of the node, with `everyone` being everyone a complement is taken relative to.
+fn combine(
+    combination: Combination,
+    results: &mut Vec<HashSet<UserId>>,
+    everyone: Option<&HashSet<UserId>>,
+) -> HashSet<UserId> {
+    let mut pop = || {
+        results
+            .pop()
+            .expect("every operand should be evaluated before combining it")
+    };
+
+    let everyone = || everyone.expect("everyone should be resolved for a complement");
+    match combination {
+        Combination::Difference => {
+            let rhs = pop();
+            pop().difference(&rhs).copied().collect::<HashSet<_>>()
+        }
+        Combination::Intersection => {
+            let rhs = pop();
+            pop().intersection(&rhs).copied().collect::<HashSet<_>>()
+        }
+        Combination::Union => {
+            let rhs = pop();
+            pop().union(&rhs).copied().collect::<HashSet<_>>()
+        }
+        Combination::Complement => {
+            let excluded = pop();
+            everyone()
+                .difference(&excluded)
+                .copied()
+                .collect::<HashSet<_>>()
+        }
+        Combination::AtLeast(0, operands) => {
+            // Everyone is a member of at least none of them, but the operands still had to resolve
+            results.truncate(results.len() - operands);
+            everyone().clone()
+        }
+        Combination::AtLeast(count, operands) => {
+            let mut memberships = HashMap::<UserId, usize>::new();
+            for member in results.drain(results.len() - operands..).flatten() {
+                *memberships.entry(member).or_default() += 1;
+            }
+            memberships
+                .into_iter()
+                .filter(|(_, memberships)| *memberships >= count)
+                .map(|(member, _)| member)
+                .collect::<HashSet<_>>()
+        }
+        Combination::Limit(count) => {
+            // Sorted, so the same members are kept each time
+            let mut members = pop().into_iter().collect::<Vec<_>>();
+            members.sort_unstable();
+            members.truncate(count);
+            members.into_iter().collect::<HashSet<_>>()
+        }
+        Combination::Sample(count) => pop()
+            .into_iter()
+            .choose_multiple(&mut rand::thread_rng(), count)
+            .into_iter()
+            .collect::<HashSet<_>>(),
+    }
 }
 
 #[cfg(test)]
@@ -65,13 +621,14 @@ mod tests {
         use anyhow::anyhow;
 
         use super::*;
+        use crate::drql::{parser::parse_drql, testing::MockGuild};
 
         // In this case, the resolver uses some basic predefined values.
         struct Resolver;
         #[async_trait]
         impl InterpreterResolver<anyhow::Error> for Resolver {
             async fn resolve_string_literal(
-                &mut self,
+                &self,
                 contents: String,
             ) -> Result<HashSet<UserId>, anyhow::Error> {
                 if contents == "test_ok_case" {
@@ -82,7 +639,7 @@ mod tests {
             }
 
             async fn resolve_unknown_id(
-                &mut self,
+                &self,
                 id: String,
             ) -> Result<HashSet<UserId>, anyhow::Error> {
                 if id == "0" {
@@ -92,10 +649,7 @@ mod tests {
                 }
             }
 
-            async fn resolve_user_id(
-                &mut self,
-                id: UserId,
-            ) -> Result<HashSet<UserId>, anyhow::Error> {
+            async fn resolve_user_id(&self, id: UserId) -> Result<HashSet<UserId>, anyhow::Error> {
                 if id.0 == 0 {
                     Ok(HashSet::from([UserId(3)]))
                 } else {
@@ -103,16 +657,87 @@ mod tests {
                 }
             }
 
-            async fn resolve_role_id(
-                &mut self,
-                id: RoleId,
-            ) -> Result<HashSet<UserId>, anyhow::Error> {
+            async fn resolve_role_id(&self, id: RoleId) -> Result<HashSet<UserId>, anyhow::Error> {
                 if id.0 == 0 {
                     Ok(HashSet::from([UserId(4)]))
                 } else {
                     Err(anyhow!("error case 4"))
                 }
             }
+
+            async fn resolve_channel_id(
+                &self,
+                id: ChannelId,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                if id.0 == 0 {
+                    Ok(HashSet::from([UserId(6)]))
+                } else {
+                    Err(anyhow!("error case 6"))
+                }
+            }
+
+            async fn resolve_me(&self) -> Result<HashSet<UserId>, anyhow::Error> {
+                Ok(HashSet::from([UserId(5)]))
+            }
+
+            async fn resolve_event(
+                &self,
+                _id: ScheduledEventId,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 7"))
+            }
+
+            async fn resolve_joined(
+                &self,
+                _side: Side,
+                _moment: Moment,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 9"))
+            }
+
+            async fn resolve_account_age(
+                &self,
+                _age: Age,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 10"))
+            }
+
+            async fn resolve_reacted(
+                &self,
+                _link: MessageLink,
+                _emoji: ReactionType,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 11"))
+            }
+
+            async fn resolve_active(
+                &self,
+                _within: Duration,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 12"))
+            }
+
+            async fn resolve_name(
+                &self,
+                _pattern: NamePattern,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 13"))
+            }
+
+            async fn resolve_permission(
+                &self,
+                _permission: Permissions,
+            ) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 8"))
+            }
+
+            async fn resolve_everyone(&self) -> Result<HashSet<UserId>, anyhow::Error> {
+                Ok(HashSet::from([UserId(1), UserId(2), UserId(3), UserId(4)]))
+            }
+
+            async fn resolve_alias(&self, _name: String) -> Result<HashSet<UserId>, anyhow::Error> {
+                Err(anyhow!("error case 9"))
+            }
         }
 
         #[tokio::test]
@@ -125,18 +750,157 @@ mod tests {
                             Box::new(Expr::UnknownID("0".to_string())),
                             Box::new(Expr::Union(
                                 Box::new(Expr::UserID(UserId(0))),
-                                Box::new(Expr::RoleID(RoleId(0)))
+                                Box::new(Expr::Union(
+                                    Box::new(Expr::RoleID(RoleId(0))),
+                                    Box::new(Expr::ChannelID(ChannelId(0)))
+                                ))
                             ))
                         ))
                     ),
-                    &mut Resolver {}
+                    &Resolver {}
+                )
+                .await
+                .expect("interpret should not fail"),
+                HashSet::from([UserId(1), UserId(2), UserId(3), UserId(4), UserId(6)])
+            );
+        }
+
+        #[tokio::test]
+        async fn complement_is_relative_to_everyone() {
+            assert_eq!(
+                interpret(
+                    Expr::Complement(Box::new(Expr::Union(
+                        Box::new(Expr::StringLiteral("test_ok_case".to_string())),
+                        Box::new(Expr::RoleID(RoleId(0)))
+                    ))),
+                    &Resolver {}
+                )
+                .await
+                .expect("interpret should not fail"),
+                HashSet::from([UserId(2), UserId(3)])
+            );
+        }
+
+        #[tokio::test]
+        async fn samples_are_subsets() {
+            let everyone_else = || {
+                Box::new(Expr::Complement(Box::new(Expr::StringLiteral(
+                    "test_ok_case".to_string(),
+                ))))
+            };
+
+            let sample = interpret(Expr::Sample(everyone_else(), 1), &Resolver {})
+                .await
+                .expect("interpret should not fail");
+            assert_eq!(sample.len(), 1);
+            assert!(sample.is_subset(&HashSet::from([UserId(2), UserId(3), UserId(4)])));
+            // Asking for more members than there are gives all of them
+            assert_eq!(
+                interpret(Expr::Sample(everyone_else(), 5), &Resolver {})
+                    .await
+                    .expect("interpret should not fail"),
+                HashSet::from([UserId(2), UserId(3), UserId(4)])
+            );
+        }
+
+        #[tokio::test]
+        async fn limits_keep_the_lowest_ids() {
+            assert_eq!(
+                interpret(
+                    Expr::Limit(
+                        Box::new(Expr::Complement(Box::new(Expr::StringLiteral(
+                            "test_ok_case".to_string()
+                        )))),
+                        2
+                    ),
+                    &Resolver {}
                 )
                 .await
                 .expect("interpret should not fail"),
+                HashSet::from([UserId(2), UserId(3)])
+            );
+        }
+
+        #[tokio::test]
+        async fn at_least_counts_memberships() {
+            let operands = || {
+                vec![
+                    Expr::StringLiteral("test_ok_case".to_string()),
+                    Expr::UnknownID("0".to_string()),
+                    Expr::Union(
+                        Box::new(Expr::UserID(UserId(0))),
+                        Box::new(Expr::StringLiteral("test_ok_case".to_string())),
+                    ),
+                ]
+            };
+
+            assert_eq!(
+                interpret(Expr::AtLeast(2, operands()), &Resolver {})
+                    .await
+                    .expect("interpret should not fail"),
+                HashSet::from([UserId(1)])
+            );
+            assert_eq!(
+                interpret(Expr::AtLeast(1, operands()), &Resolver {})
+                    .await
+                    .expect("interpret should not fail"),
+                HashSet::from([UserId(1), UserId(2), UserId(3)])
+            );
+            assert_eq!(
+                interpret(Expr::AtLeast(0, operands()), &Resolver {})
+                    .await
+                    .expect("interpret should not fail"),
                 HashSet::from([UserId(1), UserId(2), UserId(3), UserId(4)])
             );
         }
 
+        /// Nest `depth` complements around an operand, without recursing.
+        fn nested(depth: usize) -> Expr {
+            (1..depth).fold(
+                Expr::StringLiteral("test_ok_case".to_string()),
+                |inner, _| Expr::Complement(Box::new(inner)),
+            )
+        }
+
+        #[tokio::test]
+        async fn big_queries_are_refused() {
+            let limits = Limits {
+                max_nodes: 10,
+                max_depth: 3,
+            };
+
+            assert_eq!(limits.check(&nested(3)), Ok(()));
+            assert_eq!(limits.check(&nested(4)), Err(TooComplex::TooDeep(3)));
+            assert_eq!(
+                limits.check(&Expr::AtLeast(
+                    1,
+                    (0..10)
+                        .map(|_| Expr::StringLiteral("test_ok_case".to_string()))
+                        .collect()
+                )),
+                Err(TooComplex::TooManyNodes(10))
+            );
+            assert!(interpret(nested(1000), &Resolver {})
+                .await
+                .is_err_and(|err| err.downcast_ref() == Some(&TooComplex::TooDeep(500))));
+        }
+
+        #[tokio::test]
+        async fn deep_queries_do_not_recurse() {
+            let limits = Limits {
+                max_nodes: 10_001,
+                max_depth: 10_001,
+            };
+
+            // An even number of complements cancel out
+            assert_eq!(
+                interpret_with_limits(nested(10_001), &Resolver {}, limits)
+                    .await
+                    .expect("interpret should not fail"),
+                HashSet::from([UserId(1)])
+            );
+        }
+
         #[tokio::test]
         async fn errors_bubble() {
             assert!(interpret(
@@ -144,10 +908,220 @@ mod tests {
                     Box::new(Expr::StringLiteral("7".to_string())),
                     Box::new(Expr::StringLiteral("test_ok_case".to_string())),
                 ),
-                &mut Resolver {},
+                &Resolver {},
             )
             .await
             .is_err());
         }
+
+        #[tokio::test]
+        async fn every_node_is_counted() {
+            let guild = MockGuild::new()
+                .with_role(RoleId(10), "a")
+                .with_role(RoleId(11), "b")
+                .with_member(UserId(1), "xavier", &[RoleId(10)])
+                .with_member(UserId(2), "yara", &[RoleId(10), RoleId(11)])
+                .with_member(UserId(3), "zed", &[RoleId(11)]);
+            let ast = parse_drql("(a & b) | ((a & b) - b)").expect("the query should parse");
+
+            let (members, counts) = interpret_counting(&ast, &guild, Limits::default())
+                .await
+                .expect("interpret should not fail");
+
+            assert_eq!(members, HashSet::from([UserId(2)]));
+            let Expr::Union(lhs, rhs) = &ast else {
+                panic!("the query should be a union");
+            };
+            let Expr::Difference(repeated, subtracted) = &**rhs else {
+                panic!("the right operand should be a difference");
+            };
+            assert_eq!(counts.get(&ast), Some(1));
+            assert_eq!(counts.get(lhs), Some(1));
+            assert_eq!(counts.get(rhs), Some(0));
+            assert_eq!(counts.get(subtracted), Some(2));
+            // The second `a & b` isn't evaluated again, but what's inside it is still counted
+            for node in repeated.children() {
+                assert_eq!(counts.get(node), Some(2));
+            }
+            assert_eq!(counts.get(&Expr::Me), None);
+        }
+    }
+
+    mod progress {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        };
+
+        use super::*;
+        use crate::drql::parser::parse_drql;
+
+        impl From<TooComplex> for () {
+            fn from(_: TooComplex) -> Self {}
+        }
+
+        /// A resolver resolving everything to nobody, counting the operands it resolved.
+        #[derive(Default)]
+        struct Resolver {
+            /// How many operands were resolved
+            resolved: AtomicUsize,
+            /// The string literals which were resolved, in the order they started resolving
+            literals: Mutex<Vec<String>>,
+            /// How many string literals are resolving right now
+            resolving: AtomicUsize,
+            /// The most string literals which were ever resolving at once
+            most_resolving: AtomicUsize,
+        }
+        #[async_trait]
+        impl InterpreterResolver<()> for Resolver {
+            async fn resolve_string_literal(
+                &self,
+                contents: String,
+            ) -> Result<HashSet<UserId>, ()> {
+                self.literals
+                    .lock()
+                    .expect("literals lock was poisoned")
+                    .push(contents);
+                let resolving = self.resolving.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most_resolving.fetch_max(resolving, Ordering::SeqCst);
+                // Give the other literals a chance to start resolving too
+                tokio::task::yield_now().await;
+                self.resolving.fetch_sub(1, Ordering::SeqCst);
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_unknown_id(&self, _id: String) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_user_id(&self, _id: UserId) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_role_id(&self, _id: RoleId) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_channel_id(&self, _id: ChannelId) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_me(&self) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_event(&self, _id: ScheduledEventId) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_joined(
+                &self,
+                _side: Side,
+                _moment: Moment,
+            ) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_account_age(&self, _age: Age) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_reacted(
+                &self,
+                _link: MessageLink,
+                _emoji: ReactionType,
+            ) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_active(&self, _within: Duration) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_name(&self, _pattern: NamePattern) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_permission(
+                &self,
+                _permission: Permissions,
+            ) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_everyone(&self) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            async fn resolve_alias(&self, _name: String) -> Result<HashSet<UserId>, ()> {
+                Ok(HashSet::new())
+            }
+
+            fn operand_resolved(&self) {
+                self.resolved.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        #[tokio::test]
+        async fn every_operand_is_reported() {
+            let resolver = Resolver::default();
+            interpret(
+                Expr::Difference(
+                    Box::new(Expr::StringLiteral("a".to_string())),
+                    Box::new(Expr::Intersection(
+                        Box::new(Expr::UserID(UserId(1))),
+                        Box::new(Expr::RoleID(RoleId(1))),
+                    )),
+                ),
+                &resolver,
+            )
+            .await
+            .expect("interpret should not fail");
+
+            assert_eq!(resolver.resolved.into_inner(), 3);
+        }
+
+        #[tokio::test]
+        async fn repeated_subtrees_are_resolved_once() {
+            let resolver = Resolver::default();
+            interpret(
+                parse_drql("(a & b) | ((a & b) - c) | sample(c, 1) | sample(c, 1)")
+                    .expect("the query should parse"),
+                &resolver,
+            )
+            .await
+            .expect("interpret should not fail");
+
+            // Samples are random, so each one is evaluated, but the `c` inside them isn't
+            assert_eq!(
+                resolver
+                    .literals
+                    .into_inner()
+                    .expect("literals lock was poisoned"),
+                ["a", "b", "c"]
+            );
+            assert_eq!(resolver.resolved.into_inner(), 7);
+        }
+
+        #[tokio::test]
+        async fn operands_are_resolved_concurrently() {
+            let resolver = Resolver::default();
+            let query = (0..20)
+                .map(|literal| format!("\"{literal}\""))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            interpret(
+                parse_drql(&query).expect("the query should parse"),
+                &resolver,
+            )
+            .await
+            .expect("interpret should not fail");
+
+            assert_eq!(resolver.resolved.into_inner(), 20);
+            assert_eq!(
+                resolver.most_resolving.into_inner(),
+                MAX_CONCURRENT_OPERANDS
+            );
+        }
     }
 }
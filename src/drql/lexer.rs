@@ -1,8 +1,9 @@
 //! Lexer for the DRQL language
 
-use std::num::ParseIntError;
+use std::{num::ParseIntError, ops::Range, sync::LazyLock};
 
 use logos::{Lexer, Logos};
+use regex::Regex;
 
 /// Any value attached to a span within source text.
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
@@ -21,6 +22,8 @@ pub enum LexicalError {
     UnterminatedStringLiteral(usize),
     /// An error while parsing an integer
     ParseIntError(ParseIntError),
+    /// A call to a function which doesn't exist, or with an argument it doesn't take
+    InvalidFunctionCall(String),
 }
 impl From<ParseIntError> for LexicalError {
     fn from(value: ParseIntError) -> Self {
@@ -38,10 +41,76 @@ impl std::fmt::Display for LexicalError {
                 write!(f, "Unterminated string literal at index {index}")
             }
             Self::ParseIntError(err) => write!(f, "ParseIntError: {err}"),
+            Self::InvalidFunctionCall(call) => write!(f, "Invalid function call: `{call}`"),
         }
     }
 }
 
+/// Words which aren't string literals, regardless of their case
+pub const KEYWORDS: [&str; 6] = ["and", "or", "not", "minus", "me", "limit"];
+
+/// Read the escape sequences in the contents of a quoted string literal, like the `\"` in
+/// `"Role \"A\""`. `\"`, `\\` and `\n` are understood, and any other backslash is kept as it is.
+#[must_use]
+pub fn unescape(contents: &str) -> String {
+    let mut unescaped = String::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(escaped @ ('"' | '\\')) => unescaped.push(escaped),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Write `contents` as a quoted string literal, which [`unescape`] reads back as `contents`.
+#[must_use]
+pub fn quote(contents: &str) -> String {
+    let mut quoted = String::with_capacity(contents.len() + 2);
+    quoted.push('"');
+    for char in contents.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The rest of a bare string literal after its first character, like the `Mods` in `🛡️Mods`
+///
+/// logos 0.14 miscompiles repeating a union of Unicode classes this large, ending tokens in the
+/// middle of a character, so the rest of a literal is matched with [`regex`] instead.
+static NAME_CONTINUATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?:[\p{L}\p{M}\p{N}_\u{FE0F}\u{200D}]|\p{Extended_Pictographic}|\p{Emoji_Modifier})*",
+    )
+    .expect("the name continuation regex should be valid")
+});
+
+/// Extend the current token over the rest of a bare string literal, returning how much longer it
+/// got.
+fn bump_name_continuation(lex: &mut Lexer<'_, Tok>) -> usize {
+    let length = NAME_CONTINUATION
+        .find(lex.remainder())
+        .map_or(0, |continuation| continuation.end());
+    lex.bump(length);
+    length
+}
+
 /// The list of possible tokens in DRQL
 #[derive(Logos, Debug, Clone, PartialEq, Eq)]
 #[logos(error = LexicalError, skip r"[ \t\r\n\f]+")]
@@ -64,19 +133,55 @@ pub enum Tok {
     /// The token `)`
     #[token(")")]
     RightParen,
+    /// The token `!`
+    #[token("!")]
+    Bang,
+    /// The token `~`
+    #[token("~")]
+    Tilde,
+    /// The token `<`
+    #[token("<")]
+    LessThan,
+    /// The token `>`
+    #[token(">")]
+    GreaterThan,
+    /// The token `,`
+    #[token(",")]
+    Comma,
+    /// The keyword `and`, which is the same as `&`
+    #[token("and", ignore(ascii_case))]
+    And,
+    /// The keyword `or`, which is the same as `|`
+    #[token("or", ignore(ascii_case))]
+    Or,
+    /// The keyword `not`, which is the same as `!`
+    #[token("not", ignore(ascii_case))]
+    Not,
+    /// The keyword `minus`, which is the same as `-`
+    #[token("minus", ignore(ascii_case))]
+    MinusKeyword,
+    /// The keyword `me`, referring to the author of the query
+    #[token("me", ignore(ascii_case))]
+    Me,
+    /// The keyword `limit`, capping how many members a query matches, like `a limit 25`
+    #[token("limit", ignore(ascii_case))]
+    Limit,
 
     /// String literals: `"abc def"`, `abc`, `everyone`, `here`, etc
     /// From issue #25, `@everyone` and `@here` (the exact strings, which are the mentions)
     /// are treated as `everyone` and `here`.
-    #[regex(r#""([^"\\]|\\.)*""#, |lex| lex.slice()[1..(lex.slice().len()-1)].to_string())]
-    #[regex(r#"“([^"\\]|\\.)*”"#, |lex| lex.slice()[3..(lex.slice().len()-3)].to_string())]
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| unescape(&lex.slice()[1..(lex.slice().len()-1)]))]
+    #[regex(r#"“([^"\\]|\\.)*”"#, |lex| unescape(&lex.slice()[3..(lex.slice().len()-3)]))]
     #[regex(r#""([^"\\]|\\.)*"#, |lex| {
         Err(LexicalError::UnterminatedStringLiteral(lex.span().start))
     })]
     #[regex(r#"“([^"\\]|\\.)*"#, |lex| {
         Err(LexicalError::UnterminatedStringLiteral(lex.span().start))
     })]
-    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
+    #[regex(r"[\p{L}_][\p{L}\p{M}\p{N}_]*", |lex| {
+        bump_name_continuation(lex);
+        lex.slice().to_string()
+    })]
     #[token("@everyone", |lex| lex.slice()[1..].to_string())]
     #[token("@here", |lex| lex.slice()[1..].to_string())]
     StringLiteral(String),
@@ -85,6 +190,10 @@ pub enum Tok {
     #[regex(r"[0-9]+", |lex| lex.slice().to_string())]
     IDLiteral(String),
 
+    /// Durations, like `30d`
+    #[regex(r"[0-9]+[wdhms]", |lex| lex.slice().to_string())]
+    Duration(String),
+
     /// User mentions
     #[regex(r"<@!?([0-9]+)>", |lex| {
         let slice = lex.slice();
@@ -100,6 +209,35 @@ pub enum Tok {
     /// Role mentions
     #[regex(r"<@&[0-9]+>", |lex| lex.slice()[3..(lex.slice().len()-1)].to_string())]
     RoleMention(String),
+
+    /// Channel mentions
+    #[regex(r"<#[0-9]+>", |lex| lex.slice()[2..(lex.slice().len()-1)].to_string())]
+    ChannelMention(String),
+
+    /// Links to scheduled events, like `https://discord.com/events/1/2`
+    #[regex(r"https://((ptb|canary)\.)?discord(app)?\.com/events/[0-9]+/[0-9]+", |lex| lex.slice().to_string())]
+    EventLink(String),
+
+    /// Links to messages, like `https://discord.com/channels/1/2/3`
+    #[regex(r"https://((ptb|canary)\.)?discord(app)?\.com/channels/([0-9]+|@me)/[0-9]+/[0-9]+", |lex| lex.slice().to_string())]
+    MessageLink(String),
+
+    /// Emojis, either custom ones like `<:name:123>` or unicode ones like `✅`
+    #[regex(r"<a?:[a-zA-Z0-9_]+:[0-9]+>", |lex| lex.slice().to_string())]
+    ///
+    /// A unicode emoji followed by more of a name, like `🛡️Mods`, is a string literal instead (see
+    /// [`DrqlLexer`]).
+    #[regex(r"\p{Extended_Pictographic}[\u{FE0F}\p{Emoji_Modifier}]*(\u{200D}\p{Extended_Pictographic}[\u{FE0F}\p{Emoji_Modifier}]*)*", |lex| lex.slice().to_string(), priority = 10)]
+    #[regex(r"\p{Regional_Indicator}\p{Regional_Indicator}", |lex| lex.slice().to_string())]
+    Emoji(String),
+
+    /// Regexes, like `/^team-(red|blue)/`, without the slashes around them
+    #[regex(r"/([^/\\\n]|\\.)+/", |lex| lex.slice()[1..(lex.slice().len()-1)].to_string())]
+    Regex(String),
+
+    /// Aliases, like `$oncall`, without the dollar sign
+    #[regex(r"\$[\p{L}_][\p{L}\p{M}\p{N}_]*", |lex| lex.slice()[1..].to_string())]
+    Alias(String),
 }
 
 impl std::fmt::Display for Tok {
@@ -111,16 +249,37 @@ impl std::fmt::Display for Tok {
             Self::Ampersand => write!(f, "&"),
             Self::LeftParen => write!(f, "("),
             Self::RightParen => write!(f, ")"),
-            Self::StringLiteral(contents) => write!(f, "\"{contents}\""),
+            Self::Bang => write!(f, "!"),
+            Self::Tilde => write!(f, "~"),
+            Self::LessThan => write!(f, "<"),
+            Self::GreaterThan => write!(f, ">"),
+            Self::Comma => write!(f, ","),
+            Self::And => write!(f, "and"),
+            Self::Or => write!(f, "or"),
+            Self::Not => write!(f, "not"),
+            Self::MinusKeyword => write!(f, "minus"),
+            Self::Me => write!(f, "me"),
+            Self::Limit => write!(f, "limit"),
+            Self::StringLiteral(contents) => write!(f, "{}", quote(contents)),
             Self::IDLiteral(id) => write!(f, "{id}"),
+            Self::Duration(duration) => write!(f, "{duration}"),
             Self::UserMention(id) => write!(f, "<@{id}>"),
             Self::RoleMention(id) => write!(f, "<@&{id}>"),
+            Self::ChannelMention(id) => write!(f, "<#{id}>"),
+            Self::EventLink(link) | Self::MessageLink(link) => write!(f, "{link}"),
+            Self::Emoji(emoji) => write!(f, "{emoji}"),
+            Self::Regex(regex) => write!(f, "/{regex}/"),
+            Self::Alias(name) => write!(f, "${name}"),
         }
     }
 }
 
 /// A lexer for the Discord Role Query Language
+///
+/// Unicode emojis are lexed as [`Tok::Emoji`] on their own, but as part of a [`Tok::StringLiteral`]
+/// when a name follows them, like `🛡️Mods`.
 #[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
 pub struct DrqlLexer<'input> {
     /// The internal [`Lexer`] we are feeding from
     lex: Lexer<'input, Tok>,
@@ -128,18 +287,30 @@ pub struct DrqlLexer<'input> {
 
 impl<'input> DrqlLexer<'input> {
     /// Create a new [`DrqlLexer`] from a given input [`str`].
+    #[must_use]
     pub fn new(input: &'input str) -> Self {
         Self {
             lex: Tok::lexer(input),
         }
     }
+
+    /// The bytes of the input the last token (or error) was lexed from.
+    #[must_use]
+    pub fn span(&self) -> Range<usize> {
+        self.lex.span()
+    }
 }
 
-impl<'input> Iterator for DrqlLexer<'input> {
+impl Iterator for DrqlLexer<'_> {
     type Item = Spanned<Tok, usize, LexicalError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token = self.lex.next()?;
+        let mut token = self.lex.next()?;
+        if matches!(&token, Ok(Tok::Emoji(emoji)) if !emoji.starts_with('<'))
+            && bump_name_continuation(&mut self.lex) > 0
+        {
+            token = Ok(Tok::StringLiteral(self.lex.slice().to_string()));
+        }
         let span = self.lex.span();
         let slice = self.lex.slice().to_string();
         match token {
@@ -158,6 +329,8 @@ impl<'input> Iterator for DrqlLexer<'input> {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -193,6 +366,125 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lexer_keywords_are_whole_words() {
+        let lexer = DrqlLexer::new("and OR Not minus me Limit android limits \"and\"");
+        let tokens: Vec<_> = lexer
+            .map(|x| x.expect("lexing should not have failed").1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::And,
+                Tok::Or,
+                Tok::Not,
+                Tok::MinusKeyword,
+                Tok::Me,
+                Tok::Limit,
+                Tok::StringLiteral("android".to_string()),
+                Tok::StringLiteral("limits".to_string()),
+                Tok::StringLiteral("and".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_links_and_emojis() {
+        let lexer = DrqlLexer::new("https://discord.com/channels/1/2/3, \u{2705} \u{1f1fa}\u{1f1f8} \u{1f44b}\u{1f3fd} <a:yes:4>");
+        let tokens: Vec<_> = lexer
+            .map(|x| x.expect("lexing should not have failed").1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::MessageLink("https://discord.com/channels/1/2/3".to_string()),
+                Tok::Comma,
+                Tok::Emoji("\u{2705}".to_string()),
+                Tok::Emoji("\u{1f1fa}\u{1f1f8}".to_string()),
+                Tok::Emoji("\u{1f44b}\u{1f3fd}".to_string()),
+                Tok::Emoji("<a:yes:4>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_unicode_names() {
+        let lexer = DrqlLexer::new(
+            "d\u{e9}sign \u{1f6e1}\u{fe0f}Mods caf\u{e9}\u{2615} \"\u{1f6e1}\u{fe0f} Mods\" \u{2705} e\u{301}",
+        );
+        let tokens: Vec<_> = lexer
+            .map(|x| x.expect("lexing should not have failed").1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::StringLiteral("d\u{e9}sign".to_string()),
+                Tok::StringLiteral("\u{1f6e1}\u{fe0f}Mods".to_string()),
+                Tok::StringLiteral("caf\u{e9}\u{2615}".to_string()),
+                Tok::StringLiteral("\u{1f6e1}\u{fe0f} Mods".to_string()),
+                Tok::Emoji("\u{2705}".to_string()),
+                Tok::StringLiteral("e\u{301}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_escape_sequences() {
+        let lexer = DrqlLexer::new(r#""Role \"A\"" "a\\b\nc" "\d""#);
+        let tokens: Vec<_> = lexer
+            .map(|x| x.expect("lexing should not have failed").1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::StringLiteral("Role \"A\"".to_string()),
+                Tok::StringLiteral("a\\b\nc".to_string()),
+                Tok::StringLiteral(r"\d".to_string()),
+            ]
+        );
+        assert_eq!(quote(r"\d"), r#""\\d""#);
+    }
+
+    #[test]
+    fn lexer_spans_cover_errors() {
+        let mut lexer = DrqlLexer::new("a $ \"b");
+        let mut spans = Vec::new();
+        while let Some(token) = lexer.next() {
+            spans.push((token.is_ok(), lexer.span()));
+        }
+        assert_eq!(spans, vec![(true, 0..1), (false, 2..3), (false, 4..6)]);
+    }
+
+    #[test]
+    fn lexer_regexes() {
+        let lexer = DrqlLexer::new(r"/^team-(red|blue)/ /a\/b/");
+        let tokens: Vec<_> = lexer
+            .map(|x| x.expect("lexing should not have failed").1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::Regex("^team-(red|blue)".to_string()),
+                Tok::Regex(r"a\/b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_aliases() {
+        let lexer = DrqlLexer::new("$oncall&$on_call2 $");
+        let results: Vec<_> = lexer.map(|x| x.map(|(_, token, _)| token)).collect();
+        assert_eq!(
+            results,
+            vec![
+                Ok(Tok::Alias("oncall".to_string())),
+                Ok(Tok::Ampersand),
+                Ok(Tok::Alias("on_call2".to_string())),
+                Err(LexicalError::UnknownToken((18, '$'))),
+            ]
+        );
+    }
+
     #[test]
     fn lexer_unknown_token() {
         let lexer = DrqlLexer::new("a #");
@@ -208,7 +500,7 @@ mod tests {
 
     #[test]
     fn lexer_token_slices() {
-        let lexer = DrqlLexer::new("abc + \"def\" + <@123> + 456 + <@&789> + <@!111>");
+        let lexer = DrqlLexer::new("abc + \"def\" + <@123> + 456 + <@&789> + <@!111> + <#222>");
         let tokens: Vec<_> = lexer
             .map(|x| x.expect("lexing should not have failed").1)
             .collect();
@@ -226,7 +518,45 @@ mod tests {
                 Tok::RoleMention("789".to_string()),
                 Tok::Plus,
                 Tok::UserMention("111".to_string()),
+                Tok::Plus,
+                Tok::ChannelMention("222".to_string()),
             ]
         );
     }
+
+    proptest! {
+        #[test]
+        fn lexer_spans_are_ordered_and_in_bounds(input in "\\PC*") {
+            let mut last_end = 0;
+            for (start, _, end) in DrqlLexer::new(&input).flatten() {
+                prop_assert!(last_end <= start && start <= end && end <= input.len());
+                last_end = end;
+            }
+        }
+
+        #[test]
+        fn lexed_token_slices_lex_to_the_same_token(input in "\\PC*") {
+            for (start, token, end) in DrqlLexer::new(&input).flatten() {
+                let relexed = DrqlLexer::new(&input[start..end]).collect::<Vec<_>>();
+                prop_assert_eq!(relexed, vec![Ok((0, token, end - start))]);
+            }
+        }
+
+        #[test]
+        fn displayed_tokens_lex_to_the_same_token(input in "\\PC*") {
+            for token in DrqlLexer::new(&input).flatten().map(|(_, token, _)| token) {
+                let displayed = token.to_string();
+                let relexed = DrqlLexer::new(&displayed)
+                    .map(|result| result.map(|(_, token, _)| token))
+                    .collect::<Vec<_>>();
+                prop_assert_eq!(relexed, vec![Ok(token)]);
+            }
+        }
+
+        #[test]
+        fn quoted_string_literals_unescape_to_their_contents(contents in "(\\PC|\n)*") {
+            let quoted = quote(&contents);
+            prop_assert_eq!(unescape(&quoted[1..quoted.len() - 1]), contents);
+        }
+    }
 }
@@ -0,0 +1,274 @@
+//! Simplifying DRQL queries before they're interpreted
+//!
+//! Queries are often pieced together by copying and pasting, like `@{ mods | admins | mods }`, and
+//! every operand in them costs a resolver call. [`optimize`] rewrites an [`Expr`] into an
+//! equivalent one with fewer operands, by folding identical operands of unions, intersections and
+//! differences together, and dropping subtrees which can only ever be empty.
+//!
+//! `sample(...)` picks members at random, so two copies of it aren't the same set, and anything
+//! containing it is never folded together.
+
+use super::ast::Expr;
+
+/// An expression after simplifying it
+enum Simplified {
+    /// An expression which can only ever be empty, like `a - a`
+    ///
+    /// It's kept around so an empty query can still be interpreted.
+    Empty(Expr),
+    /// Any other expression
+    Other(Expr),
+}
+
+impl Simplified {
+    /// The simplified expression, whether or not it's empty.
+    fn into_expr(self) -> Expr {
+        match self {
+            Self::Empty(expr) | Self::Other(expr) => expr,
+        }
+    }
+}
+
+/// Rewrite `expr` into an equivalent expression with as few operands to resolve as possible.
+///
+/// Identical operands of unions and intersections are only kept once (with nested unions and
+/// intersections flattened first, so `(a | b) | a` becomes `a | b`), and `a - a` is known to be
+/// empty. Empty operands of unions are dropped, and intersections with one are dropped entirely.
+#[must_use]
+pub fn optimize(expr: Expr) -> Expr {
+    simplify(expr).into_expr()
+}
+
+/// Simplify `expr`, keeping track of whether it's known to be empty.
+fn simplify(expr: Expr) -> Simplified {
+    match expr {
+        Expr::Union(..) => simplify_union(expr),
+        Expr::Intersection(..) => simplify_intersection(expr),
+        Expr::Difference(lhs, rhs) => match (simplify(*lhs), simplify(*rhs)) {
+            (Simplified::Empty(lhs), _) => Simplified::Empty(lhs),
+            (Simplified::Other(lhs), Simplified::Empty(_)) => Simplified::Other(lhs),
+            (Simplified::Other(lhs), Simplified::Other(rhs)) => {
+                let empty = lhs == rhs && !lhs.is_random();
+                let difference = Expr::Difference(Box::new(lhs), Box::new(rhs));
+                if empty {
+                    Simplified::Empty(difference)
+                } else {
+                    Simplified::Other(difference)
+                }
+            }
+        },
+        // The complement of an empty set is everyone, so it's never empty itself
+        Expr::Complement(inner) => {
+            Simplified::Other(Expr::Complement(Box::new(simplify(*inner).into_expr())))
+        }
+        Expr::Sample(inner, count) => match simplify(*inner) {
+            Simplified::Empty(inner) => Simplified::Empty(inner),
+            Simplified::Other(inner) => Simplified::Other(Expr::Sample(Box::new(inner), count)),
+        },
+        Expr::Limit(inner, count) => match simplify(*inner) {
+            Simplified::Empty(inner) => Simplified::Empty(inner),
+            Simplified::Other(inner) => Simplified::Other(Expr::Limit(Box::new(inner), count)),
+        },
+        Expr::AtLeast(count, operands) => simplify_at_least(count, operands),
+        Expr::StringLiteral(_)
+        | Expr::UnknownID(_)
+        | Expr::UserID(_)
+        | Expr::RoleID(_)
+        | Expr::ChannelID(_)
+        | Expr::Me
+        | Expr::Event(_)
+        | Expr::Permission(_)
+        | Expr::Joined(_, _)
+        | Expr::AccountAge(_)
+        | Expr::Name(_)
+        | Expr::Active(_)
+        | Expr::Reacted(_, _)
+        | Expr::Alias(_) => Simplified::Other(expr),
+    }
+}
+
+/// Simplify a union, dropping its empty and repeated operands.
+fn simplify_union(expr: Expr) -> Simplified {
+    let mut operands = Vec::new();
+    let mut empty = None;
+    for operand in Chain::Union.flatten(expr) {
+        match simplify(operand) {
+            Simplified::Empty(operand) => {
+                empty.get_or_insert(operand);
+            }
+            Simplified::Other(operand) => {
+                for operand in Chain::Union.flatten(operand) {
+                    keep_once(&mut operands, operand);
+                }
+            }
+        }
+    }
+
+    match (Chain::Union.rebuild(operands), empty) {
+        (Some(union), _) => Simplified::Other(union),
+        (None, Some(empty)) => Simplified::Empty(empty),
+        (None, None) => unreachable!("a union has operands"),
+    }
+}
+
+/// Simplify an intersection, dropping its repeated operands, or all of it if one is empty.
+fn simplify_intersection(expr: Expr) -> Simplified {
+    let mut operands = Vec::new();
+    for operand in Chain::Intersection.flatten(expr) {
+        match simplify(operand) {
+            Simplified::Empty(operand) => return Simplified::Empty(operand),
+            Simplified::Other(operand) => {
+                for operand in Chain::Intersection.flatten(operand) {
+                    keep_once(&mut operands, operand);
+                }
+            }
+        }
+    }
+
+    Simplified::Other(
+        Chain::Intersection
+            .rebuild(operands)
+            .expect("an intersection has operands"),
+    )
+}
+
+/// Simplify `atleast(count, ...)`, dropping its empty operands.
+///
+/// Repeated operands each count separately, so unlike in a union, they're all kept.
+fn simplify_at_least(count: usize, operands: Vec<Expr>) -> Simplified {
+    let simplified = operands.into_iter().map(simplify);
+    // Everyone is in at least none of the operands, empty or not
+    if count == 0 {
+        return Simplified::Other(Expr::AtLeast(
+            count,
+            simplified.map(Simplified::into_expr).collect(),
+        ));
+    }
+
+    let mut operands = Vec::new();
+    let mut empty = None;
+    for operand in simplified {
+        match operand {
+            Simplified::Empty(operand) => {
+                empty.get_or_insert(operand);
+            }
+            Simplified::Other(operand) => operands.push(operand),
+        }
+    }
+
+    match empty {
+        Some(empty) if operands.is_empty() => Simplified::Empty(empty),
+        _ if operands.len() < count => Simplified::Empty(Expr::AtLeast(count, operands)),
+        _ => Simplified::Other(Expr::AtLeast(count, operands)),
+    }
+}
+
+/// An operator which can be chained, like `a | b | c`
+#[derive(Debug, Clone, Copy)]
+enum Chain {
+    /// `|`
+    Union,
+    /// `&`
+    Intersection,
+}
+
+impl Chain {
+    /// Split a chain of this operator, like `(a | b) | c`, into its operands, in order.
+    fn flatten(self, expr: Expr) -> Vec<Expr> {
+        match (self, expr) {
+            (Self::Union, Expr::Union(lhs, rhs))
+            | (Self::Intersection, Expr::Intersection(lhs, rhs)) => {
+                let mut operands = self.flatten(*lhs);
+                operands.extend(self.flatten(*rhs));
+                operands
+            }
+            (_, expr) => vec![expr],
+        }
+    }
+
+    /// Join operands back into a chain of this operator, like `(a | b) | c`.
+    fn rebuild(self, operands: Vec<Expr>) -> Option<Expr> {
+        operands.into_iter().reduce(|lhs, rhs| match self {
+            Self::Union => Expr::Union(Box::new(lhs), Box::new(rhs)),
+            Self::Intersection => Expr::Intersection(Box::new(lhs), Box::new(rhs)),
+        })
+    }
+}
+
+/// Add `operand` to `operands`, unless an identical one is already there.
+fn keep_once(operands: &mut Vec<Expr>, operand: Expr) {
+    if operand.is_random() || !operands.contains(&operand) {
+        operands.push(operand);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude::{RoleId, UserId};
+
+    use super::*;
+    use crate::drql::{interpreter::interpret, parser::parse_drql, testing::MockGuild};
+
+    /// Parse and optimize `query`, writing the result back out.
+    fn optimized(query: &str) -> String {
+        optimize(parse_drql(query).expect("the query should parse")).to_string()
+    }
+
+    #[test]
+    fn repeated_operands_are_folded() {
+        assert_eq!(optimized("a | a"), "a");
+        assert_eq!(optimized("a & a"), "a");
+        assert_eq!(optimized("(a | b) | (c | a) | b"), "((a | b) | c)");
+        assert_eq!(optimized("(a | b) & (a | b) & c"), "((a | b) & c)");
+        assert_eq!(optimized("a - b"), "(a - b)");
+    }
+
+    #[test]
+    fn empty_subtrees_are_dropped() {
+        assert_eq!(optimized("a - a"), "(a - a)");
+        assert_eq!(optimized("b & (a - a) & c"), "(a - a)");
+        assert_eq!(optimized("b | (a - a) | c"), "(b | c)");
+        assert_eq!(optimized("b - (a - a)"), "b");
+        assert_eq!(optimized("!(a - a)"), "!(a - a)");
+        assert_eq!(optimized("atleast(2, a, b - b)"), "atleast(2, a)");
+        assert_eq!(optimized("atleast(0, a - a)"), "atleast(0, (a - a))");
+    }
+
+    #[test]
+    fn random_samples_are_not_folded() {
+        assert_eq!(
+            optimized("sample(a, 1) | sample(a, 1)"),
+            "(sample(a, 1) | sample(a, 1))"
+        );
+        assert_eq!(
+            optimized("sample(a, 1) - sample(a, 1)"),
+            "(sample(a, 1) - sample(a, 1))"
+        );
+    }
+
+    #[tokio::test]
+    async fn optimized_queries_evaluate_to_the_same_members() {
+        let guild = MockGuild::new()
+            .with_role(RoleId(10), "a")
+            .with_role(RoleId(11), "b")
+            .with_member(UserId(1), "xavier", &[RoleId(10)])
+            .with_member(UserId(2), "yara", &[RoleId(10), RoleId(11)])
+            .with_member(UserId(3), "zed", &[RoleId(11)]);
+
+        for query in [
+            "(a | b) & (b | a) & a",
+            "b - (a - a) | a & a",
+            "!(a & (b - b)) - b",
+            "atleast(2, a, b, a - a, b)",
+            "atleast(3, a, b - b, b)",
+            "(a | a) limit 1",
+        ] {
+            let ast = parse_drql(query).expect("the query should parse");
+            let optimized = optimize(parse_drql(query).expect("the query should parse"));
+            assert!(optimized.operand_count() <= ast.operand_count());
+            let expected = interpret(ast, &guild).await;
+            assert!(expected.is_ok(), "{query}");
+            assert_eq!(interpret(optimized, &guild).await, expected, "{query}");
+        }
+    }
+}
@@ -12,17 +12,24 @@ use crate::parser;
 pub fn parse_drql(
     input: &str,
 ) -> Result<ast::Expr, ParseError<usize, lexer::Tok, lexer::LexicalError>> {
-    parser::ExprParser::new()
+    parser::QueryParser::new()
         .parse(lexer::DrqlLexer::new(input))
         .tap(|ast| debug!("Parser result: {ast:?}"))
 }
 
 #[cfg(test)]
 mod tests {
-    use poise::serenity_prelude::model::prelude::{RoleId, UserId};
+    use std::time::Duration;
+
+    use poise::serenity_prelude::model::prelude::{
+        ChannelId, EmojiId, MessageId, Permissions, ReactionType, RoleId, ScheduledEventId, UserId,
+    };
 
     use super::*;
-    use crate::drql::ast::Expr;
+    use crate::drql::{
+        ast::{Expr, MessageLink},
+        time::Age,
+    };
 
     #[test]
     fn many_token_types() {
@@ -53,4 +60,95 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn complement_binds_tighter_than_binary_operators() {
+        assert_eq!(
+            parse_drql("!a & ~b"),
+            Ok(Expr::Intersection(
+                Box::new(Expr::Complement(Box::new(Expr::StringLiteral(
+                    "a".to_string()
+                )))),
+                Box::new(Expr::Complement(Box::new(Expr::StringLiteral(
+                    "b".to_string()
+                ))))
+            ))
+        );
+        // Mentions of nicknamed users still lex as mentions
+        assert_eq!(
+            parse_drql("!<@!1>"),
+            Ok(Expr::Complement(Box::new(Expr::UserID(UserId(1)))))
+        );
+    }
+
+    #[test]
+    fn function_calls() {
+        assert_eq!(
+            parse_drql("event(1) & event(https://discord.com/events/2/3)"),
+            Ok(Expr::Intersection(
+                Box::new(Expr::Event(ScheduledEventId(1))),
+                Box::new(Expr::Event(ScheduledEventId(3)))
+            ))
+        );
+        assert_eq!(
+            parse_drql("perm(ban_members) - perm(Manage_Guild)"),
+            Ok(Expr::Difference(
+                Box::new(Expr::Permission(Permissions::BAN_MEMBERS)),
+                Box::new(Expr::Permission(Permissions::MANAGE_GUILD))
+            ))
+        );
+        assert_eq!(
+            parse_drql("perm(fly)"),
+            Err(ParseError::User {
+                error: lexer::LexicalError::InvalidFunctionCall("perm(fly)".to_string())
+            })
+        );
+        assert_eq!(
+            parse_drql("nothing(1)"),
+            Err(ParseError::User {
+                error: lexer::LexicalError::InvalidFunctionCall("nothing(1)".to_string())
+            })
+        );
+        assert_eq!(
+            parse_drql("account_age(< 2w)"),
+            Ok(Expr::AccountAge(Age::YoungerThan(Duration::from_hours(
+                336
+            ))))
+        );
+        assert_eq!(
+            parse_drql("account_age(2w)"),
+            Err(ParseError::User {
+                error: lexer::LexicalError::InvalidFunctionCall("account_age(2w)".to_string())
+            })
+        );
+        assert_eq!(
+            parse_drql("reacted(https://discord.com/channels/@me/2/3, <:yes:4>)"),
+            Ok(Expr::Reacted(
+                MessageLink {
+                    guild: None,
+                    channel: ChannelId(2),
+                    message: MessageId(3),
+                },
+                ReactionType::Custom {
+                    animated: false,
+                    id: EmojiId(4),
+                    name: Some("yes".to_string()),
+                }
+            ))
+        );
+        assert_eq!(
+            parse_drql("perm(https://discord.com/channels/1/2/3, \u{2705})"),
+            Err(ParseError::User {
+                error: lexer::LexicalError::InvalidFunctionCall(
+                    "perm(https://discord.com/channels/1/2/3, \u{2705})".to_string()
+                )
+            })
+        );
+        assert_eq!(
+            parse_drql("event(abc)"),
+            Err(ParseError::User {
+                error: lexer::LexicalError::InvalidFunctionCall("event(abc)".to_string())
+            })
+        );
+    }
 }
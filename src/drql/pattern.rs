@@ -0,0 +1,122 @@
+//! Matching member and role names, like the `"*dev*"` in `name("*dev*")`
+
+use std::fmt::{Display, Formatter};
+
+use regex::{Regex, RegexBuilder};
+
+/// The most memory a compiled pattern may use, so a query can't make Intersection build a huge
+/// regex
+const SIZE_LIMIT: usize = 1 << 20;
+
+/// A pattern a member's username or nickname has to match
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NamePattern {
+    /// A case-insensitive wildcard pattern matching the whole name, like `"*dev*"`, where `*`
+    /// matches anything and `?` matches any single character
+    Glob(String),
+    /// A regex matching any part of the name, like `/^team-(red|blue)/`
+    Regex(String),
+}
+
+impl NamePattern {
+    /// Compile this pattern to a [`Regex`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this is an invalid regex, or too big to compile.
+    pub fn compile(&self) -> Result<Regex, regex::Error> {
+        match self {
+            Self::Glob(glob) => {
+                let mut pattern = "^".to_string();
+                for char in glob.chars() {
+                    match char {
+                        '*' => pattern.push_str(".*"),
+                        '?' => pattern.push('.'),
+                        _ => pattern.push_str(&regex::escape(&char.to_string())),
+                    }
+                }
+                pattern.push('$');
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .size_limit(SIZE_LIMIT)
+                    .build()
+            }
+            Self::Regex(regex) => RegexBuilder::new(regex).size_limit(SIZE_LIMIT).build(),
+        }
+    }
+}
+
+impl Display for NamePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Glob(glob) => write!(f, "\"{glob}\""),
+            Self::Regex(regex) => write!(f, "/{regex}/"),
+        }
+    }
+}
+
+/// The candidates called `name`, like the roles a bare `Mods` in a query refers to.
+///
+/// Names are compared exactly first. Unless `case_sensitive`, candidates whose names only differ
+/// from `name` in case match if none match exactly, so `mods` finds a role called `Mods`, but not
+/// when there's also one called `mods`.
+pub fn named<'a, T>(
+    candidates: impl IntoIterator<Item = (T, &'a str)>,
+    name: &str,
+    case_sensitive: bool,
+) -> Vec<T> {
+    let lowercase_name = name.to_lowercase();
+    let (exact, other_case): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .filter(|(_, candidate)| {
+            *candidate == name || (!case_sensitive && candidate.to_lowercase() == lowercase_name)
+        })
+        .partition(|(_, candidate)| *candidate == name);
+    let matching = if exact.is_empty() { other_case } else { exact };
+    matching
+        .into_iter()
+        .map(|(candidate, _)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_whole_names() {
+        let glob = NamePattern::Glob("*dev?".to_string())
+            .compile()
+            .expect("the glob should compile");
+
+        assert!(glob.is_match("Backend DevS"));
+        assert!(glob.is_match("devs"));
+        assert!(!glob.is_match("dev"));
+        assert!(!glob.is_match("devs team"));
+        // Anything else is matched literally
+        assert!(NamePattern::Glob("a.b".to_string())
+            .compile()
+            .is_ok_and(|glob| !glob.is_match("axb")));
+    }
+
+    #[test]
+    fn regexes_match_anywhere() {
+        let regex = NamePattern::Regex("team-(red|blue)".to_string())
+            .compile()
+            .expect("the regex should compile");
+
+        assert!(regex.is_match("alice [team-red]"));
+        assert!(!regex.is_match("alice [Team-Red]"));
+        assert!(NamePattern::Regex("(".to_string()).compile().is_err());
+    }
+
+    #[test]
+    fn exact_names_are_preferred() {
+        let roles = [(1, "Mods"), (2, "MODS"), (3, "Admins")];
+
+        assert_eq!(named(roles, "Mods", false), vec![1]);
+        assert_eq!(named(roles, "mods", false), vec![1, 2]);
+        assert_eq!(named(roles, "admins", false), vec![3]);
+        assert!(named(roles, "admins", true).is_empty());
+    }
+}
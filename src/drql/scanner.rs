@@ -4,19 +4,33 @@
 //! for DRQL queries enclosed in `@{ ... }` and returns an Iterator over their
 //! contents.
 
-use lazy_static::lazy_static;
+use std::sync::LazyLock;
+
 use regex::Regex;
 
+/// What comes before the contents of a query in a message
+pub const OPENING: &str = "@{";
+
+/// What comes after the contents of a query in a message
+pub const CLOSING: &str = "}";
+
 /// Returns an Iterator over provided text, returning every value within `@{ ... }`.
 pub fn scan(input: &str) -> impl Iterator<Item = &'_ str> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"@\{(.+?)\}").expect("regexp should compile successfully");
-    }
+    static RE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"@\{(.+?)\}").expect("regexp should compile successfully"));
     RE.find_iter(input)
         .map(|matched| &matched.as_str()[2..(matched.as_str().len() - 1)])
 }
 
+/// Write a value returned by [`scan`] back out as it was written in the message, like
+/// `@{everyone - here}`.
+///
+/// A position within `chunk` is as many bytes further along in the result as [`OPENING`] is long.
+#[must_use]
+pub fn unscan(chunk: &str) -> String {
+    format!("{OPENING}{chunk}{CLOSING}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -39,4 +53,12 @@ mod tests {
             vec!["everyone - here", "staff"]
         );
     }
+
+    #[test]
+    fn unscan_undoes_scan() {
+        let input = "Hello @{everyone - here}!";
+        let chunk = scan(input).next().expect("there should be a chunk");
+        assert_eq!(unscan(chunk), "@{everyone - here}");
+        assert_eq!(scan(&unscan(chunk)).collect::<Vec<_>>(), vec![chunk]);
+    }
 }
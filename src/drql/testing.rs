@@ -0,0 +1,535 @@
+//! Utilities for testing DRQL queries without a connection to Discord
+//!
+//! This module provides [`MockGuild`], a small in-memory model of a Discord guild (members, roles,
+//! and presences) which implements [`InterpreterResolver`]. It is used by Intersection's own
+//! tests, and can also be used to check that your server's queries match who you expect them to.
+//!
+//! ```
+//! # use intersection::drql::testing::MockGuild;
+//! # use poise::serenity_prelude::{OnlineStatus, RoleId, UserId};
+//! # #[tokio::main]
+//! # async fn main() {
+//! let guild = MockGuild::new()
+//!     .with_role(RoleId(1), "staff")
+//!     .with_member(UserId(1), "alice", &[RoleId(1)])
+//!     .with_member(UserId(2), "bob", &[])
+//!     .with_presence(UserId(1), OnlineStatus::Online);
+//!
+//! assert_eq!(
+//!     guild.evaluate("everyone - staff").await,
+//!     Ok([UserId(2)].into())
+//! );
+//! # }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use lalrpop_util::ParseError;
+use poise::{
+    async_trait,
+    serenity_prelude::{
+        ChannelId, OnlineStatus, Permissions, ReactionType, RoleId, ScheduledEventId, UserId,
+    },
+};
+
+use super::{
+    ast::MessageLink,
+    interpreter::{interpret, InterpreterResolver, Limits, TooComplex},
+    lexer::{LexicalError, Tok},
+    optimizer::optimize,
+    parser::parse_drql,
+    pattern::{named, NamePattern},
+    time::{Age, Moment, Side},
+};
+
+/// An error produced while evaluating a query against a [`MockGuild`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum MockError {
+    /// The query could not be lexed or parsed
+    Parse(ParseError<usize, Tok, LexicalError>),
+    /// Some name or ID in the query could not be resolved (or was ambiguous)
+    Resolution(String),
+    /// The query is too big to evaluate
+    TooComplex(TooComplex),
+}
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "Error parsing query: {err}"),
+            Self::Resolution(message) => write!(f, "{message}"),
+            Self::TooComplex(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for MockError {}
+
+impl From<TooComplex> for MockError {
+    fn from(value: TooComplex) -> Self {
+        Self::TooComplex(value)
+    }
+}
+
+/// A member of a [`MockGuild`]
+#[derive(Debug, Clone)]
+struct MockMember {
+    /// The member's username
+    name: String,
+    /// The member's nickname in the guild, if they have one
+    nick: Option<String>,
+    /// The roles this member has
+    roles: HashSet<RoleId>,
+}
+
+/// An in-memory model of a Discord guild, used to evaluate DRQL queries in tests.
+///
+/// Resolution mirrors Intersection's real resolver where possible: roles are matched by their
+/// name (ignoring case if no role's name matches exactly, unless that's turned off), members are matched by a case-insensitive prefix of their name (like Discord's
+/// member search), and a literal matching more than one thing is an error. Members without a
+/// presence are considered offline.
+#[derive(Debug, Clone, Default)]
+pub struct MockGuild {
+    /// Every role in the guild, mapped to its name
+    roles: HashMap<RoleId, String>,
+    /// The permissions each role grants, if any
+    role_permissions: HashMap<RoleId, Permissions>,
+    /// Every member in the guild
+    members: HashMap<UserId, MockMember>,
+    /// The presence of each member, if known
+    presences: HashMap<UserId, OnlineStatus>,
+    /// Every voice channel in the guild
+    voice_channels: HashSet<ChannelId>,
+    /// Every text channel in the guild, mapped to the roles allowed to view it (if it's private)
+    text_channels: HashMap<ChannelId, HashSet<RoleId>>,
+    /// Every thread in the guild, mapped to its members
+    threads: HashMap<ChannelId, HashSet<UserId>>,
+    /// The voice channel each member is connected to, if any
+    voice_states: HashMap<UserId, ChannelId>,
+    /// Every scheduled event in the guild, mapped to the users interested in it
+    events: HashMap<ScheduledEventId, HashSet<UserId>>,
+    /// The users who reacted to each message in the guild with each emoji
+    reactions: HashMap<(MessageLink, ReactionType), HashSet<UserId>>,
+    /// When each member last sent a message, if known
+    last_messages: HashMap<UserId, DateTime<Utc>>,
+    /// When each member joined the guild, if known
+    joined: HashMap<UserId, DateTime<Utc>>,
+    /// The members boosting the guild
+    boosters: HashSet<UserId>,
+    /// The member queries are evaluated on behalf of, which `me` refers to
+    author: Option<UserId>,
+    /// Whether roles are only matched by their exact name, case included
+    case_sensitive_roles: bool,
+}
+
+impl MockGuild {
+    /// Create an empty [`MockGuild`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a role to this guild.
+    #[must_use]
+    pub fn with_role(mut self, id: RoleId, name: &str) -> Self {
+        self.roles.insert(id, name.to_string());
+        self
+    }
+
+    /// Choose whether roles are only matched by their exact name, case included.
+    #[must_use]
+    pub const fn with_case_sensitive_roles(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive_roles = case_sensitive;
+        self
+    }
+
+    /// Grant `permissions` to the members of one of this guild's roles.
+    #[must_use]
+    pub fn with_role_permissions(mut self, id: RoleId, permissions: Permissions) -> Self {
+        self.role_permissions.insert(id, permissions);
+        self
+    }
+
+    /// Add a member with the given roles to this guild.
+    #[must_use]
+    pub fn with_member(mut self, id: UserId, name: &str, roles: &[RoleId]) -> Self {
+        self.members.insert(
+            id,
+            MockMember {
+                name: name.to_string(),
+                nick: None,
+                roles: roles.iter().copied().collect(),
+            },
+        );
+        self
+    }
+
+    /// Set the nickname of a member of this guild.
+    #[must_use]
+    pub fn with_nickname(mut self, id: UserId, nick: &str) -> Self {
+        if let Some(member) = self.members.get_mut(&id) {
+            member.nick = Some(nick.to_string());
+        }
+        self
+    }
+
+    /// Set the presence of a member of this guild.
+    #[must_use]
+    pub fn with_presence(mut self, id: UserId, status: OnlineStatus) -> Self {
+        self.presences.insert(id, status);
+        self
+    }
+
+    /// Add a voice channel to this guild.
+    #[must_use]
+    pub fn with_voice_channel(mut self, id: ChannelId) -> Self {
+        self.voice_channels.insert(id);
+        self
+    }
+
+    /// Add a text channel to this guild, which only members with one of `roles` can view, or
+    /// everyone if there are none.
+    #[must_use]
+    pub fn with_text_channel(mut self, id: ChannelId, roles: &[RoleId]) -> Self {
+        self.text_channels
+            .insert(id, roles.iter().copied().collect());
+        self
+    }
+
+    /// Add a thread with the given members to this guild.
+    #[must_use]
+    pub fn with_thread(mut self, id: ChannelId, members: &[UserId]) -> Self {
+        self.threads.insert(id, members.iter().copied().collect());
+        self
+    }
+
+    /// Connect a member of this guild to one of its voice channels.
+    #[must_use]
+    pub fn with_voice_state(mut self, id: UserId, channel: ChannelId) -> Self {
+        self.voice_states.insert(id, channel);
+        self
+    }
+
+    /// Add a scheduled event which the given users are interested in to this guild.
+    #[must_use]
+    pub fn with_event(mut self, id: ScheduledEventId, interested: &[UserId]) -> Self {
+        self.events.insert(id, interested.iter().copied().collect());
+        self
+    }
+
+    /// Add reactions with `emoji` by the given users to a message in this guild.
+    #[must_use]
+    pub fn with_reaction(
+        mut self,
+        link: MessageLink,
+        emoji: ReactionType,
+        users: &[UserId],
+    ) -> Self {
+        self.reactions
+            .insert((link, emoji), users.iter().copied().collect());
+        self
+    }
+
+    /// Set when a member last sent a message in this guild.
+    #[must_use]
+    pub fn with_last_message(mut self, id: UserId, time: DateTime<Utc>) -> Self {
+        self.last_messages.insert(id, time);
+        self
+    }
+
+    /// Set when a member joined this guild.
+    #[must_use]
+    pub fn with_joined_at(mut self, id: UserId, time: DateTime<Utc>) -> Self {
+        self.joined.insert(id, time);
+        self
+    }
+
+    /// Mark a member of this guild as boosting it.
+    #[must_use]
+    pub fn with_booster(mut self, id: UserId) -> Self {
+        self.boosters.insert(id);
+        self
+    }
+
+    /// Evaluate queries on behalf of the member `id`, so `me` refers to them.
+    #[must_use]
+    pub const fn with_author(mut self, id: UserId) -> Self {
+        self.author = Some(id);
+        self
+    }
+
+    /// Parse, optimize and evaluate a single DRQL query (without the surrounding `@{}`) against this
+    /// guild.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails to parse or cannot be resolved.
+    pub async fn evaluate(&self, query: &str) -> Result<HashSet<UserId>, MockError> {
+        let ast = parse_drql(query).map_err(MockError::Parse)?;
+        // Optimizing recurses into the query, so it's only done once the query is known to be small
+        Limits::default().check(&ast)?;
+        interpret(optimize(ast), self).await
+    }
+
+    /// The IDs of every member of this guild
+    #[must_use]
+    pub fn everyone(&self) -> HashSet<UserId> {
+        self.members.keys().copied().collect()
+    }
+
+    /// The IDs of every member of this guild who is not offline
+    #[must_use]
+    pub fn here(&self) -> HashSet<UserId> {
+        self.members
+            .keys()
+            .filter(|id| {
+                self.presences
+                    .get(id)
+                    .is_some_and(|status| *status != OnlineStatus::Offline)
+            })
+            .copied()
+            .collect()
+    }
+
+    /// The IDs of every member of this guild with the given status, where members without a
+    /// presence or who are invisible are offline
+    #[must_use]
+    pub fn with_status(&self, status: OnlineStatus) -> HashSet<UserId> {
+        self.members
+            .keys()
+            .filter(|id| {
+                let member_status = match self.presences.get(id) {
+                    None | Some(OnlineStatus::Invisible) => OnlineStatus::Offline,
+                    Some(other) => *other,
+                };
+                member_status == status
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Every role in this guild (not including `@everyone`) and the IDs of its members
+    #[must_use]
+    pub fn role_members(&self) -> HashMap<RoleId, HashSet<UserId>> {
+        self.roles
+            .keys()
+            .map(|role| {
+                (
+                    *role,
+                    self.members
+                        .iter()
+                        .filter(|(_, member)| member.roles.contains(role))
+                        .map(|(id, _)| *id)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InterpreterResolver<MockError> for MockGuild {
+    async fn resolve_string_literal(&self, literal: String) -> Result<HashSet<UserId>, MockError> {
+        match literal.as_str() {
+            "everyone" => return Ok(self.everyone()),
+            "here" => return Ok(self.here()),
+            "online" => return Ok(self.with_status(OnlineStatus::Online)),
+            "idle" => return Ok(self.with_status(OnlineStatus::Idle)),
+            "dnd" => return Ok(self.with_status(OnlineStatus::DoNotDisturb)),
+            "offline" => return Ok(self.with_status(OnlineStatus::Offline)),
+            "boosters" => return Ok(self.boosters.clone()),
+            "in_voice" => return Ok(self.voice_states.keys().copied().collect()),
+            _ => {}
+        }
+
+        let lowercase_literal = literal.to_lowercase();
+        let members = self
+            .members
+            .iter()
+            .filter(|(_, member)| member.name.to_lowercase().starts_with(&lowercase_literal))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        let roles = named(
+            self.roles.iter().map(|(id, name)| (*id, name.as_str())),
+            &literal,
+            self.case_sensitive_roles,
+        );
+
+        match (members.as_slice(), roles.as_slice()) {
+            ([member], []) => self.resolve_user_id(*member).await,
+            ([], [role]) => self.resolve_role_id(*role).await,
+            ([], []) => Err(MockError::Resolution(format!(
+                "Unable to find a role or member with the name {literal}."
+            ))),
+            (members, roles) => Err(MockError::Resolution(format!(
+                "Found {} member(s) and {} role(s) that matched your query for \"{literal}\".",
+                members.len(),
+                roles.len()
+            ))),
+        }
+    }
+
+    async fn resolve_unknown_id(&self, id: String) -> Result<HashSet<UserId>, MockError> {
+        let parsed_id = id
+            .parse::<u64>()
+            .map_err(|_| MockError::Resolution(format!("{id} is not a valid ID")))?;
+
+        if self.members.contains_key(&UserId(parsed_id)) {
+            self.resolve_user_id(UserId(parsed_id)).await
+        } else if self.roles.contains_key(&RoleId(parsed_id)) {
+            self.resolve_role_id(RoleId(parsed_id)).await
+        } else {
+            Err(MockError::Resolution(format!(
+                "Unable to resolve role or member ID: {id}"
+            )))
+        }
+    }
+
+    async fn resolve_user_id(&self, id: UserId) -> Result<HashSet<UserId>, MockError> {
+        Ok(HashSet::from([id]))
+    }
+
+    async fn resolve_role_id(&self, id: RoleId) -> Result<HashSet<UserId>, MockError> {
+        if !self.roles.contains_key(&id) {
+            return Err(MockError::Resolution(format!(
+                "Unable to resolve role with ID {id}"
+            )));
+        }
+
+        Ok(self
+            .members
+            .iter()
+            .filter(|(_, member)| member.roles.contains(&id))
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn resolve_channel_id(&self, id: ChannelId) -> Result<HashSet<UserId>, MockError> {
+        if self.voice_channels.contains(&id) {
+            Ok(self
+                .voice_states
+                .iter()
+                .filter(|(_, channel)| **channel == id)
+                .map(|(id, _)| *id)
+                .collect())
+        } else if let Some(members) = self.threads.get(&id) {
+            Ok(members.clone())
+        } else if let Some(roles) = self.text_channels.get(&id) {
+            Ok(self
+                .members
+                .iter()
+                .filter(|(_, member)| roles.is_empty() || !member.roles.is_disjoint(roles))
+                .map(|(id, _)| *id)
+                .collect())
+        } else {
+            Err(MockError::Resolution(format!(
+                "<#{id}> is not a text or voice channel or a thread, so it can't be used in a query."
+            )))
+        }
+    }
+
+    async fn resolve_me(&self) -> Result<HashSet<UserId>, MockError> {
+        let author = self.author.ok_or_else(|| {
+            MockError::Resolution("This guild has no author for `me` to refer to.".to_string())
+        })?;
+        self.resolve_user_id(author).await
+    }
+
+    async fn resolve_event(&self, id: ScheduledEventId) -> Result<HashSet<UserId>, MockError> {
+        self.events.get(&id).cloned().ok_or_else(|| {
+            MockError::Resolution(format!("Unable to find the scheduled event with ID {id}"))
+        })
+    }
+
+    async fn resolve_joined(
+        &self,
+        side: Side,
+        moment: Moment,
+    ) -> Result<HashSet<UserId>, MockError> {
+        let moment = moment.resolve(Utc::now());
+        Ok(self
+            .joined
+            .iter()
+            .filter(|(_, joined)| side.contains(moment, **joined))
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn resolve_account_age(&self, age: Age) -> Result<HashSet<UserId>, MockError> {
+        let now = Utc::now();
+        Ok(self
+            .members
+            .keys()
+            .filter(|id| age.contains(now, *id.created_at()))
+            .copied()
+            .collect())
+    }
+
+    async fn resolve_name(&self, pattern: NamePattern) -> Result<HashSet<UserId>, MockError> {
+        let regex = pattern
+            .compile()
+            .map_err(|err| MockError::Resolution(err.to_string()))?;
+        Ok(self
+            .members
+            .iter()
+            .filter(|(_, member)| {
+                regex.is_match(&member.name)
+                    || member
+                        .nick
+                        .as_ref()
+                        .is_some_and(|nick| regex.is_match(nick))
+            })
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn resolve_active(&self, within: Duration) -> Result<HashSet<UserId>, MockError> {
+        let since = Moment::Ago(within).resolve(Utc::now());
+        Ok(self
+            .last_messages
+            .iter()
+            .filter(|(_, time)| **time >= since)
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn resolve_reacted(
+        &self,
+        link: MessageLink,
+        emoji: ReactionType,
+    ) -> Result<HashSet<UserId>, MockError> {
+        let error = MockError::Resolution(format!("Unable to find {emoji} reactions on {link}"));
+        self.reactions.get(&(link, emoji)).cloned().ok_or(error)
+    }
+
+    async fn resolve_permission(
+        &self,
+        permission: Permissions,
+    ) -> Result<HashSet<UserId>, MockError> {
+        Ok(self
+            .members
+            .iter()
+            .filter(|(_, member)| {
+                member.roles.iter().any(|role| {
+                    self.role_permissions.get(role).is_some_and(|permissions| {
+                        permissions.contains(permission) || permissions.administrator()
+                    })
+                })
+            })
+            .map(|(id, _)| *id)
+            .collect())
+    }
+
+    async fn resolve_everyone(&self) -> Result<HashSet<UserId>, MockError> {
+        Ok(self.everyone())
+    }
+
+    async fn resolve_alias(&self, name: String) -> Result<HashSet<UserId>, MockError> {
+        Err(MockError::Resolution(format!("${name} is not defined.")))
+    }
+}
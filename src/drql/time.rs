@@ -0,0 +1,205 @@
+//! Points in time and ages used by DRQL functions, like the date in
+//! `joined_after("2024-01-01")`
+
+use std::{
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+
+/// The units a duration can be written in, largest first, with how many seconds each one is
+const UNITS: [(char, u64); 5] = [
+    ('w', 7 * 24 * 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('h', 60 * 60),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Parse a duration like `30d` or `12h`.
+#[must_use]
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let unit = input.chars().last()?;
+    let (_, seconds) = UNITS.iter().find(|(name, _)| *name == unit)?;
+    let amount = input[..input.len() - unit.len_utf8()].parse::<u64>().ok()?;
+    Some(Duration::from_secs(amount.checked_mul(*seconds)?))
+}
+
+/// Write a duration in the largest unit it's a whole number of, so [`parse_duration`] reads it
+/// back as the same duration.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (unit, size) = UNITS
+        .iter()
+        .find(|(_, size)| seconds.is_multiple_of(*size))
+        .unwrap_or(&('s', 1));
+    format!("{}{unit}", seconds / size)
+}
+
+/// Which side of a [`Moment`] a time is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// Earlier than the moment
+    Before,
+    /// Later than the moment
+    After,
+}
+
+impl Side {
+    /// Whether `time` is on this side of `moment`.
+    #[must_use]
+    pub fn contains(self, moment: DateTime<Utc>, time: DateTime<Utc>) -> bool {
+        match self {
+            Self::Before => time < moment,
+            Self::After => time > moment,
+        }
+    }
+}
+
+/// A point in time, either fixed or relative to when a query is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Moment {
+    /// A date (at midnight UTC), like `2024-01-01`, or a date and time, like
+    /// `2024-01-01T12:00:00Z`
+    At(DateTime<Utc>),
+    /// Some time before the query is evaluated, like `30d`
+    Ago(Duration),
+}
+
+impl Moment {
+    /// Parse a date, a date and time, or a duration.
+    #[must_use]
+    pub fn parse(input: &str) -> Option<Self> {
+        if let Some(duration) = parse_duration(input) {
+            return Some(Self::Ago(duration));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+            return Some(Self::At(date.and_hms_opt(0, 0, 0)?.and_utc()));
+        }
+        DateTime::parse_from_rfc3339(input)
+            .ok()
+            .map(|time| Self::At(time.with_timezone(&Utc)))
+    }
+
+    /// The time this moment refers to, if the query is evaluated at `now`.
+    #[must_use]
+    pub fn resolve(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::At(time) => time,
+            Self::Ago(duration) => chrono::Duration::from_std(duration)
+                .ok()
+                .and_then(|duration| now.checked_sub_signed(duration))
+                .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        }
+    }
+}
+
+impl Display for Moment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::At(time) if time.time() == chrono::NaiveTime::MIN => {
+                write!(f, "\"{}\"", time.format("%Y-%m-%d"))
+            }
+            Self::At(time) => write!(
+                f,
+                "\"{}\"",
+                time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            ),
+            Self::Ago(duration) => write!(f, "{}", format_duration(*duration)),
+        }
+    }
+}
+
+/// How old something is, compared to a duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Age {
+    /// Created less than the duration ago, like `<7d`
+    YoungerThan(Duration),
+    /// Created more than the duration ago, like `>7d`
+    OlderThan(Duration),
+}
+
+impl Age {
+    /// Parse an age like `<7d` or `>1w`.
+    #[must_use]
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.split_at_checked(1)? {
+            ("<", duration) => parse_duration(duration).map(Self::YoungerThan),
+            (">", duration) => parse_duration(duration).map(Self::OlderThan),
+            _ => None,
+        }
+    }
+
+    /// Whether something created at `created` is this old, if the query is evaluated at `now`.
+    #[must_use]
+    pub fn contains(self, now: DateTime<Utc>, created: DateTime<Utc>) -> bool {
+        match self {
+            Self::YoungerThan(duration) => {
+                Side::After.contains(Moment::Ago(duration).resolve(now), created)
+            }
+            Self::OlderThan(duration) => {
+                Side::Before.contains(Moment::Ago(duration).resolve(now), created)
+            }
+        }
+    }
+}
+
+impl Display for Age {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::YoungerThan(duration) => write!(f, "<{}", format_duration(*duration)),
+            Self::OlderThan(duration) => write!(f, ">{}", format_duration(*duration)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_parsed() {
+        assert_eq!(parse_duration("30d"), Some(Duration::from_hours(720)));
+        assert_eq!(parse_duration("90m"), Some(Duration::from_mins(90)));
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("30y"), None);
+        assert_eq!(format_duration(Duration::from_hours(336)), "2w");
+        assert_eq!(format_duration(Duration::from_mins(90)), "90m");
+    }
+
+    #[test]
+    fn moments_are_parsed() {
+        let new_year = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("the date should be valid")
+            .and_utc();
+
+        assert_eq!(Moment::parse("2024-01-01"), Some(Moment::At(new_year)));
+        assert_eq!(
+            Moment::parse("2024-01-01T01:00:00+01:00"),
+            Some(Moment::At(new_year))
+        );
+        assert_eq!(
+            Moment::parse("1h").map(|moment| moment.resolve(new_year)),
+            Some(new_year - chrono::Duration::hours(1))
+        );
+        assert_eq!(Moment::parse("yesterday"), None);
+    }
+
+    #[test]
+    fn ages_are_compared() {
+        let now = Utc::now();
+        let week_old = now - chrono::Duration::weeks(1);
+
+        assert_eq!(
+            Age::parse("<1d"),
+            Some(Age::YoungerThan(Duration::from_hours(24)))
+        );
+        assert_eq!(Age::parse("1d"), None);
+        assert!(Age::parse(">1d").is_some_and(|age| age.contains(now, week_old)));
+        assert!(Age::parse("<2w").is_some_and(|age| age.contains(now, week_old)));
+        assert!(!Age::parse("<1d").is_some_and(|age| age.contains(now, week_old)));
+    }
+}
@@ -0,0 +1,127 @@
+//! Suppression of the same query being sent over and over
+//!
+//! Sending the same query again and again in a channel notifies the same members each time, which
+//! makes for an easy "ping storm". Each notification's normalized query (the [`Display`] form of
+//! its reduced AST, so `@{a|b}` and `@{ a | b }` count as the same query) is remembered for a
+//! while, and guilds choose whether sending it again in the same channel needs confirming or is
+//! refused outright.
+//!
+//! [`Display`]: std::fmt::Display
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::ChannelId;
+
+/// What happens when a query is sent again in a channel shortly after it was last sent there
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum DuplicateQueryMode {
+    /// The query is handled like any other
+    #[name = "Allow them"]
+    Allow,
+    /// The author is asked to confirm they want to notify everyone again
+    #[default]
+    #[name = "Ask the author to confirm"]
+    Confirm,
+    /// The query is refused
+    #[name = "Refuse them"]
+    Refuse,
+}
+
+/// How a guild treats queries sent again shortly after they were last sent in the same channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateQuerySettings {
+    /// What happens to duplicate queries
+    pub mode: DuplicateQueryMode,
+    /// How long after being sent a query counts as a duplicate if it's sent again
+    pub window: Duration,
+}
+
+impl Default for DuplicateQuerySettings {
+    fn default() -> Self {
+        Self {
+            mode: DuplicateQueryMode::default(),
+            window: Duration::from_mins(1),
+        }
+    }
+}
+
+/// Remembers the queries recently sent in every channel, to spot duplicates.
+#[derive(Debug, Default)]
+pub struct DuplicateQueryTracker {
+    /// When each normalized query was last sent in each channel
+    channels: Mutex<HashMap<ChannelId, HashMap<String, Instant>>>,
+}
+
+impl DuplicateQueryTracker {
+    /// Create a new [`DuplicateQueryTracker`] which hasn't seen any queries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long ago the normalized `query` was last sent in `channel`, if it was within
+    /// `window`.
+    pub fn last_sent(&self, channel: ChannelId, query: &str, window: Duration) -> Option<Duration> {
+        self.channels
+            .lock()
+            .expect("duplicate query tracker lock was poisoned")
+            .get(&channel)
+            .and_then(|queries| queries.get(query))
+            .map(Instant::elapsed)
+            .filter(|&elapsed| elapsed < window)
+    }
+
+    /// Record that the normalized `query` was sent in `channel`, forgetting queries sent longer
+    /// than `window` ago.
+    pub fn record(&self, channel: ChannelId, query: &str, window: Duration) {
+        let mut channels = self
+            .channels
+            .lock()
+            .expect("duplicate query tracker lock was poisoned");
+        // Opportunistically drop queries which would no longer count as duplicates
+        for queries in channels.values_mut() {
+            queries.retain(|_, sent_at| sent_at.elapsed() < window);
+        }
+        channels.retain(|_, queries| !queries.is_empty());
+        channels
+            .entry(channel)
+            .or_default()
+            .insert(query.to_string(), Instant::now());
+        drop(channels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_tracked_per_channel() {
+        let tracker = DuplicateQueryTracker::new();
+        let window = Duration::from_mins(1);
+        tracker.record(ChannelId(1), "(a | b)", window);
+
+        assert!(tracker.last_sent(ChannelId(1), "(a | b)", window).is_some());
+        assert!(tracker.last_sent(ChannelId(1), "(a & b)", window).is_none());
+        assert!(tracker.last_sent(ChannelId(2), "(a | b)", window).is_none());
+    }
+
+    #[test]
+    fn queries_outside_the_window_are_not_duplicates() {
+        let tracker = DuplicateQueryTracker::new();
+        tracker.record(ChannelId(1), "a", Duration::from_mins(1));
+
+        assert!(tracker
+            .last_sent(ChannelId(1), "a", Duration::ZERO)
+            .is_none());
+
+        // Recording with a shorter window forgets the old query
+        tracker.record(ChannelId(2), "b", Duration::ZERO);
+        assert!(tracker
+            .last_sent(ChannelId(1), "a", Duration::from_mins(1))
+            .is_none());
+    }
+}
@@ -0,0 +1,362 @@
+//! Intersection's error types
+//!
+//! Errors in the query path are represented with [`QueryError`], which separates mistakes made by
+//! the user (which should be explained to them) from internal failures (which should be logged).
+//! Internal failures are never shown to the user directly: see [`report_internal_error`].
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use lalrpop_util::ParseError;
+use poise::serenity_prelude as serenity;
+use rand::Rng;
+use tracing::error;
+
+use crate::{
+    drql::{
+        aliases::AliasError,
+        diagnostics::{Diagnostic, Problem},
+        interpreter::TooComplex,
+        lexer::{LexicalError, Tok},
+    },
+    i18n::{self, Language},
+};
+
+/// An error that occurred while parsing, evaluating, or delivering a DRQL query.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub enum QueryError {
+    /// A chunk of the query could not be lexed or parsed
+    ParseError {
+        /// The index of the chunk (within the message) that failed to parse
+        chunk: usize,
+        /// The chunk itself, as it was given to the parser
+        contents: String,
+        /// The underlying parser error
+        error: ParseError<usize, Tok, LexicalError>,
+        /// The language to explain the error in
+        language: Language,
+    },
+    /// Some part of the query could not be resolved to a set of members, e.g. an unknown or
+    /// ambiguous role name
+    ResolutionError(String),
+    /// The author is not allowed to do something their query requires, e.g. mention a role
+    PermissionDenied(String),
+    /// The query exceeds some limit imposed on queries, e.g. a cooldown
+    LimitExceeded(String),
+    /// Something went wrong on our side (or Discord's). This is not the user's fault.
+    Internal(anyhow::Error),
+}
+
+impl QueryError {
+    /// Explain why a query's `aliases` couldn't be expanded, in `language`.
+    pub fn from_alias_error(
+        error: AliasError,
+        aliases: &BTreeMap<String, String>,
+        language: Language,
+    ) -> Self {
+        match error {
+            AliasError::Unknown(name) => {
+                Self::ResolutionError(i18n::message(language, "alias-unknown", &[("name", &name)]))
+            }
+            AliasError::Cycle(names) => Self::ResolutionError(i18n::message(
+                language,
+                "alias-cycle",
+                &[
+                    ("name", &names.first().map_or("", String::as_str)),
+                    (
+                        "cycle",
+                        &names
+                            .iter()
+                            .map(|name| format!("${name}"))
+                            .collect::<Vec<_>>()
+                            .join(" \u{2192} "),
+                    ),
+                ],
+            )),
+            AliasError::Invalid(name, error) => {
+                let definition = aliases.get(&name).map_or("", String::as_str);
+                Self::ResolutionError(i18n::message(
+                    language,
+                    "alias-invalid",
+                    &[
+                        ("name", &name),
+                        (
+                            "error",
+                            &explain(&Diagnostic::new(definition, &error).problem, language),
+                        ),
+                    ],
+                ))
+            }
+            AliasError::TooLarge(limit) => Self::LimitExceeded(i18n::message(
+                language,
+                "alias-too-large",
+                &[("limit", &limit)],
+            )),
+        }
+    }
+
+    /// Explain why a query is too big to evaluate, in `language`.
+    pub fn too_complex(error: TooComplex, language: Language) -> Self {
+        Self::LimitExceeded(match error {
+            TooComplex::TooManyNodes(limit) => {
+                i18n::message(language, "too-many-nodes", &[("limit", &limit)])
+            }
+            TooComplex::TooDeep(limit) => i18n::message(language, "too-deep", &[("limit", &limit)]),
+        })
+    }
+
+    /// Whether this error was caused by the user (as opposed to an internal failure).
+    ///
+    /// User errors are explained to the user and logged at a low level, while internal errors are
+    /// logged as errors.
+    pub const fn is_user_error(&self) -> bool {
+        match self {
+            Self::ParseError { .. }
+            | Self::ResolutionError(_)
+            | Self::PermissionDenied(_)
+            | Self::LimitExceeded(_) => true,
+            Self::Internal(_) => false,
+        }
+    }
+
+    /// Whether this error is a simple mistake in the query, like a typo or an unknown role name,
+    /// whose explanation stops being useful once the author has seen it.
+    pub const fn is_mistake(&self) -> bool {
+        matches!(self, Self::ParseError { .. } | Self::ResolutionError(_))
+    }
+
+    /// Whether this error is likely to go away by itself, like a dropped connection or a server
+    /// error on Discord's side, so the operation that failed is worth retrying.
+    pub fn is_transient(&self) -> bool {
+        let Self::Internal(err) = self else {
+            return false;
+        };
+
+        let Some(err) = err.downcast_ref::<serenity::Error>() else {
+            return false;
+        };
+        if let serenity::Error::Http(err) = err {
+            if let serenity::HttpError::UnsuccessfulRequest(response) = err.as_ref() {
+                // Too Many Requests, or Discord having trouble
+                return response.status_code.as_u16() == 429
+                    || response.status_code.is_server_error();
+            }
+            // The request didn't make it to Discord, e.g. because the connection dropped
+            return matches!(err.as_ref(), serenity::HttpError::Request(_));
+        }
+        matches!(err, serenity::Error::Io(_))
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ParseError {
+                chunk,
+                contents,
+                error,
+                language,
+            } => {
+                let diagnostic = Diagnostic::new(contents, error);
+                let explanation = explain(&diagnostic.problem, *language);
+                write!(
+                    f,
+                    "{}",
+                    i18n::message(
+                        *language,
+                        "parse-error",
+                        &[
+                            ("chunk", chunk),
+                            ("error", &diagnostic.render_as(contents, &explanation)),
+                        ],
+                    )
+                )
+            }
+            Self::ResolutionError(message)
+            | Self::PermissionDenied(message)
+            | Self::LimitExceeded(message) => write!(f, "{message}"),
+            Self::Internal(err) => write!(f, "{err:#}"),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ParseError { error, .. } => Some(error),
+            Self::Internal(err) => Some(err.as_ref()),
+            Self::ResolutionError(_) | Self::PermissionDenied(_) | Self::LimitExceeded(_) => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for QueryError {
+    fn from(value: anyhow::Error) -> Self {
+        Self::Internal(value)
+    }
+}
+
+/// Queries are checked against the same limits by [`crate::pipeline::parse_chunks`] before
+/// they're interpreted, which explains them in the guild's language with
+/// [`QueryError::too_complex`], so this is only for the interpreter's own checks.
+impl From<TooComplex> for QueryError {
+    fn from(value: TooComplex) -> Self {
+        Self::LimitExceeded(value.to_string())
+    }
+}
+
+impl From<serenity::Error> for QueryError {
+    fn from(value: serenity::Error) -> Self {
+        Self::Internal(value.into())
+    }
+}
+
+/// Describe a terminal the parser expected, like `"("` or `STRING_LITERAL`, in `language`.
+fn describe_terminal(terminal: &str, language: Language) -> String {
+    if let Some(token) = terminal
+        .strip_prefix('"')
+        .and_then(|terminal| terminal.strip_suffix('"'))
+    {
+        return format!("`{token}`");
+    }
+    let id = match terminal {
+        "STRING_LITERAL" => "terminal-name",
+        "ID_LITERAL" => "terminal-id",
+        "DURATION" => "terminal-duration",
+        "USER_MENTION" => "terminal-member",
+        "ROLE_MENTION" => "terminal-role",
+        "CHANNEL_MENTION" => "terminal-channel",
+        "EVENT_LINK" => "terminal-event-link",
+        "MESSAGE_LINK" => "terminal-message-link",
+        "EMOJI" => "terminal-emoji",
+        "REGEX" => "terminal-regex",
+        "ALIAS" => "terminal-alias",
+        terminal => return terminal.to_string(),
+    };
+    i18n::message(language, id, &[])
+}
+
+/// Write out what the parser expected instead, like ", expected `)` or a name", in `language`.
+fn describe_expected(terminals: &[String], language: Language) -> String {
+    let described = terminals
+        .iter()
+        .map(|terminal| describe_terminal(terminal, language))
+        .collect::<Vec<_>>();
+    let expected = match described.split_last() {
+        None => return String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => i18n::message(
+            language,
+            "list-or",
+            &[("first", &rest.join(", ")), ("second", last)],
+        ),
+    };
+    i18n::message(language, "parse-expected", &[("expected", &expected)])
+}
+
+/// Explain why a chunk of a query couldn't be parsed, in `language`, like
+/// [`Diagnostic::message`] does in English.
+pub fn explain(problem: &Problem, language: Language) -> String {
+    match problem {
+        Problem::UnknownToken | Problem::Lexical(LexicalError::NoMatchingRule) => {
+            i18n::message(language, "parse-unknown-token", &[])
+        }
+        Problem::EndedEarly(expected) => i18n::message(
+            language,
+            "parse-ended-early",
+            &[("expected", &describe_expected(expected, language))],
+        ),
+        Problem::Unexpected(token, expected) => i18n::message(
+            language,
+            "parse-unexpected",
+            &[
+                ("token", token),
+                ("expected", &describe_expected(expected, language)),
+            ],
+        ),
+        Problem::ExtraToken(token) => {
+            i18n::message(language, "parse-extra-token", &[("token", token)])
+        }
+        Problem::UnknownCharacter(char)
+        | Problem::Lexical(LexicalError::UnknownToken((_, char))) => {
+            i18n::message(language, "parse-unknown-character", &[("char", char)])
+        }
+        Problem::UnterminatedString
+        | Problem::Lexical(LexicalError::UnterminatedStringLiteral(_)) => {
+            i18n::message(language, "parse-unterminated-string", &[])
+        }
+        Problem::Lexical(LexicalError::ParseIntError(error)) => {
+            i18n::message(language, "parse-invalid-number", &[("error", error)])
+        }
+        Problem::Lexical(LexicalError::InvalidFunctionCall(call)) => {
+            i18n::message(language, "parse-invalid-function", &[("call", call)])
+        }
+    }
+}
+
+/// Generate a short, random ID used to refer to a single internal error, like `A1B2C3`.
+fn generate_error_id() -> String {
+    format!("{:06X}", rand::thread_rng().gen_range(0..0x0100_0000))
+}
+
+/// Log an internal error under a newly generated error ID and return a message safe to show to
+/// the user.
+///
+/// The returned message only contains the error ID, so users can report the problem to us without
+/// the full error chain (which may contain internal details) being leaked into the channel.
+pub fn report_internal_error(err: impl Display, language: Language) -> String {
+    let error_id = generate_error_id();
+    error!(error_id, "Internal error {error_id}: {err}");
+    i18n::message(language, "internal-error", &[("id", &error_id)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drql::parser::parse_drql;
+
+    #[test]
+    fn error_ids_are_six_hex_digits() {
+        for _ in 0..100 {
+            let id = generate_error_id();
+            assert_eq!(id.len(), 6);
+            assert!(id.chars().all(|char| char.is_ascii_hexdigit()));
+        }
+    }
+
+    #[test]
+    fn internal_error_report_hides_details() {
+        let message = report_internal_error(
+            anyhow::anyhow!("secret internal details"),
+            Language::English,
+        );
+        assert!(message.starts_with("Something went wrong on our side"));
+        assert!(!message.contains("secret"));
+    }
+
+    #[test]
+    fn parse_errors_are_explained_in_the_language() {
+        let contents = "staff +";
+        let error = parse_drql(contents).expect_err("the chunk shouldn't parse");
+        let explained = |language| {
+            QueryError::ParseError {
+                chunk: 0,
+                contents: contents.to_string(),
+                error: error.clone(),
+                language,
+            }
+            .to_string()
+        };
+
+        let diagnostic = Diagnostic::new(contents, &error);
+        assert_eq!(
+            explained(Language::English),
+            format!("Error parsing chunk 0: {}", diagnostic.render(contents))
+        );
+        assert!(explained(Language::German).starts_with("Fehler beim Lesen von Teil 0: "));
+        assert!(explained(Language::German).ends_with("\n```\n@{staff +}\n         ^\n```"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Writing the members a query matches to a file, like the one `/dry_run` attaches, and a
+//! guild's aliases to one `/alias import` can read back
+//!
+//! Plain text is easiest to read, while CSV and JSON can be loaded into spreadsheets and scripts.
+
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use intersection::drql::{
+    aliases::alias_name,
+    diagnostics::Diagnostic,
+    lexer::{LexicalError, Tok},
+    parser::parse_drql,
+};
+use lalrpop_util::ParseError;
+use poise::serenity_prelude::{
+    json::{json, Value},
+    UserId,
+};
+
+use crate::{
+    error,
+    i18n::{self, Language},
+};
+
+/// The format members are written to a file in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum ExportFormat {
+    /// One member per line, like `alice#1 (123)`
+    #[default]
+    #[name = "Text"]
+    Text,
+    /// A header row, then one row per member
+    #[name = "CSV"]
+    Csv,
+    /// An array of objects, one per member
+    #[name = "JSON"]
+    Json,
+}
+
+/// A member, as written to an export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedMember {
+    /// The member's user ID
+    pub id: UserId,
+    /// The member's username
+    pub username: String,
+    /// The member's discriminator, like `1` for `alice#1`
+    pub discriminator: u16,
+    /// The member's nickname in the guild, if they have one
+    pub nickname: Option<String>,
+    /// The names of the member's roles
+    pub roles: Vec<String>,
+}
+
+/// Quote `field` for a CSV file if it needs it, doubling any quotes in it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl ExportFormat {
+    /// What a file in this format is called.
+    pub const fn file_name(self) -> &'static str {
+        match self {
+            Self::Text => "dry_run.txt",
+            Self::Csv => "dry_run.csv",
+            Self::Json => "dry_run.json",
+        }
+    }
+
+    /// Write `members` in this format.
+    ///
+    /// IDs are written as strings in JSON, since they're too big for some JSON parsers to read as
+    /// numbers exactly.
+    pub fn write(self, members: &[ExportedMember]) -> Result<String, std::fmt::Error> {
+        let mut contents = String::new();
+        match self {
+            Self::Text => {
+                for member in members {
+                    writeln!(
+                        &mut contents,
+                        "{}#{} ({})",
+                        member.username, member.discriminator, member.id
+                    )?;
+                }
+            }
+            Self::Csv => {
+                writeln!(&mut contents, "id,username,nickname,roles")?;
+                for member in members {
+                    writeln!(
+                        &mut contents,
+                        "{},{},{},{}",
+                        member.id,
+                        csv_field(&member.username),
+                        csv_field(member.nickname.as_deref().unwrap_or_default()),
+                        csv_field(&member.roles.join(";"))
+                    )?;
+                }
+            }
+            Self::Json => write!(
+                &mut contents,
+                "{:#}",
+                Value::Array(
+                    members
+                        .iter()
+                        .map(|member| json!({
+                            "id": member.id.to_string(),
+                            "username": member.username,
+                            "nickname": member.nickname,
+                            "roles": member.roles,
+                        }))
+                        .collect()
+                )
+            )?,
+        }
+        Ok(contents)
+    }
+}
+
+/// What an exported file of aliases is called
+pub const ALIASES_FILE_NAME: &str = "aliases.json";
+
+/// Why a file of aliases couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasImportError {
+    /// The file isn't JSON
+    NotJson,
+    /// The file is JSON, but not an object of names and queries
+    NotAnObject,
+    /// An alias has a name which aliases can't have
+    InvalidName(String),
+    /// An alias stands for something other than a string
+    NotAQuery(String),
+    /// An alias doesn't stand for a valid query
+    Invalid(String, ParseError<usize, Tok, LexicalError>),
+}
+
+impl AliasImportError {
+    /// Explain why the file couldn't be read, in `language`.
+    pub fn explain(&self, language: Language) -> String {
+        match self {
+            Self::NotJson => i18n::message(language, "import-not-json", &[]),
+            Self::NotAnObject => i18n::message(language, "import-not-an-object", &[]),
+            Self::InvalidName(name) => {
+                i18n::message(language, "import-invalid-name", &[("name", name)])
+            }
+            Self::NotAQuery(name) => {
+                i18n::message(language, "import-not-a-query", &[("name", name)])
+            }
+            Self::Invalid(name, error) => i18n::message(
+                language,
+                "alias-invalid",
+                &[
+                    ("name", name),
+                    (
+                        "error",
+                        &error::explain(&Diagnostic::new("", error).problem, language),
+                    ),
+                ],
+            ),
+        }
+    }
+}
+
+/// Write a guild's `aliases` as a JSON object of names (without the `$`) and queries.
+pub fn write_aliases(aliases: &BTreeMap<String, String>) -> String {
+    format!(
+        "{:#}",
+        Value::Object(
+            aliases
+                .iter()
+                .map(|(name, query)| (name.clone(), Value::String(query.clone())))
+                .collect()
+        )
+    )
+}
+
+/// Read aliases written by [`write_aliases`], with their names as they're stored, checking every
+/// one stands for a query which parses.
+///
+/// The aliases they use aren't checked, since they may be defined elsewhere in the guild.
+///
+/// # Errors
+///
+/// Returns an [`AliasImportError`] if `contents` isn't an object of valid names and queries.
+pub fn read_aliases(contents: &str) -> Result<BTreeMap<String, String>, AliasImportError> {
+    let Value::Object(object) = contents
+        .parse::<Value>()
+        .map_err(|_| AliasImportError::NotJson)?
+    else {
+        return Err(AliasImportError::NotAnObject);
+    };
+
+    let mut aliases = BTreeMap::new();
+    for (name, query) in object {
+        let name = alias_name(&name).ok_or(AliasImportError::InvalidName(name))?;
+        let Value::String(query) = query else {
+            return Err(AliasImportError::NotAQuery(name));
+        };
+        parse_drql(&query).map_err(|error| AliasImportError::Invalid(name.clone(), error))?;
+        aliases.insert(name, query);
+    }
+    Ok(aliases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two members, one of whose names needs quoting in a CSV file
+    fn members() -> Vec<ExportedMember> {
+        vec![
+            ExportedMember {
+                id: UserId(1),
+                username: "alice".to_string(),
+                discriminator: 1,
+                nickname: Some("Alice, \"Al\"".to_string()),
+                roles: vec!["Staff".to_string(), "Red Team".to_string()],
+            },
+            ExportedMember {
+                id: UserId(2),
+                username: "bob".to_string(),
+                discriminator: 0,
+                nickname: None,
+                roles: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn text_lists_members() {
+        assert_eq!(
+            ExportFormat::Text.write(&members()).ok(),
+            Some("alice#1 (1)\nbob#0 (2)\n".to_string())
+        );
+    }
+
+    #[test]
+    fn csv_fields_are_quoted() {
+        assert_eq!(
+            ExportFormat::Csv.write(&members()).ok(),
+            Some(
+                "id,username,nickname,roles\n1,alice,\"Alice, \"\"Al\"\"\",Staff;Red Team\n2,bob,,\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            ExportFormat::Csv.write(&[]).ok(),
+            Some("id,username,nickname,roles\n".to_string())
+        );
+    }
+
+    #[test]
+    fn json_is_an_array_of_members() {
+        let written = ExportFormat::Json
+            .write(&members())
+            .expect("writing to a string should not fail");
+        assert_eq!(
+            written.parse::<Value>().ok(),
+            Some(json!([
+                {
+                    "id": "1",
+                    "username": "alice",
+                    "nickname": "Alice, \"Al\"",
+                    "roles": ["Staff", "Red Team"],
+                },
+                { "id": "2", "username": "bob", "nickname": null, "roles": [] },
+            ]))
+        );
+        assert_eq!(ExportFormat::Json.write(&[]).ok(), Some("[]".to_string()));
+    }
+
+    #[test]
+    fn aliases_are_read_back() {
+        let aliases = BTreeMap::from([
+            ("oncall".to_string(), "\"on call\" & here".to_string()),
+            ("leads".to_string(), "<@&1> | $oncall".to_string()),
+        ]);
+        assert_eq!(read_aliases(&write_aliases(&aliases)), Ok(aliases));
+        assert_eq!(
+            read_aliases(r#"{ "$OnCall": "staff" }"#),
+            Ok(BTreeMap::from([(
+                "oncall".to_string(),
+                "staff".to_string()
+            )]))
+        );
+        assert_eq!(read_aliases("{}"), Ok(BTreeMap::new()));
+    }
+
+    #[test]
+    fn invalid_aliases_are_refused() {
+        assert_eq!(read_aliases("oncall"), Err(AliasImportError::NotJson));
+        assert_eq!(
+            read_aliases(r#"["staff"]"#),
+            Err(AliasImportError::NotAnObject)
+        );
+        assert_eq!(
+            read_aliases(r#"{ "on call": "staff" }"#),
+            Err(AliasImportError::InvalidName("on call".to_string()))
+        );
+        assert_eq!(
+            read_aliases(r#"{ "oncall": 1 }"#),
+            Err(AliasImportError::NotAQuery("oncall".to_string()))
+        );
+        assert!(matches!(
+            read_aliases(r#"{ "oncall": "staff +" }"#),
+            Err(AliasImportError::Invalid(name, _)) if name == "oncall"
+        ));
+    }
+}
@@ -5,10 +5,53 @@
 use std::collections::{HashMap, HashSet};
 
 use anyhow::Context as _;
-use poise::serenity_prelude as serenity;
+use poise::{async_trait, serenity_prelude as serenity};
 use tracing::debug;
 
-use crate::models;
+use crate::{
+    i18n::{self, Language},
+    models,
+};
+
+/// A reason a member may be able to mention a role, in the order they're checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleMentionCheck {
+    /// The member is an administrator
+    Administrator,
+    /// The role is mentionable by everyone
+    RoleMentionable,
+    /// The member can mention everyone in the guild
+    GuildMentionEveryone,
+    /// The member can mention everyone in the channel
+    ChannelMentionEveryone,
+}
+
+impl std::fmt::Display for RoleMentionCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Administrator => "the user is an administrator",
+            Self::RoleMentionable => "the role is mentionable by all",
+            Self::GuildMentionEveryone => "the user can mention everyone",
+            Self::ChannelMentionEveryone => "the user can mention everyone in this channel",
+        })
+    }
+}
+
+impl RoleMentionCheck {
+    /// Explain this check in `language`, like its [`Display`](std::fmt::Display) does in English.
+    pub fn explain(self, language: Language) -> String {
+        i18n::message(
+            language,
+            match self {
+                Self::Administrator => "role-check-administrator",
+                Self::RoleMentionable => "role-check-mentionable",
+                Self::GuildMentionEveryone => "role-check-guild-mention-everyone",
+                Self::ChannelMentionEveryone => "role-check-channel-mention-everyone",
+            },
+            &[],
+        )
+    }
+}
 
 /// Custom trait implemented on all [`serenity::Member`]s
 pub trait CustomMemberImpl {
@@ -19,46 +62,61 @@ pub trait CustomMemberImpl {
         role: &serenity::Role,
         channel: &serenity::GuildChannel,
     ) -> anyhow::Result<bool>;
+    /// Run every check [`CustomMemberImpl::can_mention_role`] makes, in order, and whether each one
+    /// passed. The member can mention the role if any of them did.
+    fn role_mention_checks(
+        &self,
+        ctx: &serenity::Context,
+        role: &serenity::Role,
+        channel: &serenity::GuildChannel,
+    ) -> anyhow::Result<[(RoleMentionCheck, bool); 4]>;
 }
 impl CustomMemberImpl for serenity::Member {
-    #[allow(clippy::cognitive_complexity)]
     fn can_mention_role(
         &self,
         ctx: &serenity::Context,
         role: &serenity::Role,
         channel: &serenity::GuildChannel,
     ) -> anyhow::Result<bool> {
+        let passed = self
+            .role_mention_checks(ctx, role, channel)?
+            .into_iter()
+            .find_map(|(check, passed)| passed.then_some(check));
+
+        let Some(check) = passed else {
+            debug!("{} cannot mention role {}", self.user.id, role.id);
+            return Ok(false);
+        };
+        debug!(
+            "{} can mention role {} because {check}",
+            self.user.id, role.id
+        );
+        Ok(true)
+    }
+    fn role_mention_checks(
+        &self,
+        ctx: &serenity::Context,
+        role: &serenity::Role,
+        channel: &serenity::GuildChannel,
+    ) -> anyhow::Result<[(RoleMentionCheck, bool); 4]> {
         let guild_permissions = self.permissions(ctx)?;
         let channel_permissions = channel.permissions_for_user(ctx, self)?;
 
-        if guild_permissions.administrator() {
-            debug!(
-                "{} can mention role {} because the user is an administrator",
-                self.user.id, role.id
-            );
-            Ok(true)
-        } else if role.mentionable {
-            debug!(
-                "{} can mention role {} because the role is mentionable by all",
-                self.user.id, role.id
-            );
-            Ok(true)
-        } else if guild_permissions.mention_everyone() {
-            debug!(
-                "{} can mention role {} because the user can mention everyone",
-                self.user.id, role.id
-            );
-            Ok(true)
-        } else if channel_permissions.mention_everyone() {
-            debug!(
-                "{} can mention role {} because the user can mention everyone in this channel",
-                self.user.id, role.id
-            );
-            Ok(true)
-        } else {
-            debug!("{} cannot mention role {}", self.user.id, role.id);
-            Ok(false)
-        }
+        Ok([
+            (
+                RoleMentionCheck::Administrator,
+                guild_permissions.administrator(),
+            ),
+            (RoleMentionCheck::RoleMentionable, role.mentionable),
+            (
+                RoleMentionCheck::GuildMentionEveryone,
+                guild_permissions.mention_everyone(),
+            ),
+            (
+                RoleMentionCheck::ChannelMentionEveryone,
+                channel_permissions.mention_everyone(),
+            ),
+        ])
     }
 }
 
@@ -68,6 +126,10 @@ pub trait CustomGuildImpl {
     fn get_everyone(&self) -> HashSet<serenity::UserId>;
     /// Obtain a [`HashSet`] of every online member in this guild's user ID
     fn get_here(&self) -> HashSet<serenity::UserId>;
+    /// Obtain a [`HashSet`] of the user ID of every member in this guild with the given status.
+    ///
+    /// Members without a presence, or who are invisible, are offline.
+    fn get_with_status(&self, status: serenity::OnlineStatus) -> HashSet<serenity::UserId>;
     /// Obtain a [`HashMap`] mapping every role in this guild to its members
     fn all_roles_and_members(
         &self,
@@ -85,12 +147,26 @@ impl CustomGuildImpl for serenity::Guild {
         self.get_everyone()
             .into_iter()
             .filter(|id| {
-                self.presences.get(id).map_or(false, |presence| {
-                    presence.status != serenity::OnlineStatus::Offline
-                })
+                self.presences
+                    .get(id)
+                    .is_some_and(|presence| presence.status != serenity::OnlineStatus::Offline)
             })
             .collect::<HashSet<_>>()
     }
+    fn get_with_status(&self, status: serenity::OnlineStatus) -> HashSet<serenity::UserId> {
+        self.get_everyone()
+            .into_iter()
+            .filter(
+                |id| match self.presences.get(id).map(|presence| presence.status) {
+                    None
+                    | Some(serenity::OnlineStatus::Offline | serenity::OnlineStatus::Invisible) => {
+                        status == serenity::OnlineStatus::Offline
+                    }
+                    Some(other) => other == status,
+                },
+            )
+            .collect::<HashSet<_>>()
+    }
     fn all_roles_and_members(
         &self,
         ctx: &serenity::Context,
@@ -130,3 +206,56 @@ impl CustomRoleImpl for serenity::Role {
             .collect::<HashSet<_>>()
     }
 }
+
+/// Custom trait implemented on all [`serenity::GuildChannel`]s
+#[async_trait]
+pub trait CustomGuildChannelImpl {
+    /// Obtain the channel whose permissions apply in this channel: the channel a thread or forum
+    /// post was created in, or this channel itself otherwise.
+    ///
+    /// Threads don't have permission overwrites of their own, so computing permissions in one
+    /// directly would ignore the overwrites of the channel it's in.
+    async fn permission_channel(
+        &self,
+        ctx: &serenity::Context,
+    ) -> anyhow::Result<serenity::GuildChannel>;
+    /// Obtain a [`HashSet`] of the user ID of every member in `guild` who can view this channel
+    fn viewers(&self, guild: &serenity::Guild) -> anyhow::Result<HashSet<serenity::UserId>>;
+}
+#[async_trait]
+impl CustomGuildChannelImpl for serenity::GuildChannel {
+    async fn permission_channel(&self, ctx: &serenity::Context) -> anyhow::Result<Self> {
+        if self.thread_metadata.is_none() {
+            return Ok(self.clone());
+        }
+
+        let parent = self
+            .parent_id
+            .with_context(|| format!("Thread {} has no parent channel", self.id))?;
+        debug!("Using the permissions of {parent} in thread {}", self.id);
+        parent
+            .to_channel(ctx)
+            .await
+            .with_context(|| format!("Failed to fetch the parent channel of thread {}", self.id))?
+            .guild()
+            .with_context(|| format!("The parent of thread {} is not a guild channel", self.id))
+    }
+    fn viewers(&self, guild: &serenity::Guild) -> anyhow::Result<HashSet<serenity::UserId>> {
+        let mut viewers = HashSet::new();
+        for member in guild.members.values() {
+            if guild
+                .user_permissions_in(self, member)
+                .with_context(|| {
+                    format!(
+                        "Failed to get the permissions of {} in {}",
+                        member.user.id, self.id
+                    )
+                })?
+                .view_channel()
+            {
+                viewers.insert(member.user.id);
+            }
+        }
+        Ok(viewers)
+    }
+}
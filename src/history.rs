@@ -0,0 +1,314 @@
+//! The history of the notifications sent in each guild
+//!
+//! Every notification is recorded with its author, its query, and how many members it matched,
+//! so `/history` can show how Intersection has been used recently. Only the most recent
+//! [`HISTORY_LENGTH`] notifications of each guild are kept, for as long as the retention period
+//! allows. History is written to a file, if given one, after each notification, and notifications
+//! past the retention period are left out of the file as well as forgotten when it's read again.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use poise::serenity_prelude::{GuildId, Timestamp, UserId};
+use tracing::debug;
+
+use crate::persistence::{escape, unescape, StoreFile};
+
+/// How many notifications are remembered in each guild
+pub const HISTORY_LENGTH: usize = 100;
+
+/// How long notifications are remembered for, unless configured otherwise
+pub const DEFAULT_RETENTION: Duration = Duration::from_hours(30 * 24);
+
+/// How often notifications older than the retention period are forgotten
+const RETENTION_INTERVAL: Duration = Duration::from_hours(1);
+
+/// A notification, as remembered in a guild's history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Who sent the query
+    pub author: UserId,
+    /// The query, as written in replies, like `` `@{staff}` ``
+    pub query: String,
+    /// How many members the query matched
+    pub members: usize,
+    /// When the query was sent
+    pub sent_at: Timestamp,
+}
+
+impl HistoryEntry {
+    /// Write this notification, sent in `guild`, as a single line with tab separated fields.
+    fn to_line(&self, guild: GuildId) -> String {
+        format!(
+            "{guild}\t{}\t{}\t{}\t{}",
+            self.sent_at.unix_timestamp(),
+            self.author,
+            self.members,
+            escape(&self.query)
+        )
+    }
+
+    /// Read a notification written by [`HistoryEntry::to_line`], along with its guild.
+    fn from_line(line: &str) -> Option<(GuildId, Self)> {
+        let mut fields = line.split('\t');
+        let guild = GuildId(fields.next()?.parse().ok()?);
+        let sent_at = Timestamp::from_unix_timestamp(fields.next()?.parse().ok()?).ok()?;
+        let author = UserId(fields.next()?.parse().ok()?);
+        let members = fields.next()?.parse().ok()?;
+        let query = unescape(fields.next()?)?;
+        fields.next().is_none().then_some((
+            guild,
+            Self {
+                author,
+                query,
+                members,
+                sent_at,
+            },
+        ))
+    }
+}
+
+/// Remembers the most recent notifications in every guild, in a file if given one.
+#[derive(Debug)]
+pub struct QueryHistory {
+    /// The file history is written to, if it's kept across restarts
+    file: StoreFile,
+    /// How long notifications are remembered for
+    retention: Duration,
+    /// The notifications sent in each guild, oldest first
+    guilds: Mutex<HashMap<GuildId, VecDeque<HistoryEntry>>>,
+}
+
+impl QueryHistory {
+    /// Create a new [`QueryHistory`] writing to `path`, remembering notifications for
+    /// `retention`, and reading the notifications remembered the last time from it.
+    pub fn open(path: Option<PathBuf>, retention: Duration) -> Self {
+        let file = StoreFile::new(path, "query history");
+        let mut guilds = HashMap::<GuildId, VecDeque<HistoryEntry>>::new();
+        for (guild, entry) in file.load(HistoryEntry::from_line) {
+            let history = guilds.entry(guild).or_default();
+            if history.len() == HISTORY_LENGTH {
+                history.pop_front();
+            }
+            history.push_back(entry);
+        }
+
+        let history = Self {
+            file,
+            retention,
+            guilds: Mutex::new(guilds),
+        };
+        history.forget_old(Timestamp::now());
+        history
+    }
+
+    /// Remember a notification sent in `guild`, forgetting the oldest one if there are too many.
+    pub fn record(&self, guild: GuildId, entry: HistoryEntry) {
+        let mut guilds = self.guilds.lock().expect("query history lock was poisoned");
+        let history = guilds.entry(guild).or_default();
+        if history.len() == HISTORY_LENGTH {
+            history.pop_front();
+        }
+        history.push_back(entry);
+        self.forget_old_in(&mut guilds, Timestamp::now());
+        self.save(&guilds);
+        drop(guilds);
+    }
+
+    /// Obtain the notifications sent in `guild`, newest first, only including those sent by
+    /// `author` if given.
+    pub fn recent(&self, guild: GuildId, author: Option<UserId>) -> Vec<HistoryEntry> {
+        self.guilds
+            .lock()
+            .expect("query history lock was poisoned")
+            .get(&guild)
+            .map(|history| {
+                history
+                    .iter()
+                    .rev()
+                    .filter(|entry| author.is_none_or(|author| entry.author == author))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Forget the notifications sent longer than the retention period before `now`.
+    pub fn forget_old(&self, now: Timestamp) {
+        let mut guilds = self.guilds.lock().expect("query history lock was poisoned");
+        if self.forget_old_in(&mut guilds, now) {
+            self.save(&guilds);
+        }
+        drop(guilds);
+    }
+
+    /// Forget every notification sent by `user`, in every guild.
+    pub fn forget_user(&self, user: UserId) {
+        let mut guilds = self.guilds.lock().expect("query history lock was poisoned");
+        for history in guilds.values_mut() {
+            history.retain(|entry| entry.author != user);
+        }
+        guilds.retain(|_, history| !history.is_empty());
+        self.save(&guilds);
+        drop(guilds);
+    }
+
+    /// Forget the notifications in `guilds` sent longer than the retention period before `now`,
+    /// returning whether any were.
+    fn forget_old_in(
+        &self,
+        guilds: &mut HashMap<GuildId, VecDeque<HistoryEntry>>,
+        now: Timestamp,
+    ) -> bool {
+        let retention = i64::try_from(self.retention.as_secs()).unwrap_or(i64::MAX);
+        let cutoff = now.unix_timestamp().saturating_sub(retention);
+        let remembered = guilds.values().map(VecDeque::len).sum::<usize>();
+        for history in guilds.values_mut() {
+            history.retain(|entry| entry.sent_at.unix_timestamp() >= cutoff);
+        }
+        guilds.retain(|_, history| !history.is_empty());
+        guilds.values().map(VecDeque::len).sum::<usize>() != remembered
+    }
+
+    /// Write every notification to the file in the background, as it's done after every
+    /// notification.
+    ///
+    /// Saving with the lock held keeps an older save from overwriting a newer one.
+    fn save(&self, guilds: &HashMap<GuildId, VecDeque<HistoryEntry>>) {
+        let guilds = guilds.clone();
+        self.file.save_in_background(move || {
+            guilds
+                .into_iter()
+                .flat_map(|(guild, history)| {
+                    history.into_iter().map(move |entry| entry.to_line(guild))
+                })
+                .collect::<Vec<_>>()
+        });
+    }
+}
+
+/// Forget old notifications every [`RETENTION_INTERVAL`], forever.
+pub async fn enforce_retention(history: Arc<QueryHistory>) {
+    let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        debug!(
+            "Forgetting notifications older than {:?}",
+            history.retention
+        );
+        history.forget_old(Timestamp::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    /// A notification sent by `author`, matching `members` members.
+    fn entry(author: u64, members: usize) -> HistoryEntry {
+        HistoryEntry {
+            author: UserId(author),
+            query: "`@{staff}`".to_string(),
+            members,
+            sent_at: Timestamp::now(),
+        }
+    }
+
+    /// The number of members each notification sent in guild 1 matched, newest first.
+    fn counts(history: &QueryHistory, author: Option<UserId>) -> Vec<usize> {
+        history
+            .recent(GuildId(1), author)
+            .iter()
+            .map(|entry| entry.members)
+            .collect()
+    }
+
+    #[test]
+    fn recent_notifications_are_listed_newest_first() {
+        let history = QueryHistory::open(None, DEFAULT_RETENTION);
+        history.record(GuildId(1), entry(1, 10));
+        history.record(GuildId(1), entry(2, 20));
+        history.record(GuildId(1), entry(1, 30));
+        history.record(GuildId(2), entry(1, 40));
+
+        assert_eq!(counts(&history, None), [30, 20, 10]);
+        assert_eq!(counts(&history, Some(UserId(1))), [30, 10]);
+        assert!(history.recent(GuildId(3), None).is_empty());
+    }
+
+    #[test]
+    fn only_the_latest_notifications_are_kept() {
+        let history = QueryHistory::open(None, DEFAULT_RETENTION);
+        for members in 0..=HISTORY_LENGTH {
+            history.record(GuildId(1), entry(1, members));
+        }
+
+        let recent = history.recent(GuildId(1), None);
+        assert_eq!(recent.len(), HISTORY_LENGTH);
+        assert_eq!(
+            recent.first().map(|entry| entry.members),
+            Some(HISTORY_LENGTH)
+        );
+        assert_eq!(recent.last().map(|entry| entry.members), Some(1));
+    }
+
+    #[test]
+    fn old_notifications_are_forgotten() {
+        let history = QueryHistory::open(None, Duration::from_hours(1));
+        let mut old = entry(1, 10);
+        old.sent_at = Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp() - 7200)
+            .expect("the time should be valid");
+        history.record(GuildId(1), old);
+        history.record(GuildId(1), entry(1, 20));
+
+        history.forget_old(Timestamp::now());
+        assert_eq!(counts(&history, None), [20]);
+    }
+
+    #[test]
+    fn users_can_be_forgotten() {
+        let history = QueryHistory::open(None, DEFAULT_RETENTION);
+        history.record(GuildId(1), entry(1, 10));
+        history.record(GuildId(1), entry(2, 20));
+        history.record(GuildId(2), entry(1, 30));
+
+        history.forget_user(UserId(1));
+        assert_eq!(counts(&history, None), [20]);
+        assert!(history.recent(GuildId(2), None).is_empty());
+    }
+
+    #[test]
+    fn history_survives_a_restart() {
+        let path = env::temp_dir().join(format!("intersection-history-{}", std::process::id()));
+        let history = QueryHistory::open(Some(path.clone()), Duration::from_hours(1));
+        let mut multiline = entry(2, 20);
+        multiline.query = "`@{staff\t&\nhere}`".to_string();
+        // Only whole seconds are written
+        multiline.sent_at = Timestamp::from_unix_timestamp(Timestamp::now().unix_timestamp())
+            .expect("the time should be valid");
+        history.record(GuildId(1), entry(1, 10));
+        history.record(GuildId(1), multiline.clone());
+        history.record(GuildId(2), entry(1, 30));
+        history.forget_user(UserId(1));
+
+        // As if Intersection restarted
+        let reopened = QueryHistory::open(Some(path.clone()), Duration::from_hours(1));
+        assert_eq!(reopened.recent(GuildId(1), None), [multiline]);
+        assert!(reopened.recent(GuildId(2), None).is_empty());
+
+        // Notifications past the retention period are forgotten when the file is read again
+        let two_hours_ago = Timestamp::now().unix_timestamp() - 7200;
+        fs::write(&path, format!("1\t{two_hours_ago}\t1\t10\t`@{{staff}}`\n"))
+            .expect("the file should be writable");
+        let reopened = QueryHistory::open(Some(path.clone()), Duration::from_hours(1));
+        assert!(reopened.recent(GuildId(1), None).is_empty());
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+}
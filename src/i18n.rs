@@ -0,0 +1,264 @@
+//! Localization of the messages Intersection sends
+//!
+//! Messages live in Fluent resources under `src/locales`, one per language, and are looked up by
+//! their ID. Each guild picks its language with `/config language`, and anything missing from a
+//! language falls back to English.
+//!
+//! Only the subset of Fluent our resources need is supported: messages (possibly spanning several
+//! lines), `{ $variable }` placeables, and `{"..."}` string literals for characters like braces.
+//! Instead of selectors, messages depending on a count come in `-one` and `-other` variants; see
+//! [`message_count`].
+
+use std::{collections::HashMap, fmt::Display, sync::LazyLock};
+
+use tracing::error;
+
+/// A language Intersection's messages are available in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Language {
+    /// English, the language every other one falls back to
+    #[default]
+    English,
+    /// German
+    #[name = "Deutsch"]
+    German,
+}
+
+impl Language {
+    /// Every supported language.
+    const ALL: [Self; 2] = [Self::English, Self::German];
+
+    /// The Fluent resource with this language's messages.
+    const fn resource(self) -> &'static str {
+        match self {
+            Self::English => include_str!("locales/en.ftl"),
+            Self::German => include_str!("locales/de.ftl"),
+        }
+    }
+}
+
+/// The messages of every language, by ID
+static MESSAGES: LazyLock<HashMap<Language, HashMap<&'static str, String>>> = LazyLock::new(|| {
+    Language::ALL
+        .into_iter()
+        .map(|language| (language, parse(language.resource())))
+        .collect()
+});
+
+/// Parse a Fluent resource into its messages' patterns, by ID.
+fn parse(resource: &str) -> HashMap<&str, String> {
+    /// Turn the lines of a message's value into its pattern.
+    fn pattern(first: &str, continued: &[&str]) -> String {
+        // Blank lines are kept within a pattern, but not after it
+        let end = continued
+            .iter()
+            .rposition(|line| !line.trim().is_empty())
+            .map_or(0, |last| last + 1);
+        let continued = &continued[..end];
+        let indent = continued
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+
+        let first = first.trim();
+        (!first.is_empty())
+            .then_some(first)
+            .into_iter()
+            .chain(
+                continued
+                    .iter()
+                    .map(|line| line.get(indent..).unwrap_or_default().trim_end()),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    let mut messages = HashMap::new();
+    let mut current: Option<(&str, &str, Vec<&str>)> = None;
+    for line in resource.lines() {
+        if line.is_empty() || line.starts_with(' ') {
+            if let Some((_, _, continued)) = &mut current {
+                continued.push(line);
+            }
+            continue;
+        }
+
+        // Anything else ends the current message
+        if let Some((id, first, continued)) = current.take() {
+            messages.insert(id, pattern(first, &continued));
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        if let Some((id, first)) = line.split_once('=') {
+            current = Some((id.trim(), first, Vec::new()));
+        } else {
+            error!("Invalid line in Fluent resource: {line}");
+        }
+    }
+    if let Some((id, first, continued)) = current {
+        messages.insert(id, pattern(first, &continued));
+    }
+
+    messages
+}
+
+/// Fill in the placeables of a pattern with the given variables.
+///
+/// Unknown variables are left in as `{$name}`, like Fluent does.
+fn format(pattern: &str, args: &[(&str, &(dyn Display + Sync))]) -> String {
+    let mut formatted = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+        let placeable = rest[start + 1..].trim_start();
+        // String literals may contain braces themselves, so look for the closing quote first
+        let (expression, after) = if let Some(literal) = placeable.strip_prefix('"') {
+            let Some(quote) = literal.find('"') else {
+                break;
+            };
+            let Some(end) = literal[quote..].find('}') else {
+                break;
+            };
+            (&placeable[..=quote + 1], &literal[quote + end + 1..])
+        } else {
+            let Some(end) = placeable.find('}') else {
+                break;
+            };
+            (&placeable[..end], &placeable[end + 1..])
+        };
+
+        let expression = expression.trim();
+        if let Some(name) = expression.strip_prefix('$') {
+            if let Some((_, value)) = args.iter().find(|(arg, _)| *arg == name) {
+                formatted.push_str(&value.to_string());
+            } else {
+                formatted.push_str("{$");
+                formatted.push_str(name);
+                formatted.push('}');
+            }
+        } else if let Some(literal) = expression
+            .strip_prefix('"')
+            .and_then(|literal| literal.strip_suffix('"'))
+        {
+            formatted.push_str(literal);
+        } else {
+            error!("Unsupported placeable in message: {expression}");
+        }
+        rest = after;
+    }
+    formatted.push_str(rest);
+
+    formatted
+}
+
+/// Look up a message in `language`, falling back to English, and fill in its variables.
+pub fn message(language: Language, id: &str, args: &[(&str, &(dyn Display + Sync))]) -> String {
+    let pattern = MESSAGES
+        .get(&language)
+        .and_then(|messages| messages.get(id))
+        .or_else(|| {
+            MESSAGES
+                .get(&Language::English)
+                .and_then(|messages| messages.get(id))
+        });
+    pattern.map_or_else(
+        || {
+            error!("Missing message {id}");
+            id.to_string()
+        },
+        |pattern| format(pattern, args),
+    )
+}
+
+/// Look up the variant of a message for `count`: `{id}-one` if it's 1, and `{id}-other`
+/// otherwise. `count` is available to the message as `$count`.
+pub fn message_count(
+    language: Language,
+    id: &str,
+    count: usize,
+    args: &[(&str, &(dyn Display + Sync))],
+) -> String {
+    let variant = if count == 1 { "one" } else { "other" };
+    let mut args = args.to_vec();
+    args.push(("count", &count));
+    message(language, &format!("{id}-{variant}"), &args)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// The variables a pattern refers to.
+    fn variables(pattern: &str) -> HashSet<&str> {
+        pattern
+            .split("{ $")
+            .skip(1)
+            .filter_map(|rest| rest.split_once(' ').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn multiline_messages_keep_inner_blank_lines() {
+        let messages = parse("# Comment\nfirst =\n    One\n\n    Two\n\nsecond = Three\n");
+
+        assert_eq!(messages["first"], "One\n\nTwo");
+        assert_eq!(messages["second"], "Three");
+    }
+
+    #[test]
+    fn placeables_are_filled_in() {
+        assert_eq!(
+            format(
+                r#"{" "}Hello { $name }, { $missing } `@{"{"}query{"}"}`"#,
+                &[("name", &"world")]
+            ),
+            " Hello world, {$missing} `@{query}`"
+        );
+    }
+
+    #[test]
+    fn messages_are_looked_up_in_the_language() {
+        assert_eq!(message(Language::German, "field-query", &[]), "Abfrage");
+        assert_eq!(
+            message_count(Language::English, "wait-minutes", 1, &[]),
+            "1 minute"
+        );
+        assert_eq!(
+            message_count(Language::German, "wait-minutes", 3, &[]),
+            "3 Minuten"
+        );
+        // Unknown messages are shown by their ID, rather than not at all
+        assert_eq!(
+            message(Language::German, "no-such-message", &[]),
+            "no-such-message"
+        );
+    }
+
+    #[test]
+    fn translations_match_english() {
+        let english = &MESSAGES[&Language::English];
+        for language in Language::ALL {
+            for (id, pattern) in &MESSAGES[&language] {
+                let english = english
+                    .get(id)
+                    .unwrap_or_else(|| panic!("{language:?} has {id}, which English doesn't"));
+                assert_eq!(
+                    variables(pattern),
+                    variables(english),
+                    "{language:?} uses different variables in {id}"
+                );
+            }
+            for id in english.keys() {
+                assert!(
+                    MESSAGES[&language].contains_key(id),
+                    "{language:?} doesn't translate {id}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,708 @@
+# German messages. Anything missing here falls back to English.
+
+## Evaluation
+
+evaluating = Deine Abfrage wird ausgewertet... ({ $resolved }/{ $total } Rollen und Mitglieder aufgelöst)
+
+## Large query confirmation
+
+confirm-mention-count =
+    **Moment!** Mit dieser Abfrage erwähnst du { $count } Personen.{ $messages } Bist du sicher?
+
+    Erwähnt werden { $summary }.{ $requirement }
+confirm-mention-count-messages = {" "}Dafür müssen { $count } Nachrichten gesendet werden.
+confirm-mention-count-approval = {" "}Da es so viele sind, muss ein Mitglied von { $role } außer dir bestätigen.
+confirm-mention-count-typed = {" "}Da es so viele sind, musst du zum Bestätigen die Anzahl der Personen eingeben, die erwähnt werden.
+confirm-yes = Ja
+confirm-typed = Zum Bestätigen eingeben
+typed-confirmation-title = { $count } Personen erwähnen?
+typed-confirmation-label = Gib { $count } ein, um zu bestätigen
+typed-confirmation-mismatch = Das ist nicht { $count }, daher wurde niemand erwähnt.
+cancel = Abbrechen
+cancelled = Abgebrochen.
+confirmed = Bestätigt.
+approved-by = Genehmigt von { $approver }.
+confirmation-timed-out = Zeitüberschreitung beim Warten auf die Bestätigung.
+confirmation-reminder = *Wartet noch auf deine Bestätigung. Läuft in { $left } ab.*
+
+## Duplicate queries
+
+duplicate-query-confirm =
+    **Moment!** { $query } wurde bereits vor { $ago } in diesem Kanal gesendet. Wenn du die Abfrage erneut sendest, werden dieselben Mitglieder noch einmal benachrichtigt. Bist du sicher?
+duplicate-query-send = Erneut senden
+duplicate-query-refused = { $query } wurde bereits vor { $ago } in diesem Kanal gesendet. Warte eine Weile, bevor du die Abfrage erneut sendest.
+
+## Incomplete results
+
+incomplete-evaluation-one =
+    **Moment!** Die Ergebnisse deiner Abfrage sind unvollständig: Beim Auflösen von { $operands } kam es zu einer Zeitüberschreitung, daher wurde es so behandelt, als träfe es auf niemanden zu. Ohne es trifft deine Abfrage auf { $members } Mitglieder zu. Du kannst die Abfrage wiederholen oder mit diesen Teilergebnissen fortfahren.
+incomplete-evaluation-other =
+    **Moment!** Die Ergebnisse deiner Abfrage sind unvollständig: Beim Auflösen von { $operands } kam es zu einer Zeitüberschreitung, daher wurden sie so behandelt, als träfen sie auf niemanden zu. Ohne sie trifft deine Abfrage auf { $members } Mitglieder zu. Du kannst die Abfrage wiederholen oder mit diesen Teilergebnissen fortfahren.
+incomplete-evaluation-retry = Wiederholen
+incomplete-evaluation-continue = Teilergebnisse verwenden
+incomplete-evaluation-retrying = Deine Abfrage wird wiederholt...
+incomplete-evaluation-continuing = Es wird mit den Teilergebnissen fortgefahren.
+
+## Unmentionable roles
+
+unmentionable-roles-one =
+    **Moment!** Du kannst { $roles } nicht erwähnen: Die Rolle ist nicht erwähnbar und du hast nicht die Berechtigung „@everyone, @here und alle Rollen erwähnen“. Du kannst stattdessen ihre Mitglieder einzeln erwähnen oder die Mitglieder deiner Abfrage auflisten, ohne jemanden zu erwähnen.
+unmentionable-roles-other =
+    **Moment!** Du kannst { $roles } nicht erwähnen: Die Rollen sind nicht erwähnbar und du hast nicht die Berechtigung „@everyone, @here und alle Rollen erwähnen“. Du kannst stattdessen ihre Mitglieder einzeln erwähnen oder die Mitglieder deiner Abfrage auflisten, ohne jemanden zu erwähnen.
+unmentionable-roles-list = Auflisten ohne Erwähnung
+unmentionable-roles-expand = Mitglieder erwähnen
+unmentionable-roles-expanding = Die Mitglieder dieser Rollen werden einzeln erwähnt.
+unmentionable-roles-listing = Die Mitglieder werden aufgelistet, ohne jemanden zu erwähnen.
+
+## Mention summaries, like "@everyone, @Staff und 3 einzelne Mitglieder"
+
+summary-more-roles = { $count } weitere Rollen
+summary-individual-members-one = { $count } einzelnes Mitglied
+summary-individual-members-other = { $count } einzelne Mitglieder
+list-two = { $first } und { $second }
+list-many = { $rest } und { $last }
+
+## Notifications
+
+results-for = **Ergebnisse für** { $query }:
+no-users-matched = Keine passenden Mitglieder gefunden.
+do-not-ping-excluded-one = { $count } Mitglied wurde ausgelassen, da es die Nicht-erwähnen-Rolle dieses Servers hat.
+do-not-ping-excluded-other = { $count } Mitglieder wurden ausgelassen, da sie die Nicht-erwähnen-Rolle dieses Servers haben.
+opted-out-excluded-one = { $count } Mitglied wurde ausgelassen, da es sich von Benachrichtigungen auf diesem Server abgemeldet hat.
+opted-out-excluded-other = { $count } Mitglieder wurden ausgelassen, da sie sich von Benachrichtigungen auf diesem Server abgemeldet haben.
+quiet-hours-excluded-one = { $count } Mitglied wurde ausgelassen, da es gerade seine Ruhezeit hat.
+quiet-hours-excluded-other = { $count } Mitglieder wurden ausgelassen, da sie gerade ihre Ruhezeit haben.
+notified-via-dm = { $count } Mitglieder wurden per Direktnachricht benachrichtigt.
+already-mentioned-one = { $count } dieser Mitglieder wurde bereits oben erwähnt.
+already-mentioned-other = { $count } dieser Mitglieder wurden bereits oben erwähnt.
+notification-header = Benachrichtigung ausgelöst von Intersection.
+what-is-this = :question: **Was ist das?** Mehr dazu erfährst du mit { $command }.
+listing-header = Mitglieder, auf die diese Abfrage zutrifft (niemand wurde benachrichtigt):
+split-notification-prefix = Benachrichtigung ausgelöst von Intersection.
+split-listing-prefix = Mitglieder, auf die diese Abfrage zutrifft (niemand wird benachrichtigt).
+split-sending = Bitte warten, { $count } Nachrichten werden gesendet...
+split-sending-progress = Bitte warten, { $count } Nachrichten werden gesendet... ({ $sent } gesendet)
+split-sent = { $count } Nachrichten gesendet.
+split-part = (Teil { $part }/{ $total })
+split-start = [Zum Anfang dieser Benachrichtigung springen]({ $link })
+split-notification-done = Benachrichtigung erfolgreich ausgelöst.
+split-listing-done = Auflistung der Mitglieder abgeschlossen.
+notification-stats = { $query } trifft über { $roles } auf { $members } zu.
+count-members-one = { $count } Mitglied
+count-members-other = { $count } Mitglieder
+count-roles-one = { $count } Rolle
+count-roles-other = { $count } Rollen
+mention-cap-exceeded = Deine Abfrage trifft auf { $count } Mitglieder zu, aber auf diesem Server dürfen höchstens { $limit } Mitglieder auf einmal erwähnt werden. Versuche, sie einzugrenzen, z. B. indem du sie mit einer weiteren Rolle schneidest: `@{"{"}deine Abfrage & Rolle{"}"}`.
+
+## Embeds
+
+embed-title = Benachrichtigung ausgelöst von Intersection
+field-query = Abfrage
+field-author = Autor
+field-channel = Kanal
+field-members = Mitglieder
+field-roles-mentioned = Erwähnte Rollen
+field-individual-mentions = Einzelne Erwähnungen
+field-notified-via-dm = Per Direktnachricht benachrichtigt
+field-already-mentioned = Bereits oben erwähnt
+field-members-added-to-thread = Zum Thread hinzugefügte Mitglieder
+field-approved-by = Genehmigt von
+field-roles-used = Verwendete Rollen
+field-messages = Nachrichten
+
+## Threads
+
+thread-adding = { $count } Mitglieder werden zu { $thread } hinzugefügt...
+thread-adding-progress = { $count } Mitglieder werden zu { $thread } hinzugefügt... ({ $added } erledigt)
+thread-added = { $count } Mitglieder wurden zu { $thread } hinzugefügt, statt sie zu erwähnen.
+
+## Direct messages
+
+dm-notification = { $author } hat dich mit { $query } in { $link } erwähnt
+
+## Notifications interrupted by a restart
+
+interrupted-resumed = [Diese Benachrichtigung]({ $link }) wurde durch einen Neustart unterbrochen und ist jetzt vollständig gesendet.
+interrupted-abandoned-one =
+    { $author }, [deine Benachrichtigung]({ $link }) wurde durch einen Neustart unterbrochen, bevor ihre letzte Nachricht gesendet wurde, daher wurden einige Mitglieder nicht erwähnt. Führe deine Abfrage erneut aus, um sie zu erwähnen.
+interrupted-abandoned-other =
+    { $author }, [deine Benachrichtigung]({ $link }) wurde durch einen Neustart unterbrochen, bevor ihre letzten { $count } Nachrichten gesendet wurden, daher wurden einige Mitglieder nicht erwähnt. Führe deine Abfrage erneut aus, um sie zu erwähnen.
+
+## Scheduled queries
+
+scheduled-announcement = { $author } hat diese Benachrichtigung für { $query } geplant.
+scheduled-missed = { $author }, deine für { $at } geplante Benachrichtigung für { $query } wurde verpasst, während Intersection offline war. Plane sie mit `/schedule run` erneut.
+scheduled-missed-recurring = { $author }, deine für { $at } geplante Benachrichtigung für { $query } wurde verpasst, während Intersection offline war. Sie wird am { $next } erneut ausgeführt.
+
+## Audit log
+
+audit-title = Benachrichtigung gesendet
+audit-jump = [Zur Benachrichtigung springen]({ $link })
+
+## Errors
+
+error-send-failed =
+    { $error }
+    Außerdem konnte dir dieser Fehler nicht gesendet werden: { $send-error }
+cooldown-author = Du hast in letzter Zeit zu viele Benachrichtigungen gesendet. Versuche es in { $wait } erneut.
+cooldown-guild = Auf diesem Server wurden in letzter Zeit zu viele Benachrichtigungen gesendet. Versuche es in { $wait } erneut.
+wait-seconds-one = { $count } Sekunde
+wait-seconds-other = { $count } Sekunden
+wait-minutes-one = { $count } Minute
+wait-minutes-other = { $count } Minuten
+access-denied = Du darfst auf diesem Server keine Abfragen ausführen. Das dürfen nur Mitglieder mit { $allowed }.
+access-permission = der Berechtigung „{ $permission }“
+list-or = { $first } oder { $second }
+queries-paused = Abfragen sind auf diesem Server pausiert, daher wurde niemand erwähnt. Sie wurden von { $user } pausiert und können mit `/resume` fortgesetzt werden.
+
+## Query errors
+
+internal-error = Bei uns ist etwas schiefgelaufen (Fehler `{ $id }`).
+command-error = Fehler: { $error }
+no-query = In deiner Nachricht ist keine DRQL-Abfrage, die bearbeitet werden könnte.
+dm-queries-unavailable = DRQL-Abfragen sind in Direktnachrichten nicht verfügbar.
+server-channels-only = DRQL-Abfragen können nur in Serverkanälen verwendet werden.
+everyone-here-refused = `{ $literal }` kann auf diesem Server nicht in Abfragen verwendet werden.{ $explanation }
+too-many-nodes = Deine Abfrage ist zu lang, um ausgewertet zu werden: Sie darf höchstens { $limit } Rollen, Mitglieder, Operatoren und Funktionen verwenden. Versuche, sie in kleinere Abfragen aufzuteilen.
+too-deep = Deine Abfrage ist zu tief verschachtelt, um ausgewertet zu werden: Sie darf nur { $limit } Ebenen tief gehen. Versuche, einige Klammern zu entfernen.
+
+## Parse errors, like "Unexpected `)`, expected `(` or a name"
+
+parse-error = Fehler beim Lesen von Teil { $chunk }: { $error }
+parse-unknown-token = Unbekanntes Zeichen
+parse-ended-early = Deine Abfrage endet zu früh{ $expected }
+parse-unexpected = Unerwartetes `{ $token }`{ $expected }
+parse-extra-token = Unerwartetes `{ $token }` nach dem Ende der Abfrage
+parse-unknown-character = Unbekanntes Zeichen `{ $char }`
+parse-unterminated-string = Diese Zeichenkette wird nie geschlossen
+parse-invalid-number = Ungültige Zahl: { $error }
+parse-invalid-function = Ungültiger Funktionsaufruf: `{ $call }`
+parse-expected = , erwartet wurde { $expected }
+terminal-name = ein Name
+terminal-id = eine Zahl oder ID
+terminal-duration = eine Dauer
+terminal-member = ein Mitglied
+terminal-role = eine Rolle
+terminal-channel = ein Kanal
+terminal-event-link = ein Event-Link
+terminal-message-link = ein Nachrichtenlink
+terminal-emoji = ein Emoji
+terminal-regex = ein regulärer Ausdruck
+terminal-alias = ein Alias
+
+## Aliases
+
+alias-unknown = `${ $name }` ist auf diesem Server kein Alias.
+alias-cycle = `${ $name }` kann nicht erweitert werden, da es sich selbst verwendet: `{ $cycle }`.
+alias-invalid = `${ $name }` steht für keine gültige Abfrage: { $error }
+alias-too-large = Die Aliase der Abfrage ergeben mehr als { $limit } Operatoren und Operanden.
+
+## Resolving the operands of queries
+
+resolve-unknown-channel = Der Kanal <#{ $id }> konnte nicht gefunden werden
+resolve-everyone-permission = Dir fehlt die Berechtigung „Erwähne @everyone, @here und alle Rollen“, die für die Rolle { $role } nötig ist.
+resolve-ambiguous = { $members } Mitglied(er) und { $roles } Rolle(n) passen zu deiner Abfrage nach „{ $name }“. Bitte grenze deine Abfrage ein oder verwende stattdessen die ID dessen, was du meinst.
+resolve-ambiguous-members = { $count } Mitglieder passen zu deiner Abfrage nach „{ $name }“. Bitte grenze deine Abfrage ein: Es kann helfen, die ID des Mitglieds zu verwenden oder seinen Diskriminator anzugeben, z. B. „luna..♡#9082“ statt „luna..♡“.
+resolve-ambiguous-roles = { $count } Rollen passen zu deiner Abfrage nach „{ $name }“. Bitte grenze deine Abfrage ein: Es kann helfen, stattdessen eine Rollen-ID zu verwenden.
+resolve-unknown-name = Es gibt keine Rolle und kein Mitglied mit dem Namen { $name }.{ $case-sensitive } Versuche es mit der ID.
+resolve-case-sensitive = {" "}Auf diesem Server wird bei der Suche nach Rollen die Groß- und Kleinschreibung beachtet!
+resolve-invalid-id = { $id } ist keine gültige Rollen- oder Mitglieds-ID
+resolve-unknown-id = Die Rollen- oder Mitglieds-ID { $id } konnte nicht gefunden werden
+resolve-unknown-role = Die Rolle mit der ID { $id } konnte nicht gefunden werden
+resolve-private-thread = Du kannst den privaten Thread <#{ $id }> nur in einer Abfrage verwenden, wenn du in ihm bist.
+resolve-unusable-channel = <#{ $id }> ist weder ein Text- oder Sprachkanal noch ein Thread und kann daher nicht in einer Abfrage verwendet werden.
+resolve-unknown-event = Das geplante Event mit der ID { $id } konnte nicht gefunden werden
+resolve-invalid-pattern = Das Muster { $pattern } kann nicht verwendet werden: { $error }
+resolve-foreign-message = { $link } ist eine Nachricht auf einem anderen Server, daher können ihre Reaktionen nicht in einer Abfrage verwendet werden.
+resolve-unreadable-reactions = Du kannst Reaktionen auf { $link } nur in einer Abfrage verwenden, wenn du <#{ $channel }> lesen kannst.
+resolve-unknown-reactions = Es wurden keine { $emoji }-Reaktionen auf { $link } gefunden
+
+## Configuration
+
+language-set = Intersection sendet Nachrichten jetzt auf Deutsch.
+config-silent-on = Erwähnungen werden jetzt still gesendet. Mitglieder sehen die Erwähnung, erhalten aber keine Push-Benachrichtigung.
+config-silent-off = Erwähnungen benachrichtigen Mitglieder jetzt wieder wie gewohnt. Beginne deine Nachricht mit `@silent`, um die Erwähnungen einer einzelnen Abfrage still zu senden.
+config-slash-only-on = Abfragen in Nachrichten werden jetzt ignoriert, daher kann `@{"{"}{"}"}` frei geschrieben werden. Nur Slash-Befehle wie `/dry_run` führen sie aus.
+config-slash-only-off = Abfragen in Nachrichten werden jetzt wieder ausgeführt.
+config-per-chunk-on = Jede Abfrage in einer Nachricht wird jetzt in einer eigenen Gruppe erwähnt, beschriftet mit der Abfrage.
+config-per-chunk-off = Alle Abfragen in einer Nachricht werden jetzt zusammen erwähnt.
+config-embed-on = Benachrichtigungen werden jetzt in einem Embed mit Abfrage, Autor und Anzahlen erklärt, sodass in der Nachricht nur die Erwähnungen stehen.
+config-embed-off = Benachrichtigungen werden jetzt im Nachrichtentext erklärt.
+config-delivery-reply = Benachrichtigungen werden jetzt von Intersection gesendet.
+config-delivery-webhook = Benachrichtigungen werden jetzt mit dem Namen und Avatar des Mitglieds gesendet, das sie ausgelöst hat. Dafür braucht Intersection im Kanal die Berechtigung „Webhooks verwalten“; ohne sie sendet Intersection Benachrichtigungen wie gewohnt.
+config-delivery-thread = Statt erwähnt zu werden, werden Mitglieder jetzt zu einem Thread hinzugefügt, der an der Abfrage gestartet wird. Das benachrichtigt sie sanfter und hält die Unterhaltung an einem Ort.
+cooldown-scope-author = jedem Mitglied
+cooldown-scope-guild = diesem Server
+config-cooldown-set = Von { $who } können jetzt höchstens { $count } Benachrichtigungen alle { $minutes } Minuten gesendet werden.
+config-cooldown-unset = Es gibt keine Grenze mehr für die Benachrichtigungen von { $who }.
+config-cooldown-bypass-on = Mitglieder von { $role } sind jetzt von Abklingzeiten ausgenommen.
+config-cooldown-bypass-off = Mitglieder von { $role } sind nicht mehr von Abklingzeiten ausgenommen.
+config-emergency-role-on = Abfragen von Mitgliedern von { $role } benachrichtigen Mitglieder jetzt auch während ihrer Ruhezeiten.
+config-emergency-role-off = Abfragen von Mitgliedern von { $role } benachrichtigen Mitglieder während ihrer Ruhezeiten nicht mehr.
+config-max-mentions-set = Abfragen, die auf mehr als { $limit } Mitglieder zutreffen, werden jetzt abgelehnt, auch wenn der Autor sie bestätigen würde.
+config-max-mentions-unset = Es gibt keine Grenze mehr dafür, auf wie viele Mitglieder eine Abfrage zutreffen darf.
+config-access-unrestricted = Jetzt kann jeder Abfragen ausführen.
+config-query-role-on = Mitglieder von { $role } können jetzt Abfragen ausführen.
+config-query-role-off = Mitglieder von { $role } können keine Abfragen mehr ausführen.
+config-query-permission-set = Mitglieder mit der Berechtigung „{ $permission }“ können jetzt Abfragen ausführen.
+config-query-permission-unset = Jetzt können nur noch Mitglieder der erlaubten Rollen Abfragen ausführen.
+config-channels-denylist = Abfragen werden jetzt überall außer in den aufgelisteten Kanälen bearbeitet.
+config-channels-allowlist = Abfragen werden jetzt nur noch in den aufgelisteten Kanälen bearbeitet.
+config-channel-processed = Abfragen werden jetzt in { $channel } bearbeitet.
+config-channel-ignored = Abfragen werden in { $channel } nicht mehr bearbeitet.
+channels-all = Abfragen werden in jedem Kanal bearbeitet.
+channels-except = Abfragen werden in jedem Kanal außer { $channels } bearbeitet.
+channels-none = Abfragen werden in keinem Kanal bearbeitet. Füge einen mit `/config channels add` hinzu.
+channels-only = Abfragen werden nur in { $channels } bearbeitet.
+config-audit-channel-set = Eine Zusammenfassung jeder Benachrichtigung wird jetzt in { $channel } gepostet.
+config-audit-channel-unset = Benachrichtigungen werden nicht mehr in einem Audit-Log-Kanal gepostet.
+config-error-expiry-set = Erklärungen von Fehlern in Abfragen, wie Tippfehlern und unbekannten Rollen, werden jetzt nach { $seconds } Sekunden gelöscht, und an der Abfrage bleibt eine Reaktion.
+config-error-expiry-unset = Erklärungen von Fehlern in Abfragen werden jetzt behalten.
+config-mention-expiry-set = Nachrichten, die Mitglieder erwähnen, werden jetzt { $minutes } Minuten nach dem Senden gelöscht. Mitglieder werden beim Senden benachrichtigt und verpassen daher nichts, können aber nicht mehr zu ihnen zurückscrollen.
+config-mention-expiry-unset = Nachrichten, die Mitglieder erwähnen, werden jetzt behalten.
+config-duplicates-allow = Abfragen können jetzt so oft erneut gesendet werden, wie Mitglieder möchten.
+config-duplicates-confirm = Mitglieder, die dieselbe Abfrage in einem Kanal innerhalb von { $seconds } Sekunden nach ihrem letzten Senden dort erneut senden, müssen jetzt bestätigen, dass sie alle erneut benachrichtigen möchten.
+config-duplicates-refuse = Abfragen, die in einem Kanal innerhalb von { $seconds } Sekunden nach ihrem letzten Senden dort erneut gesendet werden, werden jetzt abgelehnt.
+config-approval-role-missing = Wähle die Rolle, deren Mitglieder Benachrichtigungen genehmigen können.
+config-approval-set = Benachrichtigungen an { $threshold } oder mehr Mitglieder müssen jetzt von einem Mitglied von { $role } außer ihrem Autor genehmigt werden.
+config-approval-unset = Benachrichtigungen müssen nicht mehr von einem Moderator genehmigt werden.
+config-typed-confirmation-set = Autoren von Abfragen, die auf { $threshold } oder mehr Mitglieder zutreffen, müssen jetzt zur Bestätigung eintippen, auf wie viele Mitglieder sie zutreffen.
+config-typed-confirmation-unset = Große Abfragen können jetzt immer mit einem Button bestätigt werden.
+config-confirm-threshold = Autoren von Abfragen, die auf mehr als { $members } Mitglieder zutreffen, müssen sie jetzt bestätigen.
+config-confirm-timeout = Autoren haben jetzt { $seconds } Sekunden Zeit, große Abfragen zu bestätigen.
+config-confirm-timeout-reminder = Autoren haben jetzt { $seconds } Sekunden Zeit, große Abfragen zu bestätigen, und werden nach der Hälfte der Zeit daran erinnert.
+config-do-not-ping-set = Mitglieder von { $role } werden jetzt aus den Ergebnissen jeder Abfrage herausgelassen.
+config-do-not-ping-unset = Abfragen können jetzt wieder jedes Mitglied erwähnen.
+config-case-sensitive-on = Rollennamen in Abfragen müssen jetzt genau der Groß- und Kleinschreibung des Rollennamens entsprechen.
+config-case-sensitive-off = Rollennamen in Abfragen passen jetzt unabhängig von der Groß- und Kleinschreibung, sodass `mods` eine Rolle namens „Mods“ findet. Eine Rolle, deren Name genau passt, wird weiterhin bevorzugt.
+config-everyone-here-on = Mitglieder, die alle erwähnen dürfen, können jetzt `everyone` und `here` in Abfragen verwenden.
+config-everyone-here-off = Abfragen mit `everyone` oder `here` werden jetzt abgelehnt, egal wer sie sendet.
+config-prefix-invalid = Ein Präfix darf nicht leer sein und keine Leerzeichen enthalten.
+config-prefix-set = Textbefehle beginnen jetzt mit `{ $prefix }`, z. B. `{ $prefix }dry_run staff & here` oder `{ $prefix }members staff`. Nachrichten, die damit beginnen, werden nie als Abfragen behandelt.
+config-prefix-unset = Textbefehle sind jetzt ausgeschaltet. Verwende stattdessen Slash-Befehle.
+config-reset = Alle Einstellungen wurden auf ihre Standardwerte zurückgesetzt. Sieh sie dir mit `/config get` an.
+
+## The settings shown by /config get
+
+config-title = Konfiguration
+config-footer = Ändere eine Einstellung mit /config <Einstellung>.
+setting-silent = Stille Erwähnungen
+setting-per-chunk = Jede Abfrage einzeln erwähnt
+setting-embed = In einem Embed erklärt
+setting-slash-only = Nur Slash-Befehle
+setting-delivery = Zustellung
+setting-cooldown-author = Abklingzeit für jeden Autor
+setting-cooldown-guild = Abklingzeit für den ganzen Server
+setting-cooldown-bypass = Von Abklingzeiten ausgenommen
+setting-max-mentions = Höchstens Erwähnungen pro Abfrage
+setting-query-role = Rollen, die Abfragen ausführen dürfen
+setting-query-permission = Berechtigung, die Abfragen erlaubt
+setting-channels = Kanäle
+setting-audit-channel = Audit-Log-Kanal
+setting-language = Sprache
+setting-error-expiry = Fehlererklärungen behalten für
+setting-mention-expiry = Erwähnungsnachrichten behalten für
+setting-duplicate-queries = Erneut gesendete Abfragen
+setting-approval = Genehmigung durch Moderatoren
+setting-typed-confirmation = Eingetippte Bestätigung
+setting-confirmation = Bestätigung
+setting-confirm-timeout = Zeit zum Bestätigen
+setting-do-not-ping = Nie erwähnt
+setting-emergency-role = Ruhezeiten übergehen
+setting-case-sensitive-roles = Groß- und Kleinschreibung bei Rollennamen
+setting-everyone-here = Everyone und here erlaubt
+setting-aliases = Aliase
+setting-prefix = Präfix für Textbefehle
+value-yes = ja
+value-no = nein
+value-none = keine
+value-any = alle
+value-forever = für immer
+value-no-limit = keine Grenze
+value-never-needed = nie nötig
+value-rate-limit = { $count } pro { $seconds } Sekunden
+value-seconds = { $seconds } Sekunden
+value-seconds-with-reminder = { $seconds } Sekunden, mit einer Erinnerung nach der Hälfte
+value-minutes = { $minutes } Minuten
+value-channels-all = jeder Kanal
+value-channels-except = jeder Kanal außer { $channels }
+value-channels-none = keine Kanäle
+value-channels-only = nur { $channels }
+value-delivery-reply = Antwort von Intersection
+value-delivery-webhook = Webhook mit Name und Avatar des Autors
+value-delivery-thread = Thread, zu dem die Mitglieder hinzugefügt werden
+value-duplicate-queries = { $mode } innerhalb von { $seconds } Sekunden
+value-duplicates-allow = Erlauben
+value-duplicates-confirm = Den Autor bestätigen lassen
+value-duplicates-refuse = Ablehnen
+value-approval = ab { $threshold } Mitgliedern, durch { $role }
+value-threshold = ab { $threshold } Mitgliedern
+value-confirmation = über { $members } Mitglieder
+value-refused = nein ({ $explanation })
+value-aliases = { $count }, aufgelistet von `/alias list`
+
+## Buttons under notifications
+
+recipients-button = Wer wurde erwähnt?
+recipients-page =
+    Diese Benachrichtigung hat { $count } Mitglieder erwähnt (Seite { $page } von { $pages }):
+    { $list }
+recipients-expired = Diese Benachrichtigung ist zu alt, daher wissen wir nicht mehr, wen sie erwähnt hat.
+delete-button = Diese Erwähnung löschen
+delete-unknown-query = Die Abfrage zu dieser Benachrichtigung wurde nicht gefunden.
+delete-forbidden = Nur der Autor der Abfrage oder ein Moderator kann diese Benachrichtigung löschen.
+delete-too-late = Benachrichtigungen können nur innerhalb von { $minutes } Minuten nach dem Senden gelöscht werden.
+deleted = { $count } Nachrichten dieser Benachrichtigung wurden gelöscht.
+
+## Lists shown a page at a time
+
+page-previous = Zurück
+page-next = Weiter
+page-footer = Seite { $page } von { $pages }
+
+## Dry runs and member lists
+
+dry-run-timed-out = Beim Auflösen von { $operands } kam es zu einer Zeitüberschreitung, daher sind diese Ergebnisse möglicherweise unvollständig. Wenn du die Abfrage ausführst, wirst du gefragt, ob du sie wiederholen oder mit den Teilergebnissen fortfahren möchtest.
+dry-run-breakdown = Die einzelnen Teile treffen auf { $parts } zu, { $operator }: { $total }.
+dry-run-header = Deine Abfrage trifft auf die folgenden { $count } Nutzer zu:
+dry-run-unmentionable = Du kannst { $count } der Rollen in deiner Abfrage nicht erwähnen, daher wirst du gefragt, ob ihre Mitglieder einzeln erwähnt oder ohne Erwähnung aufgelistet werden sollen.
+dry-run-footer = Dafür müssen { $messages } Nachrichten gesendet werden. (Optimiert durch das Erwähnen von { $roles } Rollen, was dir { $saved } Erwähnungen spart.)
+dry-run-summary-attached = Deine Abfrage trifft auf die { $count } angehängten Nutzer zu. Dafür müssen { $messages } Nachrichten gesendet werden (optimiert durch das Erwähnen von { $roles } Rollen, was dir { $saved } Erwähnungen spart).
+dry-run-summary-below = Deine Abfrage trifft auf die { $count } unten aufgeführten Nutzer zu. Dafür müssen { $messages } Nachrichten gesendet werden (optimiert durch das Erwähnen von { $roles } Rollen, was dir { $saved } Erwähnungen spart).
+query-matches-none = Deine Abfrage trifft auf 0 Nutzer zu.
+query-excluded = { $count } weitere Mitglieder wurden ausgelassen, da sie die Nicht-erwähnen-Rolle des Servers haben.
+members-below = Deine Abfrage trifft auf die { $count } unten aufgeführten Nutzer zu.
+matched-members = Zutreffende Mitglieder
+
+## Aliases, with /alias
+
+alias-invalid-name = Alias-Namen beginnen mit einem Buchstaben oder Unterstrich, gefolgt von Buchstaben, Ziffern oder Unterstrichen, wie `oncall` oder `team_2`.
+alias-limit = Dieser Server hat bereits { $limit } Aliase. Entferne zuerst einen mit `/alias remove`.
+alias-added = Abfragen in diesem Server können jetzt `${ $name }` verwenden, wie `@{"{"}${ $name } & here{"}"}`.
+alias-removed = `${ $name }` wurde entfernt, daher funktionieren Abfragen, die ihn verwenden, nicht mehr.
+alias-list-empty = Dieser Server hat noch keine Aliase. Füge einen mit `/alias add` hinzu.
+alias-list = Dieser Server hat { $count } Aliase.
+alias-list-title = Aliase
+alias-export-empty = Dieser Server hat keine Aliase zum Exportieren. Füge einen mit `/alias add` hinzu.
+alias-exported = Die { $count } Aliase dieses Servers sind angehängt. Füge sie mit `/alias import` zu einem anderen Server hinzu.
+alias-imported = { $count } Aliase wurden importiert. Sieh sie dir mit `/alias list` an.
+import-too-large = Diese Datei ist zu groß. Dateien mit Aliasen dürfen bis zu { $limit } KiB groß sein.
+import-not-json = Diese Datei ist kein JSON.
+import-not-an-object = Diese Datei sollte ein JSON-Objekt aus Namen und Abfragen sein, wie es `/alias export` erstellt.
+import-invalid-name = `{ $name }` kann nicht der Name eines Alias sein.
+import-not-a-query = `${ $name }` sollte für eine Abfrage stehen, als Zeichenkette.
+import-query-too-long = `${ $name }` steht für eine Abfrage, die länger als { $limit } Zeichen ist.
+import-limit = Durch den Import hätte dieser Server mehr als { $limit } Aliase. Entferne zuerst einige mit `/alias remove`.
+
+## Scheduled queries, with /schedule
+
+schedule-limit = In diesem Server sind bereits { $limit } Abfragen geplant. Brich zuerst eine mit `/schedule cancel` ab.
+schedule-added = { $query } wird am { $at } in diesem Kanal ausgeführt (geplante Abfrage #{ $id }).
+schedule-added-recurring = { $query } wird { $repeat } in diesem Kanal ausgeführt, erstmals am { $at } (geplante Abfrage #{ $id }).
+schedule-invalid-time = Schreibe die Zeit wie `2024-06-01 18:00` und die Zeitzone als Abweichung von UTC, wie `UTC+2` oder `-05:00`.
+schedule-passed = { $at } ist bereits vorbei.
+schedule-too-far = Abfragen können höchstens ein Jahr im Voraus geplant werden.
+schedule-invalid-recurrence = Schreibe, wann sie ausgeführt werden soll, wie `every monday 17:00` oder `every day 09:30`, und die Zeitzone als Abweichung von UTC, wie `UTC+2` oder `-05:00`.
+schedule-list-empty = In diesem Server sind keine Abfragen geplant. Plane eine mit `/schedule run`.
+schedule-line = **#{ $id }** { $query } am { $at }, von <@{ $author }> in <#{ $channel }>
+schedule-line-recurring = **#{ $id }** { $query } am { $at }, { $repeat }, von <@{ $author }> in <#{ $channel }>
+schedule-list = In diesem Server sind { $count } Abfragen geplant. Brich eine mit `/schedule cancel` ab.
+schedule-list-title = Geplante Abfragen
+schedule-unknown = In diesem Server ist keine Abfrage als #{ $id } geplant.
+schedule-cancel-forbidden = Nur <@{ $author }>, der #{ $id } geplant hat, oder Mitglieder mit der Berechtigung „Server verwalten“ können sie abbrechen.
+schedule-cancelled = #{ $id }, { $query }, wurde abgebrochen.
+recurrence-daily = jeden Tag um { $time } (UTC{ $offset })
+recurrence-weekly = jeden { $day } um { $time } (UTC{ $offset })
+weekday-monday = Montag
+weekday-tuesday = Dienstag
+weekday-wednesday = Mittwoch
+weekday-thursday = Donnerstag
+weekday-friday = Freitag
+weekday-saturday = Samstag
+weekday-sunday = Sonntag
+
+## Query history, with /history
+
+history-empty = In letzter Zeit wurden keine Benachrichtigungen gesendet.
+history-list = Das sind die { $count } neuesten Benachrichtigungen, die neueste zuerst. Nur die letzten { $limit } im Server werden gespeichert.
+history-title = Abfrageverlauf
+history-line-one = <t:{ $sent }:R> <@{ $author }> { $query }: { $count } Mitglied
+history-line-other = <t:{ $sent }:R> <@{ $author }> { $query }: { $count } Mitglieder
+
+## Pausing queries, with /pause and /resume
+
+paused = Abfragen sind jetzt pausiert, daher wird niemand erwähnt, bis jemand `/resume` ausführt.
+already-paused = Abfragen wurden bereits von <@{ $author }> pausiert.
+resumed = Abfragen werden jetzt wieder bearbeitet.
+not-paused = Abfragen sind nicht pausiert.
+
+## Members' preferences, with /preferences, /optout, /optin, /dnd and /privacy
+
+preferences-dm-on = Du wirst jetzt per DM benachrichtigt, statt erwähnt zu werden, wenn eine Abfrage auf dich zutrifft. Wenn Intersection dir keine DM senden kann, wirst du wie gewohnt erwähnt.
+preferences-dm-off = Du wirst jetzt im Kanal erwähnt, wenn eine Abfrage auf dich zutrifft.
+opted-out = Abfragen in diesem Server benachrichtigen dich nicht mehr, auch wenn sie auf dich zutreffen. Führe `/optin` aus, um wieder benachrichtigt zu werden.
+opted-in = Abfragen in diesem Server benachrichtigen dich jetzt wieder, wenn sie auf dich zutreffen.
+not-opted-out = Du hast dich nicht abgemeldet, daher benachrichtigen dich Abfragen in diesem Server bereits.
+quiet-hours-off = Du hast in diesem Server keine Ruhezeiten mehr.
+quiet-hours-invalid = Ruhezeiten werden als zwei 24-Stunden-Zeiten geschrieben, wie `22:00-08:00`, und Zeitzonen als Abweichung von UTC, wie `UTC+2` oder `-05:30`. Zeitzonennamen wie `Europe/Berlin` werden nicht unterstützt.
+quiet-hours-set = Abfragen in diesem Server benachrichtigen dich während { $hours } nicht mehr, es sei denn, sie werden von einem Mitglied einer seiner Notfallrollen gesendet. Führe `/dnd off` aus, um keine Ruhezeiten mehr zu haben.
+privacy-deleted = Dein Abfrageverlauf, deine Einstellungen und deine Nachrichtenaktivität wurden in allen Servern gelöscht. Auch Abmeldungen und Ruhezeiten wurden gelöscht, daher benachrichtigen dich Abfragen wieder.
+
+## Usage statistics, with /stats
+
+stats-title = Nutzungsstatistik
+stats-queries = Ausgeführte Abfragen
+stats-members-pinged = Erwähnte Mitglieder
+stats-largest-query = Größte Abfrage
+stats-members = { $count } Mitglieder
+stats-most-used-roles = Meistgenutzte Rollen
+stats-no-roles = Noch keine
+stats-since = Gezählt seit
+stats-footer = Gezählt werden nur Abfragen, die jemanden erwähnt haben.
+
+## /ping and /version
+
+ping = Ping?
+pong = Pong :ping_pong:! (Hin und zurück: { $round-trip }ms. Heartbeat: { $heartbeat }.)
+heartbeat-unknown = unbekannt
+version-built = Intersection v{ $version }{ $git }, kompiliert mit { $rustc } für { $target } ({ $profile }-Build) am <t:{ $epoch }:F> (<t:{ $epoch }:R>)
+version-git = (git { $commit })
+version-git-dirty = (git { $commit }, veränderter Quellcode)
+version-powered-by = Angetrieben von:
+version-unknown = (unbekannte Version)
+
+## Debugging queries, with /debug
+
+debug-no-chunks = Es wurden keine Teile gefunden.
+debug-chunks = { $count } Teile gefunden:
+debug-token-error = Fehler: { $error }
+debug-no-tokens = Es wurden keine Tokens gelesen.
+debug-tokens = { $count } Tokens gelesen:
+debug-parse-error = Beim Lesen ist ein Fehler aufgetreten:
+debug-parsed = Erfolgreich gelesen:
+debug-chunk-error = Fehler beim Lesen von Teil { $chunk }
+debug-reduced = Erfolg! Der resultierende AST, nach der Optimierung:
+debug-format-error = Beim Lesen ist ein Fehler aufgetreten:
+debug-formatted = Formatiert:
+debug-can-mention = { $member } kann { $role } in { $channel } erwähnen, denn eine dieser Prüfungen reicht aus:
+debug-cannot-mention = { $member } kann { $role } in { $channel } nicht erwähnen, denn eine dieser Prüfungen würde ausreichen:
+role-check-administrator = der Nutzer ist Administrator
+role-check-mentionable = die Rolle kann von allen erwähnt werden
+role-check-guild-mention-everyone = der Nutzer kann alle erwähnen
+role-check-channel-mention-everyone = der Nutzer kann in diesem Kanal alle erwähnen
+bench-summary = { $chunks } Teile zu { $members } Mitgliedern ausgewertet, als { $mentions } Erwähnungen:
+bench-calls = Aufrufe des Resolvers:
+bench-no-calls = Keine
+
+## The pages of /about, in Discord's markdown
+
+about-landing =
+    **Ich wurde gerade von Intersection erwähnt, was bedeutet das?**
+
+    Das ist wahrscheinlich die häufigste Frage, die wir bekommen. Hoffentlich wird hier alles klarer! Ansonsten verlinke ich dir Quellen, falls du mehr über Intersection erfahren möchtest.
+
+    Wenn Intersection dich erwähnt hat, will der Bot nichts von dir. Ganz im Gegenteil: Wenn Intersection dich erwähnt, hat jemand anderes den Bot _benutzt_, um dir eine Benachrichtigung zu schicken. Die Nachricht, in der du erwähnt wurdest, sollte eine Antwort auf eine andere Nachricht sein (die wahrscheinlich `@{"{"} ... {"}"}` enthält). Stell dir einfach vor, du wärst in dieser Nachricht erwähnt worden – das ist eine der wichtigsten (aber verwirrendsten) Funktionen von Intersection. Intersection erweitert die @-Erwähnungen von Discord und ermöglicht in anspruchsvolleren Situationen mächtigere Erwähnungen. Discord bietet dafür keine direkte Möglichkeit, also erwähnt Intersection die nötigen Personen einfach in einer Antwort.
+
+    Kurz gesagt: Sieh dir die Nachricht an, auf die Intersection geantwortet hat, als du erwähnt wurdest. Von dort kam die Erwähnung. Beschwer dich dort, nicht bei uns.
+
+    **Möchtest du mehr über das Intersection-Projekt erfahren?** Führe { $about-intersection } aus!
+
+about-intersection-1 =
+    :wave: **Hallo, ich bin Intersection!** Massen-Pings, aber gezielt 😏
+
+    Intersection ist ein Discord-Bot, der moderne und fortgeschrittene Werkzeuge für die Verwaltung von Discord-Servern bieten möchte, genauer gesagt im Bereich der @-Erwähnungen.
+
+    Die meisten Discord-Nutzer wissen, was eine Erwähnung ist. Umgangssprachlich „Ping“ genannt, sind sie ein Weg, die Aufmerksamkeit eines Mitglieds (oder einer Gruppe von Mitgliedern) zu bekommen. Das ist eine Erwähnung: <@{ $bot }> (oh, das bin ja ich!)
+
+    Discord kann auch Rollen erwähnen – Gruppen von Nutzern. In den Communitys, die ich betrieben habe, wollte ich das aber oft noch weiter eingrenzen.
+
+    Zum Beispiel:
+
+    -   Wie erwähne ich jeden Admin, der _online_ ist?
+    -   Wie erwähne ich alle _ohne_ eine bestimmte Rolle?
+    -   Wie erwähne ich alle mit _zwei_ bestimmten Rollen?
+
+    Discord bietet dafür keinen einfachen Weg. Um das möglich zu machen, übernimmt Intersection Konzepte aus einem Teilgebiet der Mathematik, der Mengenlehre, und wendet sie auf Discord-Erwähnungen an. So kannst du komplexere Abfragen in einem Format schreiben, das wir DRQL nennen – die Discord Role Query Language.
+
+    Mit DRQL könnten wir die Abfragen oben so beschreiben:
+
+    -   `@{"{"} admins & here {"}"}` – die **Schnittmenge** (englisch „intersection“) der Rolle `admins` und `here` (alle, die Admin und online sind – daher hat Intersection seinen
+        Namen!)
+    -   `@{"{"} everyone - "my role" {"}"}` – die **Differenz** der Rolle `everyone` und `my role`
+    -   `@{"{"} "role 1" & "role 2" {"}"}` – noch eine Schnittmenge!
+
+    ...
+
+about-intersection-2 =
+    ...
+    Das wirkt anfangs vielleicht sehr kompliziert, und das ist es auch! Selbst für Leute mit viel Discord-Erfahrung hat Intersection eine steile Lernkurve! Es hilft, wenn du schon Erfahrung mit Mengenlehre, boolescher Logik und der Syntax von DRQL hast.
+
+    Wegen der steilen Lernkurve bieten wir dir verschiedene Hilfen, mit denen du lernen kannst, Intersection zu benutzen:
+
+    -   Eine einfache Erklärung der Mengenlehre und wie Intersection sie nutzt: { $about-set-theory }
+    -   Eine Erklärung, wie man DRQL benutzt: { $about-drql }
+
+    Wir arbeiten an einer geführten Einführung für dich! Halte hier Ausschau, wann sie verfügbar ist.
+
+    **Interessierst du dich für das Innenleben? Vielleicht möchtest du zu Intersection beitragen?** Sieh dir { $about-how-it-works } an! Wir freuen uns über deine Hilfe.
+
+about-set-theory =
+    :question: **Was ist Mengenlehre, und wie nutzt Intersection sie?**
+
+    Hinweis: Ich bin kein Mathematiker, und das ist nur eine einfache Erklärung nach meinem Wissen. Falls jemand hier etwas korrigieren möchte, lass es mich bitte wissen! Das soll nur eine einfache Erklärung sein.
+
+    Jetzt kommt der lästige Teil. Die Theorie. Die _Mengen_lehre. (dam dam dammmmmm)
+
+    In der Mengenlehre betrachtet man Dinge als Mengen – Gruppen von Dingen. Stell dir einfach einen zufälligen Haufen bunter Steine vor. Das ist kein _Haufen_ Steine, sondern eine _Menge_ davon!
+
+    Das ist aber Mathematik, und so abstrakt ist es schwer. Mengen sind schon ein abstraktes Konzept. Denken wir stattdessen an Zahlen. Mengen von Zahlen.
+
+    Hier sind zwei Mengen:
+
+    A: `{"{"} 1, 2, 3, 7, 9 {"}"}`
+    B: `{"{"} 8, 2, 4, 6, 5 {"}"}`
+
+    Jetzt können wir uns ein paar Operationen überlegen, die wir auf diese Mengen anwenden können. Die drei wichtigsten Operationen der Mengenlehre sind:
+
+    -   **Vereinigung:** Die resultierende Menge enthält alle Elemente von A _oder_ B (geschrieben als A ∪ B)
+    -   **Schnittmenge:** Die resultierende Menge enthält alle Elemente, die in A _und_ B sind (geschrieben als A ∩ B)
+    -   **Differenz:** Die resultierende Menge enthält alle Elemente von A, die _nicht_ in B sind (viele Schreibweisen, meist A \ B oder A - B)
+
+    Das ist mit Text schwer zu erklären. Such kurz im Internet nach ein paar Grafiken dazu.
+
+    Jedenfalls sind die Vereinigung, Schnittmenge und Differenz unserer beiden Beispielmengen oben:
+
+    -   A ∪ B = `{"{"} 1, 2, 3, 7, 9, 8, 4, 6, 5 {"}"}`
+    -   A ∩ B = `{"{"} 2 {"}"}`
+    -   A \ B = `{"{"} 1, 3, 7, 9 {"}"}`
+    -   B \ A = `{"{"} 8, 4, 6, 5 {"}"}`
+
+    Jetzt, wo du Vereinigung, Schnittmenge und Differenz hoffentlich verstehst, macht Intersection Folgendes:
+
+    -   Statt einer Markierung an Nutzern ist eine Rolle eine Menge von Nutzern.
+    -   Ein Nutzer selbst kann als Menge dargestellt werden, die nur diesen Nutzer enthält.
+    -   Diese Mengenoperationen können auf Rollen und Nutzer angewendet werden, um fortgeschrittene Erwähnungen zu erstellen.
+
+    Jetzt ist es Zeit, die Syntax von DRQL kennenzulernen: { $about-drql }
+
+about-drql-1 =
+    :question: **Was ist DRQL, und wie benutze ich es?**
+
+    DRQL, die Discord Role Query Language, ist eine einfache, an der Mengenlehre orientierte Abfragesprache, die für Intersection entworfen wurde.
+
+    **Brauchst du eine Auffrischung zur Mengenlehre?** Lies { $about-set-theory }.
+
+    Zur Erinnerung: Intersection funktioniert, indem es Rollen als Mengen von Nutzern darstellt. Mit diesem Wissen ist DRQL eine sehr einfache Sprache, mit der wir Abfragen darstellen und verarbeiten können.
+
+    DRQL hat ein paar grundlegende „primäre“ Typen, und zwar:
+
+    -   Zeichenketten oder einfache Namen: `abc` oder `"abc"` – sie stehen für den Namen eines **Nutzers** oder einer **Rolle**. Namen können Buchstaben aus jeder Sprache und Emojis enthalten, aber um Leerzeichen oder andere Symbole müssen Anführungszeichen stehen (schreibe `\"` für ein Anführungszeichen darin). `everyone` und `here` stehen für alle bzw. nur die Personen, die online sind. `online`, `idle`, `dnd` und `offline` sind die Mitglieder mit diesem Status. `boosters` sind die Booster des Servers.
+    -   IDs: `{ $bot }` – sie stehen für die ID eines Nutzers oder einer Rolle.
+    -   Direkte Erwähnungen: <@{ $bot }> – du kannst einen Nutzer oder eine Rolle direkt @-erwähnen, statt die ID zu schreiben. Das wird nicht empfohlen, da der Nutzer so doppelt erwähnt werden kann; IDs oder Namen sind besser. Das ist nur in dem EXTREM seltenen Fall nötig, dass ein Nutzer und eine Rolle dieselbe ID haben.
+
+    Mit diesen Typen kannst du unsere binären Infix-Operatoren verwenden:
+
+    -   `A + B` oder `A | B`: **Vereinigung**: A ∪ B
+    -   `A & B`: **Schnittmenge**: A ∩ B
+    -   `A - B`: **Differenz**: A \ B
+
+    Und den Präfix-Operator `!A` oder `~A`: **Komplement**, alle außer A, also ist `!afk` jeder ohne die Rolle `afk`.
+
+    Auch hier lohnt es sich vielleicht, etwas über Mengenlehre nachzulesen, um das zu verstehen.
+
+    DRQL-Abfragen werden in deiner Nachricht automatisch erkannt. Schließe sie in `@{"{"} ... {"}"}` ein, damit Intersection sie abfragt! Wenn du den Text `@{"{"} ... {"}"}` wörtlich brauchst, füge einen Backslash ein: `@\{"{"} ... {"}"}`
+    ...
+
+about-drql-2 =
+    ...
+
+    ## Beispiele
+
+    Alle mit der Rolle `cool person`, die nicht `staff` sind: `@{"{"} "cool person" - staff {"}"}`
+
+    Alle `mods`, die online sind: `@{"{"} mods & here {"}"}`
+
+    Bei Rollennamen kommt es nicht auf Groß- und Kleinschreibung an, daher findet `mods` auch eine Rolle namens `Mods`, es sei denn, es gibt eine, die genau `mods` heißt. Servermanager können das mit `/config case_sensitive_roles` abschalten.
+
+    ## Wörter
+
+    Die Operatoren können auch als Wörter geschrieben werden: `A or B`, `A and B`, `A minus B` und `not A`. `me` bist du, also erwähnt `@{"{"} raiders - me {"}"}` alle anderen Raider. Endet eine Abfrage mit `limit` und einer Zahl, wie `@{"{"} helpers limit 25 {"}"}`, werden höchstens so viele ihrer Mitglieder erwähnt. Um eine Rolle oder ein Mitglied zu verwenden, das wie eines dieser Wörter heißt, setze es in Anführungszeichen, wie `"me"`.
+
+    ## Kanäle
+
+    Erwähne einen Kanal in einer Abfrage (tippe `#` und wähle ihn aus), um die Mitglieder abzufragen, die mit ihm verbunden sind, wenn es ein Sprachkanal ist, seine Mitglieder, wenn es ein Thread ist, und sonst die Mitglieder, die ihn sehen können. `in_voice` sind alle, die mit irgendeinem Sprachkanal verbunden sind.
+
+    ## Events
+
+    `event(...)` mit der ID eines geplanten Events oder einem Link dazu sind alle, die daran interessiert sind, wie `@{"{"} event(1234) & here {"}"}`.
+
+    ## Berechtigungen
+
+    `perm(...)` mit dem Namen einer Berechtigung sind alle Mitglieder, die sie haben, wie `@{"{"} perm(manage_messages) - offline {"}"}`.
+
+about-drql-3 =
+    ...
+
+    ## Beitrittsdaten
+
+    `joined_before(...)` und `joined_after(...)` nehmen ein Datum, wie `"2024-01-01"`, oder eine vergangene Zeitspanne, wie `30d` (`w`, `d`, `h`, `m` und `s` funktionieren), und treffen auf die Mitglieder zu, die davor oder danach beigetreten sind.
+    `account_age(<7d)` und `account_age(>7d)` treffen auf die Mitglieder zu, deren Konten jünger oder älter als das sind.
+
+    ## Namen
+
+    `name(...)` sind alle Mitglieder, deren Nutzername oder Spitzname auf ein Muster passt, wie `name("*dev*")` (wobei `*` beliebig viel und `?` ein beliebiges Zeichen ist) oder einen regulären Ausdruck wie `name(/^team-(red|blue)/)`.
+
+    ## Aktivität
+
+    `active(...)` mit einer Zeitspanne, wie `active(7d)`, sind alle, die in dieser Zeit eine Nachricht gesendet haben, auch wenn sie offline erscheinen. Es zählen nur Nachrichten, die gesendet wurden, während Intersection da war.
+
+    ## Reaktionen
+
+    `reacted(...)` mit einem Link zu einer Nachricht und einem Emoji sind alle, die mit diesem Emoji auf die Nachricht reagiert haben, wie `@{"{"} reacted(https://discord.com/channels/1/2/3, ✅) - here {"}"}`.
+
+    ## Zählen
+
+    `atleast(...)` mit einer Zahl und einigen Abfragen sind alle Mitglieder von mindestens so vielen davon, wie `@{"{"} atleast(2, red, green, blue) {"}"}`.
+
+    ## Stichproben
+
+    `sample(...)` mit einer Abfrage und einer Zahl sind so viele zufällig ausgewählte Mitglieder der Abfrage, wie `@{"{"} sample(volunteers - offline, 5) {"}"}`.
+
+    ## Aliase
+
+    `$` und ein Name, wie `$oncall`, ist die Abfrage, die die Moderatoren des Servers mit `/alias add` so benannt haben, wie `@{"{"} $oncall & here {"}"}`. `/alias list` zeigt sie alle, und `/alias export` und `/alias import` kopieren sie auf einen anderen Server.
+
+    ## Rangfolge
+
+    Alle Operatoren werden von links nach rechts gelesen. Mit Klammern kannst du das selbst festlegen. `A & B & C` wird als `(A & B) & C` gelesen. `!` wird vor allem anderen angewendet, also ist `!A & B` gleich `(!A) & B`.
+
+    ## Interna (für Nerds)
+
+    Hier erfährst du mehr darüber, wie das alles funktioniert: { $about-how-it-works }
+    Wenn du dich für Parsing-Algorithmen interessierst, freuen wir uns über deine Hilfe!
+
+about-how-it-works =
+    :question: **Wie funktioniert Intersection?**
+
+    Intersection ist eine Sammlung sorgfältig handgefertigter Software, die zusammen das Endergebnis ergibt: den Discord-Bot Intersection.
+
+    Welche Version von Intersection gerade läuft, siehst du am Ergebnis von { $version }!
+
+    Intersection besteht aus ein paar Kernkomponenten, nämlich dem Discord-Bot selbst und der DRQL-Logik. Der Discord-Bot ist in [Rust](https://www.rust-lang.org/) mit den Frameworks [Serenity](https://crates.io/crates/serenity) und [Poise](https://crates.io/crates/poise) geschrieben, und die DRQL-Logik ist in ein paar Teile aufgeteilt:
+
+    -   DRQL-Frontend (Parsing): ein von [Logos](https://crates.io/crates/logos) generierter Lexer und ein von [LALRPOP](https://crates.io/crates/lalrpop) generierter LR(1)-Parser
+    -   DRQL-Backend: komplett handgemacht!
+
+    Welche Versionen all dieser wichtigen Abhängigkeiten verwendet wurden, siehst du mit dem oben genannten Versionsbefehl.
+
+    ## DRQL-Abfragen
+
+    Wenn du eine DRQL-Abfrage ausführst, wird sie durch den DRQL-Parser geschickt und ein abstrakter Syntaxbaum erzeugt. Dieser Baum stellt die Abfrage, die du geschrieben hast, direkt dar, und du kannst ihn dir sogar mit dem Befehl { $debug-parse-one } ansehen!
+
+    Die Werte in diesem Baum werden dann zu Mengen aufgelöst, aus denen das Ergebnis berechnet wird.
+
+    Dann folgt einer der kompliziertesten Schritte von Intersection: die Optimierung. Discord-Server haben bereits Rollen, und statt 10.000 verschiedene Personen zu erwähnen, warum nicht diese nutzen? Intersection versucht nach bestem Vermögen, die Ergebnismenge als Vereinigung einiger Rollen deines Servers darzustellen und stattdessen diese zu erwähnen. Das wird nie perfekt sein, und in den meisten Fällen wird es Ausreißer geben.
+
+    ## Mitmachen
+
+    Intersection ist komplett Open Source! Du kannst [unser Repository auf GitHub öffnen]({ $repository }), um zu sehen, wie du helfen kannst!
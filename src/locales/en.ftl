@@ -0,0 +1,703 @@
+# English messages, which every other language falls back to.
+#
+# Messages ending in -one are used instead of the matching -other message when a count is 1.
+
+## Evaluation
+
+evaluating = Evaluating your query... (resolved { $resolved }/{ $total } roles and members)
+
+## Large query confirmation
+
+confirm-mention-count =
+    **Hold up!** By running this query, you are about to mention { $count } people.{ $messages } Are you sure?
+
+    This will mention { $summary }.{ $requirement }
+confirm-mention-count-messages = {" "}This will require the sending of { $count } messages.
+confirm-mention-count-approval = {" "}As it's so large, a member of { $role } other than you has to confirm it.
+confirm-mention-count-typed = {" "}As it's so large, you have to type the number of people it will mention to confirm it.
+confirm-yes = Yes
+confirm-typed = Type to confirm
+typed-confirmation-title = Mention { $count } people?
+typed-confirmation-label = Type { $count } to confirm
+typed-confirmation-mismatch = That's not { $count }, so nobody was mentioned.
+cancel = Cancel
+cancelled = Cancelled.
+confirmed = Confirmed.
+approved-by = Approved by { $approver }.
+confirmation-timed-out = Timed out waiting for confirmation.
+confirmation-reminder = *Still waiting for you to confirm. This expires in { $left }.*
+
+## Duplicate queries
+
+duplicate-query-confirm =
+    **Hold up!** { $query } was already sent in this channel { $ago } ago, so sending it again will notify the same members again. Are you sure?
+duplicate-query-send = Send again
+duplicate-query-refused = { $query } was already sent in this channel { $ago } ago. Wait a while before sending it again.
+
+## Incomplete results
+
+incomplete-evaluation-one =
+    **Hold up!** Your query's results are incomplete: looking up { $operands } timed out, so it was treated as matching nobody. Without it, your query matches { $members } members. You can retry the query, or continue with these partial results.
+incomplete-evaluation-other =
+    **Hold up!** Your query's results are incomplete: looking up { $operands } timed out, so they were treated as matching nobody. Without them, your query matches { $members } members. You can retry the query, or continue with these partial results.
+incomplete-evaluation-retry = Retry
+incomplete-evaluation-continue = Use partial results
+incomplete-evaluation-retrying = Retrying your query...
+incomplete-evaluation-continuing = Continuing with the partial results.
+
+## Unmentionable roles
+
+unmentionable-roles-one =
+    **Hold up!** You can't mention { $roles }: it is not mentionable and you do not have the "Mention everyone, here, and All Roles" permission. You can mention its members individually instead, or list the members of your query without mentioning anyone.
+unmentionable-roles-other =
+    **Hold up!** You can't mention { $roles }: they are not mentionable and you do not have the "Mention everyone, here, and All Roles" permission. You can mention their members individually instead, or list the members of your query without mentioning anyone.
+unmentionable-roles-list = List without mentioning
+unmentionable-roles-expand = Mention members
+unmentionable-roles-expanding = Mentioning the members of those roles individually.
+unmentionable-roles-listing = Listing members without mentioning anyone.
+
+## Mention summaries, like "@everyone, @Staff, and 3 individual members"
+
+summary-more-roles = { $count } more roles
+summary-individual-members-one = { $count } individual member
+summary-individual-members-other = { $count } individual members
+list-two = { $first } and { $second }
+list-many = { $rest }, and { $last }
+
+## Notifications
+
+results-for = **Results for** { $query }:
+no-users-matched = No users matched.
+do-not-ping-excluded-one = { $count } member was left out, as they have this server's do-not-ping role.
+do-not-ping-excluded-other = { $count } members were left out, as they have this server's do-not-ping role.
+opted-out-excluded-one = { $count } member was left out, as they opted out of being notified in this server.
+opted-out-excluded-other = { $count } members were left out, as they opted out of being notified in this server.
+quiet-hours-excluded-one = { $count } member was left out, as they're in their quiet hours.
+quiet-hours-excluded-other = { $count } members were left out, as they're in their quiet hours.
+notified-via-dm = { $count } members notified via DM.
+already-mentioned-one = { $count } of these members was already mentioned above.
+already-mentioned-other = { $count } of these members were already mentioned above.
+notification-header = Notification triggered by Intersection.
+what-is-this = :question: **What is this?** Run { $command } for more information.
+listing-header = Members matched by this query (nobody was notified):
+split-notification-prefix = Notification triggered by Intersection.
+split-listing-prefix = Listing members matched by this query (nobody will be notified).
+split-sending = Please wait, sending { $count } messages...
+split-sending-progress = Please wait, sending { $count } messages... ({ $sent } sent)
+split-sent = Sent { $count } messages.
+split-part = (part { $part }/{ $total })
+split-start = [Jump to the start of this notification]({ $link })
+split-notification-done = Notification triggered successfully.
+split-listing-done = Finished listing members.
+notification-stats = { $query } matched { $members } via { $roles }.
+count-members-one = { $count } member
+count-members-other = { $count } members
+count-roles-one = { $count } role
+count-roles-other = { $count } roles
+mention-cap-exceeded = Your query matches { $count } members, but this server only allows mentioning { $limit } members at once. Try narrowing it down, e.g. by intersecting it with another role: `@{"{"}your query & role{"}"}`.
+
+## Embeds
+
+embed-title = Notification triggered by Intersection
+field-query = Query
+field-author = Author
+field-channel = Channel
+field-members = Members
+field-roles-mentioned = Roles mentioned
+field-individual-mentions = Individual mentions
+field-notified-via-dm = Notified via DM
+field-already-mentioned = Already mentioned above
+field-members-added-to-thread = Members added to thread
+field-approved-by = Approved by
+field-roles-used = Roles used
+field-messages = Messages
+
+## Threads
+
+thread-adding = Adding { $count } members to { $thread }...
+thread-adding-progress = Adding { $count } members to { $thread }... ({ $added } done)
+thread-added = Added { $count } members to { $thread } instead of mentioning them.
+
+## Direct messages
+
+dm-notification = { $author } mentioned you with { $query } in { $link }
+
+## Notifications interrupted by a restart
+
+interrupted-resumed = Finished sending [this notification]({ $link }), which was interrupted by a restart.
+interrupted-abandoned-one =
+    { $author }, [your notification]({ $link }) was interrupted by a restart before its last message was sent, so some members weren't mentioned. Run your query again to mention them.
+interrupted-abandoned-other =
+    { $author }, [your notification]({ $link }) was interrupted by a restart before its last { $count } messages were sent, so some members weren't mentioned. Run your query again to mention them.
+
+## Scheduled queries
+
+scheduled-announcement = { $author } scheduled this notification for { $query }.
+scheduled-missed = { $author }, your notification for { $query }, scheduled for { $at }, was missed while Intersection was offline. Schedule it again with `/schedule run`.
+scheduled-missed-recurring = { $author }, your notification for { $query }, scheduled for { $at }, was missed while Intersection was offline. It will run again at { $next }.
+
+## Audit log
+
+audit-title = Notification sent
+audit-jump = [Jump to notification]({ $link })
+
+## Errors
+
+error-send-failed =
+    { $error }
+    Additionally, we attempted to send this error to you but this failed: { $send-error }
+cooldown-author = You've sent too many notifications recently. Try again in { $wait }.
+cooldown-guild = Too many notifications have been sent in this server recently. Try again in { $wait }.
+wait-seconds-one = { $count } second
+wait-seconds-other = { $count } seconds
+wait-minutes-one = { $count } minute
+wait-minutes-other = { $count } minutes
+access-denied = You aren't allowed to run queries in this server. Only members with { $allowed } can.
+access-permission = the "{ $permission }" permission
+list-or = { $first } or { $second }
+queries-paused = Queries are paused in this server, so nobody was mentioned. They were paused by { $user }, and can be resumed with `/resume`.
+
+## Query errors
+
+internal-error = Something went wrong on our side (error `{ $id }`).
+command-error = Error: { $error }
+no-query = There is no DRQL query in your message to handle.
+dm-queries-unavailable = DRQL queries are not available in DMs.
+server-channels-only = DRQL queries can only be used in server channels.
+everyone-here-refused = `{ $literal }` can't be used in queries in this server.{ $explanation }
+too-many-nodes = Your query is too long to evaluate: it can use at most { $limit } roles, members, operators and functions. Try splitting it up into smaller queries?
+too-deep = Your query is nested too deeply to evaluate: it can only go { $limit } levels deep. Try removing some parentheses?
+
+## Parse errors, like "Unexpected `)`, expected `(` or a name"
+
+parse-error = Error parsing chunk { $chunk }: { $error }
+parse-unknown-token = Unknown token
+parse-ended-early = Your query ended too early{ $expected }
+parse-unexpected = Unexpected `{ $token }`{ $expected }
+parse-extra-token = Unexpected `{ $token }` after the end of the query
+parse-unknown-character = Unknown character `{ $char }`
+parse-unterminated-string = This string literal is never closed
+parse-invalid-number = Invalid number: { $error }
+parse-invalid-function = Invalid function call: `{ $call }`
+parse-expected = , expected { $expected }
+terminal-name = a name
+terminal-id = a number or ID
+terminal-duration = a duration
+terminal-member = a member
+terminal-role = a role
+terminal-channel = a channel
+terminal-event-link = an event link
+terminal-message-link = a message link
+terminal-emoji = an emoji
+terminal-regex = a regex
+terminal-alias = an alias
+
+## Aliases
+
+alias-unknown = `${ $name }` isn't an alias in this server.
+alias-cycle = `${ $name }` can't be expanded, as it uses itself: `{ $cycle }`.
+alias-invalid = `${ $name }` doesn't stand for a valid query: { $error }
+alias-too-large = The query's aliases expand to more than { $limit } operators and operands.
+
+## Resolving the operands of queries
+
+resolve-unknown-channel = Unable to resolve channel <#{ $id }>
+resolve-everyone-permission = You do not have the "Mention everyone, here, and All Roles" permission required to use the role { $role }.
+resolve-ambiguous = Found { $members } member(s) and { $roles } role(s) that matched your query for "{ $name }". Please narrow your query or use the ID of the object you are referring to instead.
+resolve-ambiguous-members = Found { $count } members that matched your query for "{ $name }". Please narrow your query: it may help to use the user's ID, or add their discriminator, like "luna..♡#9082" instead of "luna..♡".
+resolve-ambiguous-roles = Found { $count } roles that matched your query for "{ $name }". Please narrow your query: it may help to use a role ID instead.
+resolve-unknown-name = Unable to find a role or member with the name { $name }.{ $case-sensitive } Try using the ID instead?
+resolve-case-sensitive = {" "}Searches for roles in this server are case sensitive!
+resolve-invalid-id = { $id } is not a valid role or member ID
+resolve-unknown-id = Unable to resolve role or member ID: { $id }
+resolve-unknown-role = Unable to resolve role with ID { $id }
+resolve-private-thread = You can only use the private thread <#{ $id }> in a query if you're in it.
+resolve-unusable-channel = <#{ $id }> is not a text or voice channel or a thread, so it can't be used in a query.
+resolve-unknown-event = Unable to find the scheduled event with ID { $id }
+resolve-invalid-pattern = Unable to use the pattern { $pattern }: { $error }
+resolve-foreign-message = { $link } is a message in another server, so its reactions can't be used in a query.
+resolve-unreadable-reactions = You can only use reactions to { $link } in a query if you can read <#{ $channel }>.
+resolve-unknown-reactions = Unable to find { $emoji } reactions on { $link }
+
+## Configuration
+
+language-set = Intersection will now send messages in English.
+config-silent-on = Mentions will now be sent silently. Members will see the mention, but won't receive a push notification.
+config-silent-off = Mentions will now notify members as usual. Start your message with `@silent` to send the mentions for a single query silently.
+config-slash-only-on = Queries in messages will now be ignored, so `@{"{"}{"}"}` can be written freely. Only slash commands like `/dry_run` will run them.
+config-slash-only-off = Queries in messages will now be run again.
+config-per-chunk-on = Each query in a message will now be mentioned in its own group, labelled with the query.
+config-per-chunk-off = All queries in a message will now be mentioned together.
+config-embed-on = Notifications will now be explained in an embed with the query, author, and counts, leaving just the mentions in the message.
+config-embed-off = Notifications will now be explained in the message text.
+config-delivery-reply = Notifications will now be sent by Intersection.
+config-delivery-webhook = Notifications will now be sent with the name and avatar of the member who triggered them. This requires Intersection to have the "Manage Webhooks" permission in the channel; without it, notifications are sent by Intersection as usual.
+config-delivery-thread = Instead of being mentioned, members will now be added to a thread started from the query, which notifies them more gently and keeps the discussion in one place.
+cooldown-scope-author = each member
+cooldown-scope-guild = this server
+config-cooldown-set = At most { $count } notifications can now be sent by { $who } every { $minutes } minutes.
+config-cooldown-unset = There is no longer a limit on the notifications sent by { $who }.
+config-cooldown-bypass-on = Members of { $role } are now exempt from cooldowns.
+config-cooldown-bypass-off = Members of { $role } are no longer exempt from cooldowns.
+config-emergency-role-on = Queries sent by members of { $role } will now notify members during their quiet hours too.
+config-emergency-role-off = Queries sent by members of { $role } will no longer notify members during their quiet hours.
+config-max-mentions-set = Queries matching more than { $limit } members will now be refused, even if the author would confirm them.
+config-max-mentions-unset = There is no longer a limit on how many members a query may match.
+config-access-unrestricted = Anyone can now run queries.
+config-query-role-on = Members of { $role } can now run queries.
+config-query-role-off = Members of { $role } can no longer run queries.
+config-query-permission-set = Members with the "{ $permission }" permission can now run queries.
+config-query-permission-unset = Only members of the allowed roles can now run queries.
+config-channels-denylist = Queries will now be processed everywhere except the listed channels.
+config-channels-allowlist = Queries will now only be processed in the listed channels.
+config-channel-processed = Queries will now be processed in { $channel }.
+config-channel-ignored = Queries will no longer be processed in { $channel }.
+channels-all = Queries are processed in every channel.
+channels-except = Queries are processed in every channel except { $channels }.
+channels-none = Queries aren't processed in any channel. Add one with `/config channels add`.
+channels-only = Queries are only processed in { $channels }.
+config-audit-channel-set = A summary of every notification will now be posted to { $channel }.
+config-audit-channel-unset = Notifications will no longer be posted to an audit log channel.
+config-error-expiry-set = Explanations of mistakes in queries, like typos and unknown roles, will now be deleted after { $seconds } seconds, leaving a reaction on the query.
+config-error-expiry-unset = Explanations of mistakes in queries will now be kept.
+config-mention-expiry-set = Messages mentioning members will now be deleted { $minutes } minutes after they're sent. Members are notified when they're sent, so they won't miss anything, but they won't be able to scroll back to them.
+config-mention-expiry-unset = Messages mentioning members will now be kept.
+config-duplicates-allow = Queries can now be sent again as often as members like.
+config-duplicates-confirm = Members sending the same query in a channel within { $seconds } seconds of it last being sent there will now be asked to confirm they want to notify everyone again.
+config-duplicates-refuse = Queries sent in a channel within { $seconds } seconds of last being sent there will now be refused.
+config-approval-role-missing = Choose the role whose members can approve notifications.
+config-approval-set = Notifications to { $threshold } or more members now need approving by a member of { $role } other than their author.
+config-approval-unset = Notifications no longer need approving by a moderator.
+config-typed-confirmation-set = Authors of queries matching { $threshold } or more members will now have to type how many members they match to confirm them.
+config-typed-confirmation-unset = Large queries can now always be confirmed with a button.
+config-confirm-threshold = Authors of queries matching more than { $members } members will now have to confirm them.
+config-confirm-timeout = Authors will now have { $seconds } seconds to confirm large queries.
+config-confirm-timeout-reminder = Authors will now have { $seconds } seconds to confirm large queries, and be reminded halfway through.
+config-do-not-ping-set = Members of { $role } will now be left out of every query's results.
+config-do-not-ping-unset = Queries can now mention any member again.
+config-case-sensitive-on = Role names in queries now have to match the case of the role's name exactly.
+config-case-sensitive-off = Role names in queries now match roles whatever their case, so `mods` finds a role called "Mods". A role whose name matches exactly is still preferred.
+config-everyone-here-on = Members allowed to mention everyone can now use `everyone` and `here` in queries.
+config-everyone-here-off = Queries using `everyone` or `here` will now be refused, whoever sends them.
+config-prefix-invalid = A prefix can't be empty or contain spaces.
+config-prefix-set = Text commands now start with `{ $prefix }`, like `{ $prefix }dry_run staff & here` or `{ $prefix }members staff`. Messages starting with it are never treated as queries.
+config-prefix-unset = Text commands are now turned off. Use slash commands instead.
+config-reset = Every setting has been put back to its default. See them with `/config get`.
+
+## The settings shown by /config get
+
+config-title = Configuration
+config-footer = Change a setting with /config <setting>.
+setting-silent = Silent mentions
+setting-per-chunk = Each query mentioned separately
+setting-embed = Explained in an embed
+setting-slash-only = Slash commands only
+setting-delivery = Delivery
+setting-cooldown-author = Cooldown for each author
+setting-cooldown-guild = Cooldown for the whole server
+setting-cooldown-bypass = Exempt from cooldowns
+setting-max-mentions = Most mentions per query
+setting-query-role = Roles allowed to run queries
+setting-query-permission = Permission allowed to run queries
+setting-channels = Channels
+setting-audit-channel = Audit log channel
+setting-language = Language
+setting-error-expiry = Explanations of mistakes kept for
+setting-mention-expiry = Mention messages kept for
+setting-duplicate-queries = Queries sent again
+setting-approval = Moderator approval
+setting-typed-confirmation = Typed confirmation
+setting-confirmation = Confirmation
+setting-confirm-timeout = Time to confirm
+setting-do-not-ping = Never mentioned
+setting-emergency-role = Override quiet hours
+setting-case-sensitive-roles = Case-sensitive role names
+setting-everyone-here = Everyone and here allowed
+setting-aliases = Aliases
+setting-prefix = Text command prefix
+value-yes = yes
+value-no = no
+value-none = none
+value-any = any
+value-forever = forever
+value-no-limit = no limit
+value-never-needed = never needed
+value-rate-limit = { $count } per { $seconds } seconds
+value-seconds = { $seconds } seconds
+value-seconds-with-reminder = { $seconds } seconds, with a reminder halfway through
+value-minutes = { $minutes } minutes
+value-channels-all = every channel
+value-channels-except = every channel except { $channels }
+value-channels-none = no channels
+value-channels-only = only { $channels }
+value-delivery-reply = Reply from Intersection
+value-delivery-webhook = Webhook with the author's name and avatar
+value-delivery-thread = Thread with the members added to it
+value-duplicate-queries = { $mode } within { $seconds } seconds
+value-duplicates-allow = Allow them
+value-duplicates-confirm = Ask the author to confirm
+value-duplicates-refuse = Refuse them
+value-approval = { $threshold }+ members, by { $role }
+value-threshold = { $threshold }+ members
+value-confirmation = over { $members } members
+value-refused = no ({ $explanation })
+value-aliases = { $count }, listed by `/alias list`
+
+## Buttons under notifications
+
+recipients-button = Who was pinged?
+recipients-page =
+    This notification mentioned { $count } members (page { $page } of { $pages }):
+    { $list }
+recipients-expired = This notification is too old, so we no longer know who it mentioned.
+delete-button = Delete this ping
+delete-unknown-query = Unable to find the query for this notification.
+delete-forbidden = Only the author of the query or a moderator can delete this notification.
+delete-too-late = Notifications can only be deleted within { $minutes } minutes of being sent.
+deleted = Deleted { $count } messages sent for this notification.
+
+## Lists shown a page at a time
+
+page-previous = Previous
+page-next = Next
+page-footer = Page { $page } of { $pages }
+
+## Dry runs and member lists
+
+dry-run-timed-out = Looking up { $operands } timed out, so these results may be incomplete. Running the query will ask whether to retry or continue with the partial results.
+dry-run-breakdown = Each part of it matches { $parts }, { $operator }: { $total }.
+dry-run-header = Your query matches the following { $count } users:
+dry-run-unmentionable = You can't mention { $count } of the roles in your query, so you will be asked whether to mention their members individually or list them without mentioning anyone.
+dry-run-footer = This will require sending { $messages } messages. (optimized by pinging { $roles } roles, saving you { $saved } mentions).
+dry-run-summary-attached = Your query matches the { $count } users attached. This will require sending { $messages } messages (optimized by pinging { $roles } roles, saving you { $saved } mentions).
+dry-run-summary-below = Your query matches the { $count } users below. This will require sending { $messages } messages (optimized by pinging { $roles } roles, saving you { $saved } mentions).
+query-matches-none = Your query matches 0 users.
+query-excluded = { $count } more members were left out, as they have the server's do-not-ping role.
+members-below = Your query matches the { $count } users below.
+matched-members = Matched members
+
+## Aliases, with /alias
+
+alias-invalid-name = Alias names start with a letter or underscore, followed by letters, numbers, or underscores, like `oncall` or `team_2`.
+alias-limit = This server already has { $limit } aliases. Remove one with `/alias remove` first.
+alias-added = Queries in this server can now use `${ $name }`, like `@{"{"}${ $name } & here{"}"}`.
+alias-removed = `${ $name }` has been removed, so queries using it will no longer work.
+alias-list-empty = This server has no aliases yet. Add one with `/alias add`.
+alias-list = This server has { $count } aliases.
+alias-list-title = Aliases
+alias-export-empty = This server has no aliases to export. Add one with `/alias add`.
+alias-exported = This server's { $count } aliases are attached. Add them to another server with `/alias import`.
+alias-imported = { $count } aliases have been imported. See them with `/alias list`.
+import-too-large = That file is too large. Files of aliases can be up to { $limit } KiB.
+import-not-json = That file isn't JSON.
+import-not-an-object = That file should be a JSON object of names and queries, like the ones `/alias export` makes.
+import-invalid-name = `{ $name }` can't be the name of an alias.
+import-not-a-query = `${ $name }` should stand for a query, as a string.
+import-query-too-long = `${ $name }` stands for a query longer than { $limit } characters.
+import-limit = Importing those would give this server more than { $limit } aliases. Remove some with `/alias remove` first.
+
+## Scheduled queries, with /schedule
+
+schedule-limit = This server already has { $limit } queries scheduled. Cancel one with `/schedule cancel` first.
+schedule-added = { $query } will be run in this channel at { $at } (scheduled query #{ $id }).
+schedule-added-recurring = { $query } will be run in this channel { $repeat }, starting { $at } (scheduled query #{ $id }).
+schedule-invalid-time = Write the time like `2024-06-01 18:00`, and the time zone as an offset from UTC, like `UTC+2` or `-05:00`.
+schedule-passed = { $at } has already passed.
+schedule-too-far = Queries can only be scheduled up to a year ahead.
+schedule-invalid-recurrence = Write when to run it like `every monday 17:00` or `every day 09:30`, and the time zone as an offset from UTC, like `UTC+2` or `-05:00`.
+schedule-list-empty = No queries are scheduled in this server. Schedule one with `/schedule run`.
+schedule-line = **#{ $id }** { $query } at { $at }, by <@{ $author }> in <#{ $channel }>
+schedule-line-recurring = **#{ $id }** { $query } at { $at }, { $repeat }, by <@{ $author }> in <#{ $channel }>
+schedule-list = { $count } queries are scheduled in this server. Cancel one with `/schedule cancel`.
+schedule-list-title = Scheduled queries
+schedule-unknown = No query is scheduled as #{ $id } in this server.
+schedule-cancel-forbidden = Only <@{ $author }>, who scheduled #{ $id }, or members with the Manage Server permission can cancel it.
+schedule-cancelled = #{ $id }, { $query }, has been cancelled.
+recurrence-daily = every day at { $time } (UTC{ $offset })
+recurrence-weekly = every { $day } at { $time } (UTC{ $offset })
+weekday-monday = Monday
+weekday-tuesday = Tuesday
+weekday-wednesday = Wednesday
+weekday-thursday = Thursday
+weekday-friday = Friday
+weekday-saturday = Saturday
+weekday-sunday = Sunday
+
+## Query history, with /history
+
+history-empty = No notifications have been sent recently.
+history-list = These are the { $count } most recent notifications, newest first. Only the last { $limit } in the server are remembered.
+history-title = Query history
+history-line-one = <t:{ $sent }:R> <@{ $author }> { $query }: { $count } member
+history-line-other = <t:{ $sent }:R> <@{ $author }> { $query }: { $count } members
+
+## Pausing queries, with /pause and /resume
+
+paused = Queries are now paused, so nobody will be mentioned until someone runs `/resume`.
+already-paused = Queries were already paused by <@{ $author }>.
+resumed = Queries will now be handled again.
+not-paused = Queries aren't paused.
+
+## Members' preferences, with /preferences, /optout, /optin, /dnd and /privacy
+
+preferences-dm-on = You will now be notified by DM instead of being mentioned when a query matches you. If Intersection can't DM you, you will be mentioned as usual.
+preferences-dm-off = You will now be mentioned in the channel when a query matches you.
+opted-out = Queries in this server will no longer notify you, even if they match you. Run `/optin` to be notified again.
+opted-in = Queries in this server will now notify you again when they match you.
+not-opted-out = You haven't opted out, so queries in this server already notify you.
+quiet-hours-off = You no longer have quiet hours in this server.
+quiet-hours-invalid = Quiet hours are written as two 24-hour times, like `22:00-08:00`, and time zones as offsets from UTC, like `UTC+2` or `-05:30`. Time zone names like `Europe/Berlin` aren't supported.
+quiet-hours-set = Queries in this server will no longer notify you during { $hours }, unless they're sent by a member of one of its emergency roles. Run `/dnd off` to stop having quiet hours.
+privacy-deleted = Your query history, preferences, and message activity have been deleted in every server. Any opt-outs and quiet hours were deleted too, so queries will notify you again.
+
+## Usage statistics, with /stats
+
+stats-title = Usage statistics
+stats-queries = Queries run
+stats-members-pinged = Members pinged
+stats-largest-query = Largest query
+stats-members = { $count } members
+stats-most-used-roles = Most used roles
+stats-no-roles = None yet
+stats-since = Counting since
+stats-footer = Only queries which mentioned someone are counted.
+
+## /ping and /version
+
+ping = Ping?
+pong = Pong :ping_pong:! (Round trip: { $round-trip }ms. Heartbeat: { $heartbeat }.)
+heartbeat-unknown = unknown
+version-built = Intersection v{ $version }{ $git }, compiled by { $rustc } for { $target } ({ $profile } build) on <t:{ $epoch }:F> (<t:{ $epoch }:R>)
+version-git = (git { $commit })
+version-git-dirty = (git { $commit }, dirty source tree)
+version-powered-by = Powered by:
+version-unknown = (unknown version)
+
+## Debugging queries, with /debug
+
+debug-no-chunks = No chunks were found.
+debug-chunks = Found { $count } chunks:
+debug-token-error = Error: { $error }
+debug-no-tokens = No tokens were lexed.
+debug-tokens = Lexed { $count } tokens:
+debug-parse-error = Encountered an error while parsing:
+debug-parsed = Successfully parsed:
+debug-chunk-error = Error parsing chunk { $chunk }
+debug-reduced = Success! Resulting AST, after optimizing:
+debug-format-error = Encountered an error while parsing:
+debug-formatted = Formatted:
+debug-can-mention = { $member } can mention { $role } in { $channel }, since any of these passing is enough:
+debug-cannot-mention = { $member } cannot mention { $role } in { $channel }, since any of these passing is enough:
+role-check-administrator = the user is an administrator
+role-check-mentionable = the role is mentionable by all
+role-check-guild-mention-everyone = the user can mention everyone
+role-check-channel-mention-everyone = the user can mention everyone in this channel
+bench-summary = Evaluated { $chunks } chunks to { $members } members, as { $mentions } mentions:
+bench-calls = Resolver calls:
+bench-no-calls = None
+
+## The pages of /about, in Discord's markdown
+
+about-landing =
+    **I just got pinged by Intersection, what does this mean?**
+
+    This is probably the most common question we see. Hopefully this will clear things up! Otherwise, I'll link to resources if you'd like to learn more about Intersection.
+
+    If you got pinged by Intersection, the bot doesn't want anything from you. Actually, it's the complete opposite - when Intersection pings you, someone else was _using_ the bot to send you a notification. The message you got mentioned in should be a reply to another message (one that likely contains `@{"{"} ... {"}"}`). Just imagine it as if you had been pinged in that message - this is one of Intersections's main (but most confusing) features. Intersection is an extension to Discord @-mentions, allowing more powerful mentions to be made in advanced situations. Discord doesn't provide a facility for this directly, so Intersection just pings the people it needs to as a reply.
+
+    tl;dr: Check the message Intersection was replying to when you got pinged. That's who pinged you. Complain to them, not to us.
+
+    **Want to learn more about the Intersection project?** Run { $about-intersection }!
+about-intersection-1 =
+    :wave: **Hello there, I'm Intersection!** Mass pinging, but targeted 😏
+
+    Intersection is a Discord bot that aims to provide modern and advanced tools for Discord server administration, specifically in the area of @-mentions.
+
+    Most Discord users know what a mention is. Informally known as a "ping," they're a method of getting a member (or a group members') attention. This is a mention: <@{ $bot }> (oh hey, that's me!)
+
+    Discord also has a facility for mentioning roles -- groups of users. However, in the communities I've owned, I've found a need for being able to narrow this down even further.
+
+    For example:
+
+    -   How can I mention every _online_ admin?
+    -   How can I mention everyone _without_ a certain role?
+    -   How can I mention everyone with _two_ specific roles?
+
+    Discord does not provide a simple way to do this. In order to make this possible, Intersection takes concepts from a branch of mathematics known as Set Theory and applies them to Discord mentions. This allows you to make more complex queries in a format we call DRQL - the Discord Role Query Language.
+
+    Using DRQL, we might describe the above queries as:
+
+    -   `@{"{"} admins & here {"}"}` - the **intersection** of the role `admins` and `here` (everyone who is both an admin and online - this is where Intersection gets its
+        name!)
+    -   `@{"{"} everyone - "my role" {"}"}` - the **difference** of the role `everyone` and `my role`
+    -   `@{"{"} "role 1" & "role 2" {"}"}` - another intersection operation!
+
+    ...
+about-intersection-2 =
+    ...
+    This might seem very complicated at first, and that's because it is! Even for people with serious experience in Discord, Intersection will have a large learning curve! It might be helpful if you have existing experience with set theory, boolean logic, and the overall syntax of DRQL.
+
+    Because of the large learning curve, we provide a variety of resources for your convenience, to help you learn how to use Intersection:
+
+    -   A basic explanation of set theory and how Intersection takes advantage of it: { $about-set-theory }
+    -   An explanation of how to use DRQL: { $about-drql }
+
+    We're working on a guided tutorial for you! Keep an eye out here for when it is available.
+
+    **Are you interested in the internals? Maybe you want to contribute to Intersection?** Take a look at { $about-how-it-works }! We'd love your help.
+about-set-theory =
+    :question: **What is Set Theory, and how does Intersection use it?**
+
+    Note: I am not a mathematician, and this is just a basic explanation from my knowledge. If anyone would like to correct information here, please let me know! This is only supposed to be a basic explanation.
+
+    Here comes the annoying part. The theory. _Set_ theory. (dunn dunn dunnnnnnn)
+
+    Set Theory involves looking at things as sets - groups of things. Let's just imagine a random pile of colored rocks. This isn't a _pile_ of rocks, it's a _set_ of them!
+
+    This is math though, so keeping it abstracted like this is hard. Sets are already an abstract concept. Instead, let's think about numbers. Sets of numbers.
+
+    Here's two sets:
+
+    A: `{"{"} 1, 2, 3, 7, 9 {"}"}`
+    B: `{"{"} 8, 2, 4, 6, 5 {"}"}`
+
+    We can now think of a few operations we can perform on these sets. The core 3 set theory operations are:
+
+    -   **Union:** The resulting set is all members of _either_ A or B (notated as A ∪ B)
+    -   **Intersection:** The resulting set is all members of _both_ A and B (notated as A ∩ B)
+    -   **Difference:** The resulting set is all members of A that are _not_ members of B (many notations, commonly A \ B or A - B)
+
+    This is hard to explain with text. Make a quick internet search to find some graphics for these.
+
+    Either way, the union, intersection, and difference of our two example sets above is:
+
+    -   A ∪ B = `{"{"} 1, 2, 3, 7, 9, 8, 4, 6, 5 {"}"}`
+    -   A ∩ B = `{"{"} 2 {"}"}`
+    -   A \ B = `{"{"} 1, 3, 7, 9 {"}"}`
+    -   B \ A = `{"{"} 8, 4, 6, 5 {"}"}`
+
+    Now that you hopefully understand the union, intersection, and difference operations, this is what Intersection does:
+
+    -   Instead of a role being a tag associated with users, a role is a set of users.
+    -   A user itself can be represented as a set with just that user.
+    -   These set operations can be applied to roles and users to create advanced mentions.
+
+    Now it's time to learn about DRQL's syntax: { $about-drql }
+about-drql-1 =
+    :question: **What is DRQL, and how do I use it?**
+
+    DRQL, or the Discord Role Query Language, is a simple set-theory-oriented query language designed for Intersection.
+
+    **Need a refresher on set theory?** Read { $about-set-theory }.
+
+    Recall that Intersection functions by representing roles as sets of users. With that knowledge, DRQL is a very simple language we can use to represent and process queries.
+
+    DRQL has a few underlying "primary" types, and those are:
+
+    -   String literals or raw names: `abc` or `"abc"` - these represent the name of a **user** or a **role**. Names can contain letters in any language and emoji, but quotes must be used around spaces or other symbols (write `\"` for a quote inside them). `everyone` and `here` represent everyone and only online people, respectively. `online`, `idle`, `dnd`, and `offline` are the members with that status. `boosters` are the server's boosters.
+    -   ID literals: `{ $bot }` - these represent the ID of a user or role.
+    -   Direct mentions: <@{ $bot }> - you can directly @-mention a user or role instead of an ID literal. This is not recommended as it can result in double-pinging a user, and ID or name literals should be preferred instead. This is only needed in the EXTREMELY rare case that a user and role have the same ID.
+
+    Using these types, you can use our set of binary infix operators:
+
+    -   `A + B` or `A | B`: **Union**: A ∪ B
+    -   `A & B`: **Intersection**: A ∩ B
+    -   `A - B`: **Difference**: A \ B
+
+    And the prefix operator `!A` or `~A`: **Complement**, everyone except A, so `!afk` is everyone without the `afk` role.
+
+    Again, you might want to read up on set theory to understand these.
+
+    DRQL queries are automatically detected in your message. Enclose them in `@{"{"} ... {"}"}` to tell Intersection to query them! If you need to literally use the text `@{"{"} ... {"}"}`, put a backslash in: `@\{"{"} ... {"}"}`
+    ...
+about-drql-2 =
+    ...
+
+    ## Examples
+
+    Everyone with both role `cool person` who isn't `staff`: `@{"{"} "cool person" - staff {"}"}`
+
+    All online `mods`: `@{"{"} mods & here {"}"}`
+
+    Role names don't have to match case, so `mods` also finds a role called `Mods` unless there's one called exactly `mods`. Server managers can turn this off with `/config case_sensitive_roles`.
+
+    ## Words
+
+    The operators can also be written as words: `A or B`, `A and B`, `A minus B`, and `not A`. `me` is you, so `@{"{"} raiders - me {"}"}` mentions every other raider. Ending a query with `limit` and a number, like `@{"{"} helpers limit 25 {"}"}`, mentions at most that many of its members. To use a role or member named after one of these words, put quotes around it, like `"me"`.
+
+    ## Channels
+
+    Mention a channel in a query (type `#` and pick it) to query the members connected to it if it's a voice channel, the members of it if it's a thread, or the members who can see it otherwise. `in_voice` is everyone connected to any voice channel.
+
+    ## Events
+
+    `event(...)` with the ID of or a link to a scheduled event is everyone interested in it, like `@{"{"} event(1234) & here {"}"}`.
+
+    ## Permissions
+
+    `perm(...)` with the name of a permission is every member who has it, like `@{"{"} perm(manage_messages) - offline {"}"}`.
+about-drql-3 =
+    ...
+
+    ## Join dates
+
+    `joined_before(...)` and `joined_after(...)` take a date, like `"2024-01-01"`, or a time ago, like `30d` (`w`, `d`, `h`, `m`, and `s` work), and match the members who joined before or after it.
+    `account_age(<7d)` and `account_age(>7d)` match the members whose accounts are younger or older than that.
+
+    ## Names
+
+    `name(...)` is every member whose username or nickname matches a pattern, like `name("*dev*")` (where `*` is anything and `?` is any one character) or a regex like `name(/^team-(red|blue)/)`.
+
+    ## Activity
+
+    `active(...)` with a time, like `active(7d)`, is everyone who sent a message in that time, even if they appear offline. Only messages sent while Intersection was around count.
+
+    ## Reactions
+
+    `reacted(...)` with a link to a message and an emoji is everyone who reacted to that message with it, like `@{"{"} reacted(https://discord.com/channels/1/2/3, ✅) - here {"}"}`.
+
+    ## Counting
+
+    `atleast(...)` with a number and some queries is every member of at least that many of them, like `@{"{"} atleast(2, red, green, blue) {"}"}`.
+
+    ## Sampling
+
+    `sample(...)` with a query and a number is that many members of the query picked at random, like `@{"{"} sample(volunteers - offline, 5) {"}"}`.
+
+    ## Aliases
+
+    `$` and a name, like `$oncall`, is whatever query the server's moderators named that with `/alias add`, like `@{"{"} $oncall & here {"}"}`. `/alias list` shows them all, and `/alias export` and `/alias import` copy them to another server.
+
+    ## Precedence
+
+    All operators are parsed left-to-right. You can use parenthesis to manually override this. `A & B & C` is parsed as `(A & B) & C`. `!` applies before anything else, so `!A & B` is `(!A) & B`.
+
+    ## Internals (for nerds)
+
+    You can learn more about how it all works: { $about-how-it-works }
+    If you've got an interest in parsing algorithms, we'd love your help!
+about-how-it-works =
+    :question: **How does Intersection work?**
+
+    Intersection is a suite of delicately hand-crafted software that all works together to provide the end result which is the Intersection Discord bot.
+
+    You can see what version of Intersection that is currently running by looking at the result of { $version }!
+
+    Intersection contains a few core components, namely the Discord bot itself and the DRQL logic. The Discord bot is written in [Rust](https://www.rust-lang.org/) using the [Serenity](https://crates.io/crates/serenity) and [Poise](https://crates.io/crates/poise) frameworks, and the DRQL logic is broken into a few pieces:
+
+    -   DRQL frontend (parsing): lexer generated by [Logos](https://crates.io/crates/logos) and LR(1) parser generated by [LALRPOP](https://crates.io/crates/lalrpop)
+    -   DRQL backend: Completely hand-made!
+
+    You can see what version of all of those primary dependencies that was used by running the version command listed above.
+
+    ## DRQL queries
+
+    When you run a DRQL query, your query is passed through the DRQL parser and an Abstract Syntax Tree is generated. This tree directly represents the query you wrote, and you can actually view the tree generated with the { $debug-parse-one } command!
+
+    Values within this tree are then resolved to Sets where the result is calculated.
+
+    Then one of the most complicated steps of Intersection occurs: optimization. Discord servers already have roles, and instead of mentioning 10,000 different people, why not take advantage of that? Intersection takes a best-effort attempt at representing the result set as the union of a bunch of roles within your server and mentioning them instead. There will always be flaws here, and outliers will exist in most cases.
+
+    ## Contributing
+
+    All of Intersection is completely open-source! You can [open our repository on GitHub]({ $repository }) to take a look at how you can help!
@@ -27,7 +27,6 @@
     clippy::same_name_method,
     clippy::semicolon_inside_block,
     clippy::unseparated_literal_suffix,
-    clippy::string_to_string,
     clippy::todo,
     clippy::undocumented_unsafe_blocks,
     clippy::unimplemented,
@@ -53,52 +52,84 @@
     clippy::no_effect_underscore_binding
 )]
 
+mod access;
+mod activity;
+mod channel_filter;
 mod commands;
-mod drql;
+mod config;
+mod cooldowns;
+mod discord;
+mod duplicates;
+mod error;
+mod export;
 mod extensions;
+mod history;
+mod i18n;
 mod models;
+mod pending_sends;
+mod persistence;
+mod pipeline;
+mod preferences;
+mod query_cache;
+mod quiet_hours;
+mod recipients;
+mod reply_tracker;
 mod resolver;
+mod retry;
+mod scheduler;
+mod send_queue;
+mod stats;
 mod util;
+mod webhooks;
 
-use lalrpop_util::lalrpop_mod;
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
 
-lalrpop_mod!(
-    /// Direct access to the LALRPOP parser powering DRQL. **Do not use this module.** Use the [`drql::parser`] module instead.
-    ///
-    /// Again. **Don't use this.** The second you import this into your code, you're setting yourself
-    /// up to shoot yourself in the foot. **Just use [`drql::parser`].** There is almost *no* reason
-    /// you would need this module instead, unless you need to handle the underlying errors manually,
-    /// which I doubt.
-    #[allow(
-        clippy::all,
-        clippy::nursery,
-        clippy::pedantic,
-        missing_docs,
-        clippy::missing_docs_in_private_items,
-        clippy::restriction,
-        unused_qualifications
-    )]
-    parser
-);
-
-use std::{collections::HashSet, env, ops::ControlFlow, sync::Arc};
-
-use anyhow::{bail, Context as _};
 use dotenvy::dotenv;
-use poise::{
-    serenity_prelude::{self as serenity, Guild, GuildChannel, Member, UserId},
-    FrameworkError,
-};
-use tracing::{debug, error, info, instrument, trace, warn};
+use intersection::drql;
+use poise::{serenity_prelude as serenity, FrameworkError};
+use tracing::{debug, error, info, instrument, warn};
 use tracing_subscriber::prelude::*;
+// These dependencies are only used by the library crate
+#[cfg(test)]
+use proptest as _;
+use {futures as _, logos as _, regex as _};
+
+use crate::{
+    activity::ActivityTracker,
+    config::{ConfigStore, GuildConfig, QueryEntryPoints},
+    cooldowns::CooldownTracker,
+    duplicates::DuplicateQueryTracker,
+    error::{report_internal_error, QueryError},
+    history::QueryHistory,
+    i18n::Language,
+    pending_sends::PendingSendStore,
+    preferences::PreferenceStore,
+    query_cache::QueryCache,
+    recipients::RecipientStore,
+    reply_tracker::ReplyTracker,
+    scheduler::Schedule,
+    send_queue::SendQueues,
+    stats::UsageStats,
+    webhooks::WebhookStore,
+};
 
-use crate::{drql::ast::Expr, extensions::CustomGuildImpl};
+/// How long evaluated query results are cached for.
+///
+/// This is kept short because presence changes (which affect `here`) do not invalidate the cache.
+const QUERY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How long the replies to a query are deleted along with it.
+const REPLY_TRACKING_TTL: Duration = Duration::from_hours(24);
+
+/// How long the "Who was pinged?" button on notifications keeps working.
+const RECIPIENTS_TTL: Duration = Duration::from_hours(24);
 
 /// Compile-time information collected by the `built` crate
 ///
 /// This information is collected at compile-time and is primarily used in the [version] command.
 ///
 /// [version]: commands::version
+#[allow(clippy::needless_raw_string_hashes, clippy::doc_markdown)] // generated code
 mod build_info {
     // File is inserted by build.rs
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
@@ -114,6 +145,68 @@ pub struct Data {
     /// [`ShardManager`]: serenity::ShardManager
     /// [ping]: commands::ping
     shard_manager: Arc<serenity::Mutex<serenity::ShardManager>>,
+    /// The configuration of every guild
+    config: ConfigStore,
+    /// Recent notifications, used to enforce each guild's cooldowns
+    cooldowns: CooldownTracker,
+    /// Recently sent queries, used to spot duplicates
+    duplicate_queries: DuplicateQueryTracker,
+    /// The recent notifications in every guild, shared with the task forgetting old ones
+    history: Arc<QueryHistory>,
+    /// How much Intersection is used in every guild
+    stats: UsageStats,
+    /// The preferences of every member
+    preferences: PreferenceStore,
+    /// Recently evaluated query results, used by [`pipeline::parse_and_evaluate_query`].
+    query_cache: QueryCache,
+    /// The messages sent in reply to recent queries, deleted if the query is deleted
+    reply_tracker: ReplyTracker,
+    /// The members mentioned by recent notifications
+    recipients: RecipientStore,
+    /// The queue each notification waits in before being sent
+    send_queues: SendQueues,
+    /// The split notifications being sent, kept in case of a restart
+    pending_sends: PendingSendStore,
+    /// The webhooks used to send notifications with the author's name and avatar
+    webhooks: WebhookStore,
+    /// When each member last sent a message, for `active(...)` queries
+    activity: ActivityTracker,
+    /// The queries scheduled to notify their members later
+    schedule: Schedule,
+}
+
+impl Data {
+    /// The language `guild` chose for Intersection's messages, or English outside of a guild.
+    fn language(&self, guild: Option<serenity::GuildId>) -> Language {
+        guild.map_or_else(Language::default, |guild| self.config.get(guild).language)
+    }
+
+    /// Perform the query pipeline's operations for the query in `msg`, sent in a guild
+    /// configured with `config`.
+    fn discord<'a>(
+        &'a self,
+        ctx: &'a serenity::Context,
+        msg: &'a serenity::Message,
+        config: &'a GuildConfig,
+    ) -> discord::SerenityDiscord<'a> {
+        discord::SerenityDiscord {
+            ctx,
+            msg,
+            query_cache: &self.query_cache,
+            reply_tracker: &self.reply_tracker,
+            recipients: &self.recipients,
+            preferences: &self.preferences,
+            webhooks: &self.webhooks,
+            send_queues: &self.send_queues,
+            pending_sends: &self.pending_sends,
+            activity: &self.activity,
+            cooldowns: &self.cooldowns,
+            duplicate_queries: &self.duplicate_queries,
+            history: &self.history,
+            stats: &self.stats,
+            config,
+        }
+    }
 }
 /// Type alias for the poise [`Context`] using our custom [`Data`] type and an anyhow [`Error`].
 ///
@@ -121,365 +214,280 @@ pub struct Data {
 /// [`Error`]: anyhow::Error
 type Context<'a> = poise::Context<'a, Data, anyhow::Error>;
 
-/// Prompts the user to confirm they want to execute a query
+/// Every command, slash and text.
 ///
-/// This is used usually when there are over 50 members_to_ping in a single query.
-///
-/// Will return Ok(Continue) if the user accepted, Ok(Break) if the user cancelled or timed out,
-/// and Err if there was an error.
-#[instrument(skip_all, fields(count = members_to_ping.len()))]
-async fn confirm_mention_count(
-    ctx: &serenity::Context,
-    msg: &serenity::Message,
-    stringified_mentions: &Vec<String>,
-    members_to_ping: &HashSet<UserId>,
-) -> anyhow::Result<ControlFlow<(), ()>> {
-    let serenity::Channel::Guild(channel) = msg.channel(ctx).await? else {
-        // DMs would have been prevented already.
-        // Messages can't be sent in categories
-        bail!("unreachable");
-    };
-
-    trace!("sending confirmation message");
+/// Text commands are looked up by name or alias in order, so `text_dry_run` comes before the
+/// `dry_run` slash command it's an alias of.
+fn commands() -> Vec<poise::Command<Data, anyhow::Error>> {
+    vec![
+        commands::text_dry_run(),
+        commands::ping(),
+        commands::about(),
+        commands::config(),
+        commands::debug(),
+        commands::version(),
+        commands::dry_run(),
+        commands::members(),
+        commands::alias(),
+        commands::history(),
+        commands::stats(),
+        commands::pause(),
+        commands::resume(),
+        commands::preferences(),
+        commands::optout(),
+        commands::optin(),
+        commands::dnd(),
+        commands::privacy(),
+        commands::schedule(),
+    ]
+}
 
-    let mut confirmation_message = channel
-        .send_message(ctx, |msg_builder| {
-            msg_builder
-                .content(format!(
-                    concat!(
-                        "**Hold up!** By running this query, you are about to",
-                        " mention {} people.{} Are you sure?"
-                    ),
-                    members_to_ping.len(),
-                    {
-                        let len = util::wrap_string_vec(stringified_mentions, " ", 2000)
-                            .expect("a mention should always fit in 2000 chars")
-                            .len();
-                        if len > 2 {
-                            format!(" This will require the sending of {len} messages.")
-                        } else {
-                            String::new()
-                        }
-                    }
-                ))
-                .reference_message(msg) // basically makes it a reply
-                .components(|components| {
-                    components.create_action_row(|action_row| {
-                        action_row
-                            .create_button(|button| {
-                                button
-                                    .custom_id("large_ping_confirm_no")
-                                    // X emoji
-                                    .emoji(serenity::ReactionType::Unicode("\u{274c}".to_string()))
-                                    .label("Cancel")
-                                    .style(serenity::ButtonStyle::Secondary)
-                            })
-                            .create_button(|button| {
-                                button
-                                    .custom_id("large_ping_confirm_yes")
-                                    // check mark emoji
-                                    .emoji(serenity::ReactionType::Unicode("\u{2705}".to_string()))
-                                    .label("Yes")
-                                    .style(serenity::ButtonStyle::Primary)
-                            })
-                    })
-                })
+/// How long notifications are kept in the query history: `HISTORY_RETENTION_DAYS` days if it's
+/// set, or [`history::DEFAULT_RETENTION`].
+fn history_retention() -> Duration {
+    env::var("HISTORY_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<u64>().ok())
+        .map_or(history::DEFAULT_RETENTION, |days| {
+            Duration::from_hours(days.saturating_mul(24))
         })
-        .await?;
-
-    trace!("waiting for confirmation");
-
-    let Some(interaction) = confirmation_message
-        .await_component_interaction(ctx)
-        .collect_limit(1)
-        .author_id(msg.author.id)
-        .timeout(std::time::Duration::from_secs(30))
-        .await
-    else {
-        debug!("timed out waiting for confirmation");
-        confirmation_message
-            .edit(ctx, |edit_handle| {
-                edit_handle
-                    .content("Timed out waiting for confirmation.")
-                    .components(|components| components)
-            })
-            .await?;
-        return Ok(ControlFlow::Break(()));
-    };
-
-    match interaction.data.custom_id.as_str() {
-        "large_ping_confirm_no" => {
-            debug!("User cancelled operation");
-            confirmation_message
-                .edit(ctx, |edit_handle| {
-                    edit_handle
-                        .content("Cancelled.")
-                        .components(|components| components)
-                })
-                .await?;
-
-            return Ok(ControlFlow::Break(()));
-        }
-        "large_ping_confirm_yes" => {
-            debug!("User confirmed operation");
-            confirmation_message
-                .edit(ctx, |edit_handle| {
-                    edit_handle
-                        .content("Confirmed.")
-                        .components(|components| components)
-                })
-                .await?;
-
-            // continue normally!
-            return Ok(ControlFlow::Continue(()));
-        }
-        _ => {
-            bail!("Discord sent us an invalid interaction customId!");
-        }
-    }
 }
 
-/// Process a DRQL query from a single slice of Query chunk strings
-/// and return the resulting members_to_ping
-#[instrument(skip_all)]
-pub async fn parse_and_evaluate_query(
-    ctx: &serenity::Context,
-    chunks: &[&str],
-    guild: &Guild,
-    member: &Member,
-    channel: &GuildChannel,
-) -> anyhow::Result<HashSet<UserId>> {
-    trace!("Parsing each chunk...");
-
-    let ast = chunks
-        .iter()
-        .enumerate()
-        .map(|(n, chunk)| {
-            drql::parser::parse_drql(chunk).context(format!("Error parsing chunk {n}"))
-        })
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .reduce(|acc, chunk| Expr::Union(Box::new(acc), Box::new(chunk)))
-        .context("There is no DRQL query in your message to handle.")?; // This should never happen, as we already checked that there was at least one chunk in the input
-
-    debug!("Fully parsed and reduced AST: {ast:?}");
-
-    trace!("Running DRQL interpreter on AST");
-    let members_to_ping = drql::interpreter::interpret(
-        ast,
-        &mut resolver::Resolver {
-            guild,
-            member,
-            ctx,
-            channel,
-        },
+/// The file a store is kept in: the path in the environment variable `variable` if it's set, or
+/// `default` otherwise. An empty path keeps the store in memory only.
+fn store_path(variable: &str, default: &str) -> Option<PathBuf> {
+    env::var(variable).map_or_else(
+        |_| Some(default.into()),
+        |path| (!path.is_empty()).then(|| path.into()),
     )
-    .await
-    .context("Error calculating result")?;
-
-    debug!(
-        "Evaluated result: {:?}",
-        members_to_ping.iter().map(|id| id.0).collect::<Vec<_>>()
-    );
-
-    Ok(members_to_ping)
 }
 
-/// Handle a DRQL query from a message, sending the response message(s) to the channel.
-#[instrument(skip_all)]
-async fn handle_drql_query(ctx: &serenity::Context, msg: &serenity::Message) -> anyhow::Result<()> {
-    if msg.guild(ctx).is_none() {
-        debug!("Ignoring DRQL query sent in DMs.");
-        bail!("DRQL queries are not available in DMs.");
+/// The options for text commands, which start with the prefix a guild chose, if it chose one.
+fn prefix_options() -> poise::PrefixFrameworkOptions<Data, anyhow::Error> {
+    poise::PrefixFrameworkOptions {
+        dynamic_prefix: Some(|ctx| {
+            Box::pin(async move {
+                Ok(ctx
+                    .guild_id
+                    .and_then(|guild_id| ctx.data.config.get(guild_id).prefix))
+            })
+        }),
+        // Only guilds which chose a prefix get text commands
+        mention_as_prefix: false,
+        ..Default::default()
     }
+}
 
-    trace!("Fetching guild, channel, and member information");
-    let guild = msg.guild(ctx).context("Unable to resolve guild")?;
-    let member = msg.member(ctx).await?;
-    let serenity::Channel::Guild(channel) = msg.channel(ctx).await? else {
-        // DMs would have been prevented already.
-        // Messages can't be sent in categories
-        bail!("unreachable");
-    };
-
-    trace!("Running DRQL parser/interpreter on message");
-    let members_to_ping = parse_and_evaluate_query(
-        ctx,
-        &drql::scanner::scan(msg.content.as_str()).collect::<Vec<_>>(),
-        &guild,
-        &member,
-        &channel,
-    )
-    .await?;
-
-    // A hashmap of every role in the guild and its members.
-    let roles_and_their_members = guild.all_roles_and_members(ctx)?;
-
-    // next, we represent the list of users as a bunch of roles containing them and one outliers set.
-    let util::unionize_set::UnionizeSetResult { sets, outliers } =
-        util::unionize_set::unionize_set(&members_to_ping, &roles_and_their_members);
-
-    debug!(
-        "unionize_set result sets: {sets:?}, outliers: {outliers:?}",
-        sets = sets,
-        outliers = outliers
-    );
-
-    // Now we need to split the output message into individual pings. First, stringify each user mention...
-    // TODO: Once message splitting is complete this could result in a user being
-    // pinged multiple times if they are present in a role that is split into multiple
-    // messages.
-    // e.g.
-    // user is in @A and @C
-    // message 1: @A @B ...
-    // message 2: @C @D ...
-    // double ping!
-    let stringified_mentions = sets
-        .into_iter()
-        .copied()
-        .map(models::mention::Mention::Role)
-        .chain(
-            outliers
-                .into_iter()
-                .map(|&id| models::mention::Mention::User(id)),
-        )
-        .map(|x| x.to_string())
-        .collect::<Vec<_>>();
-
-    debug!(
-        "stringified_mentions: {stringified_mentions:?}",
-        stringified_mentions = stringified_mentions
-    );
-
-    if stringified_mentions.is_empty() {
-        debug!("Nobody to mention!");
-        msg.reply(ctx, "No users matched.").await?;
-        return Ok(());
+/// Tell the author of a command about the error it failed with, in their guild's language.
+///
+/// Mistakes in queries are explained to them, while anything else is reported as an internal
+/// error.
+async fn on_error(error: FrameworkError<'_, Data, anyhow::Error>) {
+    if let FrameworkError::Command { error, ctx } = error {
+        let language = ctx.data().language(ctx.guild_id());
+        let result = match error.downcast_ref::<QueryError>() {
+            Some(query_err) if query_err.is_user_error() => {
+                debug!("Notifying user of query error: {query_err}");
+                ctx.send(|builder| {
+                    builder
+                        .content(i18n::message(
+                            language,
+                            "command-error",
+                            &[("error", query_err)],
+                        ))
+                        .ephemeral(true)
+                })
+                .await
+            }
+            _ => {
+                let message = report_internal_error(
+                    format_args!(
+                        "Error in command {}: {error:#}",
+                        ctx.command().qualified_name
+                    ),
+                    language,
+                );
+                ctx.say(message).await
+            }
+        };
+        if let Err(err) = result {
+            error!("Unable to send error due to {err:#}");
+        }
     }
+}
 
-    if members_to_ping.len() > 50 {
-        debug!("need to wait for user to confirm large mention");
-        if confirm_mention_count(ctx, msg, &stringified_mentions, &members_to_ping).await?
-            == ControlFlow::Break(())
-        {
-            debug!("User cancelled or timed out");
-            // The user declined or the operation timed out. The message has already been edited for us.
-            return Ok(());
+/// Intersection's primary event handler, invalidating the [`QueryCache`] on member and role
+/// events, delegating [`Message`] events to [`handle_message`], and deleting our replies to
+/// deleted queries.
+///
+/// [`Message`]: serenity::Message
+#[allow(clippy::wildcard_enum_match_arm)] // there are far too many events to list
+async fn event_handler(
+    ctx: &serenity::Context,
+    event: &poise::Event<'_>,
+    _framework: poise::FrameworkContext<'_, Data, anyhow::Error>,
+    data: &Data,
+) -> anyhow::Result<()> {
+    match event {
+        // Only the first time we're ready does this have anything to resume
+        poise::Event::Ready { .. } => {
+            pending_sends::resume_interrupted(ctx, &data.pending_sends, &data.config).await;
+            // Each event is handled in a task of its own, so this doesn't hold up any others
+            scheduler::run(ctx, data).await;
+        }
+        poise::Event::Message { new_message } => {
+            if let Some(guild_id) = new_message.guild_id.filter(|_| !new_message.author.bot) {
+                data.activity
+                    .record(guild_id, new_message.author.id, *new_message.timestamp);
+            }
+            handle_message(ctx, new_message, data).await;
+        }
+        poise::Event::MessageDelete {
+            channel_id,
+            deleted_message_id,
+            ..
+        } => delete_replies(ctx, *channel_id, *deleted_message_id, data).await,
+        poise::Event::MessageDeleteBulk {
+            channel_id,
+            multiple_deleted_messages_ids,
+            ..
+        } => {
+            for deleted_message_id in multiple_deleted_messages_ids {
+                delete_replies(ctx, *channel_id, *deleted_message_id, data).await;
+            }
         }
-        debug!("User confirmed!");
-    }
 
-    let notification_string = format!(
-        concat!(
-            "Notification triggered by Intersection.\n",
-            ":question: **What is this?** Run {} for more information.\n"
-        ),
-        util::mention_application_command(ctx, "about landing").await?
-    );
+        poise::Event::InteractionCreate {
+            interaction: serenity::Interaction::MessageComponent(interaction),
+        } => {
+            // Each handler ignores buttons that aren't theirs
+            let language = data.language(interaction.guild_id);
+            if let Err(err) = tokio::try_join!(
+                recipients::handle_component_interaction(
+                    ctx,
+                    interaction,
+                    &data.recipients,
+                    language
+                ),
+                reply_tracker::handle_component_interaction(
+                    ctx,
+                    interaction,
+                    &data.reply_tracker,
+                    language
+                ),
+            ) {
+                warn!("Unable to respond to button press: {err}");
+            }
+        }
 
-    if stringified_mentions.join(" ").len() <= (2000 - notification_string.len()) {
-        trace!("Sending single message for mentions");
-        msg.reply(
-            ctx,
-            format!("{}{}", notification_string, stringified_mentions.join(" ")),
-        )
-        .await?;
-    } else {
-        let messages = util::wrap_string_vec(&stringified_mentions, " ", 2000)?;
-        trace!("Need to send {} messages.", messages.len());
-        msg.reply(
-            ctx,
-            format!(
-                "Notification triggered by Intersection. Please wait, sending {} messages...",
-                messages.len()
-            ),
-        )
-        .await?;
-        for message in messages {
-            msg.reply(ctx, message).await?;
+        poise::Event::WebhookUpdate {
+            belongs_to_channel_id,
+            ..
+        } => data.webhooks.invalidate_channel(*belongs_to_channel_id),
+
+        poise::Event::GuildMemberAddition { new_member } => {
+            data.query_cache.invalidate_guild(new_member.guild_id);
+        }
+        poise::Event::GuildMemberRemoval { guild_id, .. }
+        | poise::Event::GuildRoleDelete { guild_id, .. } => {
+            data.query_cache.invalidate_guild(*guild_id);
+        }
+        poise::Event::GuildMemberUpdate { new, .. } => {
+            data.query_cache.invalidate_guild(new.guild_id);
+        }
+        poise::Event::GuildMembersChunk { chunk } => {
+            data.query_cache.invalidate_guild(chunk.guild_id);
+        }
+        poise::Event::GuildRoleCreate { new } | poise::Event::GuildRoleUpdate { new, .. } => {
+            data.query_cache.invalidate_guild(new.guild_id);
         }
-        msg.reply(
-            ctx,
-            format!(
-                concat!(
-                    "Notification triggered successfully.\n",
-                    ":question: **What is this?** Run {} for more information."
-                ),
-                util::mention_application_command(ctx, "about landing").await?
-            ),
-        )
-        .await?;
-    }
 
-    trace!("Query handling completed!");
+        _ => {}
+    }
 
     Ok(())
 }
 
-/// Intersection's primary [`EventHandler`], delegating [`Message`] events to [`handle_drql_query`].
+/// Delete the messages we sent in reply to a (now deleted) query message, if any.
+#[instrument(skip(ctx, data))]
+async fn delete_replies(
+    ctx: &serenity::Context,
+    channel_id: serenity::ChannelId,
+    deleted_message_id: serenity::MessageId,
+    data: &Data,
+) {
+    if let Err(err) =
+        reply_tracker::delete_replies(ctx, channel_id, deleted_message_id, &data.reply_tracker)
+            .await
+    {
+        warn!("Unable to delete replies to deleted query: {err}");
+    }
+}
+
+/// Handle a single [`Message`] event, running any DRQL queries found within it.
 ///
-/// [`EventHandler`]: serenity::EventHandler
 /// [`Message`]: serenity::Message
-struct Handler;
-#[serenity::async_trait]
-#[allow(clippy::ignored_unit_patterns)] // bugged
-impl serenity::EventHandler for Handler {
-    #[instrument(skip_all, fields(author = msg.author.id.0, content = msg.content))]
-    async fn message(&self, ctx: serenity::Context, msg: serenity::Message) {
-        debug!("Received new message event");
-
-        if msg.author.bot {
-            debug!("Ignoring message from bot.");
-            return;
-        }
+#[instrument(skip_all, fields(author = msg.author.id.0, content = msg.content))]
+async fn handle_message(ctx: &serenity::Context, msg: &serenity::Message, data: &Data) {
+    debug!("Received new message event");
 
-        if drql::scanner::scan(msg.content.as_str()).count() > 0 {
-            debug!("Found DRQL queries in message! Handling queries.");
-            match handle_drql_query(&ctx, &msg)
-                .await
-                .context("Error handling DRQL query")
-            {
-                Ok(()) => debug!("Finished handling queries."),
-
-                Err(query_err) => {
-                    // THIS IS NOT OUR FAULT -- This most likely means the USER made a mistake
-                    debug!(
-                        "An error occurred handling the DRQL query, notifying user: {query_err:#}"
-                    );
-
-                    if let Err(message_send_err) = msg.reply(&ctx, format!("{query_err:#}")).await {
-                        warn!("An error occurred while notifying the user of a query error: {message_send_err:#}");
-                        warn!("Initial query error: {query_err:#}");
-                        debug!("Trying again...");
-
-                        if let Err(double_message_send_err) = msg
-                            .reply(
-                                &ctx,
-                                format!(
-                                    concat!(
-                                        "{query_err:#}\n",
-                                        "Additionally, we attempted to send this error to you but this failed:",
-                                        " {message_send_err:#}"
-                                    ),
-                                    query_err = query_err,
-                                    message_send_err = message_send_err
-                                ),
-                            )
-                            .await
-                        {
-                            // Oh god the error message.
-                            error!("Failed to notify a user of an error notifying them of an error notifying them of a query error: {double_message_send_err:#}");
-                            error!("We were attempting to notify them of this error: {message_send_err:#}");
-                            error!("That error occurred while notifying them of this error: {query_err:#}");
-                            error!("Message sending failed twice! Giving up.");
-                        } else {
-                            debug!("Alright, it worked that time.");
-                        }
-                    }
-                }
+    if msg.author.bot {
+        debug!("Ignoring message from bot.");
+        return;
+    }
+
+    let config = msg
+        .guild_id
+        .map(|guild_id| data.config.get(guild_id))
+        .unwrap_or_default();
+    if config
+        .prefix
+        .as_deref()
+        .is_some_and(|prefix| msg.content.starts_with(prefix))
+    {
+        debug!("Ignoring text command, which is handled by the framework.");
+        return;
+    }
+    if config.entry_points == QueryEntryPoints::SlashCommandsOnly {
+        debug!("Ignoring message in a server where queries are only run with slash commands.");
+        return;
+    }
+    // Threads follow the channel they're in
+    let parent = if config.channels.channels.is_empty() {
+        // Nothing is listed, so it doesn't matter which channel the thread is in
+        None
+    } else {
+        match msg.channel_id.to_channel(ctx).await {
+            Ok(channel) => channel
+                .guild()
+                .filter(|channel| channel.thread_metadata.is_some())
+                .and_then(|channel| channel.parent_id),
+            Err(err) => {
+                warn!("Unable to fetch the channel of a message: {err}");
+                None
             }
         }
+    };
+    if !config.channels.allows(msg.channel_id, parent) {
+        debug!("Ignoring message in a channel queries aren't processed in.");
+        return;
+    }
+
+    let chunks = drql::scanner::scan(msg.content.as_str()).collect::<Vec<_>>();
+    if chunks.is_empty() {
+        return;
     }
+    let options = pipeline::QueryOptions {
+        // Discord sets this flag on the message itself when it starts with @silent
+        silent: config.silent
+            || msg.flags.is_some_and(|flags| {
+                flags.contains(serenity::MessageFlags::SUPPRESS_NOTIFICATIONS)
+            }),
+        ..pipeline::QueryOptions::for_guild(&config)
+    };
+
+    debug!("Found DRQL queries in message! Handling queries.");
+    pipeline::run_query(&data.discord(ctx, msg, &config), &chunks, options).await;
 }
 
 #[tokio::main]
@@ -515,27 +523,17 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let framework: poise::FrameworkBuilder<Data, anyhow::Error> = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![
-                commands::ping(),
-                commands::about(),
-                commands::debug(),
-                commands::version(),
-                commands::dry_run(),
-            ],
-            on_error: |error| {
-                Box::pin(async move {
-                    if let FrameworkError::Command { error, ctx } = error {
-                        debug!("Notifying user of command error: {error:#}");
-                        if let Err(err) = ctx.say(format!("Error: {error:#}")).await {
-                            error!("Unable to send error due to {err:#}");
-                        }
-                    }
-                })
+            commands: commands(),
+            on_error: |error| Box::pin(on_error(error)),
+
+            prefix_options: prefix_options(),
+
+            event_handler: |ctx, event, framework, data| {
+                Box::pin(event_handler(ctx, event, framework, data))
             },
 
             ..Default::default()
         })
-        .client_settings(|client| client.event_handler(Handler))
         .token(env::var("TOKEN").expect("Expected a token in the environment"))
         .intents(serenity::GatewayIntents::all())
         .setup(|ctx, ready, framework| {
@@ -552,8 +550,34 @@ async fn main() -> Result<(), anyhow::Error> {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 info!("Finished registering global application (/) commands.");
 
+                let history = Arc::new(QueryHistory::open(
+                    store_path("HISTORY_FILE", "history.txt"),
+                    history_retention(),
+                ));
+                tokio::spawn(history::enforce_retention(Arc::clone(&history)));
+
                 Ok(Data {
                     shard_manager: Arc::clone(framework.shard_manager()),
+                    config: ConfigStore::open(store_path("CONFIG_FILE", "config.txt")),
+                    cooldowns: CooldownTracker::new(),
+                    duplicate_queries: DuplicateQueryTracker::new(),
+                    history,
+                    stats: UsageStats::open(store_path("STATS_FILE", "stats.txt")),
+                    preferences: PreferenceStore::open(store_path(
+                        "PREFERENCES_FILE",
+                        "preferences.txt",
+                    )),
+                    query_cache: QueryCache::new(QUERY_CACHE_TTL),
+                    reply_tracker: ReplyTracker::new(REPLY_TRACKING_TTL),
+                    recipients: RecipientStore::new(RECIPIENTS_TTL),
+                    send_queues: SendQueues::new(),
+                    pending_sends: PendingSendStore::open(store_path(
+                        "PENDING_SENDS_FILE",
+                        "pending_sends.txt",
+                    )),
+                    webhooks: WebhookStore::new(),
+                    activity: ActivityTracker::open(store_path("ACTIVITY_FILE", "activity.txt")),
+                    schedule: Schedule::open(store_path("SCHEDULE_FILE", "schedule.txt")),
                 })
             })
         });
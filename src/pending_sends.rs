@@ -0,0 +1,307 @@
+//! Split notifications interrupted by a restart
+//!
+//! Notifications which don't fit in a single message are sent one message at a time, so stopping
+//! Intersection part-way through one would leave the rest of its members unmentioned. While such a
+//! notification is sent, the messages it has left are written to a file, which is read again on
+//! startup. Notifications interrupted shortly before are finished, while the authors of older ones
+//! are told delivery was interrupted instead, as mentions long after the query would only confuse.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, MessageId, UserId};
+use tracing::{info, warn};
+
+use crate::{
+    config::ConfigStore,
+    i18n::{self, Language},
+    persistence::StoreFile,
+};
+
+/// How long after it started sending an interrupted notification is still finished
+const RESUME_WINDOW: Duration = Duration::from_mins(10);
+
+/// A split notification which hasn't been sent completely
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSend {
+    /// The guild it's sent in
+    pub guild: GuildId,
+    /// The channel it's sent in
+    pub channel: ChannelId,
+    /// The author of its query
+    pub author: UserId,
+    /// When it started sending
+    pub started: SystemTime,
+    /// Whether its messages are sent silently
+    pub silent: bool,
+    /// The messages it has left to send, in order
+    pub remaining: Vec<String>,
+}
+
+impl PendingSend {
+    /// Write this notification, which starts with `notice`, as a single line.
+    ///
+    /// Fields are separated by tabs, which (like newlines) can't appear in mention messages.
+    fn to_line(&self, notice: MessageId) -> String {
+        let started = self
+            .started
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        [
+            notice.to_string(),
+            self.guild.to_string(),
+            self.channel.to_string(),
+            self.author.to_string(),
+            started.to_string(),
+            u8::from(self.silent).to_string(),
+        ]
+        .into_iter()
+        .chain(self.remaining.iter().cloned())
+        .collect::<Vec<_>>()
+        .join("\t")
+    }
+
+    /// Read a notification written by [`PendingSend::to_line`], along with its first message.
+    fn from_line(line: &str) -> Option<(MessageId, Self)> {
+        let mut fields = line.split('\t');
+        let mut id = || fields.next()?.parse::<u64>().ok();
+        let notice = MessageId(id()?);
+        let guild = GuildId(id()?);
+        let channel = ChannelId(id()?);
+        let author = UserId(id()?);
+        let started = UNIX_EPOCH + Duration::from_secs(id()?);
+        let silent = id()? != 0;
+        Some((
+            notice,
+            Self {
+                guild,
+                channel,
+                author,
+                started,
+                silent,
+                remaining: fields.map(ToString::to_string).collect(),
+            },
+        ))
+    }
+}
+
+/// Keeps track of the split notifications being sent, in a file if given one
+#[derive(Debug)]
+pub struct PendingSendStore {
+    /// The file notifications being sent are written to, if they're kept across restarts
+    file: StoreFile,
+    /// Every notification being sent, keyed by the ID of its first message
+    sends: Mutex<HashMap<MessageId, PendingSend>>,
+    /// The notifications read from `file` on startup, which haven't been handled yet
+    interrupted: Mutex<Vec<MessageId>>,
+}
+
+impl PendingSendStore {
+    /// Create a new [`PendingSendStore`] writing to `path`, reading any notifications which were
+    /// interrupted the last time from it.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let file = StoreFile::new(path, "interrupted notifications");
+        let sends = file
+            .load(PendingSend::from_line)
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        Self {
+            file,
+            interrupted: Mutex::new(sends.keys().copied().collect()),
+            sends: Mutex::new(sends),
+        }
+    }
+
+    /// Remember the messages the notification starting with `notice` has left to send.
+    ///
+    /// `send.started` is ignored if the notification was already being tracked.
+    pub fn update(&self, notice: MessageId, send: PendingSend) {
+        let mut sends = self.sends.lock().expect("pending send lock was poisoned");
+        let started = sends.get(&notice).map_or(send.started, |old| old.started);
+        sends.insert(notice, PendingSend { started, ..send });
+        // Saving with the lock held keeps an older save from overwriting a newer one
+        self.save(&sends);
+        drop(sends);
+    }
+
+    /// Forget the notification starting with `notice`, once it has been sent completely.
+    pub fn finish(&self, notice: MessageId) {
+        let mut sends = self.sends.lock().expect("pending send lock was poisoned");
+        if sends.remove(&notice).is_some() {
+            self.save(&sends);
+        }
+        drop(sends);
+    }
+
+    /// Take the notifications which were interrupted the last time, so they're only handled once.
+    fn take_interrupted(&self) -> Vec<(MessageId, PendingSend)> {
+        let interrupted = std::mem::take(
+            &mut *self
+                .interrupted
+                .lock()
+                .expect("pending send lock was poisoned"),
+        );
+        let sends = self.sends.lock().expect("pending send lock was poisoned");
+        interrupted
+            .into_iter()
+            .filter_map(|notice| Some((notice, sends.get(&notice)?.clone())))
+            .collect()
+    }
+
+    /// Write every notification being sent to the file.
+    fn save(&self, sends: &HashMap<MessageId, PendingSend>) {
+        self.file
+            .save(sends.iter().map(|(notice, send)| send.to_line(*notice)));
+    }
+}
+
+/// Finish sending the notifications which were interrupted the last time Intersection stopped, or
+/// tell their authors they were interrupted if it's been too long.
+pub async fn resume_interrupted(
+    ctx: &serenity::Context,
+    store: &PendingSendStore,
+    config: &ConfigStore,
+) {
+    for (notice, send) in store.take_interrupted() {
+        let language = config.get(send.guild).language;
+        let link = notice.link(send.channel, Some(send.guild));
+        let recent = send
+            .started
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed < RESUME_WINDOW);
+        let result = if recent {
+            info!("Resuming interrupted notification {notice}");
+            resume(ctx, store, notice, &send, &link, language).await
+        } else {
+            info!("Abandoning interrupted notification {notice}");
+            send.channel
+                .send_message(ctx, |builder| {
+                    builder.content(i18n::message_count(
+                        language,
+                        "interrupted-abandoned",
+                        send.remaining.len(),
+                        &[("author", &format!("<@{}>", send.author)), ("link", &link)],
+                    ))
+                })
+                .await
+                .map(|_| ())
+        };
+        if let Err(err) = result {
+            warn!("Unable to handle interrupted notification {notice}: {err}");
+        }
+        // Either way, it's been dealt with as well as it can be
+        store.finish(notice);
+    }
+}
+
+/// Send the rest of an interrupted notification, keeping track of its progress in case of another
+/// restart.
+async fn resume(
+    ctx: &serenity::Context,
+    store: &PendingSendStore,
+    notice: MessageId,
+    send: &PendingSend,
+    link: &str,
+    language: Language,
+) -> serenity::Result<()> {
+    for (sent, message) in send.remaining.iter().enumerate() {
+        send.channel
+            .send_message(ctx, |builder| {
+                if send.silent {
+                    builder.flags(serenity::MessageFlags::SUPPRESS_NOTIFICATIONS);
+                }
+                builder.content(message)
+            })
+            .await?;
+        store.update(
+            notice,
+            PendingSend {
+                remaining: send.remaining[sent + 1..].to_vec(),
+                ..send.clone()
+            },
+        );
+    }
+
+    send.channel
+        .send_message(ctx, |builder| {
+            builder.content(i18n::message(
+                language,
+                "interrupted-resumed",
+                &[("link", &link)],
+            ))
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn pending_send() -> PendingSend {
+        PendingSend {
+            guild: GuildId(1),
+            channel: ChannelId(2),
+            author: UserId(3),
+            started: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            silent: true,
+            remaining: vec!["(part 2/3) <@4>".to_string(), "(part 3/3) <@5>".to_string()],
+        }
+    }
+
+    #[test]
+    fn pending_sends_survive_being_written_out() {
+        let send = pending_send();
+
+        assert_eq!(
+            PendingSend::from_line(&send.to_line(MessageId(10))),
+            Some((MessageId(10), send))
+        );
+        assert_eq!(PendingSend::from_line("10\tnot a guild"), None);
+    }
+
+    #[test]
+    fn interrupted_sends_are_read_on_startup() {
+        let path = env::temp_dir().join(format!("intersection-pending-{}", std::process::id()));
+        let store = PendingSendStore::open(Some(path.clone()));
+        assert!(store.take_interrupted().is_empty());
+
+        store.update(MessageId(10), pending_send());
+        store.update(MessageId(11), pending_send());
+        store.finish(MessageId(11));
+        // A later update keeps when the notification started
+        store.update(
+            MessageId(10),
+            PendingSend {
+                started: SystemTime::now(),
+                remaining: vec!["(part 3/3) <@5>".to_string()],
+                ..pending_send()
+            },
+        );
+
+        // As if Intersection restarted
+        let store = PendingSendStore::open(Some(path.clone()));
+        assert_eq!(
+            store.take_interrupted(),
+            vec![(
+                MessageId(10),
+                PendingSend {
+                    remaining: vec!["(part 3/3) <@5>".to_string()],
+                    ..pending_send()
+                }
+            )]
+        );
+        // They're only handled once
+        assert!(store.take_interrupted().is_empty());
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+}
@@ -0,0 +1,233 @@
+//! The files Intersection's stores are kept in across restarts
+//!
+//! Each store kept across restarts, like the configuration of every guild or the queries scheduled
+//! in them, is written to its own file as one entry per line, usually with tab separated fields.
+//! The whole file is replaced at once, by writing a temporary file and renaming it over the old
+//! one, so stopping part-way through a save never leaves half a file behind. Stores which aren't
+//! given a file are kept in memory only.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tracing::warn;
+
+/// The file a store is kept in, if it was given one
+#[derive(Debug, Clone)]
+pub struct StoreFile {
+    /// What the file holds, like `member activity`, to log problems with it
+    contents: &'static str,
+    /// The file, unless the store is kept in memory only
+    file: Option<Arc<File>>,
+}
+
+/// A file a store is written to
+#[derive(Debug)]
+struct File {
+    /// Where the file is
+    path: PathBuf,
+    /// The number of the next save, so a save that finishes late doesn't replace a newer one
+    next_save: AtomicU64,
+    /// The number of the latest save written, locked while writing
+    written: Mutex<u64>,
+}
+
+impl File {
+    /// Replace the file with `text` for save number `save`, unless a newer save was written first.
+    fn write(&self, save: u64, text: &str, contents: &str) {
+        let mut written = self.written.lock().expect("store file lock was poisoned");
+        if save <= *written {
+            return;
+        }
+        *written = save;
+
+        let temporary = self.path.with_extension("tmp");
+        if let Err(err) =
+            fs::write(&temporary, text).and_then(|()| fs::rename(&temporary, &self.path))
+        {
+            warn!("Unable to save {contents}: {err}");
+        }
+        drop(written);
+    }
+}
+
+/// Join `lines` into the text of a file, with every line ending in a newline.
+fn render(lines: impl IntoIterator<Item = String>) -> String {
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+impl StoreFile {
+    /// A store kept in `path`, or in memory only if it's `None`, which holds `contents`.
+    pub fn new(path: Option<PathBuf>, contents: &'static str) -> Self {
+        Self {
+            contents,
+            file: path.map(|path| {
+                Arc::new(File {
+                    path,
+                    next_save: AtomicU64::new(1),
+                    written: Mutex::new(0),
+                })
+            }),
+        }
+    }
+
+    /// Read every line of the file with `parse`, skipping (and warning about) lines it can't read.
+    ///
+    /// A file which doesn't exist yet is empty.
+    pub fn load<T>(&self, mut parse: impl FnMut(&str) -> Option<T>) -> Vec<T> {
+        let Some(file) = &self.file else {
+            return Vec::new();
+        };
+        match fs::read_to_string(&file.path) {
+            Ok(text) => text
+                .lines()
+                .filter_map(|line| {
+                    let parsed = parse(line);
+                    if parsed.is_none() {
+                        warn!("Ignoring invalid {}: {line}", self.contents);
+                    }
+                    parsed
+                })
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                warn!("Unable to read {}: {err}", self.contents);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Replace the file with `lines` straight away.
+    ///
+    /// Saving with the store's lock held keeps an older save from overwriting a newer one.
+    pub fn save(&self, lines: impl IntoIterator<Item = String>) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let save = file.next_save.fetch_add(1, Ordering::Relaxed);
+        file.write(save, &render(lines), self.contents);
+    }
+
+    /// Replace the file with the lines `lines` returns, which is run on a blocking thread so the
+    /// runtime isn't held up by writing.
+    ///
+    /// This should be called with the store's lock held, giving `lines` a snapshot of the store, so
+    /// the saves are numbered in order. The file is written straight away outside of the runtime.
+    pub fn save_in_background<L>(&self, lines: impl FnOnce() -> L + Send + 'static)
+    where
+        L: IntoIterator<Item = String>,
+    {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let save = file.next_save.fetch_add(1, Ordering::Relaxed);
+        let file = Arc::clone(file);
+        let contents = self.contents;
+        let write = move || file.write(save, &render(lines()), contents);
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(write)),
+            Err(_) => write(),
+        }
+    }
+}
+
+/// Escape backslashes, tabs and newlines in free text, like a query, so it fits in one field.
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+/// Read free text written by [`escape`], or `None` if it wasn't escaped properly.
+pub fn unescape(field: &str) -> Option<String> {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(char) = chars.next() {
+        text.push(match char {
+            '\\' => match chars.next()? {
+                '\\' => '\\',
+                't' => '\t',
+                'n' => '\n',
+                _ => return None,
+            },
+            '\t' | '\n' => return None,
+            char => char,
+        });
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn lines_survive_being_saved() {
+        let path = env::temp_dir().join(format!("intersection-store-{}", std::process::id()));
+        let file = StoreFile::new(Some(path.clone()), "test lines");
+        assert!(file.load(|line| Some(line.to_string())).is_empty());
+
+        file.save([
+            "1\ta".to_string(),
+            "invalid".to_string(),
+            "2\tb".to_string(),
+        ]);
+        assert_eq!(
+            file.load(|line| line.split_once('\t').map(|(id, _)| id.to_string())),
+            ["1", "2"]
+        );
+
+        // A save which finishes after a newer one isn't written
+        let inner = file.file.as_ref().expect("the store has a file");
+        let older = inner.next_save.fetch_add(1, Ordering::Relaxed);
+        file.save(["newer".to_string()]);
+        inner.write(older, "older\n", "test lines");
+        assert_eq!(file.load(|line| Some(line.to_string())), ["newer"]);
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+
+    #[tokio::test]
+    async fn background_saves_are_written_eventually() {
+        let path = env::temp_dir().join(format!("intersection-background-{}", std::process::id()));
+        let file = StoreFile::new(Some(path.clone()), "test lines");
+        file.save_in_background(|| ["older".to_string()]);
+        file.save_in_background(|| ["newer".to_string()]);
+
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            lines = file.load(|line| Some(line.to_string()));
+            if lines == ["newer"] {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(lines, ["newer"]);
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+
+    #[test]
+    fn stores_without_a_file_stay_in_memory() {
+        let file = StoreFile::new(None, "test lines");
+        file.save(["1".to_string()]);
+        file.save_in_background(|| ["2".to_string()]);
+        assert!(file.load(|line| Some(line.to_string())).is_empty());
+    }
+
+    #[test]
+    fn free_text_fits_in_a_field() {
+        let text = "a\tb\nc \\t";
+        assert_eq!(escape(text), "a\\tb\\nc \\\\t");
+        assert_eq!(unescape(&escape(text)).as_deref(), Some(text));
+        assert_eq!(unescape("a\\x"), None);
+        assert_eq!(unescape("a\tb"), None);
+    }
+}
@@ -0,0 +1,4971 @@
+//! The query pipeline: parsing, evaluating, confirming, and sending the mentions for a query
+//!
+//! Everything here talks to Discord through the [`Discord`] trait, so the whole pipeline can be
+//! tested against an in-memory fake.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    ops::ControlFlow,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use intersection::drql::{
+    self,
+    ast::Expr,
+    interpreter::{Counts, Limits},
+};
+use poise::serenity_prelude::{self as serenity, Guild, GuildChannel, Member, RoleId, UserId};
+use tokio::sync::watch;
+use tracing::{debug, error, info, instrument, trace, warn};
+
+use crate::{
+    activity::ActivityTracker,
+    config::{EveryoneHere, GuildConfig, RoleNameMatching},
+    cooldowns,
+    discord::{Button, ButtonResponse, Discord, Embed, OutgoingMessage, TextPrompt},
+    duplicates::DuplicateQueryMode,
+    error::{report_internal_error, QueryError},
+    extensions::CustomGuildImpl,
+    i18n::{self, Language},
+    models::{self, mention::RoleType},
+    query_cache::{QueryCache, QueryCacheKey},
+    recipients, reply_tracker, resolver, retry, util,
+};
+
+/// How many members a query must match (more than) to need confirming, unless the guild chose a
+/// different number.
+pub const DEFAULT_CONFIRMATION_THRESHOLD: usize = 50;
+
+/// How long the author has to respond to the confirmation prompt, unless the guild chose a
+/// different time.
+pub const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long moderators have to approve a notification needing [`ModeratorApproval`].
+const APPROVAL_TIMEOUT: Duration = Duration::from_mins(5);
+
+/// The longest value Discord accepts for a field of an embed.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// How many members are added to a thread between each progress update.
+const THREAD_BATCH_SIZE: usize = 25;
+
+/// How many mention messages are sent between each update of the "sending N messages" notice.
+const PROGRESS_INTERVAL: usize = 5;
+
+/// How long evaluating a query may take before the author is shown its progress.
+pub const EVALUATION_PROGRESS_DELAY: Duration = Duration::from_secs(2);
+
+/// How long each operand of a query may take to resolve before it is given up on.
+const OPERAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest name Discord allows for a thread.
+const MAX_THREAD_NAME_LENGTH: usize = 100;
+
+/// Parse each chunk of a query and reduce them into a single AST (the union of every chunk), whose
+/// `aliases` are then expanded, and which is then optimized so repeated operands are only resolved
+/// once.
+///
+/// Queries too big to interpret are refused before anything else recurses into them, both before
+/// and after their aliases are expanded. Any problems are explained in `language`.
+pub fn parse_chunks(
+    chunks: &[&str],
+    aliases: &BTreeMap<String, String>,
+    language: Language,
+) -> Result<Expr, QueryError> {
+    let ast = chunks
+        .iter()
+        .enumerate()
+        .map(|(n, chunk)| {
+            drql::parser::parse_drql(chunk).map_err(|error| QueryError::ParseError {
+                chunk: n,
+                contents: (*chunk).to_string(),
+                error,
+                language,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .reduce(|acc, chunk| Expr::Union(Box::new(acc), Box::new(chunk)))
+        .ok_or_else(|| {
+            // This should never happen, as we already checked that there was at least one chunk in the input
+            QueryError::ResolutionError(i18n::message(language, "no-query", &[]))
+        })?;
+    let limits = Limits::default();
+    let too_complex = |error| QueryError::too_complex(error, language);
+    limits.check(&ast).map_err(too_complex)?;
+    let ast = drql::aliases::expand(ast, |name| aliases.get(name).cloned(), limits.max_nodes)
+        .map_err(|error| QueryError::from_alias_error(error, aliases, language))?;
+    limits.check(&ast).map_err(too_complex)?;
+    Ok(drql::optimizer::optimize(ast))
+}
+
+/// Count the messages mentioning `members` would take, using the roles in
+/// `roles_and_their_members` where possible, split the same way as when they're notified.
+pub fn count_messages(
+    members: &HashSet<UserId>,
+    roles_and_their_members: &HashMap<RoleType, HashSet<UserId>>,
+) -> usize {
+    Mentions::new(members, roles_and_their_members)
+        .to_messages(2000)
+        .len()
+}
+
+/// Show the chunks of a query as they were written, like `` `@{a}` `@{b}` ``.
+///
+/// Each chunk is shown as code, so mentions written in it don't ping anyone. Backticks in a chunk
+/// would end the code early, so they're replaced with a similar-looking character.
+pub fn describe_query(chunks: &[&str]) -> String {
+    chunks
+        .iter()
+        .map(|chunk| format!("`@{{{}}}`", chunk.replace('`', "\u{2cb}")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The result of evaluating a query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Evaluation {
+    /// The members matched by the query
+    pub members: HashSet<UserId>,
+    /// Roles used by the query which the author is not allowed to mention
+    ///
+    /// Their members are still included in `members`, but the roles themselves must not be
+    /// mentioned.
+    pub unmentionable_roles: HashSet<RoleId>,
+    /// Operands of the query which took too long to resolve, as written in DRQL
+    ///
+    /// These are treated as matching nobody, so `members` may be incomplete.
+    pub timed_out: Vec<String>,
+    /// Members matched by the query who were left out of `members`, as they have the guild's
+    /// do-not-ping role
+    pub excluded: HashSet<UserId>,
+    /// How many members each operand of the outermost operator of the query matched, as written
+    /// in DRQL, followed by the operator itself, like `staff: 41, here: 102, intersection: 17`
+    ///
+    /// This is empty if the query is a single operand. Members with the do-not-ping role aren't
+    /// left out of these counts.
+    pub breakdown: Vec<(String, usize)>,
+}
+
+impl Evaluation {
+    /// Leave the members of `never_mentioned` out of the results, whatever the query, keeping
+    /// track of whom that removed.
+    pub fn exclude(&mut self, never_mentioned: &HashSet<UserId>) {
+        self.excluded = self
+            .members
+            .intersection(never_mentioned)
+            .copied()
+            .collect();
+        self.members
+            .retain(|member| !never_mentioned.contains(member));
+    }
+}
+
+/// Break down how many members each operand of the outermost operator of `ast` matched, followed
+/// by the operator itself (see [`Evaluation::breakdown`]).
+fn breakdown(ast: &Expr, counts: &Counts) -> Vec<(String, usize)> {
+    let operator = match ast {
+        Expr::Union(_, _) => "union",
+        Expr::Intersection(_, _) => "intersection",
+        Expr::Difference(_, _) => "difference",
+        Expr::Complement(_) => "complement",
+        Expr::Limit(_, _) => "limit",
+        Expr::Sample(_, _) => "sample",
+        Expr::AtLeast(_, _) => "atleast",
+        Expr::StringLiteral(_)
+        | Expr::UnknownID(_)
+        | Expr::UserID(_)
+        | Expr::RoleID(_)
+        | Expr::ChannelID(_)
+        | Expr::Me
+        | Expr::Event(_)
+        | Expr::Permission(_)
+        | Expr::Joined(_, _)
+        | Expr::AccountAge(_)
+        | Expr::Name(_)
+        | Expr::Active(_)
+        | Expr::Reacted(_, _)
+        | Expr::Alias(_) => return Vec::new(),
+    };
+    ast.children()
+        .into_iter()
+        .map(|operand| (drql::formatter::format(operand), operand))
+        .chain([(operator.to_string(), ast)])
+        .filter_map(|(written, node)| Some((written, counts.get(node)?)))
+        .collect()
+}
+
+/// Everything about where and by whom a query was sent that evaluating it depends on
+///
+/// This is built once for each query, and every way of evaluating it (like
+/// [`parse_and_evaluate_query`], [`explain_query`] and [`bench_query`]) makes its resolver from it.
+#[derive(Clone, Copy)]
+pub struct QueryContext<'a> {
+    /// The Context made available to the event handler or command
+    pub ctx: &'a serenity::Context,
+    /// When each member last sent a message, which `active(...)` refers to
+    pub activity: &'a ActivityTracker,
+    /// The guild the query was sent in
+    pub guild: &'a Guild,
+    /// The member who sent the query
+    pub member: &'a Member,
+    /// The channel the query was sent in, or the one its thread is in
+    pub channel: &'a GuildChannel,
+    /// The guild's do-not-ping role, whose members are left out of the results
+    pub do_not_ping: Option<RoleId>,
+    /// Whether roles are only matched by their exact name, case included
+    pub case_sensitive_roles: bool,
+    /// Whether the guild lets `everyone` and `here` be used in queries
+    pub everyone_here: &'a EveryoneHere,
+    /// The guild's aliases, which the query may use
+    pub aliases: &'a BTreeMap<String, String>,
+    /// The language to explain problems with the query in
+    pub language: Language,
+}
+
+impl<'a> QueryContext<'a> {
+    /// The context of a query sent by `member` in `channel`, evaluated with the guild's `config`.
+    pub fn new(
+        ctx: &'a serenity::Context,
+        activity: &'a ActivityTracker,
+        guild: &'a Guild,
+        member: &'a Member,
+        channel: &'a GuildChannel,
+        config: &'a GuildConfig,
+    ) -> Self {
+        Self {
+            ctx,
+            activity,
+            guild,
+            member,
+            channel,
+            do_not_ping: config.do_not_ping,
+            case_sensitive_roles: config.role_names == RoleNameMatching::ExactCase,
+            everyone_here: &config.everyone_here,
+            aliases: &config.aliases,
+            language: config.language,
+        }
+    }
+
+    /// A resolver for the query, giving up on operands after [`OPERAND_TIMEOUT`], which reports
+    /// how many operands it has resolved to `progress`, if given.
+    fn resolver<'r>(
+        &'r self,
+        progress: Option<&'r watch::Sender<usize>>,
+    ) -> resolver::Timeouts<resolver::Resolver<'r>> {
+        resolver::Timeouts {
+            inner: resolver::Resolver {
+                guild: self.guild,
+                member: self.member,
+                ctx: self.ctx,
+                channel: self.channel,
+                unmentionable_roles: Mutex::default(),
+                progress,
+                activity: self.activity,
+                case_sensitive_roles: self.case_sensitive_roles,
+                everyone_here: self.everyone_here,
+                language: self.language,
+            },
+            timeout: OPERAND_TIMEOUT,
+            timed_out: Mutex::default(),
+        }
+    }
+}
+
+/// Process a DRQL query from a single slice of Query chunk strings
+/// and return the resulting [`Evaluation`]
+///
+/// Results are cached in the provided [`QueryCache`], so evaluating the same query again shortly
+/// after will not re-run the interpreter. Incomplete results, where some operands took longer than
+/// [`OPERAND_TIMEOUT`] to resolve, are not cached, so the query can be retried.
+///
+/// Members of the guild's do-not-ping role are left out of the results, whatever the query.
+#[instrument(skip_all)]
+pub async fn parse_and_evaluate_query(
+    context: &QueryContext<'_>,
+    query_cache: &QueryCache,
+    chunks: &[&str],
+    progress: Option<&watch::Sender<usize>>,
+) -> Result<Evaluation, QueryError> {
+    trace!("Parsing each chunk...");
+
+    let ast = parse_chunks(chunks, context.aliases, context.language)?;
+
+    debug!("Fully parsed and reduced AST: {ast:?}");
+
+    let cache_key = QueryCacheKey {
+        channel: context.channel.id,
+        author: context.member.user.id,
+        query: ast.to_string(),
+    };
+    if let Some(evaluation) = query_cache.get(context.guild.id, &cache_key) {
+        debug!("Using cached result for query {}", cache_key.query);
+        return Ok(exclude_do_not_ping(
+            evaluation,
+            context.guild,
+            context.do_not_ping,
+        ));
+    }
+
+    trace!("Running DRQL interpreter on AST");
+    let resolver = context.resolver(progress);
+    let (members, counts) =
+        drql::interpreter::interpret_counting(&ast, &resolver, Limits::default()).await?;
+
+    debug!(
+        "Evaluated result: {:?}",
+        members.iter().map(|id| id.0).collect::<Vec<_>>()
+    );
+
+    let evaluation = Evaluation {
+        members,
+        unmentionable_roles: resolver
+            .inner
+            .unmentionable_roles
+            .into_inner()
+            .expect("unmentionable roles lock was poisoned"),
+        timed_out: resolver
+            .timed_out
+            .into_inner()
+            .expect("timed out operands lock was poisoned"),
+        excluded: HashSet::new(),
+        breakdown: breakdown(&ast, &counts),
+    };
+    if evaluation.timed_out.is_empty() {
+        query_cache.insert(context.guild.id, cache_key, evaluation.clone());
+    } else {
+        debug!(
+            "Not caching incomplete result, {:?} timed out",
+            evaluation.timed_out
+        );
+    }
+
+    Ok(exclude_do_not_ping(
+        evaluation,
+        context.guild,
+        context.do_not_ping,
+    ))
+}
+
+/// Evaluate a DRQL query like [`parse_and_evaluate_query`], but draw it as a tree showing how many
+/// members each part of it matched, like `& => 17` over `staff (41)` and `here (102)`.
+///
+/// This is for understanding why a query matched who it did, so nothing is cached, and the
+/// do-not-ping role isn't left out.
+#[instrument(skip_all)]
+pub async fn explain_query(
+    context: &QueryContext<'_>,
+    chunks: &[&str],
+) -> Result<String, QueryError> {
+    let ast = parse_chunks(chunks, context.aliases, context.language)?;
+    let (_, counts) =
+        drql::interpreter::interpret_counting(&ast, &context.resolver(None), Limits::default())
+            .await?;
+    Ok(drql::formatter::tree(&ast, |node| counts.get(node)))
+}
+
+/// How long each stage of evaluating the queries in a message took, from [`bench_query`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StageTimings {
+    /// How many chunks of a query were found in the message
+    pub chunks: usize,
+    /// How long finding the chunks in the message took
+    pub scan: Duration,
+    /// How long parsing and optimizing the chunks took
+    pub parse: Duration,
+    /// How long resolving the query to its members took
+    pub resolve: Duration,
+    /// How long representing the members as role and user mentions took, including finding the
+    /// members of every role
+    pub unionize: Duration,
+    /// How many times each resolver method was called, by name
+    pub calls: BTreeMap<&'static str, usize>,
+    /// How many members the query matched
+    pub members: usize,
+    /// How many roles and members would be mentioned
+    pub mentions: usize,
+}
+
+impl StageTimings {
+    /// Write out the timings and resolver calls, explained in `language`, for `/debug bench`.
+    #[must_use]
+    pub fn report(&self, language: Language) -> String {
+        let calls = if self.calls.is_empty() {
+            i18n::message(language, "bench-no-calls", &[])
+        } else {
+            self.calls
+                .iter()
+                .map(|(method, count)| format!("{method}: {count}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        format!(
+            "{}\n```\nscan:     {:?}\nparse:    {:?}\nresolve:  {:?}\nunionize: {:?}\n```\n{}\n```\n\
+             {calls}\n```",
+            i18n::message(
+                language,
+                "bench-summary",
+                &[
+                    ("chunks", &self.chunks),
+                    ("members", &self.members),
+                    ("mentions", &self.mentions),
+                ],
+            ),
+            self.scan,
+            self.parse,
+            self.resolve,
+            self.unionize,
+            i18n::message(language, "bench-calls", &[]),
+        )
+    }
+}
+
+/// Scan, parse, resolve and unionize the queries in `message` like [`handle_drql_query`] would,
+/// timing each stage and counting the resolver calls it makes, without confirming or sending
+/// anything.
+///
+/// Nothing is cached, so every operand is resolved from scratch.
+#[instrument(skip_all)]
+pub async fn bench_query(
+    context: &QueryContext<'_>,
+    message: &str,
+) -> Result<StageTimings, QueryError> {
+    let started = Instant::now();
+    let chunks = drql::scanner::scan(message).collect::<Vec<_>>();
+    let scan = started.elapsed();
+    if chunks.is_empty() {
+        return Err(QueryError::ResolutionError(i18n::message(
+            context.language,
+            "no-query",
+            &[],
+        )));
+    }
+
+    let started = Instant::now();
+    let ast = parse_chunks(&chunks, context.aliases, context.language)?;
+    let parse = started.elapsed();
+
+    let started = Instant::now();
+    let resolver = resolver::Calls {
+        inner: context.resolver(None),
+        calls: Mutex::default(),
+    };
+    let members = drql::interpreter::interpret(ast, &resolver).await?;
+    let resolve = started.elapsed();
+
+    let started = Instant::now();
+    let mentions = Mentions::new(&members, &context.guild.all_roles_and_members(context.ctx)?);
+    let unionize = started.elapsed();
+
+    Ok(StageTimings {
+        chunks: chunks.len(),
+        scan,
+        parse,
+        resolve,
+        unionize,
+        calls: resolver
+            .calls
+            .into_inner()
+            .expect("resolver calls lock was poisoned"),
+        members: members.len(),
+        mentions: mentions.roles.len() + mentions.outliers.len(),
+    })
+}
+
+/// Leave the members of the guild's do-not-ping role, if it has one, out of an evaluation.
+///
+/// This is done after caching, so changes to the role's members or to which role it is take effect
+/// straight away.
+fn exclude_do_not_ping(
+    mut evaluation: Evaluation,
+    guild: &Guild,
+    do_not_ping: Option<RoleId>,
+) -> Evaluation {
+    if let Some(role) = do_not_ping {
+        let never_mentioned = guild
+            .members
+            .values()
+            .filter(|member| member.roles.contains(&role))
+            .map(|member| member.user.id)
+            .collect();
+        evaluation.exclude(&never_mentioned);
+        debug!(
+            "Left out {} members with the do-not-ping role",
+            evaluation.excluded.len()
+        );
+    }
+
+    evaluation
+}
+
+/// Evaluate some chunks of a query with [`Discord::evaluate`], showing the author that we're
+/// working on it if it takes a while, so they don't send the query again.
+///
+/// Slow evaluations first get a typing indicator, then an interim message counting the resolved
+/// operands, which is deleted once the evaluation finishes.
+async fn evaluate_with_progress(
+    discord: &impl Discord,
+    chunks: &[&str],
+    language: Language,
+) -> Result<Evaluation, QueryError> {
+    let (progress, mut resolved) = watch::channel(0);
+    let evaluation = discord.evaluate(chunks, &progress);
+    tokio::pin!(evaluation);
+
+    // Most queries are evaluated straight from the cache, without needing any feedback
+    tokio::select! {
+        biased;
+        result = &mut evaluation => return result,
+        () = std::future::ready(()) => {}
+    }
+    if let Err(err) = discord.broadcast_typing().await {
+        debug!("Unable to show the typing indicator: {err}");
+    }
+    tokio::select! {
+        biased;
+        result = &mut evaluation => return result,
+        () = discord.wait(EVALUATION_PROGRESS_DELAY) => {}
+    }
+
+    // The query must have parsed, or evaluating it would have failed right away
+    let total = parse_chunks(chunks, discord.aliases(), language)?.operand_count();
+    let progress_message = |resolved: usize| {
+        OutgoingMessage::text(i18n::message(
+            language,
+            "evaluating",
+            &[("resolved", &resolved), ("total", &total)],
+        ))
+    };
+    let count = *resolved.borrow_and_update();
+    let interim = match discord.reply(progress_message(count)).await {
+        Ok(interim) => interim,
+        Err(err) => {
+            // Progress is only informational, so failing to show it shouldn't stop the query
+            warn!("Unable to show the progress of an evaluation: {err}");
+            return evaluation.await;
+        }
+    };
+
+    let result = loop {
+        tokio::select! {
+            biased;
+            result = &mut evaluation => break result,
+            Ok(()) = resolved.changed() => {
+                let count = *resolved.borrow_and_update();
+                if let Err(err) = discord.edit(interim, progress_message(count)).await {
+                    warn!("Unable to update the progress of an evaluation: {err}");
+                }
+            }
+        }
+    };
+    if let Err(err) = discord.delete(interim).await {
+        warn!("Unable to delete the progress of an evaluation: {err}");
+    }
+
+    result
+}
+
+/// Who has to confirm a large notification, and how
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Confirmation {
+    /// The author presses a button
+    Click,
+    /// The author types the number of members it mentions, so it can't be confirmed reflexively
+    Typed,
+    /// A member of the role other than the author presses a button
+    Approval(RoleId),
+}
+
+/// Wait up to `timeout` for the author to press one of the buttons on `prompt`, sent as `id`,
+/// returning its custom ID.
+///
+/// If `reminder`, the prompt is edited halfway through to say it's still waiting for the author,
+/// and for how much longer.
+async fn await_confirmation(
+    discord: &impl Discord,
+    id: serenity::MessageId,
+    prompt: &OutgoingMessage,
+    timeout: Duration,
+    reminder: bool,
+    language: Language,
+) -> Result<Option<String>, QueryError> {
+    if !reminder {
+        return discord.await_button_press(id, timeout).await;
+    }
+
+    let half = timeout / 2;
+    if let Some(custom_id) = discord.await_button_press(id, half).await? {
+        return Ok(Some(custom_id));
+    }
+
+    trace!("reminding the author to confirm");
+    let left = timeout.saturating_sub(half);
+    edit_with_retry(
+        discord,
+        id,
+        OutgoingMessage {
+            content: format!(
+                "{}\n\n{}",
+                prompt.content,
+                i18n::message(
+                    language,
+                    "confirmation-reminder",
+                    &[("left", &cooldowns::describe_wait(left, language))],
+                )
+            ),
+            ..prompt.clone()
+        },
+    )
+    .await?;
+    discord.await_button_press(id, left).await
+}
+
+/// Prompts the user to confirm they want to execute a query
+///
+/// This is used when there are more `members_to_ping` than the guild's confirmation threshold
+/// ([`DEFAULT_CONFIRMATION_THRESHOLD`] unless it chose another) in a single query. Depending on
+/// `confirmation`, the author may have to type the number of members instead of pressing a
+/// button, or a moderator has to confirm instead, though the author can still cancel.
+///
+/// Will return Ok(Continue) if the query was confirmed (with the approver, if there is one),
+/// Ok(Break) if it was cancelled or timed out, and Err if there was an error.
+#[instrument(skip_all, fields(count = members_to_ping.len()))]
+async fn confirm_mention_count(
+    discord: &impl Discord,
+    mentions: &Mentions,
+    members_to_ping: &HashSet<UserId>,
+    confirmation: Confirmation,
+    options: QueryOptions,
+) -> Result<ControlFlow<(), Option<UserId>>, QueryError> {
+    trace!("sending confirmation message");
+
+    let language = options.language;
+    let ConfirmationTimeout { timeout, reminder } =
+        options.confirmation_timeout.unwrap_or(ConfirmationTimeout {
+            timeout: CONFIRMATION_TIMEOUT,
+            reminder: false,
+        });
+    let prompt = OutgoingMessage {
+        content: i18n::message(
+            language,
+            "confirm-mention-count",
+            &[
+                ("count", &members_to_ping.len()),
+                ("messages", &{
+                    let len = mentions.to_messages(2000).len();
+                    if len > 2 {
+                        i18n::message(
+                            language,
+                            "confirm-mention-count-messages",
+                            &[("count", &len)],
+                        )
+                    } else {
+                        String::new()
+                    }
+                }),
+                ("summary", &mentions.summary(language)),
+                (
+                    "requirement",
+                    &match confirmation {
+                        Confirmation::Click => String::new(),
+                        Confirmation::Typed => {
+                            i18n::message(language, "confirm-mention-count-typed", &[])
+                        }
+                        Confirmation::Approval(role) => i18n::message(
+                            language,
+                            "confirm-mention-count-approval",
+                            &[(
+                                "role",
+                                &models::mention::Mention::Role(RoleType::Role(role)),
+                            )],
+                        ),
+                    },
+                ),
+            ],
+        ),
+        buttons: vec![
+            Button {
+                custom_id: "large_ping_confirm_no".to_string(),
+                label: i18n::message(language, "cancel", &[]),
+                // X emoji
+                emoji: Some("\u{274c}".to_string()),
+                style: serenity::ButtonStyle::Secondary,
+            },
+            if confirmation == Confirmation::Typed {
+                Button {
+                    custom_id: "large_ping_confirm_typed".to_string(),
+                    label: i18n::message(language, "confirm-typed", &[]),
+                    // keyboard emoji
+                    emoji: Some("\u{2328}\u{fe0f}".to_string()),
+                    style: serenity::ButtonStyle::Danger,
+                }
+            } else {
+                Button {
+                    custom_id: "large_ping_confirm_yes".to_string(),
+                    label: i18n::message(language, "confirm-yes", &[]),
+                    // check mark emoji
+                    emoji: Some("\u{2705}".to_string()),
+                    style: serenity::ButtonStyle::Primary,
+                }
+            },
+        ],
+        ..Default::default()
+    }
+    // The summary includes role mentions, which shouldn't ping before confirming
+    .suppress_mentions(true);
+    let confirmation_message = reply_with_retry(discord, prompt.clone()).await?;
+
+    trace!("waiting for confirmation");
+
+    let count = members_to_ping.len();
+    let press = match confirmation {
+        Confirmation::Click => await_confirmation(
+            discord,
+            confirmation_message,
+            &prompt,
+            timeout,
+            reminder,
+            language,
+        )
+        .await?
+        .map(|custom_id| (custom_id, None)),
+        Confirmation::Typed => {
+            let prompt = TextPrompt {
+                title: i18n::message(language, "typed-confirmation-title", &[("count", &count)]),
+                label: i18n::message(language, "typed-confirmation-label", &[("count", &count)]),
+            };
+            match discord
+                .await_button_press_or_text(
+                    confirmation_message,
+                    "large_ping_confirm_typed",
+                    &prompt,
+                    timeout,
+                )
+                .await?
+            {
+                None => None,
+                Some(ButtonResponse::Pressed(custom_id)) => Some((custom_id, None)),
+                Some(ButtonResponse::Typed(text)) if text.trim() == count.to_string() => {
+                    Some(("large_ping_confirm_yes".to_string(), None))
+                }
+                Some(ButtonResponse::Typed(text)) => {
+                    debug!("User typed {text:?} instead of the member count");
+                    edit_with_retry(
+                        discord,
+                        confirmation_message,
+                        OutgoingMessage::text(i18n::message(
+                            language,
+                            "typed-confirmation-mismatch",
+                            &[("count", &count)],
+                        )),
+                    )
+                    .await?;
+                    return Ok(ControlFlow::Break(()));
+                }
+            }
+        }
+        Confirmation::Approval(role) => discord
+            .await_approval(
+                confirmation_message,
+                role,
+                &["large_ping_confirm_no"],
+                APPROVAL_TIMEOUT,
+            )
+            .await?
+            .map(|(custom_id, approver)| (custom_id, Some(approver))),
+    };
+    let Some((custom_id, approver)) = press else {
+        debug!("timed out waiting for confirmation");
+        edit_with_retry(
+            discord,
+            confirmation_message,
+            OutgoingMessage::text(i18n::message(language, "confirmation-timed-out", &[])),
+        )
+        .await?;
+        return Ok(ControlFlow::Break(()));
+    };
+
+    match custom_id.as_str() {
+        "large_ping_confirm_no" => {
+            debug!("User cancelled operation");
+            edit_with_retry(
+                discord,
+                confirmation_message,
+                OutgoingMessage::text(i18n::message(language, "cancelled", &[])),
+            )
+            .await?;
+
+            Ok(ControlFlow::Break(()))
+        }
+        "large_ping_confirm_yes" => {
+            debug!("User confirmed operation");
+            let edit = approver.map_or_else(
+                || i18n::message(language, "confirmed", &[]),
+                |approver| {
+                    info!("Notification approved by {approver}");
+                    i18n::message(
+                        language,
+                        "approved-by",
+                        &[("approver", &format!("<@{approver}>"))],
+                    )
+                },
+            );
+            edit_with_retry(
+                discord,
+                confirmation_message,
+                OutgoingMessage::text(edit).suppress_mentions(true),
+            )
+            .await?;
+
+            // continue normally!
+            Ok(ControlFlow::Continue(approver))
+        }
+        _ => Err(anyhow!("Discord sent us an invalid interaction customId!").into()),
+    }
+}
+
+/// Options controlling how the mentions for a query are delivered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Send the mention messages without triggering push notifications
+    pub silent: bool,
+    /// Evaluate and mention each chunk of the query separately, instead of their union
+    pub per_chunk: bool,
+    /// Explain the notification in an embed, leaving only the mentions in the message content
+    pub embed: bool,
+    /// How the notification is delivered
+    pub delivery: Delivery,
+    /// The most members a query may match, above which it is refused outright
+    pub max_mentions: Option<usize>,
+    /// The channel a summary of every notification is posted to
+    pub audit_channel: Option<serenity::ChannelId>,
+    /// The language messages are sent in
+    pub language: Language,
+    /// How long explanations of mistakes in the query are kept before being deleted
+    pub error_expiry: Option<Duration>,
+    /// How long the messages mentioning members are kept before being deleted
+    pub mention_expiry: Option<Duration>,
+    /// What happens if the query was sent in the channel shortly before
+    pub duplicate_queries: DuplicateQueryMode,
+    /// Which notifications need approving by a moderator, if any
+    pub approval: Option<ModeratorApproval>,
+    /// The number of members a query must match (at least) for its author to have to type that
+    /// number to confirm it, rather than just press a button
+    pub typed_confirmation: Option<usize>,
+    /// The number of members a query must match (more than) to need confirming, if not
+    /// [`DEFAULT_CONFIRMATION_THRESHOLD`]
+    pub confirmation_threshold: Option<usize>,
+    /// How long the author has to confirm a large query, if not [`CONFIRMATION_TIMEOUT`] without
+    /// a reminder
+    pub confirmation_timeout: Option<ConfirmationTimeout>,
+    /// Who paused query handling in the guild, if it's paused
+    pub paused_by: Option<UserId>,
+}
+
+impl QueryOptions {
+    /// The options a query in a guild configured with `config` is handled with.
+    pub const fn for_guild(config: &GuildConfig) -> Self {
+        Self {
+            silent: config.silent,
+            per_chunk: config.per_chunk,
+            embed: config.embed,
+            delivery: config.delivery,
+            max_mentions: config.max_mentions,
+            audit_channel: config.audit_channel,
+            language: config.language,
+            error_expiry: config.error_expiry,
+            mention_expiry: config.mention_expiry,
+            duplicate_queries: config.duplicate_queries.mode,
+            approval: config.approval,
+            typed_confirmation: config.typed_confirmation,
+            confirmation_threshold: config.confirmation_threshold,
+            confirmation_timeout: config.confirmation_timeout,
+            paused_by: config.paused_by,
+        }
+    }
+}
+
+/// How long the author of a large query has to confirm it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmationTimeout {
+    /// How long the confirmation prompt waits for the author
+    pub timeout: Duration,
+    /// Whether the prompt reminds the author it's still waiting for them, halfway through
+    pub reminder: bool,
+}
+
+/// Very large notifications need approving by a moderator, rather than just by their author
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeratorApproval {
+    /// The number of members a query must match (at least) to need approval
+    pub threshold: usize,
+    /// The role whose members can approve notifications
+    pub role: RoleId,
+}
+
+/// How the notification for a query is delivered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Delivery {
+    /// Reply to the query from Intersection
+    #[default]
+    #[name = "Reply from Intersection"]
+    Reply,
+    /// Send the notification through a webhook with the author's name and avatar
+    #[name = "Webhook with the author's name and avatar"]
+    Webhook,
+    /// Start a thread from the query and add the members to it, instead of mentioning them
+    #[name = "Thread with the members added to it"]
+    Thread,
+}
+
+/// The members matched by some of the chunks of a query, which are mentioned together
+#[derive(Debug)]
+struct MentionGroup<'a> {
+    /// The chunks this group was evaluated from
+    chunks: &'a [&'a str],
+    /// Whether this group's messages are labelled with its chunk, to tell it apart from the
+    /// other chunks' groups
+    labelled: bool,
+    /// The result of evaluating this group
+    evaluation: Evaluation,
+}
+
+/// Prompts the user to confirm they want to send a query again, `ago` after it was last sent in
+/// the channel.
+///
+/// Will return Ok(Continue) if the user accepted, Ok(Break) if the user cancelled or timed out,
+/// and Err if there was an error.
+#[instrument(skip_all, fields(?ago))]
+async fn confirm_duplicate_query(
+    discord: &impl Discord,
+    chunks: &[&str],
+    ago: Duration,
+    language: Language,
+) -> Result<ControlFlow<()>, QueryError> {
+    trace!("sending duplicate query confirmation");
+
+    let confirmation_message = reply_with_retry(
+        discord,
+        OutgoingMessage {
+            content: i18n::message(
+                language,
+                "duplicate-query-confirm",
+                &[
+                    ("query", &describe_query(chunks)),
+                    ("ago", &cooldowns::describe_wait(ago, language)),
+                ],
+            ),
+            buttons: vec![
+                Button {
+                    custom_id: "duplicate_query_cancel".to_string(),
+                    label: i18n::message(language, "cancel", &[]),
+                    // X emoji
+                    emoji: Some("\u{274c}".to_string()),
+                    style: serenity::ButtonStyle::Secondary,
+                },
+                Button {
+                    custom_id: "duplicate_query_confirm".to_string(),
+                    label: i18n::message(language, "duplicate-query-send", &[]),
+                    // check mark emoji
+                    emoji: Some("\u{2705}".to_string()),
+                    style: serenity::ButtonStyle::Primary,
+                },
+            ],
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    trace!("waiting for confirmation");
+
+    let Some(custom_id) = discord
+        .await_button_press(confirmation_message, CONFIRMATION_TIMEOUT)
+        .await?
+    else {
+        debug!("timed out waiting for confirmation");
+        edit_with_retry(
+            discord,
+            confirmation_message,
+            OutgoingMessage::text(i18n::message(language, "confirmation-timed-out", &[])),
+        )
+        .await?;
+        return Ok(ControlFlow::Break(()));
+    };
+
+    let (flow, edit) = match custom_id.as_str() {
+        "duplicate_query_cancel" => (ControlFlow::Break(()), "cancelled"),
+        "duplicate_query_confirm" => (ControlFlow::Continue(()), "confirmed"),
+        _ => return Err(anyhow!("Discord sent us an invalid interaction customId!").into()),
+    };
+
+    debug!("User chose {flow:?}");
+    edit_with_retry(
+        discord,
+        confirmation_message,
+        OutgoingMessage::text(i18n::message(language, edit, &[])),
+    )
+    .await?;
+
+    Ok(flow)
+}
+
+/// What to do with the roles in a query that the author is not allowed to mention
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnmentionableRoleAction {
+    /// Mention each member of the roles individually
+    Expand,
+    /// List the members of the query without mentioning anyone
+    List,
+}
+
+/// Asks the user how to continue with a query that uses roles they are not allowed to mention.
+///
+/// Will return Ok(Continue) with the chosen action, Ok(Break) if the user cancelled or timed out,
+/// and Err if there was an error.
+#[instrument(skip_all, fields(?roles))]
+async fn prompt_unmentionable_roles(
+    discord: &impl Discord,
+    roles: &HashSet<RoleId>,
+    language: Language,
+) -> Result<ControlFlow<(), UnmentionableRoleAction>, QueryError> {
+    let mut roles = roles.iter().copied().collect::<Vec<_>>();
+    roles.sort_unstable();
+    let role_mentions = roles
+        .iter()
+        .map(|&id| models::mention::Mention::Role(RoleType::Role(id)).to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    trace!("sending unmentionable role prompt");
+
+    let prompt_message = reply_with_retry(
+        discord,
+        OutgoingMessage {
+            content: i18n::message_count(
+                language,
+                "unmentionable-roles",
+                roles.len(),
+                &[("roles", &role_mentions)],
+            ),
+            buttons: vec![
+                Button {
+                    custom_id: "unmentionable_role_cancel".to_string(),
+                    label: i18n::message(language, "cancel", &[]),
+                    // X emoji
+                    emoji: Some("\u{274c}".to_string()),
+                    style: serenity::ButtonStyle::Secondary,
+                },
+                Button {
+                    custom_id: "unmentionable_role_list".to_string(),
+                    label: i18n::message(language, "unmentionable-roles-list", &[]),
+                    emoji: None,
+                    style: serenity::ButtonStyle::Secondary,
+                },
+                Button {
+                    custom_id: "unmentionable_role_expand".to_string(),
+                    label: i18n::message(language, "unmentionable-roles-expand", &[]),
+                    emoji: None,
+                    style: serenity::ButtonStyle::Primary,
+                },
+            ],
+            ..Default::default()
+        }
+        // Mentioning the roles here would defeat the point
+        .suppress_mentions(true),
+    )
+    .await?;
+
+    trace!("waiting for a choice");
+
+    let Some(custom_id) = discord
+        .await_button_press(prompt_message, CONFIRMATION_TIMEOUT)
+        .await?
+    else {
+        debug!("timed out waiting for a choice");
+        edit_with_retry(
+            discord,
+            prompt_message,
+            OutgoingMessage::text(i18n::message(language, "confirmation-timed-out", &[])),
+        )
+        .await?;
+        return Ok(ControlFlow::Break(()));
+    };
+
+    let (action, edit) = match custom_id.as_str() {
+        "unmentionable_role_cancel" => {
+            debug!("User cancelled operation");
+            edit_with_retry(
+                discord,
+                prompt_message,
+                OutgoingMessage::text(i18n::message(language, "cancelled", &[])),
+            )
+            .await?;
+            return Ok(ControlFlow::Break(()));
+        }
+        "unmentionable_role_expand" => (
+            UnmentionableRoleAction::Expand,
+            "unmentionable-roles-expanding",
+        ),
+        "unmentionable_role_list" => (UnmentionableRoleAction::List, "unmentionable-roles-listing"),
+        _ => return Err(anyhow!("Discord sent us an invalid interaction customId!").into()),
+    };
+
+    debug!("User chose {action:?}");
+    edit_with_retry(
+        discord,
+        prompt_message,
+        OutgoingMessage::text(i18n::message(language, edit, &[])),
+    )
+    .await?;
+
+    Ok(ControlFlow::Continue(action))
+}
+
+/// What to do with a query whose results are incomplete, as some of its operands timed out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IncompleteEvaluationAction {
+    /// Evaluate the query again
+    Retry,
+    /// Continue with the members found without the operands which timed out
+    Continue,
+}
+
+/// Asks the user how to continue with a query whose results are incomplete, showing which
+/// operands timed out and how many members were matched without them.
+///
+/// Will return Ok(Continue) with the chosen action, Ok(Break) if the user cancelled or timed out,
+/// and Err if there was an error.
+#[instrument(skip_all, fields(?timed_out))]
+async fn prompt_incomplete_evaluation(
+    discord: &impl Discord,
+    timed_out: &[String],
+    members: usize,
+    language: Language,
+) -> Result<ControlFlow<(), IncompleteEvaluationAction>, QueryError> {
+    let operands = timed_out
+        .iter()
+        .map(|operand| format!("`{operand}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    trace!("sending incomplete evaluation prompt");
+
+    let prompt_message = reply_with_retry(
+        discord,
+        OutgoingMessage {
+            content: i18n::message_count(
+                language,
+                "incomplete-evaluation",
+                timed_out.len(),
+                &[("operands", &operands), ("members", &members)],
+            ),
+            buttons: vec![
+                Button {
+                    custom_id: "incomplete_evaluation_cancel".to_string(),
+                    label: i18n::message(language, "cancel", &[]),
+                    // X emoji
+                    emoji: Some("\u{274c}".to_string()),
+                    style: serenity::ButtonStyle::Secondary,
+                },
+                Button {
+                    custom_id: "incomplete_evaluation_continue".to_string(),
+                    label: i18n::message(language, "incomplete-evaluation-continue", &[]),
+                    emoji: None,
+                    style: serenity::ButtonStyle::Secondary,
+                },
+                Button {
+                    custom_id: "incomplete_evaluation_retry".to_string(),
+                    label: i18n::message(language, "incomplete-evaluation-retry", &[]),
+                    // Repeat emoji
+                    emoji: Some("\u{1f501}".to_string()),
+                    style: serenity::ButtonStyle::Primary,
+                },
+            ],
+            ..Default::default()
+        }
+        // The operands may be mentions themselves
+        .suppress_mentions(true),
+    )
+    .await?;
+
+    trace!("waiting for a choice");
+
+    let Some(custom_id) = discord
+        .await_button_press(prompt_message, CONFIRMATION_TIMEOUT)
+        .await?
+    else {
+        debug!("timed out waiting for a choice");
+        edit_with_retry(
+            discord,
+            prompt_message,
+            OutgoingMessage::text(i18n::message(language, "confirmation-timed-out", &[])),
+        )
+        .await?;
+        return Ok(ControlFlow::Break(()));
+    };
+
+    let (action, edit) = match custom_id.as_str() {
+        "incomplete_evaluation_cancel" => {
+            debug!("User cancelled operation");
+            edit_with_retry(
+                discord,
+                prompt_message,
+                OutgoingMessage::text(i18n::message(language, "cancelled", &[])),
+            )
+            .await?;
+            return Ok(ControlFlow::Break(()));
+        }
+        "incomplete_evaluation_retry" => (
+            IncompleteEvaluationAction::Retry,
+            "incomplete-evaluation-retrying",
+        ),
+        "incomplete_evaluation_continue" => (
+            IncompleteEvaluationAction::Continue,
+            "incomplete-evaluation-continuing",
+        ),
+        _ => return Err(anyhow!("Discord sent us an invalid interaction customId!").into()),
+    };
+
+    debug!("User chose {action:?}");
+    edit_with_retry(
+        discord,
+        prompt_message,
+        OutgoingMessage::text(i18n::message(language, edit, &[])),
+    )
+    .await?;
+
+    Ok(ControlFlow::Continue(action))
+}
+
+/// A set of members represented as role and user mentions
+#[derive(Debug)]
+struct Mentions {
+    /// The roles mentioned
+    roles: Vec<RoleType>,
+    /// The members mentioned individually, as they aren't in any of the mentioned roles
+    outliers: Vec<UserId>,
+    /// The members of each mentioned role
+    role_members: HashMap<RoleType, HashSet<UserId>>,
+}
+
+/// One message of a notification split into several, mentioning roles and the members none of
+/// them cover
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct MentionMessage {
+    /// The roles mentioned
+    roles: Vec<RoleType>,
+    /// The members mentioned individually, in the order they were added
+    users: Vec<UserId>,
+    /// Everyone the message notifies, through its roles or individually
+    members: HashSet<UserId>,
+}
+
+impl MentionMessage {
+    /// Whether the message mentions anyone
+    const fn is_empty(&self) -> bool {
+        self.roles.is_empty() && self.users.is_empty()
+    }
+
+    /// Write the message's mentions, roles first.
+    fn content(&self) -> String {
+        self.roles
+            .iter()
+            .copied()
+            .map(models::mention::Mention::Role)
+            .chain(
+                self.users
+                    .iter()
+                    .copied()
+                    .map(models::mention::Mention::User),
+            )
+            .map(|mention| mention.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Mentions {
+    /// Represent a set of members as role and user mentions, using roles where possible.
+    fn new(
+        members_to_ping: &HashSet<UserId>,
+        roles_and_their_members: &HashMap<RoleType, HashSet<UserId>>,
+    ) -> Self {
+        // we represent the list of users as a bunch of roles containing them and one outliers set.
+        let util::unionize_set::UnionizeSetResult { sets, outliers } =
+            util::unionize_set::unionize_set(members_to_ping, roles_and_their_members);
+
+        debug!(
+            "unionize_set result sets: {sets:?}, outliers: {outliers:?}",
+            sets = sets,
+            outliers = outliers
+        );
+
+        Self {
+            role_members: sets
+                .iter()
+                .filter_map(|role| Some((**role, roles_and_their_members.get(role)?.clone())))
+                .collect(),
+            roles: sets.into_iter().copied().collect(),
+            outliers: outliers.into_iter().copied().collect(),
+        }
+    }
+
+    /// Everyone the mentions notify, through a role or individually.
+    fn members(&self) -> HashSet<UserId> {
+        self.role_members
+            .values()
+            .flatten()
+            .chain(&self.outliers)
+            .copied()
+            .collect()
+    }
+
+    /// Stringify every mention, roles first.
+    ///
+    /// These only fit in a single message if they're short enough; use [`Mentions::to_messages`]
+    /// otherwise.
+    fn to_strings(&self) -> Vec<String> {
+        let stringified_mentions = self
+            .roles
+            .iter()
+            .copied()
+            .map(models::mention::Mention::Role)
+            .chain(
+                self.outliers
+                    .iter()
+                    .map(|&id| models::mention::Mention::User(id)),
+            )
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+
+        debug!(
+            "stringified_mentions: {stringified_mentions:?}",
+            stringified_mentions = stringified_mentions
+        );
+
+        stringified_mentions
+    }
+
+    /// Split the mentions into messages of at most `size` bytes, each a self-contained group of
+    /// role mentions and the outliers those roles don't cover, so nobody is pinged more than once.
+    ///
+    /// Discord only notifies a member once per message, but a member in two roles mentioned in
+    /// separate messages would be pinged twice. So roles sharing members with an earlier message
+    /// are replaced, where they'd have been, by mentions of their other members, and members
+    /// covered by a role in the same or an earlier message aren't mentioned individually.
+    fn to_messages(&self, size: usize) -> Vec<MentionMessage> {
+        let mut queue = self
+            .roles
+            .iter()
+            .copied()
+            .map(models::mention::Mention::Role)
+            .chain(
+                self.outliers
+                    .iter()
+                    .copied()
+                    .map(models::mention::Mention::User),
+            )
+            .collect::<VecDeque<_>>();
+        let mut messages = Vec::new();
+        let mut current = MentionMessage::default();
+        // Who the messages before the current one notify
+        let mut earlier_members = HashSet::new();
+        while let Some(mention) = queue.pop_front() {
+            // The current message with this mention added, and who the mention notifies
+            let mut next = MentionMessage {
+                roles: current.roles.clone(),
+                users: current.users.clone(),
+                members: HashSet::new(),
+            };
+            let covered = match mention {
+                models::mention::Mention::Role(role) => {
+                    let members = self.role_members.get(&role).cloned().unwrap_or_default();
+                    if !members.is_disjoint(&earlier_members) {
+                        // Its other members are mentioned in its place, keeping them together
+                        let mut remaining = members
+                            .difference(&earlier_members)
+                            .copied()
+                            .collect::<Vec<_>>();
+                        remaining.sort_unstable();
+                        for user in remaining.into_iter().rev() {
+                            queue.push_front(models::mention::Mention::User(user));
+                        }
+                        continue;
+                    }
+                    // The role notifies them now, so mentioning them as well would be redundant
+                    next.users.retain(|user| !members.contains(user));
+                    next.roles.push(role);
+                    members
+                }
+                models::mention::Mention::User(user) => {
+                    if current.members.contains(&user) || earlier_members.contains(&user) {
+                        continue;
+                    }
+                    next.users.push(user);
+                    HashSet::from([user])
+                }
+            };
+
+            if !current.is_empty() && next.content().len() > size {
+                earlier_members.extend(current.members.iter().copied());
+                messages.push(std::mem::take(&mut current));
+                // Now that the message is finished, this mention might overlap with it
+                queue.push_front(mention);
+                continue;
+            }
+            current.roles = next.roles;
+            current.users = next.users;
+            current.members.extend(covered);
+        }
+        if !current.is_empty() {
+            messages.push(current);
+        }
+
+        messages
+    }
+
+    /// Summarize which roles and how many individual members will be mentioned, like
+    /// "@everyone, @Staff, and 3 individual members".
+    fn summary(&self, language: Language) -> String {
+        /// The most roles listed by name before the rest are summarized as a count
+        const MAX_LISTED_ROLES: usize = 20;
+
+        let mut roles = self
+            .roles
+            .iter()
+            .map(|role| models::mention::Mention::Role(*role).to_string())
+            .collect::<Vec<_>>();
+        // @everyone and @here are listed first, as they're the most likely to be surprising
+        roles.sort_unstable_by_key(|role| (role.starts_with("<@&"), role.clone()));
+
+        let mut parts = roles
+            .iter()
+            .take(MAX_LISTED_ROLES)
+            .cloned()
+            .collect::<Vec<_>>();
+        if roles.len() > MAX_LISTED_ROLES {
+            parts.push(i18n::message(
+                language,
+                "summary-more-roles",
+                &[("count", &(roles.len() - MAX_LISTED_ROLES))],
+            ));
+        }
+        if !self.outliers.is_empty() || parts.is_empty() {
+            parts.push(i18n::message_count(
+                language,
+                "summary-individual-members",
+                self.outliers.len(),
+                &[],
+            ));
+        }
+
+        match parts.as_slice() {
+            [] => unreachable!("at least one part was added above"),
+            [only] => only.clone(),
+            [first, second] => i18n::message(
+                language,
+                "list-two",
+                &[("first", first), ("second", second)],
+            ),
+            [rest @ .., last] => i18n::message(
+                language,
+                "list-many",
+                &[("rest", &rest.join(", ")), ("last", last)],
+            ),
+        }
+    }
+}
+
+/// Send a message, retrying if it fails for a transient reason (like a dropped connection).
+async fn reply_with_retry(
+    discord: &impl Discord,
+    message: OutgoingMessage,
+) -> Result<serenity::MessageId, QueryError> {
+    retry::with_retry(discord, "Sending message", || {
+        discord.reply(message.clone())
+    })
+    .await
+}
+
+/// Edit a message, retrying if it fails for a transient reason (like a dropped connection).
+async fn edit_with_retry(
+    discord: &impl Discord,
+    id: serenity::MessageId,
+    message: OutgoingMessage,
+) -> Result<(), QueryError> {
+    retry::with_retry(discord, "Editing message", || {
+        discord.edit(id, message.clone())
+    })
+    .await
+}
+
+/// The messages the mentions for a [`MentionGroup`] were sent in
+#[derive(Debug)]
+struct SentMentions {
+    /// The last message sent, which has the notification's buttons
+    last: serenity::MessageId,
+    /// Each message mentioning members, in order, and how many members it notified
+    parts: Vec<(serenity::MessageId, usize)>,
+    /// Every message sent, in order, including the notices around split mentions
+    messages: Vec<serenity::MessageId>,
+    /// The roles the messages mentioned
+    roles: Vec<RoleType>,
+    /// How many members the messages mentioned individually
+    individual_mentions: usize,
+}
+
+impl SentMentions {
+    /// Combine the messages sent for one group with those sent for a `later` one.
+    fn followed_by(mut self, later: Self) -> Self {
+        self.parts.extend(later.parts);
+        self.messages.extend(later.messages);
+        self.roles.extend(later.roles);
+        self.individual_mentions += later.individual_mentions;
+        Self {
+            last: later.last,
+            ..self
+        }
+    }
+}
+
+/// Send the mentions for a single [`MentionGroup`], splitting them into multiple messages if
+/// needed.
+///
+/// `already_mentioned` is how many of the group's members were left out of `mentions` because an
+/// earlier group mentioned them, when each chunk is mentioned separately.
+#[instrument(skip_all, fields(chunks = ?group.chunks))]
+async fn send_mentions(
+    discord: &impl Discord,
+    group: &MentionGroup<'_>,
+    mentions: &Mentions,
+    options: QueryOptions,
+    ping: bool,
+    notified_by_dm: &HashSet<UserId>,
+    already_mentioned: usize,
+) -> Result<Option<SentMentions>, QueryError> {
+    let query = describe_query(group.chunks);
+    let language = options.language;
+    let label = if group.labelled {
+        format!(
+            "{}\n",
+            i18n::message(language, "results-for", &[("query", &query)])
+        )
+    } else {
+        String::new()
+    };
+
+    // Only count the members of this group, when each chunk is mentioned separately
+    let notified_by_dm = group
+        .evaluation
+        .members
+        .intersection(notified_by_dm)
+        .count();
+    let dm_note = if notified_by_dm == 0 {
+        String::new()
+    } else {
+        format!(
+            "{}\n",
+            i18n::message(language, "notified-via-dm", &[("count", &notified_by_dm)])
+        )
+    };
+    let already_mentioned_note = if already_mentioned == 0 {
+        String::new()
+    } else {
+        format!(
+            "{}\n",
+            i18n::message_count(language, "already-mentioned", already_mentioned, &[])
+        )
+    };
+
+    let stringified_mentions = &mentions.to_strings();
+    if stringified_mentions.is_empty() {
+        if notified_by_dm == 0 {
+            reply_with_retry(
+                discord,
+                OutgoingMessage::text(if already_mentioned == 0 {
+                    format!(
+                        "{label}{}",
+                        i18n::message(language, "no-users-matched", &[])
+                    )
+                } else {
+                    // Everyone was mentioned by an earlier group
+                    format!("{label}{}", already_mentioned_note.trim_end())
+                }),
+            )
+            .await?;
+            return Ok(None);
+        }
+
+        // Everyone was notified by DM (or mentioned earlier), so there's nobody left to mention
+        let message = reply_with_retry(
+            discord,
+            OutgoingMessage::text(format!(
+                "{label}{dm_note}{}",
+                already_mentioned_note.trim_end()
+            ))
+            .button(recipients::button(language)),
+        )
+        .await?;
+        discord.record_recipients(message, &group.evaluation.members);
+        return Ok(Some(SentMentions {
+            last: message,
+            parts: Vec::new(),
+            messages: vec![message],
+            roles: Vec::new(),
+            individual_mentions: 0,
+        }));
+    }
+
+    let about_command = retry::with_retry(discord, "Looking up a command", || {
+        discord.mention_command("about landing")
+    })
+    .await?;
+    let what_is_this = i18n::message(language, "what-is-this", &[("command", &about_command)]);
+    let field = |id| i18n::message(language, id, &[]);
+    // Lets recipients see why they were mentioned without asking the author
+    let stats = format!(
+        "{}\n",
+        i18n::message(
+            language,
+            "notification-stats",
+            &[
+                ("query", &query),
+                (
+                    "members",
+                    &i18n::message_count(
+                        language,
+                        "count-members",
+                        group.evaluation.members.len(),
+                        &[]
+                    )
+                ),
+                (
+                    "roles",
+                    &i18n::message_count(language, "count-roles", mentions.roles.len(), &[])
+                ),
+            ],
+        )
+    );
+
+    // In the embed style, the explanation lives in an embed and the content is just the mentions
+    let embed = (ping && options.embed).then(|| {
+        let mut fields = vec![
+            (field("field-query"), query.clone()),
+            (field("field-author"), format!("<@{}>", discord.author())),
+            (
+                field("field-members"),
+                group.evaluation.members.len().to_string(),
+            ),
+            (
+                field("field-roles-mentioned"),
+                mentions.roles.len().to_string(),
+            ),
+            (
+                field("field-individual-mentions"),
+                mentions.outliers.len().to_string(),
+            ),
+        ];
+        if notified_by_dm > 0 {
+            fields.push((field("field-notified-via-dm"), notified_by_dm.to_string()));
+        }
+        if already_mentioned > 0 {
+            fields.push((
+                field("field-already-mentioned"),
+                already_mentioned.to_string(),
+            ));
+        }
+
+        Embed {
+            title: field("embed-title"),
+            description: what_is_this.clone(),
+            fields,
+        }
+    });
+    let with_embed = |message: OutgoingMessage| match &embed {
+        Some(embed) => message.embed(embed.clone()),
+        None => message,
+    };
+
+    let notification_string = if embed.is_some() {
+        label.clone()
+    } else if ping {
+        format!(
+            "{}\n{what_is_this}\n{label}{dm_note}{already_mentioned_note}{stats}",
+            field("notification-header")
+        )
+    } else {
+        format!("{}\n{label}", field("listing-header"))
+    };
+
+    // Once we're done, the last message we send gets the "Who was pinged?" and "Delete this ping"
+    // buttons
+    let with_recipients_button = |message: OutgoingMessage| {
+        if ping {
+            message
+                .button(recipients::button(options.language))
+                .button(reply_tracker::delete_button(options.language))
+        } else {
+            message
+        }
+    };
+
+    // Only notifications are sent as the author; listing members is Intersection's doing
+    let send_as_author = ping && options.delivery == Delivery::Webhook;
+
+    let sent = if stringified_mentions.join(" ").len() <= (2000 - notification_string.len()) {
+        trace!("Sending single message for mentions");
+        let message = reply_with_retry(
+            discord,
+            with_recipients_button(with_embed(
+                OutgoingMessage::text(format!(
+                    "{}{}",
+                    notification_string,
+                    stringified_mentions.join(" ")
+                ))
+                .silent(options.silent)
+                .send_as_author(send_as_author)
+                .suppress_mentions(!ping),
+            )),
+        )
+        .await?;
+        SentMentions {
+            last: message,
+            parts: vec![(message, mentions.members().len())],
+            messages: vec![message],
+            roles: mentions.roles.clone(),
+            individual_mentions: mentions.outliers.len(),
+        }
+    } else {
+        // Each message is numbered, so recipients scrolling past can tell they're all one
+        // notification. There can't be more messages than mentions, so that's enough room for any
+        // number.
+        let part = |part: usize, total: usize| {
+            i18n::message(
+                language,
+                "split-part",
+                &[("part", &part), ("total", &total)],
+            )
+        };
+        let most_mentions = stringified_mentions.len();
+        let part_room = part(most_mentions, most_mentions).len() + 1;
+        let messages = mentions.to_messages(2000 - part_room);
+        trace!("Need to send {} messages.", messages.len());
+        let notice_prefix = if embed.is_some() {
+            label.clone()
+        } else if ping {
+            format!("{label}{} ", field("split-notification-prefix"))
+        } else {
+            format!("{label}{} ", field("split-listing-prefix"))
+        };
+
+        // Keep other notifications in this channel from being interleaved with ours
+        let queue_guard = discord.lock_send_queue().await;
+        // The notice is edited to show progress, and Discord only lets us edit our own messages, so
+        // it never comes from the author's webhook
+        let notice = reply_with_retry(
+            discord,
+            with_embed(
+                OutgoingMessage::text(format!(
+                    "{notice_prefix}{}",
+                    i18n::message(language, "split-sending", &[("count", &messages.len())])
+                ))
+                .silent(options.silent),
+            ),
+        )
+        .await?;
+        let notified = messages
+            .iter()
+            .map(|message| message.members.len())
+            .collect::<Vec<_>>();
+        // Roles overlapping an earlier message were mentioned through their members instead
+        let roles = messages
+            .iter()
+            .flat_map(|message| message.roles.iter().copied())
+            .collect::<Vec<_>>();
+        let individual_mentions = messages.iter().map(|message| message.users.len()).sum();
+        let messages = (1..)
+            .zip(&messages)
+            .map(|(sent, message)| format!("{} {}", part(sent, messages.len()), message.content()))
+            .collect::<Vec<_>>();
+        let mut parts = Vec::with_capacity(messages.len());
+        for (sent, message) in (1..).zip(&messages) {
+            // Listing members is harmless to cut short, but notifications should reach everyone
+            if ping {
+                discord.save_pending_send(notice, &messages[sent - 1..], options.silent);
+            }
+            let result = reply_with_retry(
+                discord,
+                OutgoingMessage::text(message)
+                    .silent(options.silent)
+                    .send_as_author(send_as_author)
+                    .suppress_mentions(!ping),
+            )
+            .await;
+            if result.is_err() {
+                // It wasn't interrupted by a restart, so there's nothing to resume later
+                discord.finish_pending_send(notice);
+            }
+            parts.push((result?, notified[sent - 1]));
+
+            let progress = if sent == messages.len() {
+                format!(
+                    "{notice_prefix}{}",
+                    i18n::message(language, "split-sent", &[("count", &sent)])
+                )
+            } else if sent % PROGRESS_INTERVAL == 0 {
+                format!(
+                    "{notice_prefix}{}",
+                    i18n::message(
+                        language,
+                        "split-sending-progress",
+                        &[("count", &messages.len()), ("sent", &sent)]
+                    )
+                )
+            } else {
+                continue;
+            };
+            // Progress is only informational, so failing to show it shouldn't stop the notification
+            if let Err(err) = discord.edit(notice, OutgoingMessage::text(progress)).await {
+                warn!("Unable to update the progress of a notification: {err}");
+            }
+        }
+        discord.finish_pending_send(notice);
+        let last_message = reply_with_retry(
+            discord,
+            with_recipients_button(
+                OutgoingMessage::text(format!(
+                    "{} {}\n{}{}{what_is_this}",
+                    field(if ping {
+                        "split-notification-done"
+                    } else {
+                        "split-listing-done"
+                    }),
+                    i18n::message(language, "split-start", &[("link", &discord.link(notice))]),
+                    // The embed already shows these
+                    if embed.is_some() {
+                        String::new()
+                    } else {
+                        format!("{dm_note}{already_mentioned_note}")
+                    },
+                    if ping && embed.is_none() { &stats } else { "" },
+                ))
+                .silent(options.silent)
+                .send_as_author(send_as_author),
+            ),
+        )
+        .await?;
+        drop(queue_guard);
+
+        SentMentions {
+            last: last_message,
+            messages: std::iter::once(notice)
+                .chain(parts.iter().map(|(message, _)| *message))
+                .chain([last_message])
+                .collect(),
+            parts,
+            roles,
+            individual_mentions,
+        }
+    };
+
+    if ping {
+        discord.record_recipients(sent.last, &group.evaluation.members);
+    }
+
+    Ok(Some(sent))
+}
+
+/// Add the members matched by a query to a thread started from it, instead of mentioning them.
+#[instrument(skip_all, fields(count = members.len()))]
+async fn add_to_thread(
+    discord: &impl Discord,
+    chunks: &[&str],
+    members: &HashSet<UserId>,
+    language: Language,
+) -> Result<serenity::MessageId, QueryError> {
+    let name = chunks
+        .iter()
+        .map(|chunk| format!("@{{{chunk}}}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_THREAD_NAME_LENGTH)
+        .collect::<String>();
+    let thread =
+        retry::with_retry(discord, "Creating thread", || discord.create_thread(&name)).await?;
+
+    let mut sorted_members = members.iter().copied().collect::<Vec<_>>();
+    sorted_members.sort_unstable();
+    let thread_mention = format!("<#{thread}>");
+    let thread_message = |id, added: usize| {
+        i18n::message(
+            language,
+            id,
+            &[
+                ("count", &members.len()),
+                ("thread", &thread_mention),
+                ("added", &added),
+            ],
+        )
+    };
+
+    let progress_message = reply_with_retry(
+        discord,
+        OutgoingMessage::text(thread_message("thread-adding", 0)).suppress_mentions(true),
+    )
+    .await?;
+
+    let mut added = 0;
+    for batch in sorted_members.chunks(THREAD_BATCH_SIZE) {
+        retry::with_retry(discord, "Adding members to thread", || {
+            discord.add_thread_members(thread, batch)
+        })
+        .await?;
+        added += batch.len();
+        trace!("Added {added} of {} members to thread", members.len());
+
+        if added < members.len() {
+            edit_with_retry(
+                discord,
+                progress_message,
+                OutgoingMessage::text(thread_message("thread-adding-progress", added)),
+            )
+            .await?;
+        }
+    }
+
+    edit_with_retry(
+        discord,
+        progress_message,
+        OutgoingMessage::text(thread_message("thread-added", added))
+            .button(recipients::button(language)),
+    )
+    .await?;
+    discord.record_recipients(progress_message, members);
+
+    Ok(progress_message)
+}
+
+/// Notify the members matched by a query who would rather be notified by DM, returning those who
+/// were.
+///
+/// Members who can't be sent a DM (for example, because they closed their DMs) are left out, so
+/// they can be mentioned in the channel as usual.
+#[instrument(skip_all)]
+async fn notify_by_dm(
+    discord: &impl Discord,
+    chunks: &[&str],
+    members: &HashSet<UserId>,
+    language: Language,
+) -> HashSet<UserId> {
+    let author = discord.author();
+    let mut recipients = members
+        .iter()
+        .copied()
+        .filter(|&member| member != author && discord.wants_dm_notifications(member))
+        .collect::<Vec<_>>();
+    if recipients.is_empty() {
+        return HashSet::new();
+    }
+    recipients.sort_unstable();
+
+    let query = describe_query(chunks);
+    let content = i18n::message(
+        language,
+        "dm-notification",
+        &[
+            ("author", &format!("<@{author}>")),
+            ("query", &query),
+            ("link", &discord.query_link()),
+        ],
+    );
+
+    debug!("Notifying {} members by DM", recipients.len());
+    let mut notified = HashSet::with_capacity(recipients.len());
+    for recipient in recipients {
+        let message = OutgoingMessage::text(content.clone()).suppress_mentions(true);
+        match retry::with_retry(discord, "Sending DM", || {
+            discord.send_dm(recipient, message.clone())
+        })
+        .await
+        {
+            Ok(()) => {
+                notified.insert(recipient);
+            }
+            Err(err) => debug!("Unable to DM {recipient}, mentioning them instead: {err}"),
+        }
+    }
+
+    notified
+}
+
+/// Write `items` for an embed field, leaving out those that don't fit (after an ellipsis), or
+/// [`None`] if there are none.
+fn fit_field(items: &Vec<String>, separator: &str) -> Option<String> {
+    let wrapped = util::wrap_string_vec(items, separator, MAX_FIELD_LENGTH - 2).ok()?;
+    let more = wrapped.len() > 1;
+    let mut first = wrapped.into_iter().next()?;
+    if more {
+        first.push_str(" \u{2026}");
+    }
+    Some(first)
+}
+
+/// Write the roles a notification mentioned for an embed field, or [`None`] if it didn't mention
+/// any.
+fn roles_used(roles: &[RoleType]) -> Option<String> {
+    fit_field(&roles.iter().map(ToString::to_string).collect(), " ")
+}
+
+/// Write which members each message of a split notification notified for an embed field, linking
+/// to each, or [`None`] if it wasn't split.
+fn messages_sent(
+    discord: &impl Discord,
+    language: Language,
+    parts: &[(serenity::MessageId, usize)],
+) -> Option<String> {
+    if parts.len() < 2 {
+        return None;
+    }
+    let lines = (1..)
+        .zip(parts)
+        .map(|(part, (message, members))| {
+            format!(
+                "[#{part}]({}): {}",
+                discord.link(*message),
+                i18n::message_count(language, "count-members", *members, &[])
+            )
+        })
+        .collect();
+    fit_field(&lines, "\n")
+}
+
+/// Post a summary of a notification to the guild's audit log channel, if it has one.
+///
+/// The notification was already sent, so failing to post the summary is only logged.
+async fn audit_notification(
+    discord: &impl Discord,
+    options: QueryOptions,
+    chunks: &[&str],
+    sent: &SentMentions,
+    approver: Option<UserId>,
+    roles: &[RoleType],
+    counts: &[(&'static str, usize)],
+) {
+    let Some(audit_channel) = options.audit_channel else {
+        return;
+    };
+
+    let query = describe_query(chunks);
+    let field = |id| i18n::message(options.language, id, &[]);
+    let embed = Embed {
+        title: field("audit-title"),
+        description: i18n::message(
+            options.language,
+            "audit-jump",
+            &[("link", &discord.link(sent.last))],
+        ),
+        fields: [
+            (field("field-author"), format!("<@{}>", discord.author())),
+            (field("field-channel"), format!("<#{}>", discord.channel())),
+            (field("field-query"), query),
+        ]
+        .into_iter()
+        .chain(approver.map(|approver| (field("field-approved-by"), format!("<@{approver}>"))))
+        .chain(roles_used(roles).map(|roles| (field("field-roles-used"), roles)))
+        .chain(
+            messages_sent(discord, options.language, &sent.parts)
+                .map(|messages| (field("field-messages"), messages)),
+        )
+        .chain(
+            counts
+                .iter()
+                .map(|(id, count)| (field(id), count.to_string())),
+        )
+        .collect(),
+    };
+
+    let message = OutgoingMessage::default()
+        .embed(embed)
+        .suppress_mentions(true);
+    if let Err(err) = retry::with_retry(discord, "Posting to the audit log", || {
+        discord.send_to_channel(audit_channel, message.clone())
+    })
+    .await
+    {
+        warn!("Unable to post notification to the audit log: {err}");
+    }
+}
+
+/// Evaluate a query into the groups of members mentioned together: one per chunk if
+/// `options.per_chunk` is set, or a single group otherwise.
+async fn evaluate_groups<'a>(
+    discord: &impl Discord,
+    chunks: &'a [&'a str],
+    options: QueryOptions,
+) -> Result<Vec<MentionGroup<'a>>, QueryError> {
+    Ok(if options.per_chunk && chunks.len() > 1 {
+        let mut groups = Vec::with_capacity(chunks.len());
+        for (n, chunk) in chunks.iter().enumerate() {
+            let evaluation = evaluate_with_progress(discord, &[chunk], options.language)
+                .await
+                .map_err(|err| {
+                    // Report the chunk's index within the whole message, not our single-chunk slice
+                    if let QueryError::ParseError {
+                        contents,
+                        error,
+                        language,
+                        ..
+                    } = err
+                    {
+                        QueryError::ParseError {
+                            chunk: n,
+                            contents,
+                            error,
+                            language,
+                        }
+                    } else {
+                        err
+                    }
+                })?;
+            groups.push(MentionGroup {
+                chunks: std::slice::from_ref(chunk),
+                labelled: true,
+                evaluation,
+            });
+        }
+        groups
+    } else {
+        vec![MentionGroup {
+            chunks,
+            labelled: false,
+            evaluation: evaluate_with_progress(discord, chunks, options.language).await?,
+        }]
+    })
+}
+
+/// How many different members were left out of a query's groups, as they didn't want to be notified
+#[derive(Debug, Default)]
+struct LeftOut {
+    /// Members who opted out of being notified in the guild
+    opted_out: usize,
+    /// Members in their quiet hours
+    quiet: usize,
+}
+
+/// Leave the members who opted out of being notified in the guild, and unless the author overrides
+/// quiet hours, those in their quiet hours, out of every group.
+fn leave_out_unwilling(discord: &impl Discord, groups: &mut [MentionGroup<'_>]) -> LeftOut {
+    let overrides_quiet_hours = discord.overrides_quiet_hours();
+    let mut opted_out = HashSet::new();
+    let mut quiet = HashSet::new();
+    for group in groups {
+        group.evaluation.members.retain(|&member| {
+            if discord.has_opted_out(member) {
+                opted_out.insert(member);
+                false
+            } else if !overrides_quiet_hours && discord.is_quiet(member) {
+                quiet.insert(member);
+                false
+            } else {
+                true
+            }
+        });
+    }
+    LeftOut {
+        opted_out: opted_out.len(),
+        quiet: quiet.len(),
+    }
+}
+
+/// Handle a DRQL query made of the given chunks, sending the response message(s) to the channel.
+#[instrument(skip_all, fields(?options))]
+pub async fn handle_drql_query(
+    discord: &impl Discord,
+    chunks: &[&str],
+    options: QueryOptions,
+) -> Result<(), QueryError> {
+    if let Some(paused_by) = options.paused_by {
+        debug!("Queries are paused in this guild");
+        return Err(QueryError::PermissionDenied(i18n::message(
+            options.language,
+            "queries-paused",
+            &[("user", &models::mention::Mention::User(paused_by))],
+        )));
+    }
+    discord.check_access().await?;
+    // Held until the notification is sent, and given back if the query is declined or fails
+    let cooldown = discord.reserve_cooldown()?;
+
+    let normalized = parse_chunks(chunks, discord.aliases(), options.language)?.to_string();
+    if let Some(ago) = discord.last_sent(&normalized) {
+        match options.duplicate_queries {
+            DuplicateQueryMode::Allow => {}
+            DuplicateQueryMode::Confirm => {
+                debug!("Query was sent {ago:?} ago, asking for confirmation");
+                if confirm_duplicate_query(discord, chunks, ago, options.language).await?
+                    == ControlFlow::Break(())
+                {
+                    debug!("User cancelled or timed out");
+                    return Ok(());
+                }
+            }
+            DuplicateQueryMode::Refuse => {
+                debug!("Query was sent {ago:?} ago, refusing it");
+                return Err(QueryError::LimitExceeded(i18n::message(
+                    options.language,
+                    "duplicate-query-refused",
+                    &[
+                        ("query", &describe_query(chunks)),
+                        ("ago", &cooldowns::describe_wait(ago, options.language)),
+                    ],
+                )));
+            }
+        }
+    }
+
+    let mut groups = loop {
+        trace!("Running DRQL parser/interpreter on message");
+        let groups = evaluate_groups(discord, chunks, options).await?;
+
+        let mut timed_out = groups
+            .iter()
+            .flat_map(|group| group.evaluation.timed_out.iter().cloned())
+            .collect::<Vec<_>>();
+        timed_out.sort_unstable();
+        timed_out.dedup();
+        if timed_out.is_empty() {
+            break groups;
+        }
+
+        debug!("query's results are incomplete");
+        let members = groups
+            .iter()
+            .flat_map(|group| group.evaluation.members.iter())
+            .collect::<HashSet<_>>()
+            .len();
+        match prompt_incomplete_evaluation(discord, &timed_out, members, options.language).await? {
+            ControlFlow::Break(()) => {
+                debug!("User cancelled or timed out");
+                return Ok(());
+            }
+            ControlFlow::Continue(IncompleteEvaluationAction::Retry) => {}
+            ControlFlow::Continue(IncompleteEvaluationAction::Continue) => break groups,
+        }
+    };
+
+    let left_out = leave_out_unwilling(discord, &mut groups);
+    for (id, count) in [
+        ("opted-out-excluded", left_out.opted_out),
+        ("quiet-hours-excluded", left_out.quiet),
+    ] {
+        if count > 0 {
+            debug!("{count} members were left out ({id})");
+            reply_with_retry(
+                discord,
+                OutgoingMessage::text(i18n::message_count(options.language, id, count, &[])),
+            )
+            .await?;
+        }
+    }
+
+    let members_to_ping = groups
+        .iter()
+        .flat_map(|group| group.evaluation.members.iter().copied())
+        .collect::<HashSet<_>>();
+
+    let excluded = groups
+        .iter()
+        .flat_map(|group| group.evaluation.excluded.iter())
+        .collect::<HashSet<_>>()
+        .len();
+    if excluded > 0 {
+        debug!("{excluded} members were left out by the do-not-ping role");
+        reply_with_retry(
+            discord,
+            OutgoingMessage::text(i18n::message_count(
+                options.language,
+                "do-not-ping-excluded",
+                excluded,
+                &[],
+            )),
+        )
+        .await?;
+    }
+
+    if members_to_ping.is_empty() {
+        debug!("Nobody to mention!");
+        reply_with_retry(
+            discord,
+            OutgoingMessage::text(i18n::message(options.language, "no-users-matched", &[])),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if let Some(max_mentions) = options.max_mentions {
+        if members_to_ping.len() > max_mentions {
+            debug!("Query exceeds the mention cap of {max_mentions}");
+            return Err(QueryError::LimitExceeded(i18n::message(
+                options.language,
+                "mention-cap-exceeded",
+                &[("count", &members_to_ping.len()), ("limit", &max_mentions)],
+            )));
+        }
+    }
+
+    // A hashmap of every role in the guild and its members.
+    let mut roles_and_their_members =
+        retry::with_retry(discord, "Fetching roles", || discord.roles_and_members()).await?;
+
+    let unmentionable_roles = groups
+        .iter()
+        .flat_map(|group| group.evaluation.unmentionable_roles.iter().copied())
+        .collect::<HashSet<_>>();
+    let mut ping = true;
+    if !unmentionable_roles.is_empty() {
+        debug!("query uses roles the author cannot mention");
+        match prompt_unmentionable_roles(discord, &unmentionable_roles, options.language).await? {
+            ControlFlow::Break(()) => {
+                debug!("User cancelled or timed out");
+                return Ok(());
+            }
+            ControlFlow::Continue(UnmentionableRoleAction::Expand) => {}
+            ControlFlow::Continue(UnmentionableRoleAction::List) => ping = false,
+        }
+
+        // Either way, these roles must never be mentioned on the author's behalf
+        for role in unmentionable_roles {
+            roles_and_their_members.remove(&RoleType::Role(role));
+        }
+    }
+
+    let mentions = Mentions::new(&members_to_ping, &roles_and_their_members);
+
+    // Who approved the notification, if it needed approving by a moderator
+    let mut approver = None;
+    let count = members_to_ping.len();
+    let confirmation = options
+        .approval
+        .filter(|approval| count >= approval.threshold)
+        .map(|approval| Confirmation::Approval(approval.role))
+        .or_else(|| {
+            options
+                .typed_confirmation
+                .filter(|&threshold| count >= threshold)
+                .map(|_| Confirmation::Typed)
+        })
+        .or_else(|| {
+            let threshold = options
+                .confirmation_threshold
+                .unwrap_or(DEFAULT_CONFIRMATION_THRESHOLD);
+            (count > threshold).then_some(Confirmation::Click)
+        });
+    if let Some(confirmation) = confirmation.filter(|_| ping) {
+        debug!("need to wait for user to confirm large mention");
+        match confirm_mention_count(discord, &mentions, &members_to_ping, confirmation, options)
+            .await?
+        {
+            ControlFlow::Break(()) => {
+                debug!("User cancelled or timed out");
+                // The user declined or the operation timed out. The message has already been edited for us.
+                return Ok(());
+            }
+            ControlFlow::Continue(confirmed_by) => {
+                debug!("User confirmed!");
+                approver = confirmed_by;
+            }
+        }
+    }
+
+    if ping && options.delivery == Delivery::Thread {
+        let notification =
+            add_to_thread(discord, chunks, &members_to_ping, options.language).await?;
+        discord.record_notification(cooldown);
+        discord.record_query(&normalized);
+        discord.record_history(&describe_query(chunks), members_to_ping.len());
+        discord.record_stats(members_to_ping.len(), &[]);
+        audit_notification(
+            discord,
+            options,
+            chunks,
+            &SentMentions {
+                last: notification,
+                parts: Vec::new(),
+                messages: vec![notification],
+                roles: Vec::new(),
+                individual_mentions: 0,
+            },
+            approver,
+            &[],
+            &[("field-members-added-to-thread", members_to_ping.len())],
+        )
+        .await;
+        return Ok(());
+    }
+
+    let notified_by_dm = if ping {
+        notify_by_dm(discord, chunks, &members_to_ping, options.language).await
+    } else {
+        HashSet::new()
+    };
+    let mentions = if notified_by_dm.is_empty() {
+        mentions
+    } else {
+        // Mentioning a role containing someone we already DMed would notify them twice
+        roles_and_their_members.retain(|_, members| members.is_disjoint(&notified_by_dm));
+        Mentions::new(
+            &(&members_to_ping - &notified_by_dm),
+            &roles_and_their_members,
+        )
+    };
+
+    let sent = if let [group] = groups.as_slice() {
+        send_mentions(discord, group, &mentions, options, ping, &notified_by_dm, 0).await?
+    } else {
+        let mut sent: Option<SentMentions> = None;
+        // Who the groups sent so far notified, so members in several chunks are only pinged once
+        let mut notified = notified_by_dm.clone();
+        for group in &groups {
+            let (group_mentions, already_mentioned) = if ping {
+                let roles = roles_and_their_members
+                    .iter()
+                    .filter(|(_, members)| members.is_disjoint(&notified))
+                    .map(|(role, members)| (*role, members.clone()))
+                    .collect::<HashMap<_, _>>();
+                let group_mentions =
+                    Mentions::new(&(&group.evaluation.members - &notified), &roles);
+                let already_mentioned =
+                    (&(&group.evaluation.members & &notified) - &notified_by_dm).len();
+                (group_mentions, already_mentioned)
+            } else {
+                // Listing members notifies nobody, so every group lists all of its members
+                let group_mentions = Mentions::new(
+                    &(&group.evaluation.members - &notified_by_dm),
+                    &roles_and_their_members,
+                );
+                (group_mentions, 0)
+            };
+            if let Some(group_sent) = send_mentions(
+                discord,
+                group,
+                &group_mentions,
+                options,
+                ping,
+                &notified_by_dm,
+                already_mentioned,
+            )
+            .await?
+            {
+                sent = Some(match sent {
+                    Some(earlier) => earlier.followed_by(group_sent),
+                    None => group_sent,
+                });
+            }
+            notified.extend(group_mentions.members());
+        }
+        sent
+    };
+
+    if ping {
+        discord.record_notification(cooldown);
+        discord.record_query(&normalized);
+        discord.record_history(&describe_query(chunks), members_to_ping.len());
+        // Each group mentions its own roles, which needn't be those of the whole query's members
+        let (roles, individual_mentions) = sent.as_ref().map_or_else(
+            || (Vec::new(), 0),
+            |sent| (sent.roles.clone(), sent.individual_mentions),
+        );
+        discord.record_stats(members_to_ping.len(), &roles);
+        if let Some(sent) = sent {
+            // The mentions have notified everyone by now, so they're only cluttering the channel
+            if let Some(expiry) = options.mention_expiry {
+                discord.delete_later(sent.messages.clone(), expiry);
+            }
+            audit_notification(
+                discord,
+                options,
+                chunks,
+                &sent,
+                approver,
+                &roles,
+                &[
+                    ("field-members", members_to_ping.len()),
+                    ("field-roles-mentioned", roles.len()),
+                    ("field-individual-mentions", individual_mentions),
+                    ("field-notified-via-dm", notified_by_dm.len()),
+                ],
+            )
+            .await;
+        }
+    }
+
+    trace!("Query handling completed!");
+
+    Ok(())
+}
+
+/// Delete the explanation of a mistake in a query after `expiry`, marking the query with a
+/// reaction so it's still apparent that it failed.
+///
+/// Keeping channels free of stale error messages is only a nicety, so failures are only logged.
+async fn expire_error_reply(discord: &impl Discord, reply: serenity::MessageId, expiry: Duration) {
+    // X emoji
+    if let Err(err) = discord.react_to_query("\u{274c}").await {
+        debug!("Unable to react to the query: {err}");
+    }
+
+    discord.wait(expiry).await;
+    // The author may have deleted the query, and its replies along with it, in the meantime
+    if let Err(err) = discord.delete(reply).await {
+        debug!("Unable to delete expired error reply: {err}");
+    }
+}
+
+/// Run [`handle_drql_query`], notifying the user of any error that occurs.
+pub async fn run_query(discord: &impl Discord, chunks: &[&str], options: QueryOptions) {
+    match handle_drql_query(discord, chunks, options).await {
+        Ok(()) => debug!("Finished handling queries."),
+
+        Err(query_err) => {
+            let reply = if query_err.is_user_error() {
+                // THIS IS NOT OUR FAULT -- This most likely means the USER made a mistake
+                debug!(
+                    "A user error occurred handling the DRQL query, notifying user: {query_err}"
+                );
+                query_err.to_string()
+            } else {
+                report_internal_error(
+                    format_args!("Error handling DRQL query: {query_err}"),
+                    options.language,
+                )
+            };
+
+            // Errors may mention roles, which shouldn't be pinged
+            match reply_with_retry(
+                discord,
+                OutgoingMessage::text(&reply).suppress_mentions(true),
+            )
+            .await
+            {
+                Ok(reply) => {
+                    if let Some(expiry) = options.error_expiry.filter(|_| query_err.is_mistake()) {
+                        expire_error_reply(discord, reply, expiry).await;
+                    }
+                }
+                Err(message_send_err) => {
+                    warn!("An error occurred while notifying the user of a query error: {message_send_err}");
+                    warn!("Initial query error: {query_err}");
+                    debug!("Trying again...");
+
+                    if let Err(double_message_send_err) = reply_with_retry(
+                        discord,
+                        OutgoingMessage::text(i18n::message(
+                            options.language,
+                            "error-send-failed",
+                            &[("error", &reply), ("send-error", &message_send_err)],
+                        ))
+                        .suppress_mentions(true),
+                    )
+                    .await
+                    {
+                        // Oh god the error message.
+                        error!("Failed to notify a user of an error notifying them of an error notifying them of a query error: {double_message_send_err}");
+                        error!(
+                            "We were attempting to notify them of this error: {message_send_err}"
+                        );
+                        error!(
+                            "That error occurred while notifying them of this error: {query_err}"
+                        );
+                        error!("Message sending failed twice! Giving up.");
+                    } else {
+                        debug!("Alright, it worked that time.");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, VecDeque},
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use intersection::drql::{
+        interpreter::interpret,
+        testing::{MockError, MockGuild},
+    };
+    use poise::{async_trait, serenity_prelude::RoleId};
+
+    use super::*;
+    use crate::{
+        cooldowns::{CooldownReservation, CooldownSettings, CooldownTracker, RateLimit},
+        models::mention::RoleType,
+        send_queue::SendQueueGuard,
+        stats::UsageStats,
+    };
+
+    #[tokio::test]
+    async fn outermost_operands_are_broken_down() {
+        let guild = MockGuild::new()
+            .with_role(RoleId(10), "staff")
+            .with_role(RoleId(11), "artists")
+            .with_member(UserId(1), "xavier", &[RoleId(10)])
+            .with_member(UserId(2), "yara", &[RoleId(10), RoleId(11)])
+            .with_member(UserId(3), "zed", &[RoleId(11)]);
+        let counted = |query: &str| {
+            let ast = drql::parser::parse_drql(query).expect("the query should parse");
+            let guild = &guild;
+            async move {
+                let (_, counts) = drql::interpreter::interpret_counting::<MockError>(
+                    &ast,
+                    guild,
+                    Limits::default(),
+                )
+                .await
+                .expect("the query should evaluate");
+                breakdown(&ast, &counts)
+            }
+        };
+
+        assert_eq!(
+            counted("staff & (artists | staff)").await,
+            [
+                ("staff".to_string(), 2),
+                ("artists | staff".to_string(), 3),
+                ("intersection".to_string(), 2)
+            ]
+        );
+        assert_eq!(
+            counted("!staff").await,
+            [("staff".to_string(), 2), ("complement".to_string(), 1)]
+        );
+        assert!(counted("staff").await.is_empty());
+    }
+
+    #[test]
+    fn stage_timings_are_reported() {
+        let timings = StageTimings {
+            chunks: 2,
+            scan: Duration::from_micros(5),
+            parse: Duration::from_micros(40),
+            resolve: Duration::from_millis(1200),
+            unionize: Duration::from_millis(3),
+            calls: BTreeMap::from([("resolve_everyone", 1), ("resolve_string_literal", 2)]),
+            members: 17,
+            mentions: 4,
+        };
+
+        assert_eq!(
+            timings.report(Language::English),
+            "Evaluated 2 chunks to 17 members, as 4 mentions:\n```\nscan:     5\u{b5}s\nparse:    \
+            40\u{b5}s\nresolve:  1.2s\nunionize: 3ms\n```\nResolver calls:\n```\nresolve_everyone: \
+            1\nresolve_string_literal: 2\n```"
+        );
+        assert!(StageTimings::default()
+            .report(Language::English)
+            .ends_with("```\nNone\n```"));
+    }
+
+    /// How evaluating a query goes in a [`FakeDiscord`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum FakeEvaluation {
+        /// The query is evaluated right away
+        Instant,
+        /// The query takes a while to evaluate, reporting progress along the way
+        Slow,
+        /// Evaluating the query fails with an internal error
+        Failing,
+    }
+
+    /// An in-memory [`Discord`] that records every message sent or edited.
+    struct FakeDiscord {
+        /// The guild queries are evaluated against
+        guild: MockGuild,
+        /// How evaluating a query goes
+        evaluation: FakeEvaluation,
+        /// Roles reported as unmentionable by the author of every query
+        unmentionable_roles: HashSet<RoleId>,
+        /// The role whose members are left out of every evaluation
+        do_not_ping: Option<RoleId>,
+        /// Every message sent, in order. The index of each message is its ID.
+        sent: Mutex<Vec<OutgoingMessage>>,
+        /// Every edit made, in order
+        edits: Mutex<Vec<(serenity::MessageId, OutgoingMessage)>>,
+        /// Every call to `record_recipients`, in order
+        recipients: Mutex<Vec<(serenity::MessageId, HashSet<UserId>)>>,
+        /// How many messages each call to `save_pending_send` had left, in order, with [`None`]
+        /// for each call to `finish_pending_send`
+        pending_sends: Mutex<Vec<Option<usize>>>,
+        /// The results of each call to `await_button_press`, `await_button_press_or_text` or
+        /// `await_approval`, in order. Approvals are always given by member 2.
+        button_presses: Mutex<VecDeque<Option<String>>>,
+        /// The text typed into each text prompt opened by `await_button_press_or_text`, in order
+        typed_text: Mutex<VecDeque<String>>,
+        /// The role passed to each call to `await_approval`, in order
+        approver_roles: Mutex<Vec<RoleId>>,
+        /// The name of every thread created, in order. The index of each thread plus 1000 is its
+        /// ID.
+        threads: Mutex<Vec<String>>,
+        /// Every batch of members added to a thread, in order
+        thread_members: Mutex<Vec<(serenity::ChannelId, Vec<UserId>)>>,
+        /// Members who would rather be notified by DM
+        dm_preferred: HashSet<UserId>,
+        /// Members who can't be sent DMs
+        closed_dms: HashSet<UserId>,
+        /// Members who opted out of being notified in the guild
+        opted_out: HashSet<UserId>,
+        /// Members in their quiet hours
+        quiet: HashSet<UserId>,
+        /// The guild's aliases
+        aliases: BTreeMap<String, String>,
+        /// If set, the author has an emergency role
+        emergency: bool,
+        /// Every DM sent, in order
+        dms: Mutex<Vec<(UserId, String)>>,
+        /// If set, the author is not allowed to run queries
+        access_denied: bool,
+        /// If set, the author is on cooldown
+        on_cooldown: bool,
+        /// The cooldowns the author's notifications reserve places within
+        cooldowns: CooldownTracker,
+        /// The guild's cooldowns
+        cooldown_settings: CooldownSettings,
+        /// How many notifications were recorded for cooldowns
+        notifications: Mutex<usize>,
+        /// If set, the next button press waits until this is notified
+        button_press_gate: Mutex<Option<Arc<tokio::sync::Notify>>>,
+        /// Every normalized query recorded as sent, in order
+        sent_queries: Mutex<Vec<String>>,
+        /// Every notification recorded in the guild's history, with how many members it matched
+        history: Mutex<Vec<(String, usize)>>,
+        /// Every notification counted in the guild's usage stats
+        stats: UsageStats,
+        /// How many of the next replies and edits fail as if the connection dropped
+        transient_failures: Mutex<usize>,
+        /// How many of the next additions of members to a thread fail as if the connection
+        /// dropped
+        thread_member_failures: Mutex<usize>,
+        /// Every call to `wait`, in order
+        waits: Mutex<Vec<Duration>>,
+        /// The send queue of the query's channel
+        send_queue: Arc<tokio::sync::Mutex<()>>,
+        /// Every message sent to a channel other than the query's, in order
+        sent_elsewhere: Mutex<Vec<(serenity::ChannelId, OutgoingMessage)>>,
+        /// Every message deleted, in order
+        deleted: Mutex<Vec<serenity::MessageId>>,
+        /// Every call to `delete_later`, in order
+        deleted_later: Mutex<Vec<(Vec<serenity::MessageId>, Duration)>>,
+        /// Every reaction added to the query, in order
+        reactions: Mutex<Vec<String>>,
+        /// How many times the typing indicator was shown
+        typing: Mutex<usize>,
+        /// How many of the next evaluations report a `slow` operand as timed out
+        timeouts: Mutex<usize>,
+    }
+
+    impl FakeDiscord {
+        fn new(guild: MockGuild) -> Self {
+            Self {
+                guild,
+                evaluation: FakeEvaluation::Instant,
+                unmentionable_roles: HashSet::new(),
+                do_not_ping: None,
+                sent: Mutex::new(Vec::new()),
+                edits: Mutex::new(Vec::new()),
+                recipients: Mutex::new(Vec::new()),
+                pending_sends: Mutex::new(Vec::new()),
+                button_presses: Mutex::new(VecDeque::new()),
+                typed_text: Mutex::new(VecDeque::new()),
+                approver_roles: Mutex::new(Vec::new()),
+                threads: Mutex::new(Vec::new()),
+                thread_members: Mutex::new(Vec::new()),
+                dm_preferred: HashSet::new(),
+                closed_dms: HashSet::new(),
+                opted_out: HashSet::new(),
+                quiet: HashSet::new(),
+                aliases: BTreeMap::new(),
+                emergency: false,
+                dms: Mutex::new(Vec::new()),
+                access_denied: false,
+                on_cooldown: false,
+                cooldowns: CooldownTracker::new(),
+                cooldown_settings: CooldownSettings::default(),
+                notifications: Mutex::new(0),
+                button_press_gate: Mutex::new(None),
+                sent_queries: Mutex::new(Vec::new()),
+                history: Mutex::new(Vec::new()),
+                stats: UsageStats::open(None),
+                transient_failures: Mutex::new(0),
+                thread_member_failures: Mutex::new(0),
+                waits: Mutex::new(Vec::new()),
+                send_queue: Arc::default(),
+                sent_elsewhere: Mutex::new(Vec::new()),
+                deleted: Mutex::new(Vec::new()),
+                deleted_later: Mutex::new(Vec::new()),
+                reactions: Mutex::new(Vec::new()),
+                typing: Mutex::new(0),
+                timeouts: Mutex::new(0),
+            }
+        }
+
+        fn with_button_press(self, custom_id: Option<&str>) -> Self {
+            self.button_presses
+                .lock()
+                .expect("lock should not be poisoned")
+                .push_back(custom_id.map(ToString::to_string));
+            self
+        }
+
+        fn with_typed_text(self, text: &str) -> Self {
+            self.typed_text
+                .lock()
+                .expect("lock should not be poisoned")
+                .push_back(text.to_string());
+            self
+        }
+
+        fn sent(&self) -> Vec<String> {
+            self.sent
+                .lock()
+                .expect("lock should not be poisoned")
+                .iter()
+                .map(|message| message.content.clone())
+                .collect()
+        }
+
+        /// Fail as if the connection dropped, if any `failures` are left.
+        fn fail_transiently(failures: &Mutex<usize>) -> Result<(), QueryError> {
+            let mut failures = failures.lock().expect("lock should not be poisoned");
+            if *failures == 0 {
+                return Ok(());
+            }
+            *failures -= 1;
+            drop(failures);
+            Err(serenity::Error::Io(io::Error::from(io::ErrorKind::ConnectionReset)).into())
+        }
+
+        fn edits(&self) -> Vec<String> {
+            self.edits
+                .lock()
+                .expect("lock should not be poisoned")
+                .iter()
+                .map(|(_, message)| message.content.clone())
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl Discord for FakeDiscord {
+        async fn evaluate(
+            &self,
+            chunks: &[&str],
+            progress: &watch::Sender<usize>,
+        ) -> Result<Evaluation, QueryError> {
+            if self.evaluation == FakeEvaluation::Failing {
+                return Err(anyhow!("the fake Discord is broken").into());
+            }
+            if self.evaluation == FakeEvaluation::Slow {
+                // Take long enough for the interim message to be sent, then resolve every operand
+                // at once, taking a while more afterwards
+                tokio::task::yield_now().await;
+                tokio::task::yield_now().await;
+                progress.send_replace(
+                    parse_chunks(chunks, &self.aliases, Language::English)?.operand_count(),
+                );
+                tokio::task::yield_now().await;
+            }
+
+            let members = interpret(
+                parse_chunks(chunks, &self.aliases, Language::English)?,
+                &self.guild,
+            )
+            .await
+            .map_err(|err| match err {
+                MockError::Resolution(message) => QueryError::ResolutionError(message),
+                MockError::Parse(_) => anyhow!("chunks were already parsed").into(),
+                MockError::TooComplex(err) => err.into(),
+            })?;
+            let mut timeouts = self.timeouts.lock().expect("lock should not be poisoned");
+            let timed_out = if *timeouts > 0 {
+                *timeouts -= 1;
+                vec!["slow".to_string()]
+            } else {
+                Vec::new()
+            };
+            drop(timeouts);
+            let mut evaluation = Evaluation {
+                members,
+                unmentionable_roles: self.unmentionable_roles.clone(),
+                timed_out,
+                excluded: HashSet::new(),
+                breakdown: Vec::new(),
+            };
+            if let Some(role) = self.do_not_ping {
+                evaluation.exclude(&self.guild.role_members()[&role]);
+            }
+            Ok(evaluation)
+        }
+
+        fn aliases(&self) -> &BTreeMap<String, String> {
+            &self.aliases
+        }
+
+        async fn roles_and_members(
+            &self,
+        ) -> Result<HashMap<RoleType, HashSet<UserId>>, QueryError> {
+            Ok(self
+                .guild
+                .role_members()
+                .into_iter()
+                .map(|(id, members)| (RoleType::Role(id), members))
+                .chain([
+                    (RoleType::Everyone, self.guild.everyone()),
+                    (RoleType::Here, self.guild.here()),
+                ])
+                .collect())
+        }
+
+        async fn reply(&self, message: OutgoingMessage) -> Result<serenity::MessageId, QueryError> {
+            Self::fail_transiently(&self.transient_failures)?;
+
+            let mut sent = self.sent.lock().expect("lock should not be poisoned");
+            sent.push(message);
+            Ok(serenity::MessageId(
+                u64::try_from(sent.len() - 1).expect("message count should fit in a u64"),
+            ))
+        }
+
+        async fn send_to_channel(
+            &self,
+            channel: serenity::ChannelId,
+            message: OutgoingMessage,
+        ) -> Result<serenity::MessageId, QueryError> {
+            let mut sent_elsewhere = self
+                .sent_elsewhere
+                .lock()
+                .expect("lock should not be poisoned");
+            sent_elsewhere.push((channel, message));
+            Ok(serenity::MessageId(
+                u64::try_from(sent_elsewhere.len() + 9999)
+                    .expect("message count should fit in a u64"),
+            ))
+        }
+
+        async fn edit(
+            &self,
+            id: serenity::MessageId,
+            message: OutgoingMessage,
+        ) -> Result<(), QueryError> {
+            Self::fail_transiently(&self.transient_failures)?;
+            // Like Discord, refuse to edit messages which were sent through a webhook
+            let sent_by_webhook = usize::try_from(id.0).ok().is_some_and(|index| {
+                self.sent
+                    .lock()
+                    .expect("lock should not be poisoned")
+                    .get(index)
+                    .is_some_and(|sent| sent.send_as_author)
+            });
+            if sent_by_webhook {
+                return Err(QueryError::Internal(anyhow!(
+                    "Cannot edit a message authored by another user"
+                )));
+            }
+            self.edits
+                .lock()
+                .expect("lock should not be poisoned")
+                .push((id, message));
+            Ok(())
+        }
+
+        async fn lock_send_queue(&self) -> SendQueueGuard {
+            Arc::clone(&self.send_queue).lock_owned().await
+        }
+
+        async fn delete(&self, id: serenity::MessageId) -> Result<(), QueryError> {
+            self.deleted
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(id);
+            Ok(())
+        }
+
+        fn delete_later(&self, messages: Vec<serenity::MessageId>, after: Duration) {
+            self.deleted_later
+                .lock()
+                .expect("lock should not be poisoned")
+                .push((messages, after));
+        }
+
+        async fn broadcast_typing(&self) -> Result<(), QueryError> {
+            *self.typing.lock().expect("lock should not be poisoned") += 1;
+            Ok(())
+        }
+
+        async fn react_to_query(&self, emoji: &str) -> Result<(), QueryError> {
+            self.reactions
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(emoji.to_string());
+            Ok(())
+        }
+
+        async fn wait(&self, duration: Duration) {
+            self.waits
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(duration);
+        }
+
+        async fn await_button_press(
+            &self,
+            _id: serenity::MessageId,
+            _timeout: Duration,
+        ) -> Result<Option<String>, QueryError> {
+            let gate = self
+                .button_press_gate
+                .lock()
+                .expect("lock should not be poisoned")
+                .take();
+            if let Some(gate) = gate {
+                gate.notified().await;
+            }
+            Ok(self
+                .button_presses
+                .lock()
+                .expect("lock should not be poisoned")
+                .pop_front()
+                .flatten())
+        }
+
+        async fn await_button_press_or_text(
+            &self,
+            id: serenity::MessageId,
+            text_button: &str,
+            _prompt: &TextPrompt,
+            timeout: Duration,
+        ) -> Result<Option<ButtonResponse>, QueryError> {
+            let press = self.await_button_press(id, timeout).await?;
+            Ok(press.map(|custom_id| {
+                if custom_id == text_button {
+                    ButtonResponse::Typed(
+                        self.typed_text
+                            .lock()
+                            .expect("lock should not be poisoned")
+                            .pop_front()
+                            .unwrap_or_default(),
+                    )
+                } else {
+                    ButtonResponse::Pressed(custom_id)
+                }
+            }))
+        }
+
+        async fn await_approval(
+            &self,
+            _id: serenity::MessageId,
+            role: RoleId,
+            _author_buttons: &[&str],
+            _timeout: Duration,
+        ) -> Result<Option<(String, UserId)>, QueryError> {
+            self.approver_roles
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(role);
+            Ok(self
+                .button_presses
+                .lock()
+                .expect("lock should not be poisoned")
+                .pop_front()
+                .flatten()
+                .map(|custom_id| (custom_id, UserId(2))))
+        }
+
+        async fn mention_command(&self, command: &str) -> Result<String, QueryError> {
+            Ok(format!("`/{command}`"))
+        }
+
+        async fn create_thread(&self, name: &str) -> Result<serenity::ChannelId, QueryError> {
+            let mut threads = self.threads.lock().expect("lock should not be poisoned");
+            threads.push(name.to_string());
+            Ok(serenity::ChannelId(
+                u64::try_from(threads.len() + 999).expect("thread count should fit in a u64"),
+            ))
+        }
+
+        async fn add_thread_members(
+            &self,
+            thread: serenity::ChannelId,
+            members: &[UserId],
+        ) -> Result<(), QueryError> {
+            Self::fail_transiently(&self.thread_member_failures)?;
+            self.thread_members
+                .lock()
+                .expect("lock should not be poisoned")
+                .push((thread, members.to_vec()));
+            Ok(())
+        }
+
+        async fn send_dm(&self, user: UserId, message: OutgoingMessage) -> Result<(), QueryError> {
+            if self.closed_dms.contains(&user) {
+                return Err(anyhow!("cannot send messages to this user").into());
+            }
+            self.dms
+                .lock()
+                .expect("lock should not be poisoned")
+                .push((user, message.content));
+            Ok(())
+        }
+
+        fn author(&self) -> UserId {
+            UserId(1)
+        }
+
+        async fn check_access(&self) -> Result<(), QueryError> {
+            if self.access_denied {
+                return Err(QueryError::PermissionDenied("Not allowed.".to_string()));
+            }
+            Ok(())
+        }
+
+        fn reserve_cooldown(&self) -> Result<Option<CooldownReservation<'_>>, QueryError> {
+            if self.on_cooldown {
+                return Err(QueryError::LimitExceeded("Try again later.".to_string()));
+            }
+            self.cooldowns.reserve(
+                serenity::GuildId(1),
+                self.author(),
+                &[],
+                &self.cooldown_settings,
+                Language::English,
+            )
+        }
+
+        fn record_notification(&self, reservation: Option<CooldownReservation<'_>>) {
+            if let Some(reservation) = reservation {
+                reservation.sent();
+            }
+            *self
+                .notifications
+                .lock()
+                .expect("lock should not be poisoned") += 1;
+        }
+
+        fn last_sent(&self, query: &str) -> Option<Duration> {
+            self.sent_queries
+                .lock()
+                .expect("lock should not be poisoned")
+                .iter()
+                .any(|sent| sent == query)
+                .then_some(Duration::from_secs(5))
+        }
+
+        fn record_query(&self, query: &str) {
+            self.sent_queries
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(query.to_string());
+        }
+
+        fn record_history(&self, query: &str, members: usize) {
+            self.history
+                .lock()
+                .expect("lock should not be poisoned")
+                .push((query.to_string(), members));
+        }
+
+        fn record_stats(&self, members: usize, roles: &[RoleType]) {
+            self.stats.record(serenity::GuildId(1), members, roles);
+        }
+
+        fn channel(&self) -> serenity::ChannelId {
+            serenity::ChannelId(2)
+        }
+
+        fn query_link(&self) -> String {
+            "https://discord.com/channels/1/2/3".to_string()
+        }
+
+        fn link(&self, message: serenity::MessageId) -> String {
+            format!("https://discord.com/channels/1/2/{message}")
+        }
+
+        fn wants_dm_notifications(&self, user: UserId) -> bool {
+            self.dm_preferred.contains(&user)
+        }
+
+        fn has_opted_out(&self, user: UserId) -> bool {
+            self.opted_out.contains(&user)
+        }
+
+        fn is_quiet(&self, user: UserId) -> bool {
+            self.quiet.contains(&user)
+        }
+
+        fn overrides_quiet_hours(&self) -> bool {
+            self.emergency
+        }
+
+        fn record_recipients(&self, message: serenity::MessageId, members: &HashSet<UserId>) {
+            self.recipients
+                .lock()
+                .expect("lock should not be poisoned")
+                .push((message, members.clone()));
+        }
+
+        fn save_pending_send(
+            &self,
+            _notice: serenity::MessageId,
+            remaining: &[String],
+            _silent: bool,
+        ) {
+            self.pending_sends
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(Some(remaining.len()));
+        }
+
+        fn finish_pending_send(&self, _notice: serenity::MessageId) {
+            self.pending_sends
+                .lock()
+                .expect("lock should not be poisoned")
+                .push(None);
+        }
+    }
+
+    /// A guild with a `staff` role of two members and a `crowd` role of `count` members with
+    /// long (realistic) IDs.
+    fn guild_with_crowd(count: u64) -> MockGuild {
+        let mut guild = MockGuild::new()
+            .with_role(RoleId(1), "staff")
+            .with_role(RoleId(2), "crowd")
+            .with_member(UserId(1), "alice", &[RoleId(1)])
+            .with_member(UserId(2), "bob", &[RoleId(1)]);
+        for n in 0..count {
+            guild = guild.with_member(
+                UserId(100_000_000_000_000_000 + n),
+                &format!("member{n}"),
+                &[RoleId(2)],
+            );
+        }
+        guild
+    }
+
+    #[tokio::test]
+    async fn small_query_sends_single_message() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("Notification triggered by Intersection."));
+        assert!(sent[0].ends_with("<@1>"));
+    }
+
+    #[tokio::test]
+    async fn paused_queries_mention_nobody() {
+        let discord = FakeDiscord::new(guild_with_crowd(3));
+        let options = QueryOptions {
+            paused_by: Some(UserId(7)),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            sent[0].content,
+            concat!(
+                "Queries are paused in this server, so nobody was mentioned. They were paused by",
+                " <@7>, and can be resumed with `/resume`."
+            )
+        );
+        assert!(sent[0].suppress_mentions);
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn notifications_explain_what_was_matched() {
+        let discord = FakeDiscord::new(guild_with_crowd(3));
+        run_query(&discord, &["crowd + alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert!(sent[0].contains("\n`@{crowd + alice}` matched 4 members via 1 role.\n"));
+    }
+
+    #[test]
+    fn described_queries_cannot_escape_their_code() {
+        assert_eq!(describe_query(&["a`<@1>`"]), "`@{a\u{2cb}<@1>\u{2cb}}`");
+    }
+
+    #[tokio::test]
+    async fn notifications_record_their_recipients() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(
+            sent[0].buttons,
+            vec![
+                recipients::button(Language::English),
+                reply_tracker::delete_button(Language::English)
+            ]
+        );
+        drop(sent);
+        assert_eq!(
+            *discord
+                .recipients
+                .lock()
+                .expect("lock should not be poisoned"),
+            vec![(
+                serenity::MessageId(0),
+                HashSet::from([UserId(1), UserId(2)])
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn split_notifications_record_recipients_on_the_last_message() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            QueryOptions::default(),
+        )
+        .await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        let last = sent.len() - 1;
+        assert!(sent[..last].iter().all(
+            |message| message.buttons.is_empty() || message.content.starts_with("**Hold up!**")
+        ));
+        assert_eq!(
+            sent[last].buttons,
+            vec![
+                recipients::button(Language::English),
+                reply_tracker::delete_button(Language::English)
+            ]
+        );
+        drop(sent);
+
+        let recipients = discord
+            .recipients
+            .lock()
+            .expect("lock should not be poisoned");
+        assert_eq!(recipients.len(), 1);
+        assert_eq!(recipients[0].1.len(), 119);
+        drop(recipients);
+    }
+
+    #[tokio::test]
+    async fn roles_are_mentioned_instead_of_members() {
+        // With an empty crowd, everyone is staff, so @everyone would be just as good
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        run_query(&discord, &["alice + bob"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].ends_with("<@&1>"));
+    }
+
+    #[tokio::test]
+    async fn slow_evaluations_show_progress() {
+        let discord = FakeDiscord {
+            evaluation: FakeEvaluation::Slow,
+            ..FakeDiscord::new(guild_with_crowd(0))
+        };
+        run_query(&discord, &["staff - alice"], QueryOptions::default()).await;
+
+        assert_eq!(
+            *discord.typing.lock().expect("lock should not be poisoned"),
+            1
+        );
+        let sent = discord.sent();
+        assert_eq!(
+            sent[0],
+            "Evaluating your query... (resolved 0/2 roles and members)"
+        );
+        assert_eq!(
+            discord.edits(),
+            vec!["Evaluating your query... (resolved 2/2 roles and members)"]
+        );
+        // The interim message goes away once the results are in
+        assert_eq!(
+            *discord.deleted.lock().expect("lock should not be poisoned"),
+            [serenity::MessageId(0)]
+        );
+        assert!(sent[1].ends_with("<@2>"));
+    }
+
+    #[tokio::test]
+    async fn fast_evaluations_do_not_show_progress() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["staff - alice"], QueryOptions::default()).await;
+
+        assert_eq!(
+            *discord.typing.lock().expect("lock should not be poisoned"),
+            0
+        );
+        assert_eq!(discord.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn no_matches() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["staff - staff"], QueryOptions::default()).await;
+
+        assert_eq!(discord.sent(), vec!["No users matched."]);
+    }
+
+    #[tokio::test]
+    async fn messages_are_sent_in_the_guild_language() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        let options = QueryOptions {
+            language: Language::German,
+            ..Default::default()
+        };
+        run_query(&discord, &["staff - staff"], options).await;
+        run_query(&discord, &["staff"], options).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent[0], "Keine passenden Mitglieder gefunden.");
+        assert!(sent[1].starts_with("Benachrichtigung ausgel\u{f6}st von Intersection."));
+    }
+
+    #[tokio::test]
+    async fn large_query_confirmed() {
+        let discord = FakeDiscord::new(guild_with_crowd(60))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        run_query(&discord, &["crowd"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].starts_with("**Hold up!**"));
+        assert!(sent[0].contains("mention 60 people"));
+        assert!(sent[0].ends_with("This will mention <@&2>."));
+        assert!(sent[1].ends_with("<@&2>"));
+        assert_eq!(discord.edits(), vec!["Confirmed."]);
+    }
+
+    #[tokio::test]
+    async fn very_large_queries_need_approval() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            approval: Some(ModeratorApproval {
+                threshold: 100,
+                role: RoleId(1),
+            }),
+            audit_channel: Some(serenity::ChannelId(50)),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].ends_with(
+            "This will mention <@&2>. As it's so large, a member of <@&1> other than you has to confirm it."
+        ));
+        assert_eq!(
+            *discord
+                .approver_roles
+                .lock()
+                .expect("lock should not be poisoned"),
+            vec![RoleId(1)]
+        );
+        assert_eq!(discord.edits(), vec!["Approved by <@2>."]);
+
+        // The approval is kept in the audit log
+        let sent_elsewhere = discord
+            .sent_elsewhere
+            .lock()
+            .expect("lock should not be poisoned");
+        let embed = sent_elsewhere[0]
+            .1
+            .embed
+            .as_ref()
+            .expect("an embed should be attached");
+        assert!(embed
+            .fields
+            .contains(&("Approved by".to_string(), "<@2>".to_string())));
+        drop(sent_elsewhere);
+    }
+
+    #[tokio::test]
+    async fn queries_below_the_approval_threshold_are_confirmed_by_their_author() {
+        let discord = FakeDiscord::new(guild_with_crowd(60))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            approval: Some(ModeratorApproval {
+                threshold: 100,
+                role: RoleId(1),
+            }),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        assert!(discord
+            .approver_roles
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+        assert_eq!(discord.edits(), vec!["Confirmed."]);
+    }
+
+    #[tokio::test]
+    async fn extreme_queries_need_the_member_count_typed() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_typed"))
+            .with_typed_text(" 120 ");
+        let options = QueryOptions {
+            typed_confirmation: Some(100),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].content.ends_with(
+            "As it's so large, you have to type the number of people it will mention to confirm it."
+        ));
+        assert_eq!(sent[0].buttons[1].custom_id, "large_ping_confirm_typed");
+        drop(sent);
+        assert_eq!(discord.edits(), vec!["Confirmed."]);
+    }
+
+    #[tokio::test]
+    async fn typing_the_wrong_member_count_cancels() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_typed"))
+            .with_typed_text("yes");
+        let options = QueryOptions {
+            typed_confirmation: Some(100),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert_eq!(
+            discord.edits(),
+            vec!["That's not 120, so nobody was mentioned."]
+        );
+    }
+
+    #[tokio::test]
+    async fn queries_below_the_typed_confirmation_threshold_are_confirmed_with_a_button() {
+        let discord = FakeDiscord::new(guild_with_crowd(60))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            typed_confirmation: Some(100),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        assert_eq!(discord.sent().len(), 2);
+        assert_eq!(discord.edits(), vec!["Confirmed."]);
+    }
+
+    #[tokio::test]
+    async fn guilds_can_choose_how_large_a_query_needs_confirming() {
+        let discord = FakeDiscord::new(guild_with_crowd(60));
+        let options = QueryOptions {
+            confirmation_threshold: Some(100),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert!(discord.edits().is_empty());
+
+        let discord = FakeDiscord::new(guild_with_crowd(3)).with_button_press(None);
+        let options = QueryOptions {
+            confirmation_threshold: Some(2),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert_eq!(discord.edits(), vec!["Timed out waiting for confirmation."]);
+    }
+
+    #[tokio::test]
+    async fn confirmation_prompts_can_remind_the_author_halfway_through() {
+        let discord = FakeDiscord::new(guild_with_crowd(60))
+            .with_button_press(None)
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            confirmation_timeout: Some(ConfirmationTimeout {
+                timeout: Duration::from_mins(2),
+                reminder: true,
+            }),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        let edits = discord.edits();
+        assert_eq!(edits.len(), 2);
+        assert!(edits[0].starts_with(&discord.sent()[0]));
+        assert!(edits[0]
+            .ends_with("\n\n*Still waiting for you to confirm. This expires in 60 seconds.*"));
+        assert_eq!(edits[1], "Confirmed.");
+    }
+
+    #[tokio::test]
+    async fn confirmation_prompt_does_not_mention_anyone() {
+        let discord = FakeDiscord::new(guild_with_crowd(60)).with_button_press(None);
+        run_query(&discord, &["crowd + alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert!(sent[0]
+            .content
+            .ends_with("This will mention <@&2> and 1 individual member."));
+        assert!(sent[0].suppress_mentions);
+        drop(sent);
+    }
+
+    #[test]
+    fn mention_summary_lists_everyone_first() {
+        let mentions = Mentions {
+            roles: vec![RoleType::Role(RoleId(5)), RoleType::Everyone],
+            outliers: vec![UserId(1), UserId(2)],
+            role_members: HashMap::new(),
+        };
+
+        assert_eq!(
+            mentions.summary(Language::English),
+            "@everyone, <@&5>, and 2 individual members"
+        );
+    }
+
+    #[test]
+    fn split_mentions_ping_everyone_at_most_once() {
+        let mentions = Mentions {
+            roles: vec![RoleType::Role(RoleId(1)), RoleType::Role(RoleId(2))],
+            outliers: vec![UserId(4)],
+            role_members: HashMap::from([
+                (
+                    RoleType::Role(RoleId(1)),
+                    HashSet::from([UserId(1), UserId(2)]),
+                ),
+                (
+                    RoleType::Role(RoleId(2)),
+                    HashSet::from([UserId(2), UserId(3)]),
+                ),
+            ]),
+        };
+
+        let contents = |size| {
+            mentions
+                .to_messages(size)
+                .iter()
+                .map(MentionMessage::content)
+                .collect::<Vec<_>>()
+        };
+        // Both roles fit in one message, where member 2 is only notified once anyway
+        assert_eq!(contents(2000), vec!["<@&1> <@&2> <@4>"]);
+        // With a message per mention, the second role would notify member 2 again, so its other
+        // member is mentioned in its place
+        assert_eq!(contents(5), vec!["<@&1>", "<@3>", "<@4>"]);
+    }
+
+    #[test]
+    fn split_messages_are_self_contained() {
+        let role = |id| RoleType::Role(RoleId(id));
+        let mentions = Mentions {
+            roles: vec![role(1), role(2), role(3)],
+            outliers: vec![UserId(5)],
+            role_members: HashMap::from([
+                (role(1), HashSet::from([UserId(1)])),
+                (role(2), HashSet::from([UserId(1), UserId(2)])),
+                (role(3), HashSet::from([UserId(2), UserId(6)])),
+            ]),
+        };
+
+        // Role 2 overlaps the first message, so member 2 is mentioned in its place, until role 3
+        // covers them in the same message
+        assert_eq!(
+            mentions.to_messages(5),
+            vec![
+                MentionMessage {
+                    roles: vec![role(1)],
+                    users: vec![],
+                    members: HashSet::from([UserId(1)]),
+                },
+                MentionMessage {
+                    roles: vec![role(3)],
+                    users: vec![],
+                    members: HashSet::from([UserId(2), UserId(6)]),
+                },
+                MentionMessage {
+                    roles: vec![],
+                    users: vec![UserId(5)],
+                    members: HashSet::from([UserId(5)]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mention_summary_truncates_long_role_lists() {
+        let mentions = Mentions {
+            roles: (1..=25).map(|id| RoleType::Role(RoleId(id))).collect(),
+            outliers: vec![],
+            role_members: HashMap::new(),
+        };
+
+        let summary = mentions.summary(Language::English);
+        assert!(summary.ends_with(", and 5 more roles"));
+        assert_eq!(summary.matches("<@&").count(), 20);
+    }
+
+    #[tokio::test]
+    async fn large_query_cancelled() {
+        let discord =
+            FakeDiscord::new(guild_with_crowd(60)).with_button_press(Some("large_ping_confirm_no"));
+        run_query(&discord, &["crowd"], QueryOptions::default()).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert_eq!(discord.edits(), vec!["Cancelled."]);
+    }
+
+    #[tokio::test]
+    async fn large_query_timed_out() {
+        let discord = FakeDiscord::new(guild_with_crowd(60)).with_button_press(None);
+        run_query(&discord, &["crowd"], QueryOptions::default()).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert_eq!(discord.edits(), vec!["Timed out waiting for confirmation."]);
+    }
+
+    #[tokio::test]
+    async fn large_query_is_split_into_multiple_messages() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        // Every member individually, so the role can't be used
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            QueryOptions::default(),
+        )
+        .await;
+
+        let sent = discord.sent();
+        assert!(sent[1].starts_with("Notification triggered by Intersection. Please wait"));
+        assert!(sent
+            .last()
+            .is_some_and(|message| message.starts_with("Notification triggered successfully.")));
+
+        let mention_messages = &sent[2..(sent.len() - 1)];
+        assert!(mention_messages.len() > 1);
+        assert!(mention_messages.iter().all(|message| message.len() <= 2000));
+        let total = mention_messages.len();
+        for (part, message) in (1..).zip(mention_messages) {
+            assert!(message.starts_with(&format!("(part {part}/{total}) <@")));
+        }
+        assert_eq!(
+            mention_messages
+                .iter()
+                .flat_map(|message| message.split(' '))
+                .filter(|mention| mention.starts_with("<@"))
+                .count(),
+            119
+        );
+        // Every message left was remembered before it was sent, in case of a restart
+        let pending_sends = discord
+            .pending_sends
+            .lock()
+            .expect("lock should not be poisoned");
+        assert_eq!(
+            *pending_sends,
+            (1..=total)
+                .rev()
+                .map(Some)
+                .chain([None])
+                .collect::<Vec<_>>()
+        );
+        drop(pending_sends);
+        // The summary links back to the start of the notification
+        assert!(sent.last().is_some_and(|message| message.contains(
+            "[Jump to the start of this notification](https://discord.com/channels/1/2/1)"
+        )));
+    }
+
+    #[tokio::test]
+    async fn silent_queries_send_silent_mentions() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            QueryOptions {
+                silent: true,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        // The confirmation prompt doesn't mention anyone, but everything after it does
+        assert!(!sent[0].silent);
+        assert!(sent[1..].iter().all(|message| message.silent));
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn queries_are_not_silent_by_default() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert!(sent.iter().all(|message| !message.silent));
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn chunks_are_merged_by_default() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        run_query(&discord, &["alice", "bob"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].ends_with("<@&1>"));
+    }
+
+    #[tokio::test]
+    async fn per_chunk_queries_are_mentioned_separately() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        let options = QueryOptions {
+            per_chunk: true,
+            ..Default::default()
+        };
+        run_query(&discord, &["alice", "bob", "alice - alice"], options).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[0].contains("`@{alice}`"));
+        assert!(sent[0].ends_with("<@1>"));
+        assert!(sent[1].contains("`@{bob}`"));
+        assert!(sent[1].ends_with("<@2>"));
+        assert!(sent[2].contains("`@{alice - alice}`"));
+        assert!(sent[2].ends_with("No users matched."));
+    }
+
+    #[tokio::test]
+    async fn members_in_several_chunks_are_only_pinged_once() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        let options = QueryOptions {
+            per_chunk: true,
+            ..Default::default()
+        };
+        // alice is in staff, so staff can't be mentioned again after her
+        run_query(&discord, &["alice", "staff", "bob"], options).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[0].ends_with("<@1>"));
+        assert!(sent[1].contains("1 of these members was already mentioned above."));
+        assert!(sent[1].ends_with("<@2>"));
+        assert!(!sent[1].contains("<@&1>"));
+        assert!(sent[2].contains("`@{bob}`"));
+        assert!(sent[2].ends_with("1 of these members was already mentioned above."));
+        assert!(!sent[2].contains("<@2>"));
+    }
+
+    #[tokio::test]
+    async fn per_chunk_notifications_credit_the_roles_they_mentioned() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        let options = QueryOptions {
+            per_chunk: true,
+            audit_channel: Some(serenity::ChannelId(50)),
+            ..Default::default()
+        };
+        // Together the chunks are exactly staff, but alice was mentioned before staff could be
+        run_query(&discord, &["alice", "staff", "bob"], options).await;
+
+        let stats = discord.stats.get(serenity::GuildId(1));
+        assert_eq!(stats.members_pinged, 2);
+        assert!(stats.roles.is_empty());
+
+        let sent_elsewhere = discord
+            .sent_elsewhere
+            .lock()
+            .expect("lock should not be poisoned");
+        let embed = sent_elsewhere[0]
+            .1
+            .embed
+            .as_ref()
+            .expect("an embed should be attached");
+        let field = |name: &str| {
+            embed
+                .fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(field("Roles used"), None);
+        assert_eq!(field("Roles mentioned").as_deref(), Some("0"));
+        assert_eq!(field("Individual mentions").as_deref(), Some("2"));
+        drop(sent_elsewhere);
+    }
+
+    #[tokio::test]
+    async fn listing_chunks_lists_every_member_of_each() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        let options = QueryOptions {
+            per_chunk: true,
+            ..Default::default()
+        };
+        run_query(&discord, &["staff", "alice"], options).await;
+
+        let sent = discord.sent();
+        assert!(sent
+            .iter()
+            .any(|message| message.contains("`@{alice}`") && message.ends_with("<@1>")));
+        assert!(!sent
+            .iter()
+            .any(|message| message.contains("already mentioned")));
+    }
+
+    #[tokio::test]
+    async fn per_chunk_parse_errors_report_the_right_chunk() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        let options = QueryOptions {
+            per_chunk: true,
+            ..Default::default()
+        };
+        run_query(&discord, &["staff", "staff +"], options).await;
+
+        assert!(discord.sent()[0].starts_with("Error parsing chunk 1:"));
+    }
+
+    #[tokio::test]
+    async fn do_not_ping_role_members_are_left_out() {
+        let discord = FakeDiscord {
+            do_not_ping: Some(RoleId(1)),
+            ..FakeDiscord::new(guild_with_crowd(2))
+        };
+        run_query(&discord, &["staff | crowd"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(
+            sent[0],
+            "2 members were left out, as they have this server's do-not-ping role."
+        );
+        assert!(sent[1].ends_with("<@&2>"));
+        assert!(!sent[1].contains("<@1>"));
+    }
+
+    #[tokio::test]
+    async fn queries_only_matching_do_not_ping_role_members_match_nobody() {
+        let discord = FakeDiscord {
+            do_not_ping: Some(RoleId(1)),
+            ..FakeDiscord::new(guild_with_crowd(0))
+        };
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        assert_eq!(
+            discord.sent(),
+            vec![
+                "1 member was left out, as they have this server's do-not-ping role.",
+                "No users matched."
+            ]
+        );
+    }
+
+    fn discord_with_unmentionable_staff() -> FakeDiscord {
+        FakeDiscord {
+            unmentionable_roles: HashSet::from([RoleId(1)]),
+            ..FakeDiscord::new(guild_with_crowd(1))
+        }
+    }
+
+    #[tokio::test]
+    async fn unmentionable_roles_can_be_expanded() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_expand"));
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].content.contains("<@&1>"));
+        assert!(sent[0].suppress_mentions);
+        assert!(!sent[1].content.contains("<@&1>"));
+        assert!(sent[1].content.contains("<@1>"));
+        assert!(sent[1].content.contains("<@2>"));
+        assert!(!sent[1].suppress_mentions);
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn unmentionable_roles_can_be_listed_without_mentions() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 2);
+        assert!(sent[1].content.contains("nobody was notified"));
+        assert!(!sent[1].content.contains("<@&1>"));
+        assert!(sent[1].content.contains("<@1>"));
+        assert!(sent[1].suppress_mentions);
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn unmentionable_roles_can_be_cancelled() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_cancel"));
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert_eq!(discord.edits(), vec!["Cancelled."]);
+    }
+
+    /// A [`FakeDiscord`] whose next evaluation times out resolving `slow`.
+    fn discord_with_timeout() -> FakeDiscord {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        *discord
+            .timeouts
+            .lock()
+            .expect("lock should not be poisoned") = 1;
+        discord
+    }
+
+    #[tokio::test]
+    async fn incomplete_results_can_be_used() {
+        let discord =
+            discord_with_timeout().with_button_press(Some("incomplete_evaluation_continue"));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].content.contains("incomplete"));
+        assert!(sent[0].content.contains("`slow`"));
+        assert!(sent[0].content.contains("matches 1 members"));
+        assert!(sent[0].suppress_mentions);
+        assert!(sent[1].content.ends_with("<@1>"));
+        drop(sent);
+        assert_eq!(
+            discord.edits(),
+            vec!["Continuing with the partial results."]
+        );
+    }
+
+    #[tokio::test]
+    async fn incomplete_results_can_be_retried() {
+        let discord = discord_with_timeout().with_button_press(Some("incomplete_evaluation_retry"));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        // The retry completed, so there's no second prompt
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[1].ends_with("<@1>"));
+        assert_eq!(discord.edits(), vec!["Retrying your query..."]);
+    }
+
+    #[tokio::test]
+    async fn incomplete_results_can_be_cancelled() {
+        let discord =
+            discord_with_timeout().with_button_press(Some("incomplete_evaluation_cancel"));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        assert_eq!(discord.sent().len(), 1);
+        assert_eq!(discord.edits(), vec!["Cancelled."]);
+    }
+
+    #[tokio::test]
+    async fn embed_style_leaves_only_mentions_in_content() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        let options = QueryOptions {
+            embed: true,
+            ..Default::default()
+        };
+        run_query(&discord, &["alice + bob"], options).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].content, "<@&1>");
+        let embed = sent[0].embed.as_ref().expect("an embed should be attached");
+        assert_eq!(
+            embed.fields,
+            [
+                ("Query", "`@{alice + bob}`"),
+                ("Author", "<@1>"),
+                ("Members", "2"),
+                ("Roles mentioned", "1"),
+                ("Individual mentions", "0"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn embed_style_is_not_used_without_pings() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        let options = QueryOptions {
+            embed: true,
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert!(sent.iter().all(|message| message.embed.is_none()));
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn webhook_mode_sends_notifications_as_the_author() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            delivery: Delivery::Webhook,
+            ..Default::default()
+        };
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            options,
+        )
+        .await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        // The confirmation prompt is only for the author, so it comes from Intersection, and so
+        // does the notice, which is edited as the messages are sent
+        assert!(!sent[0].send_as_author);
+        assert!(!sent[1].send_as_author);
+        assert!(sent[2..].iter().all(|message| message.send_as_author));
+        drop(sent);
+
+        // So the notice ends up saying they were all sent
+        let edits = discord.edits.lock().expect("lock should not be poisoned");
+        let (notice, progress) = edits.last().expect("the notice should be edited");
+        assert_eq!(*notice, serenity::MessageId(1));
+        assert!(progress.content.contains("Sent "));
+        drop(edits);
+    }
+
+    #[tokio::test]
+    async fn webhook_mode_is_not_used_without_pings() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        let options = QueryOptions {
+            delivery: Delivery::Webhook,
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert!(sent.iter().all(|message| !message.send_as_author));
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn thread_mode_adds_members_in_batches() {
+        let discord = FakeDiscord::new(guild_with_crowd(60))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            delivery: Delivery::Thread,
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        assert_eq!(
+            *discord.threads.lock().expect("lock should not be poisoned"),
+            vec!["@{crowd}"]
+        );
+        let thread_members = discord
+            .thread_members
+            .lock()
+            .expect("lock should not be poisoned");
+        assert_eq!(
+            thread_members
+                .iter()
+                .map(|(_, batch)| batch.len())
+                .collect::<Vec<_>>(),
+            vec![25, 25, 10]
+        );
+        assert!(thread_members
+            .iter()
+            .all(|(thread, _)| *thread == serenity::ChannelId(1000)));
+        drop(thread_members);
+
+        // Nobody is mentioned, only told about the thread
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1], "Adding 60 members to <#1000>...");
+        assert_eq!(
+            discord.edits().last().map(String::as_str),
+            Some("Added 60 members to <#1000> instead of mentioning them.")
+        );
+        assert_eq!(
+            discord
+                .recipients
+                .lock()
+                .expect("lock should not be poisoned")[0]
+                .1
+                .len(),
+            60
+        );
+    }
+
+    #[tokio::test]
+    async fn thread_mode_is_not_used_without_pings() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        let options = QueryOptions {
+            delivery: Delivery::Thread,
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+
+        assert!(discord
+            .threads
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn members_can_be_notified_by_dm_instead() {
+        let discord = FakeDiscord {
+            dm_preferred: HashSet::from([UserId(2)]),
+            ..FakeDiscord::new(guild_with_crowd(1))
+        };
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        assert_eq!(
+            *discord.dms.lock().expect("lock should not be poisoned"),
+            vec![(
+                UserId(2),
+                "<@1> mentioned you with `@{staff}` in https://discord.com/channels/1/2/3"
+                    .to_string()
+            )]
+        );
+        // Bob was DMed, so the staff role can't be mentioned without notifying him twice
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].contains("1 members notified via DM.\n"));
+        assert!(sent[0].ends_with("<@1>"));
+        // Everyone was still notified one way or another
+        assert_eq!(
+            discord
+                .recipients
+                .lock()
+                .expect("lock should not be poisoned")[0]
+                .1,
+            HashSet::from([UserId(1), UserId(2)])
+        );
+    }
+
+    #[tokio::test]
+    async fn opted_out_members_are_left_out() {
+        let discord = FakeDiscord {
+            opted_out: HashSet::from([UserId(2)]),
+            ..FakeDiscord::new(guild_with_crowd(1))
+        };
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(
+            sent[0],
+            "1 member was left out, as they opted out of being notified in this server."
+        );
+        // Bob is in staff, so mentioning the role would still notify him
+        assert!(sent[1].ends_with("<@1>"));
+    }
+
+    #[tokio::test]
+    async fn members_in_their_quiet_hours_are_left_out_unless_its_an_emergency() {
+        let discord = FakeDiscord {
+            quiet: HashSet::from([UserId(2)]),
+            ..FakeDiscord::new(guild_with_crowd(1))
+        };
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+        let sent = discord.sent();
+        assert_eq!(
+            sent[0],
+            "1 member was left out, as they're in their quiet hours."
+        );
+        assert!(sent[1].ends_with("<@1>"));
+
+        let discord = FakeDiscord {
+            quiet: HashSet::from([UserId(2)]),
+            emergency: true,
+            ..FakeDiscord::new(guild_with_crowd(1))
+        };
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].ends_with("<@&1>"));
+    }
+
+    #[tokio::test]
+    async fn closed_dms_fall_back_to_mentions() {
+        let discord = FakeDiscord {
+            dm_preferred: HashSet::from([UserId(2)]),
+            closed_dms: HashSet::from([UserId(2)]),
+            ..FakeDiscord::new(guild_with_crowd(1))
+        };
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(!sent[0].contains("via DM"));
+        assert!(sent[0].ends_with("<@&1>"));
+    }
+
+    #[tokio::test]
+    async fn authors_are_not_notified_by_dm() {
+        let discord = FakeDiscord {
+            dm_preferred: HashSet::from([UserId(1)]),
+            ..FakeDiscord::new(guild_with_crowd(1))
+        };
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        assert!(discord
+            .dms
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+        assert!(discord.sent()[0].ends_with("<@1>"));
+    }
+
+    #[tokio::test]
+    async fn access_is_checked_before_evaluation() {
+        let discord = FakeDiscord {
+            access_denied: true,
+            evaluation: FakeEvaluation::Failing,
+            ..FakeDiscord::new(guild_with_crowd(0))
+        };
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent.lock().expect("lock should not be poisoned");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].content, "Not allowed.");
+        assert!(sent[0].suppress_mentions);
+        drop(sent);
+    }
+
+    #[tokio::test]
+    async fn cooldowns_are_checked_before_evaluation() {
+        let discord = FakeDiscord {
+            on_cooldown: true,
+            evaluation: FakeEvaluation::Failing,
+            ..FakeDiscord::new(guild_with_crowd(0))
+        };
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        assert_eq!(discord.sent(), vec!["Try again later."]);
+    }
+
+    #[tokio::test]
+    async fn queries_waiting_for_confirmation_hold_their_cooldown() {
+        let settings = CooldownSettings {
+            per_author: Some(RateLimit {
+                count: 1,
+                window: Duration::from_mins(10),
+            }),
+            ..CooldownSettings::default()
+        };
+        let gate = Arc::new(tokio::sync::Notify::new());
+        let discord = FakeDiscord {
+            cooldown_settings: settings.clone(),
+            button_press_gate: Mutex::new(Some(Arc::clone(&gate))),
+            ..FakeDiscord::new(guild_with_crowd(60))
+        }
+        .with_button_press(Some("large_ping_confirm_yes"))
+        .with_button_press(Some("large_ping_confirm_yes"));
+
+        // The second query is sent while the first waits for its author to confirm it
+        tokio::join!(
+            run_query(&discord, &["crowd"], QueryOptions::default()),
+            async {
+                run_query(&discord, &["crowd"], QueryOptions::default()).await;
+                gate.notify_one();
+            }
+        );
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[0].starts_with("**Hold up!**"));
+        assert!(sent[1].starts_with("You've sent too many notifications recently."));
+        assert!(sent[2].ends_with("<@&2>"));
+        assert_eq!(
+            *discord
+                .notifications
+                .lock()
+                .expect("lock should not be poisoned"),
+            1
+        );
+
+        // A declined query gives its place back
+        let discord = FakeDiscord {
+            cooldown_settings: settings,
+            ..FakeDiscord::new(guild_with_crowd(60))
+        }
+        .with_button_press(Some("large_ping_confirm_no"))
+        .with_button_press(Some("large_ping_confirm_yes"));
+        run_query(&discord, &["crowd"], QueryOptions::default()).await;
+        run_query(&discord, &["crowd"], QueryOptions::default()).await;
+        assert_eq!(
+            *discord
+                .notifications
+                .lock()
+                .expect("lock should not be poisoned"),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn only_sent_notifications_count_towards_cooldowns() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+        run_query(&discord, &["alice - alice"], QueryOptions::default()).await;
+        assert_eq!(
+            *discord
+                .notifications
+                .lock()
+                .expect("lock should not be poisoned"),
+            1
+        );
+
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+        assert_eq!(
+            *discord
+                .notifications
+                .lock()
+                .expect("lock should not be poisoned"),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_queries_need_confirming() {
+        let discord = FakeDiscord::new(guild_with_crowd(0))
+            .with_button_press(Some("duplicate_query_confirm"));
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+        // Written differently, but the same query
+        run_query(&discord, &["( alice )"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 3);
+        assert!(sent[1].starts_with("**Hold up!** `@{( alice )}` was already sent"));
+        assert!(sent[2].ends_with("<@1>"));
+        assert_eq!(discord.edits(), vec!["Confirmed."]);
+        assert_eq!(
+            *discord
+                .sent_queries
+                .lock()
+                .expect("lock should not be poisoned"),
+            vec!["alice", "alice"]
+        );
+    }
+
+    #[tokio::test]
+    async fn notifications_are_recorded_in_the_history() {
+        let discord = FakeDiscord::new(guild_with_crowd(3));
+        run_query(&discord, &["crowd + alice"], QueryOptions::default()).await;
+        // Queries matching nobody aren't notifications
+        run_query(&discord, &["alice - alice"], QueryOptions::default()).await;
+
+        assert_eq!(
+            *discord.history.lock().expect("lock should not be poisoned"),
+            vec![("`@{crowd + alice}`".to_string(), 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn notifications_are_counted_in_the_stats() {
+        let discord = FakeDiscord::new(guild_with_crowd(3));
+        run_query(&discord, &["crowd + alice"], QueryOptions::default()).await;
+        run_query(&discord, &["crowd"], QueryOptions::default()).await;
+
+        let stats = discord.stats.get(serenity::GuildId(1));
+        assert_eq!(stats.queries, 2);
+        assert_eq!(stats.members_pinged, 7);
+        assert_eq!(stats.largest_query, 4);
+        assert_eq!(
+            stats.most_used_roles(1),
+            [(RoleType::Role(serenity::RoleId(2)), 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn aliases_are_expanded() {
+        let discord = FakeDiscord {
+            aliases: BTreeMap::from([("leads".to_string(), "alice".to_string())]),
+            ..FakeDiscord::new(guild_with_crowd(3))
+        };
+        run_query(&discord, &["crowd + $Leads"], QueryOptions::default()).await;
+        assert!(discord.sent()[0].ends_with("<@&2> <@1>"));
+
+        let discord = FakeDiscord::new(guild_with_crowd(3));
+        run_query(&discord, &["crowd + $leads"], QueryOptions::default()).await;
+        assert_eq!(discord.sent(), ["`$leads` isn't an alias in this server."]);
+    }
+
+    #[tokio::test]
+    async fn duplicate_queries_can_be_refused() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        let options = QueryOptions {
+            duplicate_queries: DuplicateQueryMode::Refuse,
+            ..Default::default()
+        };
+        run_query(&discord, &["alice"], options).await;
+        run_query(&discord, &["alice"], options).await;
+        run_query(&discord, &["bob"], options).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(
+            sent[1],
+            concat!(
+                "`@{alice}` was already sent in this channel 5 seconds ago.",
+                " Wait a while before sending it again."
+            )
+        );
+        // Other queries are unaffected
+        assert!(sent[2].ends_with("<@2>"));
+    }
+
+    #[tokio::test]
+    async fn duplicate_queries_can_be_allowed() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        let options = QueryOptions {
+            duplicate_queries: DuplicateQueryMode::Allow,
+            ..Default::default()
+        };
+        run_query(&discord, &["alice"], options).await;
+        run_query(&discord, &["alice"], options).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|message| message.ends_with("<@1>")));
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        *discord
+            .transient_failures
+            .lock()
+            .expect("lock should not be poisoned") = 2;
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].ends_with("<@1>"));
+        let waits = discord.waits.lock().expect("lock should not be poisoned");
+        assert_eq!(waits.len(), 2);
+        // Each retry backs off further, give or take some jitter
+        for (retry, &wait) in (1..).zip(waits.iter()) {
+            assert!(wait >= retry::backoff(retry));
+            assert!(wait < retry::backoff(retry).mul_f64(1.5));
+        }
+        drop(waits);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_mid_batch_are_retried() {
+        let discord = FakeDiscord::new(guild_with_crowd(60))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            delivery: Delivery::Thread,
+            ..Default::default()
+        };
+        *discord
+            .thread_member_failures
+            .lock()
+            .expect("lock should not be poisoned") = 2;
+        run_query(&discord, &["crowd"], options).await;
+
+        // Every batch still makes it into the thread, rather than stopping at the failed one
+        assert_eq!(
+            discord
+                .thread_members
+                .lock()
+                .expect("lock should not be poisoned")
+                .iter()
+                .map(|(_, batch)| batch.len())
+                .collect::<Vec<_>>(),
+            vec![25, 25, 10]
+        );
+        assert_eq!(
+            discord
+                .waits
+                .lock()
+                .expect("lock should not be poisoned")
+                .len(),
+            2
+        );
+        assert!(discord
+            .edits()
+            .last()
+            .is_some_and(|edit| edit.starts_with("Added 60 members")));
+    }
+
+    #[tokio::test]
+    async fn persistent_failures_are_given_up_on() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        *discord
+            .transient_failures
+            .lock()
+            .expect("lock should not be poisoned") = 100;
+        run_query(&discord, &["alice"], QueryOptions::default()).await;
+
+        assert!(discord.sent().is_empty());
+        assert_eq!(
+            discord
+                .waits
+                .lock()
+                .expect("lock should not be poisoned")
+                .len(),
+            // The error is reported (with retries) after giving up, which fails too, twice
+            3 * usize::try_from(retry::MAX_ATTEMPTS - 1).expect("attempts should fit in a usize")
+        );
+    }
+
+    #[tokio::test]
+    async fn split_notifications_report_their_progress() {
+        let discord = FakeDiscord::new(guild_with_crowd(1000))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            QueryOptions::default(),
+        )
+        .await;
+
+        let notice = "Notification triggered by Intersection. Please wait, sending 12 messages...";
+        assert_eq!(
+            discord.edits(),
+            vec![
+                "Confirmed.".to_string(),
+                format!("{notice} (5 sent)"),
+                format!("{notice} (10 sent)"),
+                "Notification triggered by Intersection. Sent 12 messages.".to_string(),
+            ]
+        );
+        // The queue is released once the notification is sent
+        assert!(discord.send_queue.try_lock().is_ok());
+    }
+
+    #[tokio::test]
+    async fn queries_above_the_mention_cap_are_refused() {
+        let discord = FakeDiscord::new(guild_with_crowd(10));
+        let options = QueryOptions {
+            max_mentions: Some(5),
+            ..Default::default()
+        };
+        run_query(&discord, &["crowd"], options).await;
+
+        // Refused before the confirmation prompt, so it can't be confirmed by accident
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with(
+            "Your query matches 10 members, but this server only allows mentioning 5 members"
+        ));
+
+        run_query(&discord, &["staff"], options).await;
+        assert!(discord.sent()[1].ends_with("<@&1>"));
+    }
+
+    #[tokio::test]
+    async fn notifications_are_posted_to_the_audit_log() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        let options = QueryOptions {
+            audit_channel: Some(serenity::ChannelId(50)),
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+        // Queries matching nobody aren't notifications
+        run_query(&discord, &["alice - alice"], options).await;
+
+        let sent_elsewhere = discord
+            .sent_elsewhere
+            .lock()
+            .expect("lock should not be poisoned");
+        assert_eq!(sent_elsewhere.len(), 1);
+        let (channel, message) = &sent_elsewhere[0];
+        assert_eq!(*channel, serenity::ChannelId(50));
+        let embed = message.embed.as_ref().expect("an embed should be attached");
+        assert_eq!(
+            embed.description,
+            "[Jump to notification](https://discord.com/channels/1/2/0)"
+        );
+        assert_eq!(
+            embed.fields,
+            [
+                ("Author", "<@1>"),
+                ("Channel", "<#2>"),
+                ("Query", "`@{staff}`"),
+                ("Roles used", "<@&1>"),
+                ("Members", "2"),
+                ("Roles mentioned", "1"),
+                ("Individual mentions", "0"),
+                ("Notified via DM", "0"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+        drop(sent_elsewhere);
+    }
+
+    #[tokio::test]
+    async fn split_notifications_are_audited_per_message() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            audit_channel: Some(serenity::ChannelId(50)),
+            ..Default::default()
+        };
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            options,
+        )
+        .await;
+
+        let sent_elsewhere = discord
+            .sent_elsewhere
+            .lock()
+            .expect("lock should not be poisoned");
+        let embed = sent_elsewhere[0]
+            .1
+            .embed
+            .as_ref()
+            .expect("an embed should be attached");
+        let (_, messages) = embed
+            .fields
+            .iter()
+            .find(|(name, _)| name == "Messages")
+            .expect("each message should be listed");
+        let lines = messages.lines().collect::<Vec<_>>();
+        assert!(lines.len() > 1);
+        assert!(lines[0].starts_with("[#1](https://discord.com/channels/1/2/"));
+        // Every member is notified by exactly one of the messages
+        let notified = lines
+            .iter()
+            .map(|line| {
+                line.rsplit(": ")
+                    .next()
+                    .and_then(|count| count.trim_end_matches(" members").parse::<usize>().ok())
+                    .expect("each line should count its members")
+            })
+            .sum::<usize>();
+        assert_eq!(notified, 119);
+        drop(sent_elsewhere);
+    }
+
+    #[test]
+    fn audited_roles_fit_in_a_field() {
+        assert_eq!(roles_used(&[]), None);
+        assert_eq!(
+            roles_used(&[RoleType::Everyone, RoleType::Role(RoleId(3))]).as_deref(),
+            Some("@everyone <@&3>")
+        );
+
+        let roles = (0..100)
+            .map(|n| RoleType::Role(RoleId(100_000_000_000_000_000 + n)))
+            .collect::<Vec<_>>();
+        let used = roles_used(&roles).expect("roles were used");
+        assert!(used.chars().count() <= MAX_FIELD_LENGTH);
+        assert!(used.ends_with(" \u{2026}"));
+    }
+
+    #[tokio::test]
+    async fn listing_members_is_not_audited() {
+        let discord =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        let options = QueryOptions {
+            audit_channel: Some(serenity::ChannelId(50)),
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+
+        assert!(discord
+            .sent_elsewhere
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn user_errors_are_shown_to_the_user() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["nobody"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("Unable to find a role or member with the name nobody."));
+    }
+
+    #[tokio::test]
+    async fn parse_errors_are_shown_to_the_user() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["staff", "staff +"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("Error parsing chunk 1: Your query ended too early"));
+        assert!(sent[0].ends_with("\n```\n@{staff +}\n         ^\n```"));
+    }
+
+    #[tokio::test]
+    async fn explanations_of_mistakes_expire() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        let options = QueryOptions {
+            error_expiry: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        run_query(&discord, &["nobody"], options).await;
+
+        assert_eq!(
+            *discord
+                .reactions
+                .lock()
+                .expect("lock should not be poisoned"),
+            ["\u{274c}"]
+        );
+        assert_eq!(
+            *discord.waits.lock().expect("lock should not be poisoned"),
+            [Duration::from_secs(30)]
+        );
+        assert_eq!(
+            *discord.deleted.lock().expect("lock should not be poisoned"),
+            [serenity::MessageId(0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn mention_messages_expire() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        let options = QueryOptions {
+            mention_expiry: Some(Duration::from_mins(10)),
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+
+        assert_eq!(
+            *discord
+                .deleted_later
+                .lock()
+                .expect("lock should not be poisoned"),
+            [(vec![serenity::MessageId(0)], Duration::from_mins(10))]
+        );
+    }
+
+    #[tokio::test]
+    async fn every_message_of_a_split_notification_expires() {
+        let discord = FakeDiscord::new(guild_with_crowd(120))
+            .with_button_press(Some("large_ping_confirm_yes"));
+        let options = QueryOptions {
+            mention_expiry: Some(Duration::from_mins(10)),
+            ..Default::default()
+        };
+        run_query(
+            &discord,
+            &["crowd - staff - <@100000000000000000>"],
+            options,
+        )
+        .await;
+
+        let sent = discord.sent();
+        let deleted_later = discord
+            .deleted_later
+            .lock()
+            .expect("lock should not be poisoned");
+        assert_eq!(deleted_later.len(), 1);
+        let (messages, _) = &deleted_later[0];
+        // Everything but the confirmation prompt, which was already answered
+        assert!(sent[0].starts_with("**Hold up!**"));
+        assert_eq!(messages.len(), sent.len() - 1);
+        assert_eq!(messages[0], serenity::MessageId(1));
+        drop(deleted_later);
+    }
+
+    #[tokio::test]
+    async fn listed_members_and_kept_mentions_do_not_expire() {
+        let discord = FakeDiscord::new(guild_with_crowd(1));
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let listing =
+            discord_with_unmentionable_staff().with_button_press(Some("unmentionable_role_list"));
+        let options = QueryOptions {
+            mention_expiry: Some(Duration::from_mins(10)),
+            ..Default::default()
+        };
+        run_query(&listing, &["staff"], options).await;
+
+        for discord in [&discord, &listing] {
+            assert!(discord
+                .deleted_later
+                .lock()
+                .expect("lock should not be poisoned")
+                .is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn other_errors_do_not_expire() {
+        let discord = FakeDiscord {
+            on_cooldown: true,
+            ..FakeDiscord::new(guild_with_crowd(0))
+        };
+        let options = QueryOptions {
+            error_expiry: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        run_query(&discord, &["staff"], options).await;
+
+        assert_eq!(discord.sent(), vec!["Try again later."]);
+        assert!(discord
+            .deleted
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+        assert!(discord
+            .reactions
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn mistakes_are_kept_without_an_expiry() {
+        let discord = FakeDiscord::new(guild_with_crowd(0));
+        run_query(&discord, &["staff +"], QueryOptions::default()).await;
+
+        assert!(discord
+            .deleted
+            .lock()
+            .expect("lock should not be poisoned")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn internal_errors_are_hidden_from_the_user() {
+        let discord = FakeDiscord {
+            evaluation: FakeEvaluation::Failing,
+            ..FakeDiscord::new(guild_with_crowd(0))
+        };
+        run_query(&discord, &["staff"], QueryOptions::default()).await;
+
+        let sent = discord.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("Something went wrong on our side"));
+        assert!(!sent[0].contains("broken"));
+    }
+}
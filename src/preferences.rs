@@ -0,0 +1,260 @@
+//! Per-member preferences
+//!
+//! Members can change how Intersection notifies them, regardless of the guild, and opt out of
+//! being notified in a guild altogether or during their quiet hours. Preferences are written to a
+//! file, if given one, as soon as they change, so members who opted out stay opted out after a
+//! restart.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+use crate::{persistence::StoreFile, quiet_hours::QuietHours};
+
+/// The preferences members changed from the defaults
+#[derive(Debug, Default)]
+struct Preferences {
+    /// The members who would rather be notified by DM than mentioned in the channel
+    dm_notifications: HashSet<UserId>,
+    /// The members who never want to be notified by queries in each guild
+    opted_out: HashSet<(GuildId, UserId)>,
+    /// When members don't want to be notified by queries in each guild, if they chose a time
+    quiet_hours: HashMap<(GuildId, UserId), QuietHours>,
+}
+
+impl Preferences {
+    /// Write every preference as lines, with tab separated fields starting with the kind of
+    /// preference.
+    fn to_lines(&self) -> Vec<String> {
+        self.dm_notifications
+            .iter()
+            .map(|user| format!("dm\t{user}"))
+            .chain(
+                self.opted_out
+                    .iter()
+                    .map(|(guild, user)| format!("opted_out\t{guild}\t{user}")),
+            )
+            .chain(self.quiet_hours.iter().map(|((guild, user), hours)| {
+                format!("quiet_hours\t{guild}\t{user}\t{}", hours.to_field())
+            }))
+            .collect()
+    }
+
+    /// Remember the preference written as `line` by [`Preferences::to_lines`].
+    fn read_line(&mut self, line: &str) -> Option<()> {
+        let mut fields = line.split('\t');
+        let kind = fields.next()?;
+        let mut id = || fields.next()?.parse::<u64>().ok();
+        match kind {
+            "dm" => {
+                self.dm_notifications.insert(UserId(id()?));
+            }
+            "opted_out" => {
+                self.opted_out.insert((GuildId(id()?), UserId(id()?)));
+            }
+            "quiet_hours" => {
+                let member = (GuildId(id()?), UserId(id()?));
+                let hours = QuietHours::from_field(fields.next()?)?;
+                self.quiet_hours.insert(member, hours);
+            }
+            _ => return None,
+        }
+        fields.next().is_none().then_some(())
+    }
+}
+
+/// The preferences of every member, falling back to the defaults for members who haven't changed
+/// anything.
+#[derive(Debug)]
+pub struct PreferenceStore {
+    /// The file preferences are written to, if they're kept across restarts
+    file: StoreFile,
+    /// Every member's preferences
+    preferences: Mutex<Preferences>,
+}
+
+impl PreferenceStore {
+    /// Create a new [`PreferenceStore`] writing to `path`, reading the preferences members chose
+    /// the last time from it. Every other member has the default preferences.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let file = StoreFile::new(path, "member preferences");
+        let mut preferences = Preferences::default();
+        file.load(|line| preferences.read_line(line));
+
+        Self {
+            file,
+            preferences: Mutex::new(preferences),
+        }
+    }
+
+    /// Read the preferences with `read`.
+    fn read<T>(&self, read: impl FnOnce(&Preferences) -> T) -> T {
+        read(
+            &self
+                .preferences
+                .lock()
+                .expect("preference store lock was poisoned"),
+        )
+    }
+
+    /// Change the preferences with `change`, writing them to the file.
+    fn change(&self, change: impl FnOnce(&mut Preferences)) {
+        let mut preferences = self
+            .preferences
+            .lock()
+            .expect("preference store lock was poisoned");
+        change(&mut preferences);
+        // Saving with the lock held keeps an older save from overwriting a newer one
+        self.file.save(preferences.to_lines());
+        drop(preferences);
+    }
+
+    /// Whether a member would rather be notified by DM than mentioned in the channel.
+    pub fn wants_dm_notifications(&self, user: UserId) -> bool {
+        self.read(|preferences| preferences.dm_notifications.contains(&user))
+    }
+
+    /// Set whether a member would rather be notified by DM than mentioned in the channel.
+    pub fn set_dm_notifications(&self, user: UserId, enabled: bool) {
+        self.change(|preferences| {
+            if enabled {
+                preferences.dm_notifications.insert(user);
+            } else {
+                preferences.dm_notifications.remove(&user);
+            }
+        });
+    }
+
+    /// Whether a member opted out of being notified by queries in `guild`.
+    pub fn has_opted_out(&self, guild: GuildId, user: UserId) -> bool {
+        self.read(|preferences| preferences.opted_out.contains(&(guild, user)))
+    }
+
+    /// Set whether a member opted out of being notified by queries in `guild`.
+    pub fn set_opted_out(&self, guild: GuildId, user: UserId, opted_out: bool) {
+        self.change(|preferences| {
+            if opted_out {
+                preferences.opted_out.insert((guild, user));
+            } else {
+                preferences.opted_out.remove(&(guild, user));
+            }
+        });
+    }
+
+    /// The quiet hours a member chose in `guild`, if any.
+    pub fn quiet_hours(&self, guild: GuildId, user: UserId) -> Option<QuietHours> {
+        self.read(|preferences| preferences.quiet_hours.get(&(guild, user)).copied())
+    }
+
+    /// Set the quiet hours of a member in `guild`, or stop having any.
+    pub fn set_quiet_hours(&self, guild: GuildId, user: UserId, hours: Option<QuietHours>) {
+        self.change(|preferences| {
+            match hours {
+                Some(hours) => preferences.quiet_hours.insert((guild, user), hours),
+                None => preferences.quiet_hours.remove(&(guild, user)),
+            };
+        });
+    }
+
+    /// Forget every preference of a member, in every guild.
+    pub fn forget_user(&self, user: UserId) {
+        self.change(|preferences| {
+            preferences.dm_notifications.remove(&user);
+            preferences.opted_out.retain(|(_, member)| *member != user);
+            preferences
+                .quiet_hours
+                .retain(|(_, member), _| *member != user);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn members_are_mentioned_by_default() {
+        assert!(!PreferenceStore::open(None).wants_dm_notifications(UserId(1)));
+    }
+
+    #[test]
+    fn dm_notifications_can_be_toggled() {
+        let store = PreferenceStore::open(None);
+        store.set_dm_notifications(UserId(1), true);
+        assert!(store.wants_dm_notifications(UserId(1)));
+        assert!(!store.wants_dm_notifications(UserId(2)));
+
+        store.set_dm_notifications(UserId(1), false);
+        assert!(!store.wants_dm_notifications(UserId(1)));
+    }
+
+    #[test]
+    fn members_opt_out_in_one_guild_at_a_time() {
+        let store = PreferenceStore::open(None);
+        store.set_opted_out(GuildId(1), UserId(1), true);
+        assert!(store.has_opted_out(GuildId(1), UserId(1)));
+        assert!(!store.has_opted_out(GuildId(2), UserId(1)));
+        assert!(!store.has_opted_out(GuildId(1), UserId(2)));
+
+        store.set_opted_out(GuildId(1), UserId(1), false);
+        assert!(!store.has_opted_out(GuildId(1), UserId(1)));
+    }
+
+    #[test]
+    fn quiet_hours_can_be_cleared() {
+        let store = PreferenceStore::open(None);
+        let hours = QuietHours::parse("22:00-08:00", None);
+        store.set_quiet_hours(GuildId(1), UserId(1), hours);
+        assert_eq!(store.quiet_hours(GuildId(1), UserId(1)), hours);
+        assert_eq!(store.quiet_hours(GuildId(2), UserId(1)), None);
+
+        store.set_quiet_hours(GuildId(1), UserId(1), None);
+        assert_eq!(store.quiet_hours(GuildId(1), UserId(1)), None);
+    }
+
+    #[test]
+    fn members_can_be_forgotten() {
+        let store = PreferenceStore::open(None);
+        store.set_dm_notifications(UserId(1), true);
+        store.set_opted_out(GuildId(1), UserId(1), true);
+        store.set_opted_out(GuildId(1), UserId(2), true);
+        store.set_quiet_hours(
+            GuildId(2),
+            UserId(1),
+            QuietHours::parse("22:00-08:00", None),
+        );
+
+        store.forget_user(UserId(1));
+        assert!(!store.wants_dm_notifications(UserId(1)));
+        assert!(!store.has_opted_out(GuildId(1), UserId(1)));
+        assert!(store.has_opted_out(GuildId(1), UserId(2)));
+        assert_eq!(store.quiet_hours(GuildId(2), UserId(1)), None);
+    }
+
+    #[test]
+    fn preferences_survive_a_restart() {
+        let path = env::temp_dir().join(format!("intersection-preferences-{}", std::process::id()));
+        let store = PreferenceStore::open(Some(path.clone()));
+        let hours = QuietHours::parse("22:00-08:00", Some("UTC+2"));
+        store.set_opted_out(GuildId(1), UserId(1), true);
+        store.set_opted_out(GuildId(1), UserId(2), true);
+        store.set_opted_out(GuildId(1), UserId(2), false);
+        store.set_dm_notifications(UserId(3), true);
+        store.set_quiet_hours(GuildId(1), UserId(3), hours);
+
+        // As if Intersection restarted
+        let store = PreferenceStore::open(Some(path.clone()));
+        assert!(store.has_opted_out(GuildId(1), UserId(1)));
+        assert!(!store.has_opted_out(GuildId(1), UserId(2)));
+        assert!(store.wants_dm_notifications(UserId(3)));
+        assert_eq!(store.quiet_hours(GuildId(1), UserId(3)), hours);
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+}
@@ -0,0 +1,161 @@
+//! A short-lived cache of evaluated DRQL query results
+//!
+//! Evaluating a query may require several REST calls (member searches, for example), so
+//! re-running the exact same query shortly after is wasteful. This module caches evaluation
+//! results per guild, keyed by the normalized query, for a short TTL. Entries for a guild
+//! are invalidated whenever a member or role event is received for that guild, as those may
+//! change the result of any query.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+use tracing::{debug, trace};
+
+use crate::pipeline::Evaluation;
+
+/// Identifies a single cached query result within a guild.
+///
+/// Query results depend not only on the query itself but on who ran it and where, as the
+/// resolver checks the author's permissions (in the channel) while resolving roles.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryCacheKey {
+    /// The channel the query was run in
+    pub channel: ChannelId,
+    /// The user that ran the query
+    pub author: UserId,
+    /// The normalized query, usually the [`Display`] form of the reduced AST
+    ///
+    /// [`Display`]: std::fmt::Display
+    pub query: String,
+}
+
+/// A single cached query result
+#[derive(Debug)]
+struct CachedResult {
+    /// When this result was inserted into the cache
+    inserted_at: Instant,
+    /// The result of evaluating the query
+    evaluation: Evaluation,
+}
+
+/// A per-guild cache of evaluated query results with a fixed TTL.
+#[derive(Debug)]
+pub struct QueryCache {
+    /// How long a result remains valid after it is inserted
+    ttl: Duration,
+    /// Cached results, grouped by guild so that they can be invalidated together
+    entries: Mutex<HashMap<GuildId, HashMap<QueryCacheKey, CachedResult>>>,
+}
+
+impl QueryCache {
+    /// Create a new, empty [`QueryCache`] whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Look up a cached result, returning [`None`] if there is none or it has expired.
+    pub fn get(&self, guild: GuildId, key: &QueryCacheKey) -> Option<Evaluation> {
+        let mut entries = self.entries.lock().expect("query cache lock was poisoned");
+        let guild_entries = entries.get_mut(&guild)?;
+
+        let result = match guild_entries.get(key) {
+            Some(cached) if cached.inserted_at.elapsed() < self.ttl => {
+                trace!("Query cache hit for {key:?}");
+                Some(cached.evaluation.clone())
+            }
+            Some(_) => {
+                trace!("Query cache entry for {key:?} expired, removing");
+                guild_entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        drop(entries);
+
+        result
+    }
+
+    /// Cache the evaluated result of a query.
+    pub fn insert(&self, guild: GuildId, key: QueryCacheKey, evaluation: Evaluation) {
+        let mut entries = self.entries.lock().expect("query cache lock was poisoned");
+        let guild_entries = entries.entry(guild).or_default();
+
+        // Opportunistically drop expired entries so the cache doesn't grow unbounded
+        guild_entries.retain(|_, cached| cached.inserted_at.elapsed() < self.ttl);
+        guild_entries.insert(
+            key,
+            CachedResult {
+                inserted_at: Instant::now(),
+                evaluation,
+            },
+        );
+        drop(entries);
+    }
+
+    /// Remove every cached result for a guild.
+    pub fn invalidate_guild(&self, guild: GuildId) {
+        let mut entries = self.entries.lock().expect("query cache lock was poisoned");
+        if entries.remove(&guild).is_some() {
+            debug!("Invalidated query cache for guild {guild}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn evaluation(member: u64) -> Evaluation {
+        Evaluation {
+            members: HashSet::from([UserId(member)]),
+            ..Default::default()
+        }
+    }
+
+    fn key(query: &str) -> QueryCacheKey {
+        QueryCacheKey {
+            channel: ChannelId(1),
+            author: UserId(2),
+            query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn cached_results_are_returned() {
+        let cache = QueryCache::new(Duration::from_mins(1));
+        cache.insert(GuildId(1), key("a"), evaluation(3));
+
+        assert_eq!(cache.get(GuildId(1), &key("a")), Some(evaluation(3)));
+        assert_eq!(cache.get(GuildId(1), &key("b")), None);
+        assert_eq!(cache.get(GuildId(2), &key("a")), None);
+    }
+
+    #[test]
+    fn expired_results_are_not_returned() {
+        let cache = QueryCache::new(Duration::ZERO);
+        cache.insert(GuildId(1), key("a"), evaluation(3));
+
+        assert_eq!(cache.get(GuildId(1), &key("a")), None);
+    }
+
+    #[test]
+    fn invalidation_only_affects_one_guild() {
+        let cache = QueryCache::new(Duration::from_mins(1));
+        cache.insert(GuildId(1), key("a"), evaluation(3));
+        cache.insert(GuildId(2), key("a"), evaluation(4));
+
+        cache.invalidate_guild(GuildId(1));
+
+        assert_eq!(cache.get(GuildId(1), &key("a")), None);
+        assert_eq!(cache.get(GuildId(2), &key("a")), Some(evaluation(4)));
+    }
+}
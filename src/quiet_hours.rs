@@ -0,0 +1,172 @@
+//! Quiet hours, during which a member isn't notified by queries
+//!
+//! Quiet hours are a daily window in the member's time zone, like 22:00 to 08:00, which may wrap
+//! around midnight. Time zones are fixed offsets from UTC, as Intersection doesn't ship the time
+//! zone database needed to follow daylight saving time.
+
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+
+/// How times of day are written in quiet hours, like `22:00`
+const TIME_FORMAT: &str = "%H:%M";
+
+/// A daily window during which a member isn't notified by queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    /// When quiet hours start each day, in the member's time zone
+    start: NaiveTime,
+    /// When quiet hours end each day, in the member's time zone
+    end: NaiveTime,
+    /// The member's time zone
+    offset: FixedOffset,
+}
+
+/// Read a UTC offset like `UTC`, `UTC+2`, `+05:30`, or `-0800`.
+pub fn parse_offset(tz: &str) -> Option<FixedOffset> {
+    let tz = tz.trim();
+    let offset = ["UTC", "GMT"]
+        .into_iter()
+        .find_map(|prefix| {
+            tz.get(..prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(prefix))
+                .map(|_| &tz[prefix.len()..])
+        })
+        .unwrap_or(tz);
+    if offset.is_empty() {
+        return FixedOffset::east_opt(0);
+    }
+
+    let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if offset.len() == 4 => offset.split_at(2),
+        None => (offset, "0"),
+    };
+    if !(1..=2).contains(&hours.len()) || !(1..=2).contains(&minutes.len()) {
+        return None;
+    }
+    let hours = hours.parse::<i32>().ok()?;
+    let minutes = minutes.parse::<i32>().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl QuietHours {
+    /// Read quiet hours written like `22:00-08:00`, in the time zone `tz` (see [`parse_offset`]),
+    /// or UTC if not given.
+    ///
+    /// Returns [`None`] if either is written wrong, or if the window starts and ends at the same
+    /// time.
+    pub fn parse(window: &str, tz: Option<&str>) -> Option<Self> {
+        let (start, end) = window.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), TIME_FORMAT).ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), TIME_FORMAT).ok()?;
+        let offset = tz.map_or_else(|| FixedOffset::east_opt(0), parse_offset)?;
+        (start != end).then_some(Self { start, end, offset })
+    }
+
+    /// Write these quiet hours as a single field, like `22:00/08:00/3600` for 22:00 to 08:00 in
+    /// UTC+1.
+    pub fn to_field(self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.start.format(TIME_FORMAT),
+            self.end.format(TIME_FORMAT),
+            self.offset.local_minus_utc()
+        )
+    }
+
+    /// Read quiet hours written by [`QuietHours::to_field`].
+    pub fn from_field(field: &str) -> Option<Self> {
+        let mut parts = field.split('/');
+        let start = NaiveTime::parse_from_str(parts.next()?, TIME_FORMAT).ok()?;
+        let end = NaiveTime::parse_from_str(parts.next()?, TIME_FORMAT).ok()?;
+        let offset = FixedOffset::east_opt(parts.next()?.parse().ok()?)?;
+        (parts.next().is_none() && start != end).then_some(Self { start, end, offset })
+    }
+
+    /// Whether `at` is during these quiet hours.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = at.with_timezone(&self.offset).time();
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            // The window wraps around midnight
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} (UTC{})",
+            self.start.format(TIME_FORMAT),
+            self.end.format(TIME_FORMAT),
+            self.offset
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The time `time` on an arbitrary day, in UTC.
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2024-03-01T{time}:00Z")
+            .parse()
+            .expect("test times should be valid")
+    }
+
+    #[test]
+    fn offsets_are_parsed() {
+        let offset = |tz| parse_offset(tz).map(|offset| offset.local_minus_utc());
+        assert_eq!(offset("UTC"), Some(0));
+        assert_eq!(offset("utc+2"), Some(7200));
+        assert_eq!(offset("GMT-05:30"), Some(-19800));
+        assert_eq!(offset("+0100"), Some(3600));
+        assert_eq!(offset("-8"), Some(-28800));
+        assert_eq!(offset("Europe/Berlin"), None);
+        assert_eq!(offset("+15"), None);
+        assert_eq!(offset("+01:60"), None);
+        assert_eq!(offset("2"), None);
+    }
+
+    #[test]
+    fn windows_are_parsed() {
+        assert_eq!(
+            QuietHours::parse("22:00-08:00", Some("UTC+1")).map(|hours| hours.to_string()),
+            Some("22:00-08:00 (UTC+01:00)".to_string())
+        );
+        assert!(QuietHours::parse("9:30 - 17:00", None).is_some());
+        assert!(QuietHours::parse("22:00", None).is_none());
+        assert!(QuietHours::parse("25:00-08:00", None).is_none());
+        assert!(QuietHours::parse("08:00-08:00", None).is_none());
+        assert!(QuietHours::parse("22:00-08:00", Some("Mars")).is_none());
+    }
+
+    #[test]
+    fn windows_can_wrap_around_midnight() {
+        let night = QuietHours::parse("22:00-08:00", Some("UTC+2")).expect("window is valid");
+        // 22:00 to 08:00 at UTC+2 is 20:00 to 06:00 UTC
+        assert!(night.contains(at("20:00")));
+        assert!(night.contains(at("23:59")));
+        assert!(night.contains(at("05:59")));
+        assert!(!night.contains(at("06:00")));
+        assert!(!night.contains(at("19:59")));
+
+        let day = QuietHours::parse("09:00-17:00", None).expect("window is valid");
+        assert!(day.contains(at("09:00")));
+        assert!(!day.contains(at("17:00")));
+        assert!(!day.contains(at("08:00")));
+    }
+}
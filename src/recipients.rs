@@ -0,0 +1,273 @@
+//! The "Who was pinged?" button on notification messages
+//!
+//! After role optimization and message splitting, it's hard to tell exactly who a notification
+//! reached. Each notification's final message has a button which shows the full list of members
+//! it mentioned, paginated, to whoever pressed it. The members are remembered in memory for a
+//! limited time.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{self as serenity, MessageId, UserId};
+use tracing::debug;
+
+use crate::{
+    discord::Button,
+    i18n::{self, Language},
+};
+
+/// The custom ID of the button attached to notification messages
+const BUTTON_ID: &str = "who_was_pinged";
+
+/// The prefix of the custom IDs of the pagination buttons, followed by
+/// `{notification message ID}:{page}`
+const PAGE_BUTTON_PREFIX: &str = "who_was_pinged:";
+
+/// How many members are listed on each page
+const PAGE_SIZE: usize = 50;
+
+/// The "Who was pinged?" button attached to the final message of each notification, labelled in
+/// `language`
+pub fn button(language: Language) -> Button {
+    Button {
+        custom_id: BUTTON_ID.to_string(),
+        label: i18n::message(language, "recipients-button", &[]),
+        emoji: None,
+        style: serenity::ButtonStyle::Secondary,
+    }
+}
+
+/// The members mentioned by a single notification
+#[derive(Debug)]
+struct Recipients {
+    /// When the notification was sent
+    sent_at: Instant,
+    /// Every member mentioned, sorted by ID
+    members: Vec<UserId>,
+}
+
+/// Remembers the members mentioned by each notification, for a fixed TTL.
+#[derive(Debug)]
+pub struct RecipientStore {
+    /// How long the members of a notification are remembered
+    ttl: Duration,
+    /// The members mentioned by each notification, keyed by the ID of its final message
+    entries: Mutex<HashMap<MessageId, Recipients>>,
+}
+
+impl RecipientStore {
+    /// Create a new, empty [`RecipientStore`] which forgets notifications after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remember the members mentioned by the notification ending with `message`.
+    pub fn record(&self, message: MessageId, members: &HashSet<UserId>) {
+        let mut members = members.iter().copied().collect::<Vec<_>>();
+        members.sort_unstable();
+
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("recipient store lock was poisoned");
+        // Opportunistically drop expired entries so the store doesn't grow unbounded
+        entries.retain(|_, recipients| recipients.sent_at.elapsed() < self.ttl);
+        entries.insert(
+            message,
+            Recipients {
+                sent_at: Instant::now(),
+                members,
+            },
+        );
+        drop(entries);
+    }
+
+    /// Obtain the members mentioned by the notification ending with `message`, unless it expired.
+    fn get(&self, message: MessageId) -> Option<Vec<UserId>> {
+        self.entries
+            .lock()
+            .expect("recipient store lock was poisoned")
+            .get(&message)
+            .filter(|recipients| recipients.sent_at.elapsed() < self.ttl)
+            .map(|recipients| recipients.members.clone())
+    }
+}
+
+/// Render one page of a member list in `language`, returning its content and the total number of
+/// pages.
+///
+/// Out-of-range pages are clamped to the last page.
+fn render_page(members: &[UserId], page: usize, language: Language) -> (String, usize) {
+    let pages = members.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let list = members
+        .iter()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|id| format!("<@{id}>"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    (
+        i18n::message(
+            language,
+            "recipients-page",
+            &[
+                ("count", &members.len()),
+                ("page", &(page + 1)),
+                ("pages", &pages),
+                ("list", &list),
+            ],
+        ),
+        pages,
+    )
+}
+
+/// Parse the custom ID of a pagination button into the notification message ID and page.
+fn parse_page_button(custom_id: &str) -> Option<(MessageId, usize)> {
+    let (message, page) = custom_id
+        .strip_prefix(PAGE_BUTTON_PREFIX)?
+        .split_once(':')?;
+    Some((MessageId(message.parse().ok()?), page.parse().ok()?))
+}
+
+/// Handle a button press, if it was one of the "Who was pinged?" buttons, responding in
+/// `language`.
+pub async fn handle_component_interaction(
+    ctx: &serenity::Context,
+    interaction: &serenity::MessageComponentInteraction,
+    store: &RecipientStore,
+    language: Language,
+) -> serenity::Result<()> {
+    let custom_id = interaction.data.custom_id.as_str();
+    let (notification, page, kind) = if custom_id == BUTTON_ID {
+        (
+            interaction.message.id,
+            0,
+            serenity::InteractionResponseType::ChannelMessageWithSource,
+        )
+    } else if let Some((notification, page)) = parse_page_button(custom_id) {
+        (
+            notification,
+            page,
+            serenity::InteractionResponseType::UpdateMessage,
+        )
+    } else {
+        return Ok(());
+    };
+
+    debug!("Showing page {page} of recipients of notification {notification}");
+    let Some(members) = store.get(notification) else {
+        return interaction
+            .create_interaction_response(ctx, |response| {
+                response
+                    .kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.ephemeral(true).content(i18n::message(
+                            language,
+                            "recipients-expired",
+                            &[],
+                        ))
+                    })
+            })
+            .await;
+    };
+
+    let (content, pages) = render_page(&members, page, language);
+    let page = page.min(pages - 1);
+
+    interaction
+        .create_interaction_response(ctx, |response| {
+            response.kind(kind).interaction_response_data(|data| {
+                data.ephemeral(true)
+                    .content(content)
+                    // Listing the members must never ping them again
+                    .allowed_mentions(|allowed_mentions| {
+                        allowed_mentions.empty_parse().empty_users().empty_roles()
+                    });
+                if pages > 1 {
+                    data.components(|components| {
+                        components.create_action_row(|action_row| {
+                            action_row
+                                .create_button(|button| {
+                                    button
+                                        .custom_id(format!(
+                                            "{PAGE_BUTTON_PREFIX}{notification}:{}",
+                                            page.saturating_sub(1)
+                                        ))
+                                        .label(i18n::message(language, "page-previous", &[]))
+                                        .style(serenity::ButtonStyle::Secondary)
+                                        .disabled(page == 0)
+                                })
+                                .create_button(|button| {
+                                    button
+                                        .custom_id(format!(
+                                            "{PAGE_BUTTON_PREFIX}{notification}:{}",
+                                            page + 1
+                                        ))
+                                        .label(i18n::message(language, "page-next", &[]))
+                                        .style(serenity::ButtonStyle::Secondary)
+                                        .disabled(page + 1 >= pages)
+                                })
+                        })
+                    });
+                }
+                data
+            })
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipients_are_remembered_sorted() {
+        let store = RecipientStore::new(Duration::from_mins(1));
+        store.record(MessageId(1), &HashSet::from([UserId(3), UserId(2)]));
+
+        assert_eq!(store.get(MessageId(1)), Some(vec![UserId(2), UserId(3)]));
+        assert_eq!(store.get(MessageId(2)), None);
+    }
+
+    #[test]
+    fn expired_recipients_are_forgotten() {
+        let store = RecipientStore::new(Duration::ZERO);
+        store.record(MessageId(1), &HashSet::from([UserId(2)]));
+
+        assert_eq!(store.get(MessageId(1)), None);
+    }
+
+    #[test]
+    fn pages_are_clamped() {
+        let members = (1..=120).map(UserId).collect::<Vec<_>>();
+
+        let (first, pages) = render_page(&members, 0, Language::English);
+        assert_eq!(pages, 3);
+        assert!(first.contains("page 1 of 3"));
+        assert!(first.contains("<@1>\n"));
+        assert!(!first.contains("<@51>"));
+
+        let (last, _) = render_page(&members, 10, Language::English);
+        assert!(last.contains("page 3 of 3"));
+        assert!(last.ends_with("<@120>"));
+    }
+
+    #[test]
+    fn page_buttons_round_trip() {
+        assert_eq!(
+            parse_page_button(&format!("{PAGE_BUTTON_PREFIX}123:4")),
+            Some((MessageId(123), 4))
+        );
+        assert_eq!(parse_page_button(BUTTON_ID), None);
+        assert_eq!(parse_page_button("large_ping_confirm_yes"), None);
+    }
+}
@@ -0,0 +1,281 @@
+//! Tracking of the messages Intersection sends in response to a query
+//!
+//! When the message containing a query is deleted, the announcement it triggered was most likely
+//! cancelled, so the confirmation prompts and mention messages we sent for it should go too. This
+//! module remembers which messages we sent in reply to each query message for a limited time.
+//!
+//! Notifications also have a "Delete this ping" button, which lets the author of the query (or a
+//! moderator) delete every message we sent for it shortly after it was sent, limiting the damage
+//! from an accidental mass ping.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{self as serenity, MessageId};
+use tracing::{debug, trace};
+
+use crate::{
+    discord::Button,
+    i18n::{self, Language},
+};
+
+/// The custom ID of the "Delete this ping" button
+const DELETE_BUTTON_ID: &str = "delete_notification";
+
+/// How long after a notification is sent it can be deleted with its button
+const UNDO_WINDOW: Duration = Duration::from_mins(10);
+
+/// The "Delete this ping" button attached to the final message of each notification, labelled in
+/// `language`
+pub fn delete_button(language: Language) -> Button {
+    Button {
+        custom_id: DELETE_BUTTON_ID.to_string(),
+        label: i18n::message(language, "delete-button", &[]),
+        // wastebasket emoji
+        emoji: Some("\u{1f5d1}".to_string()),
+        style: serenity::ButtonStyle::Danger,
+    }
+}
+
+/// The messages sent in reply to a single query message
+#[derive(Debug)]
+struct TrackedReplies {
+    /// When the first reply was sent
+    first_sent_at: Instant,
+    /// Every message sent in reply, in order
+    replies: Vec<MessageId>,
+}
+
+/// Remembers the messages sent in reply to each query message, for a fixed TTL.
+#[derive(Debug)]
+pub struct ReplyTracker {
+    /// How long replies are remembered after the first one is sent
+    ttl: Duration,
+    /// The replies sent for each query message
+    entries: Mutex<HashMap<MessageId, TrackedReplies>>,
+}
+
+impl ReplyTracker {
+    /// Create a new, empty [`ReplyTracker`] which forgets replies after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record that `reply` was sent in reply to the query message `trigger`.
+    pub fn record(&self, trigger: MessageId, reply: MessageId) {
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("reply tracker lock was poisoned");
+
+        // Opportunistically drop expired entries so the tracker doesn't grow unbounded
+        entries.retain(|_, tracked| tracked.first_sent_at.elapsed() < self.ttl);
+        entries
+            .entry(trigger)
+            .or_insert_with(|| TrackedReplies {
+                first_sent_at: Instant::now(),
+                replies: Vec::new(),
+            })
+            .replies
+            .push(reply);
+        drop(entries);
+    }
+
+    /// Find the query message that `reply` was sent in reply to, unless it expired.
+    pub fn find_trigger(&self, reply: MessageId) -> Option<MessageId> {
+        self.entries
+            .lock()
+            .expect("reply tracker lock was poisoned")
+            .iter()
+            .find(|(_, tracked)| {
+                tracked.first_sent_at.elapsed() < self.ttl && tracked.replies.contains(&reply)
+            })
+            .map(|(trigger, _)| *trigger)
+    }
+
+    /// Stop tracking the replies to `trigger`, returning them if they haven't expired.
+    pub fn take(&self, trigger: MessageId) -> Option<Vec<MessageId>> {
+        let tracked = self
+            .entries
+            .lock()
+            .expect("reply tracker lock was poisoned")
+            .remove(&trigger)?;
+
+        if tracked.first_sent_at.elapsed() < self.ttl {
+            Some(tracked.replies)
+        } else {
+            trace!("Replies to {trigger} expired");
+            None
+        }
+    }
+}
+
+/// Delete every message we sent in reply to the query message `trigger`, returning how many were
+/// deleted.
+pub async fn delete_replies(
+    ctx: &serenity::Context,
+    channel_id: serenity::ChannelId,
+    trigger: MessageId,
+    tracker: &ReplyTracker,
+) -> serenity::Result<usize> {
+    let Some(replies) = tracker.take(trigger) else {
+        return Ok(0);
+    };
+
+    debug!("Deleting {} replies to {trigger}", replies.len());
+    delete_messages(ctx, channel_id, &replies).await?;
+
+    Ok(replies.len())
+}
+
+/// Delete `messages` from the channel `channel_id`, as few at a time as Discord allows.
+pub async fn delete_messages(
+    ctx: &serenity::Context,
+    channel_id: serenity::ChannelId,
+    messages: &[MessageId],
+) -> serenity::Result<()> {
+    // Bulk deletion takes between 2 and 100 messages at once
+    for batch in messages.chunks(100) {
+        match batch {
+            [message] => channel_id.delete_message(ctx, message).await?,
+            _ => channel_id.delete_messages(ctx, batch).await?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Respond to a button press with an ephemeral message.
+async fn respond(
+    ctx: &serenity::Context,
+    interaction: &serenity::MessageComponentInteraction,
+    content: &str,
+) -> serenity::Result<()> {
+    interaction
+        .create_interaction_response(ctx, |response| {
+            response
+                .kind(serenity::InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|data| data.ephemeral(true).content(content))
+        })
+        .await
+}
+
+/// Handle a button press, if it was the "Delete this ping" button, responding in `language`.
+pub async fn handle_component_interaction(
+    ctx: &serenity::Context,
+    interaction: &serenity::MessageComponentInteraction,
+    tracker: &ReplyTracker,
+    language: Language,
+) -> serenity::Result<()> {
+    if interaction.data.custom_id != DELETE_BUTTON_ID {
+        return Ok(());
+    }
+
+    // Notifications are replies to the query message, except when sent through a webhook
+    let Some(trigger) = interaction
+        .message
+        .message_reference
+        .as_ref()
+        .and_then(|reference| reference.message_id)
+        .or_else(|| tracker.find_trigger(interaction.message.id))
+    else {
+        return respond(
+            ctx,
+            interaction,
+            &i18n::message(language, "delete-unknown-query", &[]),
+        )
+        .await;
+    };
+
+    let trigger_author = match &interaction.message.referenced_message {
+        Some(message) => Some(message.author.id),
+        None => interaction
+            .channel_id
+            .message(ctx, trigger)
+            .await
+            .ok()
+            .map(|message| message.author.id),
+    };
+    let is_author = trigger_author == Some(interaction.user.id);
+    let is_moderator = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(serenity::Permissions::manage_messages);
+    if !is_author && !is_moderator {
+        debug!("Refusing to delete notification for someone else");
+        return respond(
+            ctx,
+            interaction,
+            &i18n::message(language, "delete-forbidden", &[]),
+        )
+        .await;
+    }
+
+    let age = serenity::Timestamp::now().unix_timestamp()
+        - interaction.message.timestamp.unix_timestamp();
+    if u64::try_from(age).is_ok_and(|age| age > UNDO_WINDOW.as_secs()) {
+        return respond(
+            ctx,
+            interaction,
+            &i18n::message(
+                language,
+                "delete-too-late",
+                &[("minutes", &(UNDO_WINDOW.as_secs() / 60))],
+            ),
+        )
+        .await;
+    }
+
+    let deleted = delete_replies(ctx, interaction.channel_id, trigger, tracker).await?;
+    respond(
+        ctx,
+        interaction,
+        &i18n::message(language, "deleted", &[("count", &deleted)]),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replies_are_returned_once() {
+        let tracker = ReplyTracker::new(Duration::from_mins(1));
+        tracker.record(MessageId(1), MessageId(2));
+        tracker.record(MessageId(1), MessageId(3));
+        tracker.record(MessageId(4), MessageId(5));
+
+        assert_eq!(
+            tracker.take(MessageId(1)),
+            Some(vec![MessageId(2), MessageId(3)])
+        );
+        assert_eq!(tracker.take(MessageId(1)), None);
+        assert_eq!(tracker.take(MessageId(4)), Some(vec![MessageId(5)]));
+    }
+
+    #[test]
+    fn expired_replies_are_not_returned() {
+        let tracker = ReplyTracker::new(Duration::ZERO);
+        tracker.record(MessageId(1), MessageId(2));
+
+        assert_eq!(tracker.take(MessageId(1)), None);
+    }
+
+    #[test]
+    fn triggers_are_found_from_their_replies() {
+        let tracker = ReplyTracker::new(Duration::from_mins(1));
+        tracker.record(MessageId(1), MessageId(2));
+        tracker.record(MessageId(1), MessageId(3));
+
+        assert_eq!(tracker.find_trigger(MessageId(3)), Some(MessageId(1)));
+        assert_eq!(tracker.find_trigger(MessageId(1)), None);
+    }
+}
@@ -1,17 +1,50 @@
 //! The instance of the DRQL interpreter resolver used for Intersection
 
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashSet},
+    future::Future,
+    sync::Mutex,
+    time::Duration,
+};
 
-use anyhow::{bail, Context as _};
+use anyhow::anyhow;
 use poise::{async_trait, serenity_prelude as serenity};
 use tap::Tap;
+use tokio::sync::watch;
 use tracing::{debug, error, instrument, trace};
 
 use crate::{
-    drql::interpreter::InterpreterResolver,
-    extensions::{CustomGuildImpl, CustomMemberImpl, CustomRoleImpl},
+    activity::ActivityTracker,
+    config::EveryoneHere,
+    drql::{
+        ast::{Expr, MessageLink},
+        interpreter::InterpreterResolver,
+        pattern::{named, NamePattern},
+        time::{Age, Moment, Side},
+    },
+    error::QueryError,
+    extensions::{CustomGuildChannelImpl, CustomGuildImpl, CustomMemberImpl, CustomRoleImpl},
+    i18n::{self, Language},
 };
 
+/// How many of the users interested in a scheduled event are fetched at once, the most Discord
+/// allows
+const EVENT_USERS_PAGE_SIZE: u8 = 100;
+
+/// How many of the users who reacted to a message are fetched at once, the most Discord allows
+const REACTION_USERS_PAGE_SIZE: u8 = 100;
+
+/// Turn an error from Discord into a [`QueryError::ResolutionError`] with `message` if it's because
+/// something wasn't found, or an internal error otherwise.
+fn not_found(err: serenity::Error, message: impl FnOnce() -> String) -> QueryError {
+    if let serenity::Error::Http(http) = &err {
+        if http.status_code() == Some(serenity::StatusCode::NOT_FOUND) {
+            return QueryError::ResolutionError(message());
+        }
+    }
+    err.into()
+}
+
 /// The custom instance of the DRQL [`InterpreterResolver`] used for Intersection.
 pub struct Resolver<'a> {
     /// The guild that the query was originally sent in
@@ -22,24 +55,81 @@ pub struct Resolver<'a> {
     pub ctx: &'a serenity::Context,
     /// `THe` channel the query was originally sent in
     pub channel: &'a serenity::GuildChannel,
+    /// Roles used by the query which the member is not allowed to mention
+    ///
+    /// Rather than failing the query, these roles are resolved to their members as usual and
+    /// collected here, so the member can choose how to continue.
+    pub unmentionable_roles: Mutex<HashSet<serenity::RoleId>>,
+    /// Where to report how many operands of the query have been resolved so far, if anywhere
+    pub progress: Option<&'a watch::Sender<usize>>,
+    /// When each member last sent a message, which `active(...)` refers to
+    pub activity: &'a ActivityTracker,
+    /// Whether roles are only matched by their exact name, case included
+    ///
+    /// Otherwise, a name like `mods` also finds a role called `Mods`, as long as no role is called
+    /// exactly `mods`.
+    pub case_sensitive_roles: bool,
+    /// Whether the guild lets `everyone` and `here` be used in queries
+    pub everyone_here: &'a EveryoneHere,
+    /// The language to explain problems with the query in
+    pub language: Language,
 }
+impl Resolver<'_> {
+    /// Find a channel or thread in the guild, which might not be cached if it's an archived thread.
+    async fn guild_channel(
+        &self,
+        id: serenity::ChannelId,
+    ) -> Result<serenity::GuildChannel, QueryError> {
+        let cached = self
+            .guild
+            .channels
+            .get(&id)
+            .cloned()
+            .and_then(serenity::Channel::guild)
+            .or_else(|| {
+                self.guild
+                    .threads
+                    .iter()
+                    .find(|thread| thread.id == id)
+                    .cloned()
+            });
+        if let Some(channel) = cached {
+            return Ok(channel);
+        }
+
+        // Archived threads aren't cached
+        trace!("Channel isn't cached, fetching it");
+        id.to_channel(self.ctx)
+            .await
+            .ok()
+            .and_then(serenity::Channel::guild)
+            .filter(|channel| channel.guild_id == self.guild.id)
+            .ok_or_else(|| {
+                QueryError::ResolutionError(i18n::message(
+                    self.language,
+                    "resolve-unknown-channel",
+                    &[("id", &id)],
+                ))
+            })
+    }
+}
+
 #[async_trait]
-impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
+impl InterpreterResolver<QueryError> for Resolver<'_> {
     #[instrument(skip(self))]
     async fn resolve_string_literal(
-        &mut self,
+        &self,
         literal: String,
-    ) -> Result<HashSet<serenity::UserId>, anyhow::Error> {
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
         if literal == "everyone" || literal == "here" {
+            self.everyone_here.check(&literal, self.language)?;
             if !self.member.permissions(self.ctx)?.mention_everyone() {
                 debug!("Member does not have permission to mention everyone or here, bailing!");
-                bail!(
-                    concat!(
-                        "You do not have the \"Mention everyone, here, and ",
-                        "All Roles\" permission required to use the role {}."
-                    ),
-                    literal
-                );
+                return Err(QueryError::PermissionDenied(i18n::message(
+                    self.language,
+                    "resolve-everyone-permission",
+                    &[("role", &literal)],
+                )));
             }
 
             Ok(match literal.as_str() {
@@ -53,6 +143,38 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
                     &x.iter().map(|x| x.0).collect::<Vec<_>>()
                 );
             }))
+        } else if let Some(status) = match literal.as_str() {
+            "online" => Some(serenity::OnlineStatus::Online),
+            "idle" => Some(serenity::OnlineStatus::Idle),
+            "dnd" => Some(serenity::OnlineStatus::DoNotDisturb),
+            "offline" => Some(serenity::OnlineStatus::Offline),
+            _ => None,
+        } {
+            // Unlike here, these are mentioned individually, so they don't need any permission
+            Ok(self.guild.get_with_status(status).tap(|x| {
+                debug!(
+                    "Resolved presence literal to {:?}",
+                    &x.iter().map(|x| x.0).collect::<Vec<_>>()
+                );
+            }))
+        } else if literal == "in_voice" {
+            Ok(self
+                .guild
+                .voice_states
+                .values()
+                .filter(|state| state.channel_id.is_some())
+                .map(|state| state.user_id)
+                .collect::<HashSet<_>>()
+                .tap(|x| debug!("Resolved in_voice literal to {x:?}")))
+        } else if literal == "boosters" {
+            Ok(self
+                .guild
+                .members
+                .values()
+                .filter(|member| member.premium_since.is_some())
+                .map(|member| member.user.id)
+                .collect::<HashSet<_>>()
+                .tap(|x| debug!("Resolved boosters literal to {x:?}")))
         } else {
             trace!("Finding possible members/roles for string literal");
 
@@ -61,12 +183,14 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
                 .search_members(self.ctx, literal.as_str(), None)
                 .await?;
 
-            let possible_roles = self
-                .guild
-                .roles
-                .iter()
-                .filter(|(_, role)| role.name == literal)
-                .collect::<Vec<_>>();
+            let possible_roles = named(
+                self.guild
+                    .roles
+                    .values()
+                    .map(|role| (role, role.name.as_str())),
+                &literal,
+                self.case_sensitive_roles,
+            );
 
             debug!(
                 "Found possible members: {:?}",
@@ -77,60 +201,57 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
             );
             debug!(
                 "Found possible roles: {:?}",
-                possible_roles
-                    .iter()
-                    .map(|(_, x)| x.id.0)
-                    .collect::<Vec<_>>()
+                possible_roles.iter().map(|x| x.id.0).collect::<Vec<_>>()
             );
 
             match (possible_members.len(), possible_roles.len()) {
                 (members_matched, roles_matched) if members_matched >= 1 && roles_matched >= 1 => {
                     debug!("Found both members and roles that matched the query, bailing!");
-                    bail!(
-                        concat!(
-                            "Found {} member(s) and {} role(s) that matched your query for \"{}\".",
-                            " Please narrow your query or use the ID of the object you are referring",
-                            " to instead."
-                        ),
-                        members_matched,
-                        roles_matched,
-                        literal
-                    );
+                    return Err(QueryError::ResolutionError(i18n::message(
+                        self.language,
+                        "resolve-ambiguous",
+                        &[
+                            ("members", &members_matched),
+                            ("roles", &roles_matched),
+                            ("name", &literal),
+                        ],
+                    )));
                 }
                 (members_matched, _) if members_matched > 1 => {
                     debug!("Found multiple members that matched the query, bailing!");
-                    bail!(
-                        concat!(
-                            "Found {} members that matched your query for \"{}\". Please narrow your",
-                            " query: it may help to use the user's ID, or add their discriminator,",
-                            " like \"luna..♡#9082\" instead of \"luna..♡\"."
-                        ),
-                        members_matched,
-                        literal
-                    );
+                    return Err(QueryError::ResolutionError(i18n::message(
+                        self.language,
+                        "resolve-ambiguous-members",
+                        &[("count", &members_matched), ("name", &literal)],
+                    )));
                 }
                 (_, roles_matched) if roles_matched > 1 => {
                     debug!("Found multiple roles that matched the query, bailing!");
-                    bail!(
-                        concat!(
-                            "Found {} roles that matched your query for \"{}\". Please narrow your",
-                            " query: it may help to use a role ID instead."
-                        ),
-                        roles_matched,
-                        literal
-                    );
+                    return Err(QueryError::ResolutionError(i18n::message(
+                        self.language,
+                        "resolve-ambiguous-roles",
+                        &[("count", &roles_matched), ("name", &literal)],
+                    )));
                 }
                 // At this point, we KNOW that members_matched and roles_matched are <= 1, and
                 // only ONE of them is 1. Let's make sure that they aren't both 0:
                 (members_matched, roles_matched) if members_matched == 0 && roles_matched == 0 => {
                     debug!("Found no members or roles that matched the query, bailing!");
-                    bail!(
-                        concat!(
-                            "Unable to find a role or member with the name {}. Searches for roles",
-                            " are case sensitive! Try using the ID instead?"
-                        ),
-                        literal
-                    );
+                    return Err(QueryError::ResolutionError(i18n::message(
+                        self.language,
+                        "resolve-unknown-name",
+                        &[
+                            ("name", &literal),
+                            (
+                                "case-sensitive",
+                                &if self.case_sensitive_roles {
+                                    i18n::message(self.language, "resolve-case-sensitive", &[])
+                                } else {
+                                    String::new()
+                                },
+                            ),
+                        ],
+                    )));
                 }
                 // Continue, members_matched + roles_matched == 1.
                 _ => {}
@@ -141,7 +262,7 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
             // .first() more like .only() (len asserted == 1) and only one will be Some
             // TODO: use custom enum or perhaps Either
             let member = possible_members.first();
-            let role = possible_roles.first().map(|(_, x)| x);
+            let role = possible_roles.first();
 
             match (member, role) {
                 (Some(member), None) => {
@@ -149,25 +270,15 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
                     self.resolve_user_id(member.user.id).await
                 }
 
-                (None, Some(role))
-                    if !self.member.can_mention_role(self.ctx, role, self.channel)? =>
-                {
-                    debug!(
-                        "Chose to use role {}, but user cannot mention it!",
-                        role.id.0
-                    );
-                    bail!(
-                        concat!(
-                            "The role {} is not mentionable and you do not have",
-                            " the \"Mention everyone, here, and All",
-                            " Roles\" permission."
-                        ),
-                        role.name
-                    );
-                }
-
                 (None, Some(role)) => {
                     debug!("Chose to use role {}", role.id.0);
+                    if !self.member.can_mention_role(self.ctx, role, self.channel)? {
+                        debug!("User cannot mention role {}!", role.id.0);
+                        self.unmentionable_roles
+                            .lock()
+                            .expect("unmentionable roles lock was poisoned")
+                            .insert(role.id);
+                    }
                     self.resolve_role_id(role.id).await
                 }
 
@@ -179,14 +290,20 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
 
     #[instrument(skip(self))]
     async fn resolve_unknown_id(
-        &mut self,
+        &self,
         id: String,
-    ) -> Result<HashSet<serenity::UserId>, anyhow::Error> {
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
         if id == self.guild.id.to_string() {
             debug!("Unknown ID is the guild's ID, treating it as everyone");
             self.resolve_string_literal("everyone".to_string()).await
         } else {
-            let id = id.parse::<u64>()?;
+            let id = id.parse::<u64>().map_err(|_| {
+                QueryError::ResolutionError(i18n::message(
+                    self.language,
+                    "resolve-invalid-id",
+                    &[("id", &id)],
+                ))
+            })?;
             debug!("Finding possible member/role for unknown ID");
             let possible_member = self.guild.member(self.ctx, id).await;
             let possible_role = self.guild.roles.get(&serenity::RoleId::from(id));
@@ -199,10 +316,10 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
             match (possible_member, possible_role) {
                 (Ok(_), Some(_)) => {
                     error!("Somehow both a member and a role had the same ID, bailing!");
-                    bail!(
+                    Err(QueryError::Internal(anyhow!(
                         "Somehow there was both a member and a role with the ID {}??",
                         id
-                    )
+                    )))
                 }
 
                 (Ok(member), None) => {
@@ -210,28 +327,25 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
                     self.resolve_user_id(member.user.id).await
                 }
 
-                (Err(_), Some(role))
-                    if !self.member.can_mention_role(self.ctx, role, self.channel)? =>
-                {
-                    debug!("Treating ID as a role ID, but user cannot mention role! Bailing.");
-                    bail!(
-                        concat!(
-                            "The role {} is not mentionable and you do not have",
-                            " the \"Mention everyone, here, and All Roles\"",
-                            " permission."
-                        ),
-                        role.name
-                    )
-                }
-
                 (Err(_), Some(role)) => {
                     debug!("Treating ID as a role ID.");
+                    if !self.member.can_mention_role(self.ctx, role, self.channel)? {
+                        debug!("User cannot mention role {}!", role.id.0);
+                        self.unmentionable_roles
+                            .lock()
+                            .expect("unmentionable roles lock was poisoned")
+                            .insert(role.id);
+                    }
                     self.resolve_role_id(role.id).await
                 }
 
                 (Err(_), None) => {
                     debug!("Nothing found!");
-                    bail!("Unable to resolve role or member ID: {}", id)
+                    Err(QueryError::ResolutionError(i18n::message(
+                        self.language,
+                        "resolve-unknown-id",
+                        &[("id", &id)],
+                    )))
                 }
             }
         }
@@ -239,18 +353,18 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
 
     #[instrument(skip(self))]
     async fn resolve_user_id(
-        &mut self,
+        &self,
         id: serenity::UserId,
-    ) -> Result<HashSet<serenity::UserId>, anyhow::Error> {
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
         debug!("Resolving User ID to itself: {}", id);
         Ok(HashSet::from([id]))
     }
 
     #[instrument(skip(self))]
     async fn resolve_role_id(
-        &mut self,
+        &self,
         id: serenity::RoleId,
-    ) -> Result<HashSet<serenity::UserId>, anyhow::Error> {
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
         if id.to_string() == self.guild.id.to_string() {
             debug!("Role ID is the guild's ID, treating it as everyone");
             self.resolve_string_literal("everyone".to_string()).await
@@ -259,9 +373,817 @@ impl<'a> InterpreterResolver<anyhow::Error> for Resolver<'a> {
                 .guild
                 .roles
                 .get(&id)
-                .context(format!("Unable to resolve role with ID {id}"))?
+                .ok_or_else(|| {
+                    QueryError::ResolutionError(i18n::message(
+                        self.language,
+                        "resolve-unknown-role",
+                        &[("id", &id)],
+                    ))
+                })?
                 .members(self.guild)
                 .tap(|x| debug!("Resolved role ID to {x:?}")))
         }
     }
+
+    #[instrument(skip(self))]
+    async fn resolve_channel_id(
+        &self,
+        id: serenity::ChannelId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let channel = self.guild_channel(id).await?;
+
+        if channel.thread_metadata.is_some() {
+            let members = id
+                .get_thread_members(self.ctx)
+                .await?
+                .into_iter()
+                .filter_map(|member| member.user_id)
+                .collect::<HashSet<_>>();
+            // Only the members of a private thread know who else is in it
+            if channel.kind == serenity::ChannelType::PrivateThread
+                && !members.contains(&self.member.user.id)
+            {
+                return Err(QueryError::PermissionDenied(i18n::message(
+                    self.language,
+                    "resolve-private-thread",
+                    &[("id", &id)],
+                )));
+            }
+            Ok(members.tap(|x| debug!("Resolved thread to its members: {x:?}")))
+        } else if matches!(
+            channel.kind,
+            serenity::ChannelType::Voice | serenity::ChannelType::Stage
+        ) {
+            Ok(self
+                .guild
+                .voice_states
+                .values()
+                .filter(|state| state.channel_id == Some(id))
+                .map(|state| state.user_id)
+                .collect::<HashSet<_>>()
+                .tap(|x| debug!("Resolved voice channel to {x:?}")))
+        } else if matches!(
+            channel.kind,
+            serenity::ChannelType::Text | serenity::ChannelType::News
+        ) {
+            Ok(channel
+                .viewers(self.guild)?
+                .tap(|x| debug!("Resolved text channel to its viewers: {x:?}")))
+        } else {
+            Err(QueryError::ResolutionError(i18n::message(
+                self.language,
+                "resolve-unusable-channel",
+                &[("id", &id)],
+            )))
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_me(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+        debug!("Resolving me to the author: {}", self.member.user.id);
+        self.resolve_user_id(self.member.user.id).await
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_event(
+        &self,
+        id: serenity::ScheduledEventId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let mut interested = HashSet::new();
+        let mut after = None;
+        loop {
+            let page = self
+                .guild
+                .id
+                .scheduled_event_users_optioned(
+                    self.ctx,
+                    id,
+                    Some(u64::from(EVENT_USERS_PAGE_SIZE)),
+                    after.map(serenity::UserPagination::After),
+                    None,
+                )
+                .await
+                .map_err(|err| {
+                    not_found(err, || {
+                        i18n::message(self.language, "resolve-unknown-event", &[("id", &id)])
+                    })
+                })?;
+            let full = page.len() == usize::from(EVENT_USERS_PAGE_SIZE);
+            after = page.last().map(|user| user.user.id);
+            interested.extend(page.into_iter().map(|user| user.user.id));
+            if !full {
+                break;
+            }
+        }
+        debug!("Resolved scheduled event to {interested:?}");
+        Ok(interested)
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_joined(
+        &self,
+        side: Side,
+        moment: Moment,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let moment = moment.resolve(chrono::Utc::now());
+        Ok(self
+            .guild
+            .members
+            .values()
+            .filter(|member| {
+                member
+                    .joined_at
+                    .is_some_and(|joined| side.contains(moment, *joined))
+            })
+            .map(|member| member.user.id)
+            .collect::<HashSet<_>>()
+            .tap(|x| debug!("Resolved joined {side:?} {moment} to {x:?}")))
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_account_age(&self, age: Age) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let now = chrono::Utc::now();
+        Ok(self
+            .guild
+            .members
+            .keys()
+            .filter(|id| age.contains(now, *id.created_at()))
+            .copied()
+            .collect::<HashSet<_>>()
+            .tap(|x| debug!("Resolved account age {age} to {x:?}")))
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_name(
+        &self,
+        pattern: NamePattern,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let regex = pattern.compile().map_err(|err| {
+            QueryError::ResolutionError(i18n::message(
+                self.language,
+                "resolve-invalid-pattern",
+                &[("pattern", &pattern), ("error", &err)],
+            ))
+        })?;
+        Ok(self
+            .guild
+            .members
+            .values()
+            .filter(|member| {
+                regex.is_match(&member.user.name)
+                    || member
+                        .nick
+                        .as_ref()
+                        .is_some_and(|nick| regex.is_match(nick))
+            })
+            .map(|member| member.user.id)
+            .collect::<HashSet<_>>()
+            .tap(|x| debug!("Resolved name pattern {pattern} to {x:?}")))
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_active(
+        &self,
+        within: Duration,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let since = Moment::Ago(within).resolve(chrono::Utc::now());
+        Ok(self
+            .activity
+            .active_since(self.guild.id, since)
+            .into_iter()
+            // Members who have left since aren't active here anymore
+            .filter(|id| self.guild.members.contains_key(id))
+            .collect::<HashSet<_>>()
+            .tap(|x| debug!("Resolved members active since {since} to {x:?}")))
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_reacted(
+        &self,
+        link: MessageLink,
+        emoji: serenity::ReactionType,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        if link.guild != Some(self.guild.id) {
+            return Err(QueryError::ResolutionError(i18n::message(
+                self.language,
+                "resolve-foreign-message",
+                &[("link", &link)],
+            )));
+        }
+        let channel = self.guild_channel(link.channel).await?;
+        // Otherwise anyone could find out who reacted to messages they can't see
+        let permissions = self
+            .guild
+            .user_permissions_in(&channel.permission_channel(self.ctx).await?, self.member)?;
+        if !permissions.view_channel() || !permissions.read_message_history() {
+            return Err(QueryError::PermissionDenied(i18n::message(
+                self.language,
+                "resolve-unreadable-reactions",
+                &[("link", &link), ("channel", &link.channel)],
+            )));
+        }
+
+        let mut reacted = HashSet::new();
+        let mut after = None;
+        loop {
+            let page = link
+                .channel
+                .reaction_users(
+                    self.ctx,
+                    link.message,
+                    emoji.clone(),
+                    Some(REACTION_USERS_PAGE_SIZE),
+                    after,
+                )
+                .await
+                .map_err(|err| {
+                    not_found(err, || {
+                        i18n::message(
+                            self.language,
+                            "resolve-unknown-reactions",
+                            &[("emoji", &emoji), ("link", &link)],
+                        )
+                    })
+                })?;
+            let full = page.len() == usize::from(REACTION_USERS_PAGE_SIZE);
+            after = page.last().map(|user| user.id);
+            reacted.extend(page.into_iter().map(|user| user.id));
+            if !full {
+                break;
+            }
+        }
+        debug!("Resolved {emoji} reactions on {link} to {reacted:?}");
+        Ok(reacted)
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_permission(
+        &self,
+        permission: serenity::Permissions,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        let mut members = HashSet::new();
+        for id in self.guild.members.keys() {
+            if self
+                .guild
+                .member_permissions(self.ctx, *id)
+                .await?
+                .contains(permission)
+            {
+                members.insert(*id);
+            }
+        }
+        debug!("Resolved permission to {members:?}");
+        Ok(members)
+    }
+
+    #[instrument(skip(self))]
+    async fn resolve_everyone(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+        // Unlike the `everyone` role, a complement mentions its members individually, so it
+        // doesn't need permission to mention everyone
+        Ok(self.guild.get_everyone())
+    }
+
+    async fn resolve_alias(&self, name: String) -> Result<HashSet<serenity::UserId>, QueryError> {
+        // Aliases are expanded when the query is parsed, so this one can't have been defined
+        Err(QueryError::ResolutionError(i18n::message(
+            self.language,
+            "alias-unknown",
+            &[("name", &name)],
+        )))
+    }
+
+    fn operand_resolved(&self) {
+        if let Some(progress) = self.progress {
+            progress.send_modify(|resolved| *resolved += 1);
+        }
+    }
+}
+
+/// Wraps another [`InterpreterResolver`], giving up on operands which take longer than `timeout`
+/// to resolve.
+///
+/// Rather than failing the whole query, an operand which times out is treated as matching nobody
+/// and recorded in `timed_out`, so the author can decide whether the partial result is good
+/// enough.
+pub struct Timeouts<R> {
+    /// The resolver doing the actual work
+    pub inner: R,
+    /// How long each operand may take to resolve
+    pub timeout: Duration,
+    /// Every operand which timed out, as written in DRQL
+    pub timed_out: Mutex<Vec<String>>,
+}
+
+/// Wait for an operand to resolve, or for `timeout` to pass, in which case the operand is added to
+/// `timed_out` and matches nobody.
+async fn limit(
+    timeout: Duration,
+    timed_out: &Mutex<Vec<String>>,
+    operand: Expr,
+    resolution: impl Future<Output = Result<HashSet<serenity::UserId>, QueryError>> + Send,
+) -> Result<HashSet<serenity::UserId>, QueryError> {
+    tokio::time::timeout(timeout, resolution)
+        .await
+        .unwrap_or_else(|_| {
+            debug!("Timed out resolving {operand}");
+            timed_out
+                .lock()
+                .expect("timed out operands lock was poisoned")
+                .push(operand.to_string());
+            Ok(HashSet::new())
+        })
+}
+
+#[async_trait]
+impl<R: InterpreterResolver<QueryError> + Sync> InterpreterResolver<QueryError> for Timeouts<R> {
+    async fn resolve_string_literal(
+        &self,
+        literal: String,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::StringLiteral(literal.clone()),
+            self.inner.resolve_string_literal(literal),
+        )
+        .await
+    }
+
+    async fn resolve_unknown_id(
+        &self,
+        id: String,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::UnknownID(id.clone()),
+            self.inner.resolve_unknown_id(id),
+        )
+        .await
+    }
+
+    async fn resolve_user_id(
+        &self,
+        id: serenity::UserId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::UserID(id),
+            self.inner.resolve_user_id(id),
+        )
+        .await
+    }
+
+    async fn resolve_role_id(
+        &self,
+        id: serenity::RoleId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::RoleID(id),
+            self.inner.resolve_role_id(id),
+        )
+        .await
+    }
+
+    async fn resolve_channel_id(
+        &self,
+        id: serenity::ChannelId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::ChannelID(id),
+            self.inner.resolve_channel_id(id),
+        )
+        .await
+    }
+
+    async fn resolve_me(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Me,
+            self.inner.resolve_me(),
+        )
+        .await
+    }
+
+    async fn resolve_event(
+        &self,
+        id: serenity::ScheduledEventId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Event(id),
+            self.inner.resolve_event(id),
+        )
+        .await
+    }
+
+    async fn resolve_joined(
+        &self,
+        side: Side,
+        moment: Moment,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Joined(side, moment),
+            self.inner.resolve_joined(side, moment),
+        )
+        .await
+    }
+
+    async fn resolve_account_age(&self, age: Age) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::AccountAge(age),
+            self.inner.resolve_account_age(age),
+        )
+        .await
+    }
+
+    async fn resolve_name(
+        &self,
+        pattern: NamePattern,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Name(pattern.clone()),
+            self.inner.resolve_name(pattern),
+        )
+        .await
+    }
+
+    async fn resolve_active(
+        &self,
+        within: Duration,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Active(within),
+            self.inner.resolve_active(within),
+        )
+        .await
+    }
+
+    async fn resolve_reacted(
+        &self,
+        link: MessageLink,
+        emoji: serenity::ReactionType,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Reacted(link, emoji.clone()),
+            self.inner.resolve_reacted(link, emoji),
+        )
+        .await
+    }
+
+    async fn resolve_permission(
+        &self,
+        permission: serenity::Permissions,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        limit(
+            self.timeout,
+            &self.timed_out,
+            Expr::Permission(permission),
+            self.inner.resolve_permission(permission),
+        )
+        .await
+    }
+
+    async fn resolve_everyone(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+        // Not an operand of the query, so it isn't subject to the timeout
+        self.inner.resolve_everyone().await
+    }
+
+    async fn resolve_alias(&self, name: String) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.inner.resolve_alias(name).await
+    }
+
+    fn operand_resolved(&self) {
+        self.inner.operand_resolved();
+    }
+}
+
+/// Wraps another [`InterpreterResolver`], counting how many times each of its methods is called,
+/// to see how much work a query takes.
+pub struct Calls<R> {
+    /// The resolver doing the actual work
+    pub inner: R,
+    /// How many times each method was called, by name
+    pub calls: Mutex<BTreeMap<&'static str, usize>>,
+}
+
+impl<R> Calls<R> {
+    /// Count a call to `method`.
+    fn count(&self, method: &'static str) {
+        *self
+            .calls
+            .lock()
+            .expect("resolver calls lock was poisoned")
+            .entry(method)
+            .or_default() += 1;
+    }
+}
+
+#[async_trait]
+impl<R: InterpreterResolver<QueryError> + Sync> InterpreterResolver<QueryError> for Calls<R> {
+    async fn resolve_string_literal(
+        &self,
+        literal: String,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_string_literal");
+        self.inner.resolve_string_literal(literal).await
+    }
+
+    async fn resolve_unknown_id(
+        &self,
+        id: String,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_unknown_id");
+        self.inner.resolve_unknown_id(id).await
+    }
+
+    async fn resolve_user_id(
+        &self,
+        id: serenity::UserId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_user_id");
+        self.inner.resolve_user_id(id).await
+    }
+
+    async fn resolve_role_id(
+        &self,
+        id: serenity::RoleId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_role_id");
+        self.inner.resolve_role_id(id).await
+    }
+
+    async fn resolve_channel_id(
+        &self,
+        id: serenity::ChannelId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_channel_id");
+        self.inner.resolve_channel_id(id).await
+    }
+
+    async fn resolve_me(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_me");
+        self.inner.resolve_me().await
+    }
+
+    async fn resolve_event(
+        &self,
+        id: serenity::ScheduledEventId,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_event");
+        self.inner.resolve_event(id).await
+    }
+
+    async fn resolve_joined(
+        &self,
+        side: Side,
+        moment: Moment,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_joined");
+        self.inner.resolve_joined(side, moment).await
+    }
+
+    async fn resolve_account_age(&self, age: Age) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_account_age");
+        self.inner.resolve_account_age(age).await
+    }
+
+    async fn resolve_name(
+        &self,
+        pattern: NamePattern,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_name");
+        self.inner.resolve_name(pattern).await
+    }
+
+    async fn resolve_active(
+        &self,
+        within: Duration,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_active");
+        self.inner.resolve_active(within).await
+    }
+
+    async fn resolve_reacted(
+        &self,
+        link: MessageLink,
+        emoji: serenity::ReactionType,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_reacted");
+        self.inner.resolve_reacted(link, emoji).await
+    }
+
+    async fn resolve_permission(
+        &self,
+        permission: serenity::Permissions,
+    ) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_permission");
+        self.inner.resolve_permission(permission).await
+    }
+
+    async fn resolve_everyone(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_everyone");
+        self.inner.resolve_everyone().await
+    }
+
+    async fn resolve_alias(&self, name: String) -> Result<HashSet<serenity::UserId>, QueryError> {
+        self.count("resolve_alias");
+        self.inner.resolve_alias(name).await
+    }
+
+    fn operand_resolved(&self) {
+        self.inner.operand_resolved();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drql::interpreter::interpret;
+
+    /// Resolves `slow` never, and everything else to user 1 right away.
+    struct SlowResolver;
+
+    #[async_trait]
+    impl InterpreterResolver<QueryError> for SlowResolver {
+        async fn resolve_string_literal(
+            &self,
+            literal: String,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            if literal == "slow" {
+                std::future::pending::<()>().await;
+            }
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_unknown_id(
+            &self,
+            _id: String,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_user_id(
+            &self,
+            id: serenity::UserId,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([id]))
+        }
+
+        async fn resolve_role_id(
+            &self,
+            _id: serenity::RoleId,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_channel_id(
+            &self,
+            _id: serenity::ChannelId,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_me(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_event(
+            &self,
+            _id: serenity::ScheduledEventId,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_joined(
+            &self,
+            _side: Side,
+            _moment: Moment,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_account_age(
+            &self,
+            _age: Age,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_name(
+            &self,
+            _pattern: NamePattern,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_active(
+            &self,
+            _within: Duration,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_reacted(
+            &self,
+            _link: MessageLink,
+            _emoji: serenity::ReactionType,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_permission(
+            &self,
+            _permission: serenity::Permissions,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_everyone(&self) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+
+        async fn resolve_alias(
+            &self,
+            _name: String,
+        ) -> Result<HashSet<serenity::UserId>, QueryError> {
+            Ok(HashSet::from([serenity::UserId(1)]))
+        }
+    }
+
+    #[tokio::test]
+    async fn operands_which_time_out_match_nobody() {
+        let resolver = Timeouts {
+            inner: SlowResolver,
+            timeout: Duration::ZERO,
+            timed_out: Mutex::default(),
+        };
+        let ast = Expr::Union(
+            Box::new(Expr::StringLiteral("fast".to_string())),
+            Box::new(Expr::StringLiteral("slow".to_string())),
+        );
+
+        let members = interpret(ast, &resolver)
+            .await
+            .expect("timeouts should not fail the query");
+
+        assert_eq!(members, HashSet::from([serenity::UserId(1)]));
+        assert_eq!(
+            resolver
+                .timed_out
+                .into_inner()
+                .expect("timed out operands lock was poisoned"),
+            vec!["slow"]
+        );
+    }
+
+    #[tokio::test]
+    async fn calls_are_counted() {
+        let resolver = Calls {
+            inner: SlowResolver,
+            calls: Mutex::default(),
+        };
+        let ast = Expr::Union(
+            Box::new(Expr::Complement(Box::new(Expr::StringLiteral(
+                "a".to_string(),
+            )))),
+            Box::new(Expr::Union(
+                Box::new(Expr::StringLiteral("b".to_string())),
+                Box::new(Expr::Me),
+            )),
+        );
+
+        interpret(ast, &resolver)
+            .await
+            .expect("the query should evaluate");
+
+        assert_eq!(
+            resolver
+                .calls
+                .into_inner()
+                .expect("resolver calls lock was poisoned"),
+            BTreeMap::from([
+                ("resolve_everyone", 1),
+                ("resolve_me", 1),
+                ("resolve_string_literal", 2),
+            ])
+        );
+    }
 }
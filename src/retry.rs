@@ -0,0 +1,78 @@
+//! Retrying Discord requests which fail for transient reasons
+//!
+//! A dropped connection or a server error on Discord's side shouldn't leave a notification half
+//! sent, so the requests made while handling a query are retried a few times before giving up,
+//! backing off between attempts. The delays are jittered, so queries which failed together don't
+//! all retry at the same moment.
+
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use tracing::warn;
+
+use crate::{discord::Discord, error::QueryError};
+
+/// How many times a request is attempted before giving up
+pub const MAX_ATTEMPTS: u32 = 4;
+
+/// How long to back off before the given retry (starting from 1), before jitter is added.
+///
+/// Rate limits are already waited out by Serenity, so this only needs to give transient failures
+/// (like a dropped connection) time to go away.
+pub fn backoff(retry: u32) -> Duration {
+    Duration::from_secs(1 << retry.min(5))
+}
+
+/// How long to wait before the given retry (starting from 1): its [`backoff`], plus up to half
+/// as long again at random.
+pub fn retry_delay(retry: u32) -> Duration {
+    let backoff = backoff(retry);
+    backoff + backoff.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+}
+
+/// Make a request with `request`, retrying if it fails for a transient reason (see
+/// [`QueryError::is_transient`]) until it has been attempted [`MAX_ATTEMPTS`] times.
+///
+/// `action` describes the request in the logs, like "Sending message".
+pub async fn with_retry<T, F>(
+    discord: &impl Discord,
+    action: &str,
+    mut request: impl FnMut() -> F + Send,
+) -> Result<T, QueryError>
+where
+    F: Future<Output = Result<T, QueryError>> + Send,
+{
+    let mut attempt = 1;
+    loop {
+        match request().await {
+            Err(err) if err.is_transient() && attempt < MAX_ATTEMPTS => {
+                let delay = retry_delay(attempt);
+                warn!("{action} failed (attempt {attempt}), retrying in {delay:?}: {err}");
+                discord.wait(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delays_back_off() {
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(2), Duration::from_secs(4));
+        assert_eq!(backoff(100), Duration::from_secs(32));
+    }
+
+    #[test]
+    fn retry_delays_are_jittered() {
+        for retry in 1..=6 {
+            let delay = retry_delay(retry);
+            assert!(delay >= backoff(retry));
+            assert!(delay < backoff(retry).mul_f64(1.5));
+        }
+    }
+}
@@ -0,0 +1,717 @@
+//! Queries scheduled to notify their members later, with `/schedule`
+//!
+//! A scheduled query is evaluated when it's due, not when it's scheduled, so it notifies whoever
+//! matches it then. When it's due, Intersection announces it in its channel and runs it through the
+//! [query pipeline] as if its author had just sent it in that announcement, so it's checked,
+//! confirmed, and sent like any other query. Scheduled queries are written to a file, if given one,
+//! so they survive restarts; those which came due while Intersection was stopped are still run if
+//! they're less than [`LATE_WINDOW`] late, and their authors are told they were missed otherwise.
+//!
+//! A query may also be scheduled to run again every day or week, like `every monday 17:00`, in
+//! which case it's scheduled for its next time as soon as it's run (or missed).
+//!
+//! [query pipeline]: crate::pipeline
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Datelike, Days, FixedOffset, NaiveDateTime, NaiveTime, Utc, Weekday};
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, UserId};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+use crate::{
+    i18n::{self, Language},
+    persistence::StoreFile,
+    pipeline::{self, QueryOptions},
+    quiet_hours, Data,
+};
+
+/// How late a scheduled query may be run, if it came due while Intersection was stopped
+const LATE_WINDOW: Duration = Duration::from_mins(10);
+
+/// How times are written when scheduling a query, like `2024-06-01 18:00`
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// How times of day are written in recurrences, like `17:00`
+const TIME_OF_DAY_FORMAT: &str = "%H:%M";
+
+/// How often a scheduled query is run again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    /// The day of the week it's run on, or [`None`] if it's run every day
+    day: Option<Weekday>,
+    /// The time of day it's run at, in its time zone
+    time: NaiveTime,
+    /// Its time zone
+    offset: FixedOffset,
+}
+
+impl Recurrence {
+    /// Read a recurrence written like `every monday 17:00` or `every day 09:30` (where `every` may
+    /// be left out), in the time zone `tz` (a UTC offset, like `UTC+2`), or UTC if not given.
+    pub fn parse(when: &str, tz: Option<&str>) -> Option<Self> {
+        let when = when.trim();
+        let when = when
+            .get(..6)
+            .filter(|every| every.eq_ignore_ascii_case("every "))
+            .map_or(when, |_| &when[6..]);
+        let (day, time) = when.trim().split_once(char::is_whitespace)?;
+        let day = if day.eq_ignore_ascii_case("day") {
+            None
+        } else {
+            Some(day.parse::<Weekday>().ok()?)
+        };
+        let time = NaiveTime::parse_from_str(time.trim(), TIME_OF_DAY_FORMAT).ok()?;
+        let offset = tz.map_or_else(|| FixedOffset::east_opt(0), quiet_hours::parse_offset)?;
+        Some(Self { day, time, offset })
+    }
+
+    /// The first time this recurs after `after`.
+    pub fn next_after(&self, after: SystemTime) -> SystemTime {
+        let after = DateTime::<Utc>::from(after).with_timezone(&self.offset);
+        // Whichever day it's on, it's on one of the next eight (counting today, if it's later)
+        (0..=7)
+            .filter_map(|days| after.date_naive().checked_add_days(Days::new(days)))
+            .filter(|date| self.day.is_none_or(|day| date.weekday() == day))
+            .filter_map(|date| {
+                date.and_time(self.time)
+                    .and_local_timezone(self.offset)
+                    .single()
+            })
+            .find(|at| *at > after)
+            .map_or_else(
+                || SystemTime::from(after) + Duration::from_hours(7 * 24),
+                SystemTime::from,
+            )
+    }
+
+    /// Write this recurrence as a single field, like `0/17:00/3600` for every Monday at 17:00 in
+    /// UTC+1.
+    fn to_field(self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.day.map_or_else(
+                || "*".to_string(),
+                |day| day.num_days_from_monday().to_string()
+            ),
+            self.time.format(TIME_OF_DAY_FORMAT),
+            self.offset.local_minus_utc()
+        )
+    }
+
+    /// Read a recurrence written by [`Recurrence::to_field`].
+    fn from_field(field: &str) -> Option<Self> {
+        let mut parts = field.split('/');
+        let day = match parts.next()? {
+            "*" => None,
+            day => Some(Weekday::try_from(day.parse::<u8>().ok()?).ok()?),
+        };
+        let time = NaiveTime::parse_from_str(parts.next()?, TIME_OF_DAY_FORMAT).ok()?;
+        let offset = FixedOffset::east_opt(parts.next()?.parse().ok()?)?;
+        parts.next().is_none().then_some(Self { day, time, offset })
+    }
+
+    /// Describe when the query runs in `language`, like `every Monday at 17:00 (UTC+01:00)`.
+    pub fn describe(&self, language: Language) -> String {
+        let time = self.time.format(TIME_OF_DAY_FORMAT).to_string();
+        let offset = self.offset.to_string();
+        let Some(day) = self.day else {
+            return i18n::message(
+                language,
+                "recurrence-daily",
+                &[("time", &time), ("offset", &offset)],
+            );
+        };
+        let day = i18n::message(
+            language,
+            match day {
+                Weekday::Mon => "weekday-monday",
+                Weekday::Tue => "weekday-tuesday",
+                Weekday::Wed => "weekday-wednesday",
+                Weekday::Thu => "weekday-thursday",
+                Weekday::Fri => "weekday-friday",
+                Weekday::Sat => "weekday-saturday",
+                Weekday::Sun => "weekday-sunday",
+            },
+            &[],
+        );
+        i18n::message(
+            language,
+            "recurrence-weekly",
+            &[("day", &day), ("time", &time), ("offset", &offset)],
+        )
+    }
+}
+
+/// A query scheduled to notify its members later
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledQuery {
+    /// The guild it's run in
+    pub guild: GuildId,
+    /// The channel it's run in
+    pub channel: ChannelId,
+    /// Who scheduled it, who it's run as
+    pub author: UserId,
+    /// When it's due
+    pub at: SystemTime,
+    /// How often it's run again, if it is
+    pub repeat: Option<Recurrence>,
+    /// The query, without its `@{...}`
+    pub query: String,
+}
+
+impl ScheduledQuery {
+    /// Write this query, scheduled as `id`, as a single line.
+    ///
+    /// Fields are separated by tabs, with the query last so tabs in it are kept.
+    fn to_line(&self, id: u64) -> String {
+        let at = self
+            .at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        [
+            id.to_string(),
+            self.guild.to_string(),
+            self.channel.to_string(),
+            self.author.to_string(),
+            at.to_string(),
+            self.repeat.map(Recurrence::to_field).unwrap_or_default(),
+            self.query.clone(),
+        ]
+        .join("\t")
+    }
+
+    /// Read a query written by [`ScheduledQuery::to_line`], along with its ID.
+    fn from_line(line: &str) -> Option<(u64, Self)> {
+        let mut fields = line.splitn(7, '\t');
+        let mut id = || fields.next()?.parse::<u64>().ok();
+        let scheduled_id = id()?;
+        let guild = GuildId(id()?);
+        let channel = ChannelId(id()?);
+        let author = UserId(id()?);
+        let at = UNIX_EPOCH + Duration::from_secs(id()?);
+        let repeat = match fields.next()? {
+            "" => None,
+            field => Some(Recurrence::from_field(field)?),
+        };
+        let query = fields.next()?.to_string();
+        Some((
+            scheduled_id,
+            Self {
+                guild,
+                channel,
+                author,
+                at,
+                repeat,
+                query,
+            },
+        ))
+    }
+}
+
+/// Read a time written like `2024-06-01 18:00`, in the time zone `tz` (a UTC offset, like
+/// `UTC+2`), or UTC if not given.
+pub fn parse_time(at: &str, tz: Option<&str>) -> Option<SystemTime> {
+    let offset = tz.map_or_else(|| FixedOffset::east_opt(0), quiet_hours::parse_offset)?;
+    let at = NaiveDateTime::parse_from_str(at.trim(), TIME_FORMAT)
+        .ok()?
+        .and_local_timezone(offset)
+        .single()?;
+    Some(SystemTime::from(at.with_timezone(&Utc)))
+}
+
+/// Write `at` so Discord shows it in each reader's own time zone.
+pub fn timestamp(at: SystemTime) -> String {
+    format!("<t:{}:f>", DateTime::<Utc>::from(at).timestamp())
+}
+
+/// Keeps track of the queries scheduled in every guild, in a file if given one
+#[derive(Debug)]
+pub struct Schedule {
+    /// The file scheduled queries are written to, if they're kept across restarts
+    file: StoreFile,
+    /// Every scheduled query, keyed by its ID
+    queries: Mutex<BTreeMap<u64, ScheduledQuery>>,
+    /// The ID the next scheduled query is given
+    next_id: AtomicU64,
+    /// Notified when a query is scheduled, so [`run`] can wake up sooner for it
+    changed: Notify,
+    /// Whether [`run`] has started running scheduled queries
+    running: AtomicBool,
+}
+
+impl Schedule {
+    /// Create a new [`Schedule`] writing to `path`, reading the queries scheduled the last time
+    /// from it.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let file = StoreFile::new(path, "scheduled queries");
+        let queries = file
+            .load(ScheduledQuery::from_line)
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+
+        Self {
+            file,
+            next_id: AtomicU64::new(queries.keys().next_back().map_or(1, |id| id + 1)),
+            queries: Mutex::new(queries),
+            changed: Notify::new(),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// Schedule `query`, returning its ID.
+    pub fn add(&self, query: ScheduledQuery) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut queries = self.queries.lock().expect("schedule lock was poisoned");
+        queries.insert(id, query);
+        // Saving with the lock held keeps an older save from overwriting a newer one
+        self.save(&queries);
+        drop(queries);
+        self.changed.notify_one();
+        id
+    }
+
+    /// The number of queries scheduled in `guild`.
+    pub fn count(&self, guild: GuildId) -> usize {
+        self.queries
+            .lock()
+            .expect("schedule lock was poisoned")
+            .values()
+            .filter(|query| query.guild == guild)
+            .count()
+    }
+
+    /// The queries scheduled in `guild`, soonest first.
+    pub fn in_guild(&self, guild: GuildId) -> Vec<(u64, ScheduledQuery)> {
+        let mut scheduled = self
+            .queries
+            .lock()
+            .expect("schedule lock was poisoned")
+            .iter()
+            .filter(|(_, query)| query.guild == guild)
+            .map(|(id, query)| (*id, query.clone()))
+            .collect::<Vec<_>>();
+        scheduled.sort_unstable_by_key(|(id, query)| (query.at, *id));
+        scheduled
+    }
+
+    /// The query scheduled as `id` in `guild`, if there is one.
+    pub fn get(&self, guild: GuildId, id: u64) -> Option<ScheduledQuery> {
+        self.queries
+            .lock()
+            .expect("schedule lock was poisoned")
+            .get(&id)
+            .filter(|query| query.guild == guild)
+            .cloned()
+    }
+
+    /// Stop the query scheduled as `id` in `guild` from running, returning it if there was one.
+    pub fn cancel(&self, guild: GuildId, id: u64) -> Option<ScheduledQuery> {
+        let mut queries = self.queries.lock().expect("schedule lock was poisoned");
+        queries.get(&id).filter(|query| query.guild == guild)?;
+        let cancelled = queries.remove(&id);
+        self.save(&queries);
+        drop(queries);
+        self.changed.notify_one();
+        cancelled
+    }
+
+    /// When the next scheduled query is due, if any are scheduled.
+    fn next_due(&self) -> Option<SystemTime> {
+        self.queries
+            .lock()
+            .expect("schedule lock was poisoned")
+            .values()
+            .map(|query| query.at)
+            .min()
+    }
+
+    /// Take the queries due by `now`, so they're only run once, scheduling those which recur for
+    /// their next time.
+    fn take_due(&self, now: SystemTime) -> Vec<(u64, ScheduledQuery)> {
+        let mut queries = self.queries.lock().expect("schedule lock was poisoned");
+        let mut due = Vec::new();
+        for (id, query) in queries.iter_mut().filter(|(_, query)| query.at <= now) {
+            due.push((*id, query.clone()));
+            if let Some(repeat) = query.repeat {
+                query.at = repeat.next_after(now);
+            }
+        }
+        if !due.is_empty() {
+            queries.retain(|_, query| query.at > now);
+            self.save(&queries);
+        }
+        drop(queries);
+        due
+    }
+
+    /// Write every scheduled query to the file.
+    fn save(&self, queries: &BTreeMap<u64, ScheduledQuery>) {
+        self.file
+            .save(queries.iter().map(|(id, query)| query.to_line(*id)));
+    }
+}
+
+/// Run each scheduled query when it's due, forever.
+///
+/// This is started every time Intersection is ready, but only the first call does anything, so
+/// queries aren't run twice after reconnecting.
+pub async fn run(ctx: &serenity::Context, data: &Data) {
+    if data.schedule.running.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    loop {
+        let changed = data.schedule.changed.notified();
+        let Some(next) = data.schedule.next_due() else {
+            changed.await;
+            continue;
+        };
+        let wait = next.duration_since(SystemTime::now()).unwrap_or_default();
+        tokio::select! {
+            () = tokio::time::sleep(wait) => {}
+            // A query may have been scheduled before the one we're waiting for
+            () = changed => continue,
+        }
+
+        let due = data.schedule.take_due(SystemTime::now());
+        futures::future::join_all(
+            due.into_iter()
+                .map(|(id, scheduled)| run_scheduled(ctx, data, id, scheduled)),
+        )
+        .await;
+    }
+}
+
+/// Announce a scheduled query in its channel and run it as its author, or tell them it was
+/// missed if it's more than [`LATE_WINDOW`] late.
+async fn run_scheduled(ctx: &serenity::Context, data: &Data, id: u64, scheduled: ScheduledQuery) {
+    let config = data.config.get(scheduled.guild);
+    let author = format!("<@{}>", scheduled.author);
+    let query = pipeline::describe_query(&[&scheduled.query]);
+    let late = scheduled.at.elapsed().unwrap_or_default();
+    if late > LATE_WINDOW {
+        info!("Scheduled query {id} is {late:?} late, telling its author it was missed");
+        let result = scheduled
+            .channel
+            .send_message(ctx, |builder| {
+                let at = timestamp(scheduled.at);
+                builder.content(match scheduled.repeat {
+                    None => i18n::message(
+                        config.language,
+                        "scheduled-missed",
+                        &[("author", &author), ("query", &query), ("at", &at)],
+                    ),
+                    Some(repeat) => i18n::message(
+                        config.language,
+                        "scheduled-missed-recurring",
+                        &[
+                            ("author", &author),
+                            ("query", &query),
+                            ("at", &at),
+                            ("next", &timestamp(repeat.next_after(SystemTime::now()))),
+                        ],
+                    ),
+                })
+            })
+            .await;
+        if let Err(err) = result {
+            warn!("Unable to tell the author of scheduled query {id} it was missed: {err}");
+        }
+        return;
+    }
+
+    debug!("Running scheduled query {id}");
+    let member = match scheduled.guild.member(ctx, scheduled.author).await {
+        Ok(member) => member,
+        Err(err) => {
+            // Most likely, they've left the guild
+            warn!("Unable to fetch the author of scheduled query {id}: {err}");
+            return;
+        }
+    };
+    let announcement = scheduled
+        .channel
+        .send_message(ctx, |builder| {
+            builder
+                .allowed_mentions(|allowed_mentions| {
+                    allowed_mentions.empty_parse().empty_users().empty_roles()
+                })
+                .content(i18n::message(
+                    config.language,
+                    "scheduled-announcement",
+                    &[("author", &author), ("query", &query)],
+                ))
+        })
+        .await;
+    let mut msg = match announcement {
+        Ok(msg) => msg,
+        Err(err) => {
+            warn!("Unable to announce scheduled query {id}: {err}");
+            return;
+        }
+    };
+
+    // The pipeline handles the announcement as if the author had just sent the query in it
+    msg.guild_id = Some(scheduled.guild);
+    msg.member = partial_member(&member);
+    msg.author = member.user;
+    pipeline::run_query(
+        &data.discord(ctx, &msg, &config),
+        &[&scheduled.query],
+        QueryOptions::for_guild(&config),
+    )
+    .await;
+}
+
+/// Write `member` the way messages describe their author's membership, so the pipeline sees the
+/// roles of a scheduled query's author (like those bypassing cooldowns) as if they'd sent it.
+fn partial_member(member: &serenity::Member) -> Option<serenity::PartialMember> {
+    let value = serenity::json::prelude::to_value(member).ok()?;
+    serenity::json::prelude::from_value(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, env, fs};
+
+    use super::*;
+    use crate::cooldowns::{CooldownSettings, CooldownTracker, RateLimit};
+
+    fn scheduled_query() -> ScheduledQuery {
+        ScheduledQuery {
+            guild: GuildId(1),
+            channel: ChannelId(2),
+            author: UserId(3),
+            at: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            repeat: None,
+            query: "raiders\t& here".to_string(),
+        }
+    }
+
+    /// The time `at`, written like `2024-06-03T17:00:00Z`.
+    fn time(at: &str) -> SystemTime {
+        SystemTime::from(DateTime::parse_from_rfc3339(at).expect("test times should be valid"))
+    }
+
+    #[test]
+    fn scheduled_queries_keep_their_authors_bypass_roles() {
+        let member: serenity::Member = serenity::json::prelude::from_value(serenity::json::json!({
+            "user": {
+                "id": "3",
+                "username": "alice",
+                "discriminator": "0",
+                "avatar": null,
+            },
+            "roles": ["5"],
+            "joined_at": null,
+            "deaf": false,
+            "mute": false,
+            "guild_id": "1",
+        }))
+        .expect("test members should be valid");
+        let partial = partial_member(&member).expect("members should convert");
+        assert_eq!(partial.roles, [serenity::RoleId(5)]);
+
+        let settings = CooldownSettings {
+            per_author: Some(RateLimit {
+                count: 1,
+                window: Duration::from_mins(10),
+            }),
+            per_guild: None,
+            bypass_roles: HashSet::from([serenity::RoleId(5)]),
+        };
+        let tracker = CooldownTracker::new();
+        tracker
+            .reserve(GuildId(1), UserId(3), &[], &settings, Language::English)
+            .expect("the author shouldn't be on cooldown yet")
+            .expect("the author's notification should count towards their cooldown")
+            .sent();
+        assert!(tracker
+            .reserve(GuildId(1), UserId(3), &[], &settings, Language::English)
+            .is_err());
+        assert!(tracker
+            .reserve(
+                GuildId(1),
+                UserId(3),
+                &partial.roles,
+                &settings,
+                Language::English
+            )
+            .is_ok_and(|reservation| reservation.is_none()));
+    }
+
+    #[test]
+    fn scheduled_queries_survive_being_written_out() {
+        let scheduled = scheduled_query();
+        assert_eq!(
+            ScheduledQuery::from_line(&scheduled.to_line(10)),
+            Some((10, scheduled))
+        );
+
+        let recurring = ScheduledQuery {
+            repeat: Recurrence::parse("every monday 17:00", Some("UTC+1")),
+            ..scheduled_query()
+        };
+        assert_eq!(
+            ScheduledQuery::from_line(&recurring.to_line(11)),
+            Some((11, recurring))
+        );
+        assert_eq!(ScheduledQuery::from_line("10\tnot a guild"), None);
+    }
+
+    #[test]
+    fn recurrences_are_read_in_the_given_time_zone() {
+        let weekly = Recurrence::parse("every Monday 17:00", Some("UTC+1"));
+        assert_eq!(
+            weekly.map(|weekly| weekly.describe(Language::English)),
+            Some("every Monday at 17:00 (UTC+01:00)".to_string())
+        );
+        assert_eq!(Recurrence::parse("mon 17:00", Some("+01:00")), weekly);
+        assert_eq!(
+            Recurrence::parse("every day 09:30", None)
+                .map(|daily| daily.describe(Language::English)),
+            Some("every day at 09:30 (UTC+00:00)".to_string())
+        );
+        assert_eq!(Recurrence::parse("every monday", None), None);
+        assert_eq!(Recurrence::parse("every month 17:00", None), None);
+        assert_eq!(Recurrence::parse("every monday 25:00", None), None);
+    }
+
+    #[test]
+    fn recurrences_find_their_next_time() {
+        // 2024-06-03 was a Monday
+        let weekly = Recurrence::parse("every monday 17:00", Some("UTC+1"))
+            .expect("the recurrence should be valid");
+        assert_eq!(
+            weekly.next_after(time("2024-06-01T12:00:00Z")),
+            time("2024-06-03T16:00:00Z")
+        );
+        assert_eq!(
+            weekly.next_after(time("2024-06-03T16:00:00Z")),
+            time("2024-06-10T16:00:00Z"),
+            "the next time is always later"
+        );
+
+        let daily =
+            Recurrence::parse("every day 09:30", None).expect("the recurrence should be valid");
+        assert_eq!(
+            daily.next_after(time("2024-06-03T09:00:00Z")),
+            time("2024-06-03T09:30:00Z")
+        );
+        assert_eq!(
+            daily.next_after(time("2024-06-03T10:00:00Z")),
+            time("2024-06-04T09:30:00Z")
+        );
+    }
+
+    #[test]
+    fn times_are_read_in_the_given_time_zone() {
+        let at = SystemTime::from(
+            DateTime::parse_from_rfc3339("2024-06-01T18:00:00Z").expect("the time should be valid"),
+        );
+        assert_eq!(parse_time("2024-06-01 18:00", None), Some(at));
+        assert_eq!(
+            parse_time("2024-06-01 20:00", Some("UTC+2")),
+            Some(at),
+            "18:00 UTC is 20:00 in UTC+2"
+        );
+        assert_eq!(parse_time("2024-06-01", None), None);
+        assert_eq!(parse_time("2024-06-01 18:00", Some("Europe/Berlin")), None);
+        assert_eq!(timestamp(at), "<t:1717264800:f>");
+    }
+
+    #[test]
+    fn due_queries_are_taken_once() {
+        let schedule = Schedule::open(None);
+        let first = schedule.add(scheduled_query());
+        let second = schedule.add(ScheduledQuery {
+            at: scheduled_query().at + Duration::from_hours(24),
+            ..scheduled_query()
+        });
+        assert_ne!(first, second);
+        assert_eq!(schedule.count(GuildId(1)), 2);
+        assert_eq!(schedule.count(GuildId(2)), 0);
+        assert_eq!(schedule.next_due(), Some(scheduled_query().at));
+
+        let now = scheduled_query().at + Duration::from_hours(1);
+        assert_eq!(schedule.take_due(now), vec![(first, scheduled_query())]);
+        assert!(schedule.take_due(now).is_empty());
+        assert_eq!(schedule.count(GuildId(1)), 1);
+    }
+
+    #[test]
+    fn recurring_queries_are_scheduled_again() {
+        let schedule = Schedule::open(None);
+        let repeat = Recurrence::parse("every day 09:30", None);
+        let id = schedule.add(ScheduledQuery {
+            at: time("2024-06-03T09:30:00Z"),
+            repeat,
+            ..scheduled_query()
+        });
+
+        let due = schedule.take_due(time("2024-06-03T09:31:00Z"));
+        assert_eq!(due.len(), 1);
+        assert_eq!(
+            schedule.get(GuildId(1), id).map(|scheduled| scheduled.at),
+            Some(time("2024-06-04T09:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn scheduled_queries_can_be_listed_and_cancelled() {
+        let schedule = Schedule::open(None);
+        let later = schedule.add(ScheduledQuery {
+            at: scheduled_query().at + Duration::from_hours(1),
+            ..scheduled_query()
+        });
+        let sooner = schedule.add(scheduled_query());
+        let elsewhere = schedule.add(ScheduledQuery {
+            guild: GuildId(2),
+            ..scheduled_query()
+        });
+
+        let ids = |guild| {
+            schedule
+                .in_guild(guild)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(GuildId(1)), [sooner, later]);
+
+        // Only a guild's own queries can be cancelled from it
+        assert_eq!(schedule.cancel(GuildId(1), elsewhere), None);
+        assert_eq!(schedule.cancel(GuildId(1), sooner), Some(scheduled_query()));
+        assert_eq!(schedule.cancel(GuildId(1), sooner), None);
+        assert_eq!(ids(GuildId(1)), [later]);
+        assert_eq!(ids(GuildId(2)), [elsewhere]);
+    }
+
+    #[test]
+    fn scheduled_queries_are_read_on_startup() {
+        let path = env::temp_dir().join(format!("intersection-schedule-{}", std::process::id()));
+        let schedule = Schedule::open(Some(path.clone()));
+        let id = schedule.add(scheduled_query());
+
+        // As if Intersection restarted
+        let schedule = Schedule::open(Some(path.clone()));
+        assert_eq!(
+            schedule.take_due(SystemTime::now()),
+            vec![(id, scheduled_query())]
+        );
+        // Newly scheduled queries don't reuse the IDs of old ones
+        assert!(schedule.add(scheduled_query()) > id);
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+}
@@ -0,0 +1,66 @@
+//! Per-channel queues for sending notifications
+//!
+//! Notifications which don't fit in a single message are sent one message at a time. If two such
+//! notifications were sent in the same channel at once, their messages would be interleaved, so
+//! each channel has a queue which notifications wait in until the previous one has been sent.
+
+use std::{collections::HashMap, sync::Arc, sync::Mutex};
+
+use poise::serenity_prelude::ChannelId;
+
+/// Held while sending a notification, keeping its channel's queue locked
+pub type SendQueueGuard = tokio::sync::OwnedMutexGuard<()>;
+
+/// The send queue of every channel
+#[derive(Debug, Default)]
+pub struct SendQueues {
+    /// The queue of every channel a notification was recently sent in
+    channels: Mutex<HashMap<ChannelId, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl SendQueues {
+    /// Create a new [`SendQueues`] in which every channel's queue is empty.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait for our turn to send a notification in `channel`, which lasts until the returned
+    /// guard is dropped.
+    pub async fn acquire(&self, channel: ChannelId) -> SendQueueGuard {
+        let queue = {
+            let mut channels = self.channels.lock().expect("send queue lock was poisoned");
+            // Opportunistically drop queues nobody is waiting in, so this doesn't grow unbounded
+            channels.retain(|_, queue| Arc::strong_count(queue) > 1);
+            Arc::clone(channels.entry(channel).or_default())
+        };
+
+        queue.lock_owned().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn channels_are_queued_separately() {
+        let queues = SendQueues::new();
+        let first = queues.acquire(ChannelId(1)).await;
+
+        // Another channel doesn't have to wait
+        drop(queues.acquire(ChannelId(2)).await);
+
+        // The same channel does, until the first notification is sent
+        let channel = Arc::clone(
+            queues
+                .channels
+                .lock()
+                .expect("lock should not be poisoned")
+                .get(&ChannelId(1))
+                .expect("the channel should have a queue"),
+        );
+        assert!(channel.try_lock().is_err());
+        drop(first);
+        assert!(channel.try_lock().is_ok());
+    }
+}
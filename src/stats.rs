@@ -0,0 +1,228 @@
+//! Counting how much Intersection is used in each guild, for `/stats`
+//!
+//! Only queries which notified someone are counted. The counts are written to a file, if given
+//! one, after each notification, so they keep adding up across restarts; `/stats` shows when each
+//! guild's counting started.
+
+use std::{cmp::Reverse, collections::HashMap, iter, path::PathBuf, sync::Mutex};
+
+use poise::serenity_prelude::{GuildId, RoleId, Timestamp};
+
+use crate::{models::mention::RoleType, persistence::StoreFile};
+
+/// How much Intersection has been used in a guild
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuildStats {
+    /// How many queries notified someone
+    pub queries: u64,
+    /// How many members were notified, counting members once for every query notifying them
+    pub members_pinged: u64,
+    /// The most members a single query notified
+    pub largest_query: usize,
+    /// How many queries mentioned each role
+    pub roles: HashMap<RoleType, u64>,
+    /// When the first query was counted, if any were
+    pub since: Option<Timestamp>,
+}
+
+impl GuildStats {
+    /// The `count` roles mentioned by the most queries, with how many mentioned them, most used
+    /// first.
+    pub fn most_used_roles(&self, count: usize) -> Vec<(RoleType, u64)> {
+        let mut roles = self
+            .roles
+            .iter()
+            .map(|(role, uses)| (*role, *uses))
+            .collect::<Vec<_>>();
+        // Ties are broken by the role's mention so the order doesn't change between calls
+        roles.sort_unstable_by_key(|(role, uses)| (Reverse(*uses), role.to_string()));
+        roles.truncate(count);
+        roles
+    }
+
+    /// Write these counts, for `guild`, as lines with tab separated fields: one line for the
+    /// counts of queries, then one for each role.
+    fn to_lines(&self, guild: GuildId) -> Vec<String> {
+        let since = self
+            .since
+            .map_or_else(String::new, |since| since.unix_timestamp().to_string());
+        let counts = format!(
+            "{guild}\tcounts\t{}\t{}\t{}\t{since}",
+            self.queries, self.members_pinged, self.largest_query
+        );
+        iter::once(counts)
+            .chain(self.roles.iter().map(|(role, uses)| {
+                let role = match role {
+                    RoleType::Everyone => "everyone".to_string(),
+                    RoleType::Here => "here".to_string(),
+                    RoleType::Role(id) => id.to_string(),
+                };
+                format!("{guild}\trole\t{role}\t{uses}")
+            }))
+            .collect()
+    }
+
+    /// Read a line written by [`GuildStats::to_lines`] into these counts, from the kind of line
+    /// and the fields after it.
+    fn read_line(&mut self, kind: &str, fields: &[&str]) -> Option<()> {
+        match (kind, fields) {
+            ("counts", [queries, members_pinged, largest_query, since]) => {
+                self.queries = queries.parse().ok()?;
+                self.members_pinged = members_pinged.parse().ok()?;
+                self.largest_query = largest_query.parse().ok()?;
+                self.since = if since.is_empty() {
+                    None
+                } else {
+                    Some(Timestamp::from_unix_timestamp(since.parse().ok()?).ok()?)
+                };
+            }
+            ("role", [role, uses]) => {
+                let role = match *role {
+                    "everyone" => RoleType::Everyone,
+                    "here" => RoleType::Here,
+                    id => RoleType::Role(RoleId(id.parse().ok()?)),
+                };
+                self.roles.insert(role, uses.parse().ok()?);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// Counts how much Intersection is used in every guild, in a file if given one.
+#[derive(Debug)]
+pub struct UsageStats {
+    /// The file the counts are written to, if they're kept across restarts
+    file: StoreFile,
+    /// The counts for each guild
+    guilds: Mutex<HashMap<GuildId, GuildStats>>,
+}
+
+impl UsageStats {
+    /// Create a new [`UsageStats`] writing to `path`, reading what was counted the last time from
+    /// it.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let file = StoreFile::new(path, "usage statistics");
+        let mut guilds = HashMap::<GuildId, GuildStats>::new();
+        file.load(|line| {
+            let fields = line.split('\t').collect::<Vec<_>>();
+            let [guild, kind, fields @ ..] = fields.as_slice() else {
+                return None;
+            };
+            let guild = GuildId(guild.parse().ok()?);
+            guilds.entry(guild).or_default().read_line(kind, fields)
+        });
+
+        Self {
+            file,
+            guilds: Mutex::new(guilds),
+        }
+    }
+
+    /// Count a query in `guild` which notified `members` members, mentioning `roles`.
+    pub fn record(&self, guild: GuildId, members: usize, roles: &[RoleType]) {
+        let mut guilds = self.guilds.lock().expect("usage stats lock was poisoned");
+        let stats = guilds.entry(guild).or_default();
+        stats.queries += 1;
+        stats.members_pinged += u64::try_from(members).unwrap_or(u64::MAX);
+        stats.largest_query = stats.largest_query.max(members);
+        for role in roles {
+            *stats.roles.entry(*role).or_default() += 1;
+        }
+        stats.since.get_or_insert_with(Timestamp::now);
+        // Saving with the lock held keeps an older save from overwriting a newer one
+        let snapshot = guilds.clone();
+        self.file.save_in_background(move || {
+            snapshot
+                .iter()
+                .flat_map(|(guild, stats)| stats.to_lines(*guild))
+                .collect::<Vec<_>>()
+        });
+        drop(guilds);
+    }
+
+    /// Obtain how much Intersection has been used in `guild`.
+    pub fn get(&self, guild: GuildId) -> GuildStats {
+        self.guilds
+            .lock()
+            .expect("usage stats lock was poisoned")
+            .get(&guild)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn queries_are_counted_per_guild() {
+        let stats = UsageStats::open(None);
+        stats.record(GuildId(1), 5, &[RoleType::Role(RoleId(1))]);
+        stats.record(GuildId(1), 12, &[RoleType::Role(RoleId(1)), RoleType::Here]);
+        stats.record(GuildId(2), 100, &[]);
+
+        let guild = stats.get(GuildId(1));
+        assert_eq!(guild.queries, 2);
+        assert_eq!(guild.members_pinged, 17);
+        assert_eq!(guild.largest_query, 12);
+        assert!(guild.since.is_some());
+        assert_eq!(stats.get(GuildId(3)), GuildStats::default());
+    }
+
+    #[test]
+    fn roles_are_ranked_by_use() {
+        let stats = UsageStats::open(None);
+        stats.record(GuildId(1), 1, &[RoleType::Role(RoleId(2))]);
+        stats.record(
+            GuildId(1),
+            1,
+            &[RoleType::Role(RoleId(3)), RoleType::Everyone],
+        );
+        stats.record(
+            GuildId(1),
+            1,
+            &[RoleType::Role(RoleId(3)), RoleType::Role(RoleId(2))],
+        );
+        stats.record(GuildId(1), 1, &[RoleType::Role(RoleId(3))]);
+
+        assert_eq!(
+            stats.get(GuildId(1)).most_used_roles(2),
+            [
+                (RoleType::Role(RoleId(3)), 3),
+                (RoleType::Role(RoleId(2)), 2)
+            ]
+        );
+        assert_eq!(stats.get(GuildId(1)).most_used_roles(5).len(), 3);
+    }
+
+    #[test]
+    fn counts_survive_a_restart() {
+        let path = env::temp_dir().join(format!("intersection-stats-{}", std::process::id()));
+        let stats = UsageStats::open(Some(path.clone()));
+        stats.record(GuildId(1), 5, &[RoleType::Role(RoleId(2)), RoleType::Here]);
+        stats.record(GuildId(1), 7, &[RoleType::Everyone]);
+        stats.record(GuildId(3), 1, &[]);
+
+        // As if Intersection restarted, with the counts carrying on
+        let reopened = UsageStats::open(Some(path.clone()));
+        let since = stats
+            .get(GuildId(1))
+            .since
+            .map(|since| since.unix_timestamp());
+        let guild = reopened.get(GuildId(1));
+        assert_eq!(guild.queries, 2);
+        assert_eq!(guild.members_pinged, 12);
+        assert_eq!(guild.largest_query, 7);
+        assert_eq!(guild.roles, stats.get(GuildId(1)).roles);
+        assert_eq!(guild.since.map(|since| since.unix_timestamp()), since);
+        reopened.record(GuildId(3), 2, &[]);
+        assert_eq!(reopened.get(GuildId(3)).queries, 2);
+
+        fs::remove_file(path).expect("the file should have been written");
+    }
+}
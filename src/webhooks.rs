@@ -0,0 +1,100 @@
+//! Channel webhooks used to send notifications under the author's name
+//!
+//! When a guild enables it, notifications are sent through a webhook owned by Intersection, with
+//! the author's name and avatar, so the announcement reads as coming from the organizer rather
+//! than the bot. This requires the "Manage Webhooks" permission in the channel; without it,
+//! notifications are sent as normal replies.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use poise::serenity_prelude::{self as serenity, ChannelId};
+use tracing::debug;
+
+/// The name of the webhooks Intersection creates
+const WEBHOOK_NAME: &str = "Intersection";
+
+/// Remembers the webhook Intersection uses in each channel, to avoid looking it up for every
+/// message.
+#[derive(Debug, Default)]
+pub struct WebhookStore {
+    /// The webhook used in each channel
+    webhooks: Mutex<HashMap<ChannelId, serenity::Webhook>>,
+}
+
+impl WebhookStore {
+    /// Create a new, empty [`WebhookStore`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Obtain the webhook used in a channel, if it is known.
+    fn get(&self, channel: ChannelId) -> Option<serenity::Webhook> {
+        self.webhooks
+            .lock()
+            .expect("webhook store lock was poisoned")
+            .get(&channel)
+            .cloned()
+    }
+
+    /// Remember the webhook used in a channel.
+    fn insert(&self, channel: ChannelId, webhook: serenity::Webhook) {
+        self.webhooks
+            .lock()
+            .expect("webhook store lock was poisoned")
+            .insert(channel, webhook);
+    }
+
+    /// Forget the webhook used in a channel, as its webhooks were changed.
+    pub fn invalidate_channel(&self, channel: ChannelId) {
+        self.webhooks
+            .lock()
+            .expect("webhook store lock was poisoned")
+            .remove(&channel);
+    }
+}
+
+/// Obtain Intersection's webhook in a channel, creating it if needed.
+///
+/// Returns [`None`] if webhooks can't be used in the channel: either we lack the "Manage Webhooks"
+/// permission there, or it's a thread.
+pub async fn channel_webhook(
+    ctx: &serenity::Context,
+    channel: &serenity::GuildChannel,
+    store: &WebhookStore,
+) -> serenity::Result<Option<serenity::Webhook>> {
+    if channel.thread_metadata.is_some() {
+        debug!("Not using a webhook in thread {}", channel.id);
+        return Ok(None);
+    }
+
+    let current_user = ctx.cache.current_user_id();
+    if !channel
+        .permissions_for_user(ctx, current_user)?
+        .manage_webhooks()
+    {
+        debug!("Missing permission to use webhooks in {}", channel.id);
+        return Ok(None);
+    }
+
+    if let Some(webhook) = store.get(channel.id) {
+        return Ok(Some(webhook));
+    }
+
+    let existing = channel.webhooks(ctx).await?.into_iter().find(|webhook| {
+        webhook.kind == serenity::WebhookType::Incoming
+            && webhook.token.is_some()
+            && webhook
+                .user
+                .as_ref()
+                .is_some_and(|user| user.id == current_user)
+    });
+    let webhook = if let Some(webhook) = existing {
+        webhook
+    } else {
+        debug!("Creating webhook in {}", channel.id);
+        channel.create_webhook(ctx, WEBHOOK_NAME).await?
+    };
+    store.insert(channel.id, webhook.clone());
+
+    Ok(Some(webhook))
+}
@@ -0,0 +1,435 @@
+//! End-to-end tests of DRQL queries against a [`MockGuild`]
+
+use std::collections::HashSet;
+
+use chrono::{Duration, Utc};
+use intersection::drql::{
+    ast::MessageLink,
+    testing::{MockError, MockGuild},
+};
+use poise::serenity_prelude::{
+    ChannelId, GuildId, MessageId, OnlineStatus, Permissions, ReactionType, RoleId,
+    ScheduledEventId, UserId,
+};
+
+fn guild() -> MockGuild {
+    MockGuild::new()
+        .with_role(RoleId(10), "staff")
+        .with_role(RoleId(11), "artists")
+        .with_role(RoleId(12), "Red Team")
+        .with_member(UserId(1), "alice", &[RoleId(10)])
+        .with_member(UserId(2), "bob", &[RoleId(10), RoleId(11)])
+        .with_member(UserId(3), "carol", &[RoleId(11), RoleId(12)])
+        .with_member(UserId(4), "dave", &[])
+        .with_presence(UserId(1), OnlineStatus::Online)
+        .with_presence(UserId(3), OnlineStatus::Idle)
+        .with_presence(UserId(4), OnlineStatus::Offline)
+}
+
+fn users(ids: &[u64]) -> HashSet<UserId> {
+    ids.iter().copied().map(UserId).collect()
+}
+
+#[tokio::test]
+async fn set_operations() {
+    let guild = guild();
+
+    assert_eq!(
+        guild.evaluate("staff + artists").await,
+        Ok(users(&[1, 2, 3]))
+    );
+    assert_eq!(
+        guild.evaluate("staff | artists").await,
+        Ok(users(&[1, 2, 3]))
+    );
+    assert_eq!(guild.evaluate("staff & artists").await, Ok(users(&[2])));
+    assert_eq!(guild.evaluate("staff - artists").await, Ok(users(&[1])));
+    assert_eq!(
+        guild.evaluate("everyone - (staff | artists)").await,
+        Ok(users(&[4]))
+    );
+}
+
+#[tokio::test]
+async fn word_operators() {
+    let guild = guild();
+
+    assert_eq!(
+        guild.evaluate("staff or artists").await,
+        Ok(users(&[1, 2, 3]))
+    );
+    assert_eq!(guild.evaluate("staff and artists").await, Ok(users(&[2])));
+    assert_eq!(guild.evaluate("staff minus artists").await, Ok(users(&[1])));
+    assert_eq!(
+        guild.evaluate("everyone MINUS (staff Or artists)").await,
+        Ok(users(&[4]))
+    );
+    assert_eq!(
+        guild.evaluate("artists and not staff").await,
+        Ok(users(&[3]))
+    );
+}
+
+#[tokio::test]
+async fn me() {
+    let guild = guild().with_author(UserId(2));
+
+    assert_eq!(guild.evaluate("staff - me").await, Ok(users(&[1])));
+    assert_eq!(guild.evaluate("me + dave").await, Ok(users(&[2, 4])));
+    assert!(matches!(
+        self::guild().evaluate("me").await,
+        Err(MockError::Resolution(_))
+    ));
+}
+
+#[tokio::test]
+async fn complement() {
+    let guild = guild();
+
+    assert_eq!(guild.evaluate("!staff").await, Ok(users(&[3, 4])));
+    assert_eq!(guild.evaluate("~(staff | artists)").await, Ok(users(&[4])));
+    assert_eq!(guild.evaluate("artists & !staff").await, Ok(users(&[3])));
+    assert_eq!(guild.evaluate("!!staff").await, Ok(users(&[1, 2])));
+}
+
+#[tokio::test]
+async fn everyone_and_here() {
+    let guild = guild();
+
+    assert_eq!(guild.evaluate("everyone").await, Ok(users(&[1, 2, 3, 4])));
+    assert_eq!(guild.evaluate("@here").await, Ok(users(&[1, 3])));
+    assert_eq!(guild.evaluate("artists & here").await, Ok(users(&[3])));
+}
+
+#[tokio::test]
+async fn role_names_ignore_case() {
+    let guild = guild();
+
+    assert_eq!(guild.evaluate("STAFF").await, Ok(users(&[1, 2])));
+    assert_eq!(guild.evaluate("\"red team\"").await, Ok(users(&[3])));
+
+    // A role named exactly as written wins, but two which only differ in case are ambiguous
+    let guild = guild
+        .with_role(RoleId(13), "Staff")
+        .with_member(UserId(5), "erin", &[RoleId(13)]);
+    assert_eq!(guild.evaluate("Staff").await, Ok(users(&[5])));
+    assert_eq!(guild.evaluate("staff").await, Ok(users(&[1, 2])));
+    assert!(guild.evaluate("STAFF").await.is_err());
+
+    let guild = guild.with_case_sensitive_roles(true);
+    assert!(guild.evaluate("ARTISTS").await.is_err());
+}
+
+#[tokio::test]
+async fn unicode_names() {
+    let guild = guild()
+        .with_role(RoleId(13), "d\u{e9}sign")
+        .with_role(RoleId(14), "\u{1f6e1}\u{fe0f}Mods")
+        .with_member(UserId(5), "erin", &[RoleId(13), RoleId(14)])
+        .with_member(UserId(6), "\u{e9}lodie", &[RoleId(14)]);
+
+    assert_eq!(guild.evaluate("d\u{e9}sign").await, Ok(users(&[5])));
+    assert_eq!(
+        guild.evaluate("\u{1f6e1}\u{fe0f}Mods - \u{e9}lodie").await,
+        Ok(users(&[5]))
+    );
+    assert_eq!(guild.evaluate("D\u{c9}SIGN").await, Ok(users(&[5])));
+}
+
+#[tokio::test]
+async fn presences() {
+    let guild = guild().with_presence(UserId(2), OnlineStatus::DoNotDisturb);
+
+    assert_eq!(guild.evaluate("online").await, Ok(users(&[1])));
+    assert_eq!(guild.evaluate("idle").await, Ok(users(&[3])));
+    assert_eq!(guild.evaluate("staff & dnd").await, Ok(users(&[2])));
+    // Members without a presence are offline too
+    assert_eq!(
+        guild
+            .with_member(UserId(5), "erin", &[])
+            .evaluate("offline")
+            .await,
+        Ok(users(&[4, 5]))
+    );
+}
+
+#[tokio::test]
+async fn boosters() {
+    let guild = guild().with_booster(UserId(2)).with_booster(UserId(3));
+
+    assert_eq!(guild.evaluate("boosters").await, Ok(users(&[2, 3])));
+    assert_eq!(guild.evaluate("boosters & staff").await, Ok(users(&[2])));
+}
+
+#[tokio::test]
+async fn voice_channels() {
+    let guild = guild()
+        .with_voice_channel(ChannelId(20))
+        .with_voice_channel(ChannelId(21))
+        .with_voice_state(UserId(1), ChannelId(20))
+        .with_voice_state(UserId(3), ChannelId(20))
+        .with_voice_state(UserId(4), ChannelId(21));
+
+    assert_eq!(guild.evaluate("<#20>").await, Ok(users(&[1, 3])));
+    assert_eq!(guild.evaluate("<#20> - staff").await, Ok(users(&[3])));
+    assert_eq!(guild.evaluate("in_voice").await, Ok(users(&[1, 3, 4])));
+    assert!(matches!(
+        guild.evaluate("<#22>").await,
+        Err(MockError::Resolution(_))
+    ));
+}
+
+#[tokio::test]
+async fn text_channels() {
+    let guild = guild()
+        .with_text_channel(ChannelId(30), &[])
+        .with_text_channel(ChannelId(31), &[RoleId(10), RoleId(12)]);
+
+    assert_eq!(guild.evaluate("<#30>").await, Ok(users(&[1, 2, 3, 4])));
+    assert_eq!(guild.evaluate("<#31>").await, Ok(users(&[1, 2, 3])));
+    assert_eq!(guild.evaluate("<#31> - artists").await, Ok(users(&[1])));
+}
+
+#[tokio::test]
+async fn threads() {
+    let guild = guild().with_thread(ChannelId(40), &[UserId(1), UserId(3), UserId(4)]);
+
+    assert_eq!(guild.evaluate("<#40>").await, Ok(users(&[1, 3, 4])));
+    assert_eq!(guild.evaluate("<#40> - staff").await, Ok(users(&[3, 4])));
+}
+
+#[tokio::test]
+async fn scheduled_events() {
+    let guild = guild().with_event(ScheduledEventId(50), &[UserId(1), UserId(3)]);
+
+    assert_eq!(guild.evaluate("event(50) & here").await, Ok(users(&[1, 3])));
+    assert_eq!(
+        guild
+            .evaluate("event(https://discord.com/events/1/50) - staff")
+            .await,
+        Ok(users(&[3]))
+    );
+    assert!(matches!(
+        guild.evaluate("event(51)").await,
+        Err(MockError::Resolution(_))
+    ));
+}
+
+#[tokio::test]
+async fn limits() {
+    let guild = guild();
+
+    assert_eq!(guild.evaluate("everyone limit 2").await, Ok(users(&[1, 2])));
+    assert_eq!(
+        guild.evaluate("(artists LIMIT 1) + dave").await,
+        Ok(users(&[2, 4]))
+    );
+    assert_eq!(guild.evaluate("staff limit 10").await, Ok(users(&[1, 2])));
+    assert!(matches!(
+        guild.evaluate("staff limit 1 + dave").await,
+        Err(MockError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn at_least() {
+    let guild = guild();
+
+    assert_eq!(
+        guild
+            .evaluate("atleast(2, staff, artists, \"Red Team\")")
+            .await,
+        Ok(users(&[2, 3]))
+    );
+    assert_eq!(
+        guild
+            .evaluate("atleast(3, staff, artists, \"Red Team\")")
+            .await,
+        Ok(users(&[]))
+    );
+    assert_eq!(
+        guild.evaluate("atleast(1, staff, dave)").await,
+        Ok(users(&[1, 2, 4]))
+    );
+    assert!(matches!(
+        guild.evaluate("atleast(staff, artists)").await,
+        Err(MockError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn samples() {
+    let guild = guild();
+
+    let sample = guild
+        .evaluate("sample(staff + artists, 2)")
+        .await
+        .expect("the query should evaluate");
+    assert_eq!(sample.len(), 2);
+    assert!(sample.is_subset(&users(&[1, 2, 3])));
+    assert_eq!(
+        guild.evaluate("sample(staff, 0) + dave").await,
+        Ok(users(&[4]))
+    );
+    assert!(matches!(
+        guild.evaluate("sample(staff, lots)").await,
+        Err(MockError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn name_patterns() {
+    let guild = guild()
+        .with_nickname(UserId(2), "bob [team-red]")
+        .with_nickname(UserId(4), "dave [Team-Blue]");
+
+    assert_eq!(guild.evaluate("name(\"*team*\")").await, Ok(users(&[2, 4])));
+    assert_eq!(guild.evaluate("name(\"?a*\")").await, Ok(users(&[3, 4])));
+    assert_eq!(
+        guild.evaluate("name(/team-(red|green)/)").await,
+        Ok(users(&[2]))
+    );
+    assert!(matches!(
+        guild.evaluate("name(/(/)").await,
+        Err(MockError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn activity() {
+    let guild = guild()
+        .with_last_message(UserId(1), Utc::now() - Duration::hours(1))
+        .with_last_message(UserId(2), Utc::now() - Duration::days(10))
+        .with_last_message(UserId(4), Utc::now() - Duration::days(2));
+
+    assert_eq!(guild.evaluate("active(7d)").await, Ok(users(&[1, 4])));
+    // Unlike `here`, offline members count if they've been chatting
+    assert_eq!(guild.evaluate("active(7d) - here").await, Ok(users(&[4])));
+    assert_eq!(guild.evaluate("active(2w)").await, Ok(users(&[1, 2, 4])));
+}
+
+#[tokio::test]
+async fn reactions() {
+    let link = MessageLink {
+        guild: Some(GuildId(1)),
+        channel: ChannelId(20),
+        message: MessageId(60),
+    };
+    let guild = guild().with_reaction(
+        link,
+        ReactionType::Unicode("✅".to_string()),
+        &[UserId(1), UserId(3), UserId(4)],
+    );
+
+    assert_eq!(
+        guild
+            .evaluate("reacted(https://discord.com/channels/1/20/60, ✅) - here")
+            .await,
+        Ok(users(&[4]))
+    );
+    assert!(matches!(
+        guild
+            .evaluate("reacted(https://discord.com/channels/1/20/60, 👍)")
+            .await,
+        Err(MockError::Resolution(_))
+    ));
+}
+
+#[tokio::test]
+async fn permissions() {
+    let guild = guild()
+        .with_role_permissions(RoleId(10), Permissions::MANAGE_MESSAGES)
+        .with_role_permissions(RoleId(12), Permissions::ADMINISTRATOR);
+
+    assert_eq!(
+        guild.evaluate("perm(manage_messages)").await,
+        Ok(users(&[1, 2, 3]))
+    );
+    assert_eq!(
+        guild
+            .evaluate("perm(ban_members) | perm(administrator)")
+            .await,
+        Ok(users(&[3]))
+    );
+    assert!(matches!(
+        guild.evaluate("perm(fly)").await,
+        Err(MockError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn join_dates() {
+    let now = Utc::now();
+    let guild = guild()
+        .with_joined_at(UserId(1), now - Duration::days(400))
+        .with_joined_at(UserId(2), now - Duration::days(20))
+        .with_joined_at(UserId(3), now - Duration::hours(1));
+
+    assert_eq!(
+        guild.evaluate("joined_after(30d)").await,
+        Ok(users(&[2, 3]))
+    );
+    assert_eq!(
+        guild.evaluate("joined_before(2h)").await,
+        Ok(users(&[1, 2]))
+    );
+    assert_eq!(
+        guild.evaluate("joined_before(\"2000-01-01\")").await,
+        Ok(users(&[]))
+    );
+    assert!(matches!(
+        guild.evaluate("joined_after(tomorrow)").await,
+        Err(MockError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn account_ages() {
+    /// The ID of a user whose account was created `ago`.
+    fn created(ago: Duration) -> UserId {
+        /// The first second of 2015, which Discord's IDs count from
+        const DISCORD_EPOCH: i64 = 1_420_070_400_000;
+        let millis = (Utc::now() - ago).timestamp_millis() - DISCORD_EPOCH;
+        UserId(u64::try_from(millis).expect("the time should be after 2015") << 22)
+    }
+
+    let new = created(Duration::days(1));
+    let guild = guild().with_member(new, "newcomer", &[]);
+
+    assert_eq!(
+        guild.evaluate("account_age(<7d)").await,
+        Ok(HashSet::from([new]))
+    );
+    assert_eq!(
+        guild.evaluate("account_age(>1w)").await,
+        Ok(users(&[1, 2, 3, 4]))
+    );
+}
+
+#[tokio::test]
+async fn ids_mentions_and_quoted_names() {
+    let guild = guild();
+
+    assert_eq!(guild.evaluate("<@4> + <@&12>").await, Ok(users(&[3, 4])));
+    assert_eq!(guild.evaluate("10 - 1").await, Ok(users(&[2])));
+    assert_eq!(guild.evaluate("\"Red Team\"").await, Ok(users(&[3])));
+    assert_eq!(guild.evaluate("ali").await, Ok(users(&[1])));
+}
+
+#[tokio::test]
+async fn errors() {
+    let guild = guild();
+
+    assert!(matches!(
+        guild.evaluate("staff +").await,
+        Err(MockError::Parse(_))
+    ));
+    assert!(matches!(
+        guild.evaluate("nobody").await,
+        Err(MockError::Resolution(_))
+    ));
+    assert!(matches!(
+        guild.evaluate("999").await,
+        Err(MockError::Resolution(_))
+    ));
+}